@@ -0,0 +1,341 @@
+//! App-wide keyboard shortcuts, matched before the page sees the key.
+//!
+//! Combos are written like `"Ctrl+Shift+R"`, `"CmdOrCtrl+Q"` or `"F11"`:
+//! modifiers joined with `+`, then one key, case-insensitive. A key is a single
+//! character or a key name from the UI Events spec (`Enter`, `ArrowUp`,
+//! `PageDown`, ...). `CmdOrCtrl` is Cmd on macOS and Ctrl elsewhere.
+
+use std::str::FromStr;
+use std::sync::Mutex;
+
+use keyboard_types::{Code, Key, Modifiers, NamedKey};
+
+use crate::keyutils::CMD_OR_CONTROL;
+
+/// The modifiers a combo can require; others, like Caps Lock, are ignored.
+const COMBO_MODIFIERS: Modifiers = Modifiers::CONTROL
+    .union(Modifiers::SHIFT)
+    .union(Modifiers::ALT)
+    .union(Modifiers::META);
+
+#[derive(Clone, Debug, PartialEq)]
+enum ComboKey {
+    /// A lowercase character.
+    Character(String),
+    Named(NamedKey),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Accelerator {
+    pub action_id: u32,
+    /// The combo as registered, for events.
+    pub combo: String,
+    /// Pass the key on to the page as well.
+    pub deliver_to_page: bool,
+    modifiers: Modifiers,
+    key: ComboKey,
+}
+
+pub static ACCELERATORS: Mutex<Vec<Accelerator>> = Mutex::new(Vec::new());
+
+impl Accelerator {
+    pub fn parse(combo: &str, action_id: u32, deliver_to_page: bool) -> Option<Accelerator> {
+        let (modifiers, key) = parse_combo(combo)?;
+        Some(Accelerator {
+            action_id,
+            combo: combo.trim().to_string(),
+            deliver_to_page,
+            modifiers,
+            key,
+        })
+    }
+
+    /// Whether a key press of `key` (physical `code`) with `modifiers` held
+    /// triggers this accelerator.
+    pub fn matches(&self, key: &Key, code: Code, modifiers: Modifiers) -> bool {
+        if modifiers & COMBO_MODIFIERS != self.modifiers {
+            return false;
+        }
+        match (&self.key, key) {
+            (ComboKey::Named(named), Key::Named(pressed)) => named == pressed,
+            (ComboKey::Character(character), Key::Character(pressed)) => {
+                // Shift and Alt can turn the character into a symbol
+                // (`Shift+1` types `!`); then letters and digits match by
+                // their physical key.
+                pressed.to_lowercase() == *character
+                    || (!pressed.chars().any(char::is_alphanumeric)
+                        && character_code(character) == Some(code))
+            }
+            (ComboKey::Character(character), _) => character_code(character) == Some(code),
+            _ => false,
+        }
+    }
+}
+
+/// Split `combo` into its modifiers and key.
+fn parse_combo(combo: &str) -> Option<(Modifiers, ComboKey)> {
+    let mut parts: Vec<&str> = combo.split('+').map(str::trim).collect();
+    // "Ctrl++" binds the plus key.
+    if combo.trim_end().ends_with("++") {
+        parts.truncate(parts.len() - 2);
+        parts.push("+");
+    }
+    let (key, modifier_names) = parts.split_last()?;
+
+    let mut modifiers = Modifiers::empty();
+    for name in modifier_names {
+        modifiers |= match name.to_ascii_lowercase().as_str() {
+            "ctrl" | "control" => Modifiers::CONTROL,
+            "shift" => Modifiers::SHIFT,
+            "alt" | "option" => Modifiers::ALT,
+            "cmd" | "command" | "meta" | "super" => Modifiers::META,
+            "cmdorctrl" | "commandorcontrol" => CMD_OR_CONTROL,
+            _ => return None,
+        };
+    }
+
+    let key = if key.eq_ignore_ascii_case("space") {
+        ComboKey::Character(" ".to_string())
+    } else if key.chars().count() == 1 {
+        ComboKey::Character(key.to_lowercase())
+    } else {
+        ComboKey::Named(named_key(key)?)
+    };
+    Some((modifiers, key))
+}
+
+/// The key press for `combo`, for `pw_send_key`: the key, uppercased for a
+/// letter with Shift (`"Shift+A"` types `A`), its physical key on a US layout
+/// (unidentified for characters off the letter and digit keys) and the
+/// modifiers.
+pub fn key_press(combo: &str) -> Option<(Key, Code, Modifiers)> {
+    let (modifiers, key) = parse_combo(combo)?;
+    Some(match key {
+        ComboKey::Character(character) => {
+            let code = character_code(&character).unwrap_or(Code::Unidentified);
+            let character = if modifiers.contains(Modifiers::SHIFT) {
+                character.to_uppercase()
+            } else {
+                character
+            };
+            (Key::Character(character), code, modifiers)
+        }
+        ComboKey::Named(named) => {
+            // Most named keys have a physical key of the same name.
+            let code = Code::from_str(&named.to_string()).unwrap_or(Code::Unidentified);
+            (Key::Named(named), code, modifiers)
+        }
+    })
+}
+
+/// The named key called `name`, case-insensitively, with a few common
+/// abbreviations.
+fn named_key(name: &str) -> Option<NamedKey> {
+    let alias = match name.to_ascii_lowercase().as_str() {
+        "esc" => "Escape",
+        "del" => "Delete",
+        "return" => "Enter",
+        "up" => "ArrowUp",
+        "down" => "ArrowDown",
+        "left" => "ArrowLeft",
+        "right" => "ArrowRight",
+        _ => "",
+    };
+    if !alias.is_empty() {
+        return NamedKey::from_str(alias).ok();
+    }
+    if let Ok(key) = NamedKey::from_str(name) {
+        return Some(key);
+    }
+    // Key names are CamelCase (`PageDown`); accept any capitalization.
+    let lowercase = name.to_ascii_lowercase();
+    NAMED_KEYS
+        .iter()
+        .find(|key| key.to_ascii_lowercase() == lowercase)
+        .and_then(|key| NamedKey::from_str(key).ok())
+}
+
+/// Named keys that make sense in a shortcut, for case-insensitive lookup.
+const NAMED_KEYS: &[&str] = &[
+    "Enter",
+    "Tab",
+    "Escape",
+    "Backspace",
+    "Delete",
+    "Insert",
+    "Home",
+    "End",
+    "PageUp",
+    "PageDown",
+    "ArrowUp",
+    "ArrowDown",
+    "ArrowLeft",
+    "ArrowRight",
+    "F1",
+    "F2",
+    "F3",
+    "F4",
+    "F5",
+    "F6",
+    "F7",
+    "F8",
+    "F9",
+    "F10",
+    "F11",
+    "F12",
+    "F13",
+    "F14",
+    "F15",
+    "F16",
+    "F17",
+    "F18",
+    "F19",
+    "F20",
+    "F21",
+    "F22",
+    "F23",
+    "F24",
+    "ContextMenu",
+    "PrintScreen",
+    "Pause",
+    "MediaPlayPause",
+    "MediaTrackNext",
+    "MediaTrackPrevious",
+    "MediaStop",
+    "AudioVolumeUp",
+    "AudioVolumeDown",
+    "AudioVolumeMute",
+    "BrowserBack",
+    "BrowserForward",
+    "BrowserRefresh",
+];
+
+/// The physical key of a letter or digit on a US layout.
+fn character_code(character: &str) -> Option<Code> {
+    let mut chars = character.chars();
+    let (Some(character), None) = (chars.next(), chars.next()) else {
+        return None;
+    };
+    let name = if character.is_ascii_alphabetic() {
+        format!("Key{}", character.to_ascii_uppercase())
+    } else if character.is_ascii_digit() {
+        format!("Digit{}", character)
+    } else if character == ' ' {
+        "Space".to_string()
+    } else {
+        return None;
+    };
+    Code::from_str(&name).ok()
+}
+
+/// Register `accelerator`, replacing one with the same action id.
+pub fn register(accelerator: Accelerator) {
+    let mut accelerators = ACCELERATORS.lock().unwrap();
+    accelerators.retain(|existing| existing.action_id != accelerator.action_id);
+    accelerators.push(accelerator);
+}
+
+/// Remove the accelerator for `action_id`. Returns false if there was none.
+pub fn unregister(action_id: u32) -> bool {
+    let mut accelerators = ACCELERATORS.lock().unwrap();
+    let before = accelerators.len();
+    accelerators.retain(|existing| existing.action_id != action_id);
+    accelerators.len() != before
+}
+
+/// The accelerator a key press triggers, if any.
+pub fn find(key: &Key, code: Code, modifiers: Modifiers) -> Option<Accelerator> {
+    ACCELERATORS
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|accelerator| accelerator.matches(key, code, modifiers))
+        .cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(combo: &str) -> Accelerator {
+        Accelerator::parse(combo, 1, false).unwrap()
+    }
+
+    fn character(c: &str) -> Key {
+        Key::Character(c.to_string())
+    }
+
+    #[test]
+    fn matches_characters_case_insensitively() {
+        let reload = parse("Ctrl+Shift+R");
+        let modifiers = Modifiers::CONTROL | Modifiers::SHIFT;
+        assert!(reload.matches(&character("R"), Code::KeyR, modifiers));
+        assert!(reload.matches(&character("r"), Code::KeyR, modifiers));
+        assert!(!reload.matches(&character("r"), Code::KeyR, Modifiers::CONTROL));
+        assert!(!reload.matches(&character("t"), Code::KeyT, modifiers));
+    }
+
+    #[test]
+    fn matches_shifted_digits_by_physical_key() {
+        let tab = parse("ctrl+shift+1");
+        let modifiers = Modifiers::CONTROL | Modifiers::SHIFT;
+        assert!(tab.matches(&character("!"), Code::Digit1, modifiers));
+    }
+
+    #[test]
+    fn matches_named_keys_and_ignores_lock_modifiers() {
+        let fullscreen = parse("f11");
+        let key = Key::Named(NamedKey::F11);
+        assert!(fullscreen.matches(&key, Code::F11, Modifiers::empty()));
+        assert!(fullscreen.matches(&key, Code::F11, Modifiers::CAPS_LOCK));
+        assert!(!fullscreen.matches(&key, Code::F11, Modifiers::ALT));
+        assert!(parse("Alt+pagedown").matches(
+            &Key::Named(NamedKey::PageDown),
+            Code::PageDown,
+            Modifiers::ALT
+        ));
+        assert!(parse("Shift+Space").matches(&character(" "), Code::Space, Modifiers::SHIFT));
+        assert!(parse("Esc").matches(
+            &Key::Named(NamedKey::Escape),
+            Code::Escape,
+            Modifiers::empty()
+        ));
+    }
+
+    #[test]
+    fn cmd_or_ctrl_follows_the_platform() {
+        let quit = parse("CmdOrCtrl+Q");
+        assert!(quit.matches(&character("q"), Code::KeyQ, CMD_OR_CONTROL));
+    }
+
+    #[test]
+    fn binds_the_plus_key() {
+        let zoom = parse("Ctrl++");
+        assert!(zoom.matches(&character("+"), Code::Equal, Modifiers::CONTROL));
+    }
+
+    #[test]
+    fn rejects_malformed_combos() {
+        assert_eq!(Accelerator::parse("", 1, false), None);
+        assert_eq!(Accelerator::parse("Ctrl+", 1, false), None);
+        assert_eq!(Accelerator::parse("Hyper+K", 1, false), None);
+        assert_eq!(Accelerator::parse("Ctrl+NotAKey", 1, false), None);
+    }
+
+    #[test]
+    fn key_press_for_combo() {
+        assert_eq!(
+            key_press("Shift+a"),
+            Some((character("A"), Code::KeyA, Modifiers::SHIFT))
+        );
+        assert_eq!(
+            key_press("Ctrl+Enter"),
+            Some((Key::Named(NamedKey::Enter), Code::Enter, Modifiers::CONTROL))
+        );
+        assert_eq!(
+            key_press("/"),
+            Some((character("/"), Code::Unidentified, Modifiers::empty()))
+        );
+        assert_eq!(key_press("Hyper+A"), None);
+    }
+}
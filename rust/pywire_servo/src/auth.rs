@@ -0,0 +1,128 @@
+//! HTTP authentication challenges waiting for Python to answer.
+//!
+//! Servo holds the request until its `AuthenticationRequest` is answered or
+//! dropped. Dropping it without credentials lets the load finish with the
+//! server's `401` response, which is how challenges are cancelled. Challenges
+//! nobody answers in time are cancelled too, so a load never hangs on an app
+//! that doesn't handle `auth_required`.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use servo::AuthenticationRequest;
+
+/// How long a challenge may go unanswered before it is cancelled, in
+/// milliseconds.
+pub static AUTH_TIMEOUT_MS: AtomicU64 = AtomicU64::new(60_000);
+
+pub fn timeout() -> Duration {
+    Duration::from_millis(AUTH_TIMEOUT_MS.load(Ordering::Relaxed))
+}
+
+struct Pending<R> {
+    webview_id: u32,
+    deadline: Instant,
+    request: R,
+}
+
+pub struct PendingAuthentications<R = AuthenticationRequest> {
+    /// Ids start at 1 so that 0 can signal failure across the FFI.
+    last_id: u32,
+    requests: HashMap<u32, Pending<R>>,
+}
+
+impl<R> Default for PendingAuthentications<R> {
+    fn default() -> Self {
+        PendingAuthentications {
+            last_id: 0,
+            requests: HashMap::new(),
+        }
+    }
+}
+
+impl<R> PendingAuthentications<R> {
+    /// Hold `request` from webview `webview_id` until `deadline`, and return
+    /// its id.
+    pub fn add(&mut self, webview_id: u32, request: R, deadline: Instant) -> u32 {
+        self.last_id += 1;
+        self.requests.insert(
+            self.last_id,
+            Pending {
+                webview_id,
+                deadline,
+                request,
+            },
+        );
+        self.last_id
+    }
+
+    pub fn take(&mut self, id: u32) -> Option<R> {
+        self.requests.remove(&id).map(|pending| pending.request)
+    }
+
+    /// Cancel the challenges whose deadline has passed, returning their ids.
+    pub fn expire(&mut self, now: Instant) -> Vec<u32> {
+        let mut expired: Vec<u32> = self
+            .requests
+            .iter()
+            .filter(|(_, pending)| pending.deadline <= now)
+            .map(|(id, _)| *id)
+            .collect();
+        expired.sort_unstable();
+        for id in &expired {
+            self.requests.remove(id);
+        }
+        expired
+    }
+
+    /// When the next unanswered challenge expires.
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.requests.values().map(|pending| pending.deadline).min()
+    }
+
+    /// Cancel the challenges of a webview that is going away.
+    pub fn cancel_for_webview(&mut self, webview_id: u32) {
+        self.requests
+            .retain(|_, pending| pending.webview_id != webview_id);
+    }
+
+    pub fn cancel_all(&mut self) {
+        self.requests.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    /// Records whether it was dropped, which is how challenges are cancelled.
+    struct FakeRequest(Rc<Cell<bool>>);
+
+    impl Drop for FakeRequest {
+        fn drop(&mut self) {
+            self.0.set(true);
+        }
+    }
+
+    #[test]
+    fn unanswered_challenge_is_cancelled_after_the_timeout() {
+        let timeout = Duration::from_millis(500);
+        let start = Instant::now();
+        let mut pending = PendingAuthentications::default();
+        let cancelled = Rc::new(Cell::new(false));
+        let id = pending.add(1, FakeRequest(cancelled.clone()), start + timeout);
+
+        assert_eq!(pending.next_deadline(), Some(start + timeout));
+        assert!(pending.expire(start + timeout / 2).is_empty());
+        assert!(!cancelled.get());
+
+        assert_eq!(pending.expire(start + timeout), vec![id]);
+        assert!(cancelled.get());
+        assert_eq!(pending.next_deadline(), None);
+        // Too late to answer it now.
+        assert!(pending.take(id).is_none());
+    }
+}
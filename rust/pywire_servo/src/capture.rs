@@ -0,0 +1,185 @@
+//! Reading rendered frames back from the GPU.
+//!
+//! Captures are requested from anywhere on the event loop and served by
+//! `AppState::repaint` right after Servo has painted into the offscreen
+//! framebuffer, the only point at which its contents are known to be current.
+//!
+//! [`FrameCopy`] keeps a frame on the GPU instead, for showing again while
+//! the window is resized.
+
+use glow::HasContext;
+
+/// A captured frame as tightly packed RGBA rows, top row first.
+pub struct Frame {
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>,
+}
+
+/// Called with the next frame Servo paints.
+pub type CaptureCallback = Box<dyn FnOnce(&Frame)>;
+
+impl Frame {
+    /// Read the `width` x `height` framebuffer currently bound on `gl`.
+    pub fn read(gl: &glow::Context, width: u32, height: u32) -> Frame {
+        let mut rgba = vec![0; width as usize * height as usize * 4];
+        unsafe {
+            gl.pixel_store_i32(glow::PACK_ALIGNMENT, 1);
+            gl.read_pixels(
+                0,
+                0,
+                width as i32,
+                height as i32,
+                glow::RGBA,
+                glow::UNSIGNED_BYTE,
+                glow::PixelPackData::Slice(Some(&mut rgba)),
+            );
+        }
+        let mut frame = Frame {
+            width,
+            height,
+            rgba,
+        };
+        frame.flip_rows();
+        frame
+    }
+
+    /// GL's origin is the bottom-left corner; turn the rows the right way up.
+    fn flip_rows(&mut self) {
+        let stride = self.width as usize * 4;
+        let rows = self.height as usize;
+        for row in 0..rows / 2 {
+            let (top, bottom) = self.rgba.split_at_mut((rows - row - 1) * stride);
+            top[row * stride..(row + 1) * stride].swap_with_slice(&mut bottom[..stride]);
+        }
+    }
+
+    /// A copy shrunk to fit within `max` x `max` pixels, averaging each block
+    /// of source pixels. Frames that already fit are copied unchanged.
+    pub fn scaled_to_fit(&self, max: u32) -> Frame {
+        let scale = (max as f64 / self.width.max(self.height).max(1) as f64).min(1.0);
+        let width = ((self.width as f64 * scale).round() as u32).max(1);
+        let height = ((self.height as f64 * scale).round() as u32).max(1);
+        let mut rgba = Vec::with_capacity(width as usize * height as usize * 4);
+        for y in 0..height {
+            let y0 = y * self.height / height;
+            let y1 = ((y + 1) * self.height / height).max(y0 + 1);
+            for x in 0..width {
+                let x0 = x * self.width / width;
+                let x1 = ((x + 1) * self.width / width).max(x0 + 1);
+                let mut sum = [0u64; 4];
+                for sy in y0..y1 {
+                    for sx in x0..x1 {
+                        let offset = (sy as usize * self.width as usize + sx as usize) * 4;
+                        for (channel, total) in sum.iter_mut().enumerate() {
+                            *total += self.rgba[offset + channel] as u64;
+                        }
+                    }
+                }
+                let count = ((y1 - y0) * (x1 - x0)) as u64;
+                rgba.extend(sum.iter().map(|total| (total / count) as u8));
+            }
+        }
+        Frame {
+            width,
+            height,
+            rgba,
+        }
+    }
+
+    /// Encode the frame as a PNG file.
+    pub fn encode_png(&self) -> Result<Vec<u8>, png::EncodingError> {
+        let mut png = Vec::new();
+        let mut encoder = png::Encoder::new(&mut png, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        // The offscreen framebuffer holds sRGB-encoded colors, as displayed.
+        encoder.set_source_srgb(png::SrgbRenderingIntent::Perceptual);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.rgba)?;
+        writer.finish()?;
+        Ok(png)
+    }
+}
+
+/// A copy of a presented frame kept on the GPU, which the window shows
+/// stretched to its new size after a resize, until Servo has painted the
+/// page at that size.
+pub struct FrameCopy {
+    framebuffer: glow::NativeFramebuffer,
+    renderbuffer: glow::NativeRenderbuffer,
+    width: i32,
+    height: i32,
+}
+
+impl FrameCopy {
+    /// Copy the `width` x `height` framebuffer bound for reading on `gl`.
+    pub fn copy(gl: &glow::Context, width: i32, height: i32) -> Option<FrameCopy> {
+        unsafe {
+            let renderbuffer = gl.create_renderbuffer().ok()?;
+            let Ok(framebuffer) = gl.create_framebuffer() else {
+                gl.delete_renderbuffer(renderbuffer);
+                return None;
+            };
+            let target = gl.get_parameter_framebuffer(glow::DRAW_FRAMEBUFFER_BINDING);
+            gl.bind_renderbuffer(glow::RENDERBUFFER, Some(renderbuffer));
+            gl.renderbuffer_storage(glow::RENDERBUFFER, glow::RGBA8, width, height);
+            gl.bind_framebuffer(glow::DRAW_FRAMEBUFFER, Some(framebuffer));
+            gl.framebuffer_renderbuffer(
+                glow::DRAW_FRAMEBUFFER,
+                glow::COLOR_ATTACHMENT0,
+                glow::RENDERBUFFER,
+                Some(renderbuffer),
+            );
+            gl.blit_framebuffer(
+                0,
+                0,
+                width,
+                height,
+                0,
+                0,
+                width,
+                height,
+                glow::COLOR_BUFFER_BIT,
+                glow::NEAREST,
+            );
+            gl.bind_framebuffer(glow::DRAW_FRAMEBUFFER, target);
+            Some(FrameCopy {
+                framebuffer,
+                renderbuffer,
+                width,
+                height,
+            })
+        }
+    }
+
+    /// Draw the copy stretched over the `width` x `height` framebuffer bound
+    /// for drawing on `gl`.
+    pub fn draw(&self, gl: &glow::Context, width: i32, height: i32) {
+        unsafe {
+            let source = gl.get_parameter_framebuffer(glow::READ_FRAMEBUFFER_BINDING);
+            gl.bind_framebuffer(glow::READ_FRAMEBUFFER, Some(self.framebuffer));
+            gl.blit_framebuffer(
+                0,
+                0,
+                self.width,
+                self.height,
+                0,
+                0,
+                width,
+                height,
+                glow::COLOR_BUFFER_BIT,
+                glow::LINEAR,
+            );
+            gl.bind_framebuffer(glow::READ_FRAMEBUFFER, source);
+        }
+    }
+
+    /// Free the copy's GPU memory. `gl` must be the context it was made on.
+    pub fn delete(self, gl: &glow::Context) {
+        unsafe {
+            gl.delete_framebuffer(self.framebuffer);
+            gl.delete_renderbuffer(self.renderbuffer);
+        }
+    }
+}
@@ -0,0 +1,134 @@
+//! TLS certificate errors waiting for Python to answer.
+//!
+//! Servo has no delegate hook for certificate errors. It answers the request
+//! with its `badcert.html` resource instead, filling in the reason, the
+//! certificate and a secret with which the page can accept the certificate by
+//! posting it to `chrome:allowcert`. The shell serves its own version of that
+//! page (`js/badcert.html`). The page reports a `cert_error` event and accepts
+//! the certificate only when Python calls `pw_allow_cert`. Accepted
+//! certificates are trusted until the app exits.
+//!
+//! Like the network error page, the page carries a nonce its report must
+//! return, so another page can't raise a `cert_error` by logging the marker.
+
+use std::collections::HashMap;
+
+const BAD_CERT_HTML: &str = include_str!("js/badcert.html");
+
+/// The page Servo shows in place of a site whose certificate failed.
+pub fn page() -> Vec<u8> {
+    let html = BAD_CERT_HTML.replace("__NONCE__", &crate::net_error::issue_nonce());
+    crate::net_error::with_custom_pages(&html).into_bytes()
+}
+
+/// Accepts the certificate and reloads. Evaluates to whether the page was a
+/// certificate error it could accept.
+pub const ALLOW_JS: &str = "window.__pywireCertError ? window.__pywireCertError.allow() : false";
+
+/// What the certificate error page reports after its `PW_CERT:` marker.
+#[derive(Debug, PartialEq)]
+pub struct CertError {
+    /// The nonce the page was served with, for `net_error::take_nonce`.
+    pub nonce: String,
+    pub url: String,
+    pub host: String,
+    pub error: String,
+    /// Whether Servo passed the certificate along, so it can be accepted.
+    pub allowable: bool,
+}
+
+pub fn parse_report(payload: &str) -> Option<CertError> {
+    let report: serde_json::Value = serde_json::from_str(payload).ok()?;
+    let text = |key: &str| {
+        report
+            .get(key)?
+            .as_str()
+            .map(|text| text.trim().to_string())
+    };
+    Some(CertError {
+        nonce: text("nonce")?,
+        url: text("url")?,
+        host: text("host")?,
+        error: text("error").unwrap_or_default(),
+        allowable: report.get("allowable")?.as_bool()?,
+    })
+}
+
+#[derive(Default)]
+pub struct PendingCertErrors {
+    /// Ids start at 1 so that 0 can signal failure across the FFI.
+    last_id: u32,
+    /// Error id -> the id of the webview showing it, and the URL it failed
+    /// for.
+    errors: HashMap<u32, (u32, String)>,
+}
+
+impl PendingCertErrors {
+    /// Hold the error for `url` shown in webview `webview_id` and return its
+    /// id.
+    pub fn add(&mut self, webview_id: u32, url: String) -> u32 {
+        self.last_id += 1;
+        self.errors.insert(self.last_id, (webview_id, url));
+        self.last_id
+    }
+
+    /// The webview showing error `id` and the URL it failed for. The error is
+    /// answered from now on.
+    pub fn take(&mut self, id: u32) -> Option<(u32, String)> {
+        self.errors.remove(&id)
+    }
+
+    /// Forget the errors of a webview that is going away.
+    pub fn cancel_for_webview(&mut self, webview_id: u32) {
+        self.errors.retain(|_, (owner, _)| *owner != webview_id);
+    }
+
+    pub fn cancel_all(&mut self) {
+        self.errors.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_error_reports() {
+        let report = r#"{"nonce": "ab12", "url": "https://intranet/", "host": "intranet",
+            "error": " invalid peer certificate: UnknownIssuer\n", "allowable": true}"#;
+        assert_eq!(
+            parse_report(report),
+            Some(CertError {
+                nonce: "ab12".to_string(),
+                url: "https://intranet/".to_string(),
+                host: "intranet".to_string(),
+                error: "invalid peer certificate: UnknownIssuer".to_string(),
+                allowable: true,
+            })
+        );
+        assert_eq!(parse_report(r#"{"host": "intranet"}"#), None);
+        let unsigned = r#"{"url": "https://intranet/", "host": "intranet", "allowable": true}"#;
+        assert_eq!(parse_report(unsigned), None);
+        assert_eq!(parse_report("not json"), None);
+    }
+
+    #[test]
+    fn pages_carry_a_nonce() {
+        assert!(!String::from_utf8(page()).unwrap().contains("__NONCE__"));
+    }
+
+    #[test]
+    fn answered_errors_are_forgotten() {
+        let mut pending = PendingCertErrors::default();
+        let first = pending.add(1, "https://intranet/".to_string());
+        let second = pending.add(2, "https://wiki/".to_string());
+        assert_eq!(
+            pending.take(first),
+            Some((1, "https://intranet/".to_string()))
+        );
+        assert_eq!(pending.take(first), None);
+
+        pending.cancel_for_webview(2);
+        assert_eq!(pending.take(second), None);
+    }
+}
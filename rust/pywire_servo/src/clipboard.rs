@@ -0,0 +1,67 @@
+//! System clipboard access for the page's copy/paste and for Python.
+//!
+//! Everything runs on the event-loop thread through a single long-lived
+//! `arboard::Clipboard`: on X11 and Wayland the clipboard contents are served
+//! by their owner, so text we copy must stay owned for paste to keep working.
+
+use std::cell::RefCell;
+
+use servo::{ClipboardDelegate, StringRequest, WebView};
+
+thread_local! {
+    static CLIPBOARD: RefCell<Option<arboard::Clipboard>> = const { RefCell::new(None) };
+}
+
+fn with_clipboard<T>(f: impl FnOnce(&mut arboard::Clipboard) -> T) -> Result<T, String> {
+    CLIPBOARD.with(|cell| {
+        let mut clipboard = cell.borrow_mut();
+        if clipboard.is_none() {
+            *clipboard = Some(arboard::Clipboard::new().map_err(|e| e.to_string())?);
+        }
+        Ok(f(clipboard.as_mut().unwrap()))
+    })
+}
+
+/// The clipboard's text. Empty or non-text contents (e.g. an image) read as
+/// `None` rather than an error.
+pub fn get_text() -> Result<Option<String>, String> {
+    with_clipboard(|clipboard| match clipboard.get_text() {
+        Ok(text) => Ok(Some(text)),
+        Err(arboard::Error::ContentNotAvailable) => Ok(None),
+        Err(e) => Err(e.to_string()),
+    })?
+}
+
+pub fn set_text(text: String) -> Result<(), String> {
+    with_clipboard(|clipboard| clipboard.set_text(text).map_err(|e| e.to_string()))?
+}
+
+pub fn clear() -> Result<(), String> {
+    with_clipboard(|clipboard| clipboard.clear().map_err(|e| e.to_string()))?
+}
+
+/// Serves the page's `Copy`/`Cut`/`Paste` editing actions and
+/// `navigator.clipboard`.
+pub struct PyWireClipboardDelegate;
+
+impl ClipboardDelegate for PyWireClipboardDelegate {
+    fn clear(&self, _webview: WebView) {
+        if let Err(e) = clear() {
+            log::warn!("Clipboard clear failed: {}", e);
+        }
+    }
+
+    fn get_text(&self, _webview: WebView, request: StringRequest) {
+        // Servo waits for an answer, so resolve it right away from the OS.
+        match get_text() {
+            Ok(text) => request.success(text.unwrap_or_default()),
+            Err(e) => request.failure(e),
+        }
+    }
+
+    fn set_text(&self, _webview: WebView, new_contents: String) {
+        if let Err(e) = set_text(new_contents) {
+            log::warn!("Clipboard write failed: {}", e);
+        }
+    }
+}
@@ -0,0 +1,69 @@
+//! The color scheme pages see through `prefers-color-scheme`.
+//!
+//! By default it follows the OS theme winit reports, and changes along with
+//! it. `pw_set_color_scheme` can pin it to light or dark instead. Servo takes
+//! the theme per webview rather than as a preference, so every webview is
+//! told whenever the outcome changes.
+
+use std::sync::Mutex;
+
+use servo::Theme;
+use winit::window::Theme as WindowTheme;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ColorScheme {
+    /// Follow the OS.
+    Auto,
+    Light,
+    Dark,
+}
+
+pub static COLOR_SCHEME: Mutex<ColorScheme> = Mutex::new(ColorScheme::Auto);
+
+impl ColorScheme {
+    pub fn parse(name: &str) -> Option<ColorScheme> {
+        match name.to_ascii_lowercase().as_str() {
+            "auto" => Some(ColorScheme::Auto),
+            "light" => Some(ColorScheme::Light),
+            "dark" => Some(ColorScheme::Dark),
+            _ => None,
+        }
+    }
+
+    /// The theme pages get while the OS theme is `os_theme`, which is `None`
+    /// where the platform doesn't report one (and when running headless).
+    pub fn theme(self, os_theme: Option<WindowTheme>) -> Theme {
+        match (self, os_theme) {
+            (ColorScheme::Light, _) => Theme::Light,
+            (ColorScheme::Dark, _) => Theme::Dark,
+            (ColorScheme::Auto, Some(WindowTheme::Dark)) => Theme::Dark,
+            (ColorScheme::Auto, _) => Theme::Light,
+        }
+    }
+}
+
+/// The theme pages get under the current setting.
+pub fn current(os_theme: Option<WindowTheme>) -> Theme {
+    COLOR_SCHEME.lock().unwrap().theme(os_theme)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_schemes() {
+        assert_eq!(ColorScheme::parse("Dark"), Some(ColorScheme::Dark));
+        assert_eq!(ColorScheme::parse("auto"), Some(ColorScheme::Auto));
+        assert_eq!(ColorScheme::parse("sepia"), None);
+    }
+
+    #[test]
+    fn auto_follows_the_os_and_overrides_win() {
+        let dark = Some(WindowTheme::Dark);
+        assert!(matches!(ColorScheme::Auto.theme(dark), Theme::Dark));
+        assert!(matches!(ColorScheme::Auto.theme(None), Theme::Light));
+        assert!(matches!(ColorScheme::Light.theme(dark), Theme::Light));
+        assert!(matches!(ColorScheme::Dark.theme(None), Theme::Dark));
+    }
+}
@@ -0,0 +1,76 @@
+//! Reporting crashes instead of dying silently.
+//!
+//! Three kinds end up as `crash` events: a page's content crashing (Servo
+//! reports it through `WebViewDelegate::notify_crashed`), engine errors
+//! Servo reports to its delegate, and panics on any of the engine's threads,
+//! caught by a panic hook. A panic on the event loop thread itself still ends
+//! `pw_start_app`, with `-12` and the message in `pw_last_error`.
+//!
+//! With crash recovery on, a crashed page is reloaded, like a browser's "sad
+//! tab" button, unless it crashed again within [`RELOAD_BACKOFF`].
+
+use std::panic::{self, PanicHookInfo};
+use std::sync::atomic::AtomicBool;
+use std::sync::Once;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Whether crashed pages are reloaded, set by `pw_set_crash_recovery`.
+pub static CRASH_RECOVERY: AtomicBool = AtomicBool::new(false);
+
+/// A page that crashes again this soon after being reloaded is left crashed,
+/// so a page that always crashes doesn't reload forever.
+pub const RELOAD_BACKOFF: Duration = Duration::from_secs(30);
+
+static INSTALL_HOOK: Once = Once::new();
+
+/// Whether a page that crashes at `now`, and last crashed at `last_crash`,
+/// should be reloaded.
+pub fn should_reload(last_crash: Option<Instant>, now: Instant) -> bool {
+    last_crash.is_none_or(|last_crash| now.duration_since(last_crash) >= RELOAD_BACKOFF)
+}
+
+/// What a panic says, where and on which thread.
+fn panic_detail(info: &PanicHookInfo) -> String {
+    let message = info
+        .payload()
+        .downcast_ref::<String>()
+        .map(String::as_str)
+        .or_else(|| info.payload().downcast_ref::<&str>().copied())
+        .unwrap_or("unknown panic");
+    let thread = thread::current();
+    let location = info
+        .location()
+        .map(|location| format!(" at {}:{}", location.file(), location.line()))
+        .unwrap_or_default();
+    format!(
+        "thread '{}' panicked{}: {}",
+        thread.name().unwrap_or("<unnamed>"),
+        location,
+        message
+    )
+}
+
+/// Pass every panic's details to `report`, after the usual panic output.
+pub fn install_panic_hook(report: fn(String)) {
+    INSTALL_HOOK.call_once(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            previous(info);
+            report(panic_detail(info));
+        }));
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reloads_unless_the_page_just_crashed() {
+        let now = Instant::now();
+        assert!(should_reload(None, now));
+        assert!(!should_reload(Some(now), now + Duration::from_secs(5)));
+        assert!(should_reload(Some(now), now + RELOAD_BACKOFF));
+    }
+}
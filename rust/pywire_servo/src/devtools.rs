@@ -0,0 +1,69 @@
+//! Servo's remote devtools server, for inspecting the page from Firefox's
+//! `about:debugging` during development.
+//!
+//! Servo's engine-wide delegate lives here too, since the devtools server is
+//! most of what it reports on. Servo's other errors become `crash` events.
+
+use std::net::TcpListener;
+
+use servo::{AllowOrDenyRequest, Servo, ServoDelegate, ServoError};
+
+use crate::events::{emit_event, EventType};
+
+/// `requested` if it is free on the loopback interface, otherwise a port the
+/// OS picks. The check and Servo's own bind race, but only against other
+/// local processes grabbing the same port in between.
+pub fn choose_port(requested: u16) -> u16 {
+    if TcpListener::bind(("127.0.0.1", requested)).is_ok() {
+        return requested;
+    }
+    match TcpListener::bind(("127.0.0.1", 0)).and_then(|listener| listener.local_addr()) {
+        Ok(addr) => {
+            log::warn!(
+                "Devtools port {} is in use, using {} instead",
+                requested,
+                addr.port()
+            );
+            addr.port()
+        }
+        // Let Servo try anyway and report the failure.
+        Err(_) => requested,
+    }
+}
+
+pub struct PyWireServoDelegate;
+
+impl ServoDelegate for PyWireServoDelegate {
+    fn notify_devtools_server_started(&self, _servo: &Servo, port: u16, _token: String) {
+        log::info!("Devtools server listening on port {}", port);
+        emit_event(
+            EventType::Devtools,
+            serde_json::json!({"type": "devtools_started", "port": port}),
+        );
+    }
+
+    fn request_devtools_connection(&self, _servo: &Servo, request: AllowOrDenyRequest) {
+        // The server only exists when the app asked for it.
+        request.allow();
+    }
+
+    fn notify_error(&self, _servo: &Servo, error: ServoError) {
+        log::error!("Servo error: {:?}", error);
+        if matches!(error, ServoError::DevtoolsFailedToStart) {
+            emit_event(
+                EventType::Devtools,
+                serde_json::json!({"type": "devtools_failed"}),
+            );
+            return;
+        }
+        emit_event(
+            EventType::Crash,
+            serde_json::json!({
+                "type": "crash",
+                "webview_id": null,
+                "detail": format!("{:?}", error),
+                "reloading": false,
+            }),
+        );
+    }
+}
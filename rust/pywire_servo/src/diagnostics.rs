@@ -0,0 +1,102 @@
+//! The environment report behind `pw_run_diagnostics`, meant to be attached
+//! to bug reports.
+
+use std::env;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use glow::HasContext;
+use serde_json::{json, Value};
+use winit::window::Window;
+
+use crate::resource_reader::{
+    PyWireResourceReader, RESOURCES_PATH, RESOURCE_BUNDLE, RESOURCE_CALLBACK,
+};
+
+/// How `pw_start_app` set up rustls' crypto provider, once it has.
+pub static CRYPTO_PROVIDER: OnceLock<&'static str> = OnceLock::new();
+
+/// Renderer names of software GL implementations, which are slow enough to
+/// explain most "the app is sluggish" reports.
+const SOFTWARE_RENDERERS: &[&str] = &["llvmpipe", "softpipe", "swiftshader", "software"];
+
+/// Checks that need no running app: versions, engine resources and crypto.
+pub fn environment_report() -> Value {
+    // Before `pw_start_app` only the environment variable is known.
+    let path = RESOURCES_PATH
+        .lock()
+        .unwrap()
+        .clone()
+        .or_else(|| env::var("SERVO_RESOURCES_PATH").ok().map(PathBuf::from));
+    let reader = PyWireResourceReader {
+        path: path.clone(),
+        callback: *RESOURCE_CALLBACK.lock().unwrap(),
+    };
+    json!({
+        "version": crate::VERSION.to_str().unwrap_or_default(),
+        "os": env::consts::OS,
+        "arch": env::consts::ARCH,
+        "resources": {
+            "path": path.as_ref().map(|path| path.to_string_lossy().into_owned()),
+            "path_exists": path.as_ref().is_some_and(|path| path.is_dir()),
+            "bundled": RESOURCE_BUNDLE.lock().unwrap().len(),
+            "callback": reader.callback.is_some(),
+            "missing": reader.missing_required(),
+        },
+        "crypto": CRYPTO_PROVIDER.get(),
+    })
+}
+
+/// Checks against the running app's window and GL context. `gl` must be
+/// current, since `glGetString` reads whichever context is; `None` when it
+/// couldn't be made current leaves the `gl` section null.
+pub fn runtime_report(window: &Window, gl: Option<&glow::Context>) -> Value {
+    let gl = gl.map(|gl| {
+        let (vendor, renderer, version) = unsafe {
+            (
+                gl.get_parameter_string(glow::VENDOR),
+                gl.get_parameter_string(glow::RENDERER),
+                gl.get_parameter_string(glow::VERSION),
+            )
+        };
+        let lowercase_renderer = renderer.to_lowercase();
+        let software = SOFTWARE_RENDERERS
+            .iter()
+            .any(|name| lowercase_renderer.contains(name));
+        json!({
+            "vendor": vendor,
+            "renderer": renderer,
+            "version": version,
+            "software_rendering": software,
+        })
+    });
+
+    let current = window.current_monitor();
+    let monitors: Vec<Value> = window
+        .available_monitors()
+        .map(|monitor| {
+            let size = monitor.size();
+            let position = monitor.position();
+            json!({
+                "name": monitor.name(),
+                "width": size.width,
+                "height": size.height,
+                "x": position.x,
+                "y": position.y,
+                "scale_factor": monitor.scale_factor(),
+                "current": current.as_ref() == Some(&monitor),
+            })
+        })
+        .collect();
+
+    let size = window.inner_size();
+    json!({
+        "gl": gl,
+        "monitors": monitors,
+        "window": {
+            "width": size.width,
+            "height": size.height,
+            "scale_factor": window.scale_factor(),
+        },
+    })
+}
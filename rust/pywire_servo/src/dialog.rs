@@ -0,0 +1,51 @@
+//! Native message boxes for `alert()` and `confirm()`.
+//!
+//! Like file dialogs, they are modal and run on the event loop thread, while
+//! the page's script waits for the answer. `prompt()` has no native
+//! counterpart, so it is dismissed and returns `null`.
+
+use servo::SimpleDialog;
+use winit::window::Window;
+
+/// Show `dialog` over `window` and answer it with the user's choice. Without
+/// a window there is nothing to anchor it to, so headless apps dismiss it
+/// (`confirm()` then returns `false`).
+pub fn show(dialog: SimpleDialog, window: Option<&Window>, title: &str) {
+    let Some(window) = window else {
+        dismiss(dialog);
+        return;
+    };
+    match dialog {
+        SimpleDialog::Alert(alert) => {
+            rfd::MessageDialog::new()
+                .set_parent(window)
+                .set_title(title)
+                .set_description(alert.message())
+                .set_buttons(rfd::MessageButtons::Ok)
+                .show();
+            alert.confirm();
+        }
+        SimpleDialog::Confirm(confirm) => {
+            let result = rfd::MessageDialog::new()
+                .set_parent(window)
+                .set_title(title)
+                .set_description(confirm.message())
+                .set_buttons(rfd::MessageButtons::OkCancel)
+                .show();
+            if result == rfd::MessageDialogResult::Ok {
+                confirm.confirm();
+            } else {
+                confirm.dismiss();
+            }
+        }
+        SimpleDialog::Prompt(prompt) => prompt.dismiss(),
+    }
+}
+
+fn dismiss(dialog: SimpleDialog) {
+    match dialog {
+        SimpleDialog::Alert(alert) => alert.confirm(),
+        SimpleDialog::Confirm(confirm) => confirm.dismiss(),
+        SimpleDialog::Prompt(prompt) => prompt.dismiss(),
+    }
+}
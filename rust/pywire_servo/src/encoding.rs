@@ -0,0 +1,334 @@
+//! Fallback text encoding for legacy documents the app serves itself.
+//!
+//! Servo has no preference for the encoding used when a document does not
+//! declare one, so the shell supplies it itself: HTML and text documents
+//! without a byte order mark or `<meta charset>` are answered with a
+//! `Content-Type` that names the configured encoding. That covers `file:`
+//! URLs, which are intercepted and streamed from a thread of their own so
+//! large files don't hold up the event loop, and the schemes the app answers
+//! (`pw_register_protocol`, `InitParams::app_dir`). Responses from the
+//! network keep their own headers, and fall back to the engine's built-in
+//! detection.
+
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::thread;
+
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8};
+use http::header::CONTENT_TYPE;
+use http::{HeaderMap, HeaderValue};
+use servo::{WebResourceLoad, WebResourceResponse};
+use url::Url;
+
+/// The encoding for undeclared documents; `None` keeps the engine default
+/// (UTF-8 with auto-detection).
+pub static DEFAULT_ENCODING: Mutex<Option<&'static Encoding>> = Mutex::new(None);
+
+/// How far into a document to look for a `charset` declaration. Matches the
+/// HTML prescan limit.
+const PRESCAN_BYTES: usize = 1024;
+
+/// How much of a `file:` document is read at a time while streaming it.
+const CHUNK_BYTES: usize = 64 * 1024;
+
+/// Look up an encoding by any of its WHATWG labels (`"shift_jis"`,
+/// `"windows-1251"`, `"latin1"`, ...). An empty label means the engine default.
+pub fn parse_label(label: &str) -> Result<Option<&'static Encoding>, ()> {
+    if label.trim().is_empty() {
+        return Ok(None);
+    }
+    Encoding::for_label(label.trim().as_bytes())
+        .map(Some)
+        .ok_or(())
+}
+
+/// Whether a document starting with `head` brings its own encoding: a byte
+/// order mark, or for HTML a `<meta>` declaration the prescan finds.
+fn declares_encoding(head: &[u8], html: bool) -> bool {
+    if Encoding::for_bom(head).is_some() {
+        return true;
+    }
+    let head = &head[..head.len().min(PRESCAN_BYTES)];
+    html && prescan(head).is_some()
+}
+
+/// The encoding `head` declares with `<meta charset>` or
+/// `<meta http-equiv="Content-Type" content="...; charset=...">`, following
+/// the HTML prescan: comments and other tags' attributes are skipped, and
+/// the first declaration of a known encoding counts.
+fn prescan(head: &[u8]) -> Option<&'static Encoding> {
+    let mut pos = 0;
+    while pos < head.len() {
+        let rest = &head[pos..];
+        if rest.starts_with(b"<!--") {
+            let end = find(&head[pos + 2..], b"-->")?;
+            pos += 2 + end + 3;
+        } else if starts_with_ignore_case(rest, b"<meta")
+            && rest
+                .get(5)
+                .is_some_and(|&byte| byte.is_ascii_whitespace() || byte == b'/')
+        {
+            pos += 5;
+            let mut charset = None;
+            let mut http_equiv = false;
+            let mut content = None;
+            while let Some((name, value, next)) = attribute(head, pos) {
+                pos = next;
+                match name.as_str() {
+                    "charset" if charset.is_none() => charset = Some(value),
+                    "http-equiv" => http_equiv = value.eq_ignore_ascii_case("content-type"),
+                    "content" if content.is_none() => content = Some(value),
+                    _ => {}
+                }
+            }
+            let label = charset.or_else(|| {
+                content
+                    .filter(|_| http_equiv)
+                    .and_then(|content| charset_from_content(&content))
+            });
+            if let Some(encoding) = label.and_then(|label| Encoding::for_label(label.as_bytes())) {
+                // A document read as ASCII-compatible bytes can't be UTF-16.
+                if encoding == UTF_16BE || encoding == UTF_16LE {
+                    return Some(UTF_8);
+                }
+                return Some(encoding);
+            }
+        } else if starts_tag(rest) {
+            // Skip the tag name, then its attributes, which may hold `>`
+            // inside quotes.
+            pos += rest
+                .iter()
+                .position(|&byte| byte.is_ascii_whitespace() || byte == b'>')
+                .unwrap_or(rest.len());
+            while let Some((_, _, next)) = attribute(head, pos) {
+                pos = next;
+            }
+        } else if rest.starts_with(b"<!") || rest.starts_with(b"</") || rest.starts_with(b"<?") {
+            pos += find(rest, b">")? + 1;
+        } else {
+            pos += 1;
+        }
+    }
+    None
+}
+
+/// The attribute of a tag starting at `pos`, as its lowercased name, its
+/// value and the position after it. `None` once the tag ends (the position
+/// then being past its `>`) or the bytes run out.
+fn attribute(head: &[u8], mut pos: usize) -> Option<(String, String, usize)> {
+    let at = |pos: usize| head.get(pos).copied();
+    while at(pos).is_some_and(|byte| byte.is_ascii_whitespace() || byte == b'/') {
+        pos += 1;
+    }
+    if at(pos)? == b'>' {
+        return None;
+    }
+    let start = pos;
+    while at(pos).is_some_and(|byte| {
+        !(byte.is_ascii_whitespace() || matches!(byte, b'=' | b'/' | b'>')) || pos == start
+    }) {
+        pos += 1;
+    }
+    let name = String::from_utf8_lossy(&head[start..pos]).to_ascii_lowercase();
+    while at(pos).is_some_and(|byte| byte.is_ascii_whitespace()) {
+        pos += 1;
+    }
+    if at(pos) != Some(b'=') {
+        return Some((name, String::new(), pos));
+    }
+    pos += 1;
+    while at(pos).is_some_and(|byte| byte.is_ascii_whitespace()) {
+        pos += 1;
+    }
+    let (value, next) = match at(pos)? {
+        quote @ (b'"' | b'\'') => {
+            let end = pos + 1 + head[pos + 1..].iter().position(|&byte| byte == quote)?;
+            (&head[pos + 1..end], end + 1)
+        }
+        _ => {
+            let end = pos
+                + head[pos..]
+                    .iter()
+                    .position(|&byte| byte.is_ascii_whitespace() || byte == b'>')
+                    .unwrap_or(head.len() - pos);
+            (&head[pos..end], end)
+        }
+    };
+    Some((name, String::from_utf8_lossy(value).into_owned(), next))
+}
+
+/// The charset named in a `<meta http-equiv>` element's `content`, e.g.
+/// `"text/html; charset=shift_jis"`.
+fn charset_from_content(content: &str) -> Option<String> {
+    let lower = content.to_ascii_lowercase();
+    let mut from = 0;
+    while let Some(found) = lower[from..].find("charset") {
+        let rest = lower[from + found + "charset".len()..].trim_start();
+        from += found + "charset".len();
+        let Some(value) = rest.strip_prefix('=') else {
+            continue;
+        };
+        let value = value.trim_start();
+        let value = match value.chars().next()? {
+            quote @ ('"' | '\'') => &value[1..1 + value[1..].find(quote)?],
+            _ => value
+                .split(|c: char| c == ';' || c.is_ascii_whitespace())
+                .next()?,
+        };
+        return (!value.is_empty()).then(|| value.to_string());
+    }
+    None
+}
+
+/// Whether `bytes` starts with an opening or closing tag (`<p`, `</p`).
+fn starts_tag(bytes: &[u8]) -> bool {
+    let name = bytes
+        .strip_prefix(b"</")
+        .or_else(|| bytes.strip_prefix(b"<"));
+    name.and_then(|name| name.first())
+        .is_some_and(|byte| byte.is_ascii_alphabetic())
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+fn starts_with_ignore_case(bytes: &[u8], prefix: &[u8]) -> bool {
+    bytes.len() >= prefix.len() && bytes[..prefix.len()].eq_ignore_ascii_case(prefix)
+}
+
+/// The MIME type to serve a local document as, if it is a text document the
+/// fallback encoding applies to.
+fn text_mime_type(path: &Path) -> Option<&'static str> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    match extension.as_str() {
+        "html" | "htm" => Some("text/html"),
+        "txt" => Some("text/plain"),
+        _ => None,
+    }
+}
+
+/// The `Content-Type` to answer an app-served document of type `mime_type`
+/// with: naming the fallback encoding if the document is HTML or text that
+/// declares none, either in `mime_type` or in `body`.
+pub fn content_type_with_fallback(mime_type: &str, body: &[u8]) -> Option<String> {
+    let encoding = (*DEFAULT_ENCODING.lock().unwrap())?;
+    let mut parts = mime_type.split(';');
+    let essence = parts.next()?.trim().to_ascii_lowercase();
+    if !matches!(essence.as_str(), "text/html" | "text/plain")
+        || parts.any(|param| param.trim().to_ascii_lowercase().starts_with("charset="))
+        || declares_encoding(body, essence == "text/html")
+    {
+        return None;
+    }
+    Some(format!("{}; charset={}", essence, encoding.name()))
+}
+
+/// Serve `load` with the fallback encoding if it is a local text document.
+/// Hands `load` back untouched otherwise.
+pub fn handle_load(load: WebResourceLoad) -> Option<WebResourceLoad> {
+    let Some(encoding) = *DEFAULT_ENCODING.lock().unwrap() else {
+        return Some(load);
+    };
+    let url = load.request().url.clone();
+    if url.scheme() != "file" {
+        return Some(load);
+    }
+    let Ok(path) = url.to_file_path() else {
+        return Some(load);
+    };
+    let Some(mime_type) = text_mime_type(&path) else {
+        return Some(load);
+    };
+    thread::spawn(move || serve_file(load, url, path, mime_type, encoding));
+    None
+}
+
+/// Answer `load` with the file at `path`, unless it declares its own
+/// encoding. Dropping `load` instead leaves it to Servo, which then also
+/// reports a file that can't be read as usual.
+fn serve_file(
+    load: WebResourceLoad,
+    url: Url,
+    path: PathBuf,
+    mime_type: &str,
+    encoding: &'static Encoding,
+) {
+    let Ok(mut file) = File::open(&path) else {
+        return;
+    };
+    let mut head = Vec::with_capacity(PRESCAN_BYTES);
+    if (&mut file)
+        .take(PRESCAN_BYTES as u64)
+        .read_to_end(&mut head)
+        .is_err()
+        || declares_encoding(&head, mime_type == "text/html")
+    {
+        return;
+    }
+
+    let content_type = format!("{}; charset={}", mime_type, encoding.name());
+    let Ok(content_type) = HeaderValue::from_str(&content_type) else {
+        return;
+    };
+    let mut headers = HeaderMap::new();
+    headers.insert(CONTENT_TYPE, content_type);
+    let intercepted = load.intercept(WebResourceResponse::new(url).headers(headers));
+    intercepted.send_body_data(head);
+    let mut chunk = vec![0; CHUNK_BYTES];
+    loop {
+        match file.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(read) => intercepted.send_body_data(chunk[..read].to_vec()),
+            Err(e) => {
+                log::warn!("Failed to read {:?}: {}", path, e);
+                intercepted.cancel();
+                return;
+            }
+        }
+    }
+    intercepted.finish();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_meta_declarations() {
+        assert_eq!(
+            prescan(b"<meta charset=shift_jis>").map(Encoding::name),
+            Some("Shift_JIS")
+        );
+        assert_eq!(
+            prescan(
+                b"<head><META HTTP-EQUIV='Content-Type' CONTENT='text/html; charset=\"koi8-r\"'>"
+            )
+            .map(Encoding::name),
+            Some("KOI8-R")
+        );
+        assert_eq!(prescan(b"<meta charset=\"utf-16le\">"), Some(UTF_8));
+    }
+
+    #[test]
+    fn ignores_charset_outside_meta() {
+        assert_eq!(prescan(b"<p>Set charset=utf-8 in the header</p>"), None);
+        assert_eq!(prescan(b"<!-- <meta charset=utf-8> --><p>"), None);
+        assert_eq!(prescan(b"<div title='<meta charset=utf-8>'>"), None);
+        assert_eq!(prescan(b"<meta content='text/html; charset=utf-8'>"), None);
+        assert_eq!(prescan(b"<meta charset=bogus>"), None);
+    }
+
+    #[test]
+    fn only_looks_at_the_start() {
+        let mut late = vec![b' '; PRESCAN_BYTES];
+        late.extend_from_slice(b"<meta charset=utf-8>");
+        assert!(!declares_encoding(&late, true));
+        assert!(declares_encoding(b"\xEF\xBB\xBFplain", false));
+        assert!(!declares_encoding(b"<meta charset=utf-8>", false));
+    }
+}
@@ -0,0 +1,80 @@
+//! The most recent failure, kept for `pw_last_error`.
+//!
+//! Return codes only say which kind of failure happened; the message here
+//! says why, e.g. which resource files are missing.
+
+use std::sync::Mutex;
+
+/// The codes `pw_*` functions return on failure; `0` is success. Functions
+/// that return a count, an id or a pointer say so in their own docs. Values
+/// are part of the FFI contract: never renumber them, only append.
+#[repr(i32)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ErrorCode {
+    /// A required pointer argument was null.
+    NullArgument = -1,
+    /// The event loop has exited: the app closed or is shutting down.
+    LoopExited = -2,
+    /// `pw_start_app` hasn't been called yet.
+    NotStarted = -3,
+    /// An argument was out of range, unknown or couldn't be parsed.
+    InvalidArgument = -4,
+    /// The event loop didn't answer within 2 seconds, e.g. because the call
+    /// came from an event callback, which runs on the event loop itself.
+    Timeout = -5,
+    /// What was asked for isn't available right now.
+    Unavailable = -6,
+    /// The call is only allowed before `pw_start_app`, or `pw_start_app`
+    /// was called a second time.
+    AlreadyStarted = -7,
+    /// Servo's engine resources are missing.
+    MissingResources = -8,
+    /// The single-instance lock couldn't be created.
+    LockFailed = -9,
+    /// A file couldn't be written.
+    WriteFailed = -10,
+    /// The window, or its OpenGL context, couldn't be created, e.g. without
+    /// a display.
+    WindowFailed = -11,
+    /// The runtime panicked; `pw_last_error` has the panic message.
+    Panicked = -12,
+    /// The call was made on the wrong thread: `pw_start_app` off the main
+    /// thread on macOS, or `pw_poll_events` off the thread that started the
+    /// app.
+    WrongThread = -13,
+}
+
+impl From<ErrorCode> for i32 {
+    fn from(code: ErrorCode) -> i32 {
+        code as i32
+    }
+}
+
+static LAST_ERROR: Mutex<Option<String>> = Mutex::new(None);
+
+/// Record `message` as the most recent failure, and log it.
+pub fn set_last_error(message: impl Into<String>) {
+    let message = message.into();
+    // Failures before `pw_start_app` set the level are logged too.
+    crate::logging::install();
+    log::error!("{}", message);
+    *LAST_ERROR.lock().unwrap() = Some(message);
+}
+
+/// Record `message` as the most recent failure without logging it, for
+/// failures callers expect and handle, like calls before `pw_start_app`.
+pub fn set_last_error_quietly(message: impl Into<String>) {
+    *LAST_ERROR.lock().unwrap() = Some(message.into());
+}
+
+/// Record that the pointer argument `name` was null, and return
+/// [`ErrorCode::NullArgument`] for the `pw_*` function to pass on.
+pub fn null_argument(name: &str) -> i32 {
+    set_last_error(format!("{} must not be null", name));
+    ErrorCode::NullArgument.into()
+}
+
+/// The most recent failure, if any.
+pub fn last_error() -> Option<String> {
+    LAST_ERROR.lock().unwrap().clone()
+}
@@ -0,0 +1,238 @@
+//! Delivering bridge events to native listeners.
+//!
+//! Every event — messages the page sends with `pywire.send` and the shell's
+//! own `{"type": ...}` notifications — goes to every registered listener, in
+//! registration order. Listeners run synchronously on the event loop thread,
+//! so a slow listener stalls rendering and input; hand heavy work off to
+//! another thread.
+//!
+//! Plain listeners receive only the payload. Typed listeners also receive the
+//! event's [`EventType`], so they can skip parsing payloads they don't need.
+//! Named listeners only receive events of one kind, picked by category
+//! (`"load"`) or by the payload's `type` (`"load_status"`).
+//!
+//! Messages go the other way with `pw_post_message`, which the page receives
+//! as `pywire-message` events (see `js/post_message.js`).
+
+use std::ffi::CString;
+use std::os::raw::c_char;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Mutex;
+
+const POST_MESSAGE_JS: &str = include_str!("js/post_message.js");
+
+pub type EventCallback = extern "C" fn(*const c_char);
+pub type TypedEventCallback = extern "C" fn(event_type: u32, payload: *const c_char);
+
+/// Stable numeric event categories passed to typed listeners. Values are
+/// part of the FFI contract: never renumber them, only append.
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EventType {
+    /// A message the page sent with `pywire.send(channel, data)`: `message`,
+    /// or `message_error` if it was malformed.
+    Bridge = 0,
+    /// Load lifecycle: `load_status`, `load_timeout`, `load_stopped`.
+    Load = 1,
+    /// The page's `document.title` changed: `title`, its icon: `favicon`, or
+    /// the status bar text, such as a hovered link's target: `status_text`.
+    Title = 2,
+    /// Console output from the page: `console`.
+    Console = 3,
+    /// Navigation and URL changes: `url_changed`, `new_window`, loads that
+    /// failed: `navigation_failed`, and session history changes:
+    /// `history_changed`.
+    Navigation = 4,
+    /// The window was asked to close: `close_requested`.
+    Close = 5,
+    /// The effective page zoom changed: `zoom`, or `pinch_zoom` for the
+    /// visual zoom.
+    Zoom = 6,
+    /// Request-level network policy actions: `mixed_content_blocked`, HTTP
+    /// authentication challenges: `auth_required`, and TLS certificate
+    /// failures: `cert_error`.
+    Network = 7,
+    /// Another launch of a single-instance app: `second_instance`.
+    Instance = 8,
+    /// Window state changes: `fullscreen`, `scale_factor`, and the webviews
+    /// it hosts: `webview_activated`, `webview_closed`.
+    Window = 9,
+    /// Persistent storage problems: `profile_unavailable`.
+    Storage = 10,
+    /// The devtools server: `devtools_started`, `devtools_failed`.
+    Devtools = 11,
+    /// User input the page left to the app: `context_menu`, files dragged
+    /// onto the window: `file_hover`, `file_dropped`, and shortcuts the app
+    /// registered: `accelerator`.
+    Input = 12,
+    /// A page asked for a permission such as geolocation: `permission`, and
+    /// `permission_timeout` when nobody answered it.
+    Permission = 13,
+    /// Printing to PDF: `pdf_printed`, `print_failed`.
+    Print = 14,
+    /// Find-in-page: `find_result`, and `find_cleared` when a navigation
+    /// ended the search.
+    Find = 15,
+    /// Engine resources that couldn't be read and were replaced by empty
+    /// files: `resource_missing`.
+    Resource = 16,
+    /// Memory use reports from `pw_get_memory_report`: `memory_report`,
+    /// `memory_report_failed`.
+    Memory = 17,
+    /// A page's content, an engine thread or the engine crashed: `crash`.
+    /// With `InitParams::watchdog_timeout_ms`, also `unresponsive` for a
+    /// blank or hung page and `responsive` once it recovers.
+    Crash = 18,
+}
+
+impl EventType {
+    /// The category's name for [`add_named_listener`].
+    pub fn name(self) -> &'static str {
+        match self {
+            EventType::Bridge => "bridge",
+            EventType::Load => "load",
+            EventType::Title => "title",
+            EventType::Console => "console",
+            EventType::Navigation => "navigation",
+            EventType::Close => "close",
+            EventType::Zoom => "zoom",
+            EventType::Network => "network",
+            EventType::Instance => "instance",
+            EventType::Window => "window",
+            EventType::Storage => "storage",
+            EventType::Devtools => "devtools",
+            EventType::Input => "input",
+            EventType::Permission => "permission",
+            EventType::Print => "print",
+            EventType::Find => "find",
+            EventType::Resource => "resource",
+            EventType::Memory => "memory",
+            EventType::Crash => "crash",
+        }
+    }
+}
+
+#[derive(Clone)]
+enum Listener {
+    Plain(EventCallback),
+    Typed(TypedEventCallback),
+    Named(String, EventCallback),
+}
+
+static LISTENERS: Mutex<Vec<(u32, Listener)>> = Mutex::new(Vec::new());
+
+/// Tokens start at 1 so that 0 can signal failure across the FFI.
+static NEXT_TOKEN: AtomicU32 = AtomicU32::new(1);
+
+fn add(listener: Listener) -> u32 {
+    let token = NEXT_TOKEN.fetch_add(1, Ordering::Relaxed);
+    LISTENERS.lock().unwrap().push((token, listener));
+    token
+}
+
+/// Register `callback` for every subsequent event.
+pub fn add_listener(callback: EventCallback) -> u32 {
+    add(Listener::Plain(callback))
+}
+
+/// Register `callback` for every subsequent event, with its [`EventType`].
+pub fn add_typed_listener(callback: TypedEventCallback) -> u32 {
+    add(Listener::Typed(callback))
+}
+
+/// Register `callback` for the events whose category or `type` is `name`.
+pub fn add_named_listener(name: String, callback: EventCallback) -> u32 {
+    add(Listener::Named(name, callback))
+}
+
+/// Unregister the listener behind `token`. Returns false for unknown tokens.
+pub fn remove_listener(token: u32) -> bool {
+    let mut listeners = LISTENERS.lock().unwrap();
+    let before = listeners.len();
+    listeners.retain(|(listener, _)| *listener != token);
+    listeners.len() != before
+}
+
+fn dispatch(event_type: EventType, kind: Option<&str>, payload: &str) {
+    let Ok(c_payload) = CString::new(payload) else {
+        return;
+    };
+    // Call outside the lock so listeners may add or remove listeners.
+    let listeners: Vec<Listener> = LISTENERS
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(_, listener)| listener.clone())
+        .collect();
+    for listener in listeners {
+        match listener {
+            Listener::Plain(callback) => callback(c_payload.as_ptr()),
+            Listener::Typed(callback) => callback(event_type as u32, c_payload.as_ptr()),
+            Listener::Named(name, callback) => {
+                if name == event_type.name() || Some(name.as_str()) == kind {
+                    callback(c_payload.as_ptr());
+                }
+            }
+        }
+    }
+}
+
+/// The script that delivers a message from the app to the page, with
+/// `channel` and `data` JSON-encoded so neither can break out of it.
+pub fn post_message_script(channel: &str, data: &serde_json::Value) -> String {
+    format!(
+        "{POST_MESSAGE_JS}({}, {})",
+        serde_json::to_string(channel).unwrap(),
+        data
+    )
+}
+
+/// Validate a bridge message, `{"channel": "...", "data": ...}`, and wrap it
+/// in the envelope listeners receive.
+fn parse_bridge_message(payload: &str) -> Result<serde_json::Value, String> {
+    let message: serde_json::Value =
+        serde_json::from_str(payload).map_err(|e| format!("invalid JSON: {}", e))?;
+    let channel = match message.get("channel") {
+        Some(serde_json::Value::String(channel)) if !channel.is_empty() => channel,
+        _ => return Err("missing \"channel\" string".to_string()),
+    };
+    let Some(data) = message.get("data") else {
+        return Err("missing \"data\" field".to_string());
+    };
+    Ok(serde_json::json!({
+        "type": "message",
+        "channel": channel,
+        "data": data,
+    }))
+}
+
+/// Forward a message from the page (the text after its `PW_MSG:` marker) to
+/// every listener as a `message` event, or report it as `message_error`.
+pub fn emit_bridge_message(payload: &str) {
+    let event = parse_bridge_message(payload).unwrap_or_else(|error| {
+        serde_json::json!({
+            "type": "message_error",
+            "error": error,
+            "raw": payload,
+        })
+    });
+    emit_event(EventType::Bridge, event);
+}
+
+/// Forward a shell-originated event (`{"type": ..., ...}`) to every listener.
+pub fn emit_event(event_type: EventType, event: serde_json::Value) {
+    let kind = event.get("type").and_then(serde_json::Value::as_str);
+    dispatch(event_type, kind, &event.to_string());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn posted_messages_are_json_encoded() {
+        let data = serde_json::json!({"text": "\"); alert(1); ("});
+        let script = post_message_script("chat\"", &data);
+        assert!(script.ends_with(r#"("chat\"", {"text":"\"); alert(1); ("})"#));
+    }
+}
@@ -0,0 +1,77 @@
+//! The page's icon, sent to Python as `favicon` events for tab bars.
+//!
+//! Servo fetches and decodes the icon the page links to itself, `data:` URLs
+//! included, and reports it decoded. From an icon file holding several sizes
+//! (`.ico`) it decodes the largest. The shell passes it on as a PNG, scaled
+//! down to [`MAX_SIZE`] if it is larger, so Python needn't fetch or decode
+//! anything.
+
+use base64::Engine;
+use serde_json::{json, Value};
+use servo::{Image, PixelFormat};
+
+use crate::capture::Frame;
+
+/// Largest edge of a reported icon, in pixels. Bigger icons are scaled down.
+pub const MAX_SIZE: u32 = 256;
+
+/// `data` in `format` as RGBA pixels.
+fn to_rgba(format: PixelFormat, data: &[u8]) -> Vec<u8> {
+    match format {
+        PixelFormat::RGBA8 => data.to_vec(),
+        PixelFormat::BGRA8 => data
+            .chunks_exact(4)
+            .flat_map(|pixel| [pixel[2], pixel[1], pixel[0], pixel[3]])
+            .collect(),
+        PixelFormat::RGB8 => data
+            .chunks_exact(3)
+            .flat_map(|pixel| [pixel[0], pixel[1], pixel[2], 255])
+            .collect(),
+        PixelFormat::KA8 => data
+            .chunks_exact(2)
+            .flat_map(|pixel| [pixel[0], pixel[0], pixel[0], pixel[1]])
+            .collect(),
+        PixelFormat::K8 => data.iter().flat_map(|&k| [k, k, k, 255]).collect(),
+    }
+}
+
+/// The `favicon` event for the page's icon: the PNG in base64 under `data`,
+/// with `format`, `width` and `height`. `data` is null when the page has no
+/// icon.
+pub fn event(icon: Option<&Image>) -> Value {
+    let Some(icon) = icon else {
+        return json!({"type": "favicon", "data": null});
+    };
+    let frame = Frame {
+        width: icon.width,
+        height: icon.height,
+        rgba: to_rgba(icon.format, icon.data()),
+    }
+    .scaled_to_fit(MAX_SIZE);
+    match frame.encode_png() {
+        Ok(png) => json!({
+            "type": "favicon",
+            "data": base64::engine::general_purpose::STANDARD.encode(png),
+            "format": "png",
+            "width": frame.width,
+            "height": frame.height,
+        }),
+        Err(e) => {
+            log::warn!("Failed to encode the page's icon: {}", e);
+            json!({"type": "favicon", "data": null})
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_pixels_to_rgba() {
+        assert_eq!(to_rgba(PixelFormat::BGRA8, &[1, 2, 3, 4]), [3, 2, 1, 4]);
+        assert_eq!(to_rgba(PixelFormat::RGB8, &[1, 2, 3]), [1, 2, 3, 255]);
+        assert_eq!(to_rgba(PixelFormat::KA8, &[7, 8]), [7, 7, 7, 8]);
+        assert_eq!(to_rgba(PixelFormat::K8, &[9]), [9, 9, 9, 255]);
+    }
+}
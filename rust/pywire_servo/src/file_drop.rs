@@ -0,0 +1,89 @@
+//! Files dragged onto the window.
+//!
+//! winit reports a drag of several files as one `HoveredFile` or
+//! `DroppedFile` event per file, delivered back to back. [`FileBatch`] gathers
+//! the ones that arrive within [`BATCH_WINDOW`] of each other, so Python sees
+//! a single `file_hover` or `file_dropped` event per drag.
+//!
+//! Servo has no input event for dragging files into a page, so drops are
+//! only reported to Python; the page never sees them.
+
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// How long after a file the next one still counts as part of the same drag.
+pub const BATCH_WINDOW: Duration = Duration::from_millis(50);
+
+#[derive(Default)]
+pub struct FileBatch {
+    paths: Vec<PathBuf>,
+    /// When the batch is complete, while it has paths.
+    deadline: Option<Instant>,
+}
+
+impl FileBatch {
+    pub fn add(&mut self, path: PathBuf, now: Instant) {
+        self.paths.push(path);
+        self.deadline = Some(now + BATCH_WINDOW);
+    }
+
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    /// Drop the paths gathered so far.
+    pub fn discard(&mut self) {
+        *self = FileBatch::default();
+    }
+
+    /// The batch's paths, once no more have arrived for [`BATCH_WINDOW`].
+    pub fn take_complete(&mut self, now: Instant) -> Option<Vec<PathBuf>> {
+        if self.deadline? > now {
+            return None;
+        }
+        self.deadline = None;
+        Some(std::mem::take(&mut self.paths))
+    }
+}
+
+/// Paths as Python receives them.
+pub fn path_strings(paths: &[PathBuf]) -> Vec<String> {
+    paths
+        .iter()
+        .map(|path| path.to_string_lossy().into_owned())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn files_in_quick_succession_make_one_batch() {
+        let start = Instant::now();
+        let mut batch = FileBatch::default();
+        assert_eq!(batch.take_complete(start), None);
+
+        batch.add(PathBuf::from("a.mp4"), start);
+        batch.add(PathBuf::from("b.mp4"), start + Duration::from_millis(30));
+        assert_eq!(batch.take_complete(start + BATCH_WINDOW), None);
+
+        let done = start + Duration::from_millis(30) + BATCH_WINDOW;
+        assert_eq!(batch.deadline(), Some(done));
+        assert_eq!(
+            batch.take_complete(done),
+            Some(vec![PathBuf::from("a.mp4"), PathBuf::from("b.mp4")])
+        );
+        assert_eq!(batch.deadline(), None);
+        assert_eq!(batch.take_complete(done), None);
+    }
+
+    #[test]
+    fn discarded_paths_are_not_reported() {
+        let start = Instant::now();
+        let mut batch = FileBatch::default();
+        batch.add(PathBuf::from("a.mp4"), start);
+        batch.discard();
+        assert_eq!(batch.take_complete(start + BATCH_WINDOW), None);
+    }
+}
@@ -0,0 +1,55 @@
+//! Native file dialogs for `<input type=file>` and `window.print()`.
+//!
+//! Servo turns the input's `accept` attribute into file extension filters and
+//! tells us whether `multiple` is set. Dialogs are modal and run on the event
+//! loop thread.
+
+use std::path::PathBuf;
+
+use servo::FilePicker;
+use winit::window::Window;
+
+/// Ask the user for files to answer `picker` with, or dismiss it if they
+/// cancel. Without a window there is nothing to anchor a dialog to, so
+/// headless apps always dismiss.
+pub fn show(picker: FilePicker, window: Option<&Window>) {
+    let Some(window) = window else {
+        picker.dismiss();
+        return;
+    };
+
+    let mut dialog = rfd::FileDialog::new().set_parent(window);
+    let extensions: Vec<String> = picker
+        .filter_patterns()
+        .iter()
+        .map(|pattern| pattern.0.trim_start_matches('.').to_string())
+        .filter(|extension| !extension.is_empty())
+        .collect();
+    if !extensions.is_empty() {
+        dialog = dialog.add_filter("Accepted files", &extensions);
+    }
+
+    let paths: Option<Vec<PathBuf>> = if picker.allow_select_multiple() {
+        dialog.pick_files()
+    } else {
+        dialog.pick_file().map(|path| vec![path])
+    };
+    match paths {
+        Some(paths) if !paths.is_empty() => {
+            picker.select(&paths);
+            picker.submit();
+        }
+        _ => picker.dismiss(),
+    }
+}
+
+/// Ask the user where to save a page printed with `window.print()`. Returns
+/// `None` if they cancel, or without a window.
+pub fn save_pdf(window: Option<&Window>, file_name: &str) -> Option<PathBuf> {
+    let window = window?;
+    rfd::FileDialog::new()
+        .set_parent(window)
+        .add_filter("PDF document", &["pdf"])
+        .set_file_name(file_name)
+        .save_file()
+}
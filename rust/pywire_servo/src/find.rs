@@ -0,0 +1,56 @@
+//! Find-in-page.
+//!
+//! Servo has no find-in-page of its own, so `pw_find` runs a script that
+//! searches the page's text and highlights the matches in an overlay (see
+//! `js/find.js`). Each search reports its match count and the current match
+//! as a `find_result` event; repeating a search steps through the matches.
+
+use servo::JSValue;
+
+const FIND_JS: &str = include_str!("js/find.js");
+
+/// Removes the highlights. Evaluates to whether a search was showing.
+pub const CLEAR_JS: &str = "window.__pywireFind ? window.__pywireFind.clear() : false";
+
+/// The script that searches for `text`, moving to the next match (or the
+/// previous one unless `forward`) if `text` is what was last searched for.
+pub fn find_script(text: &str, forward: bool, case_sensitive: bool) -> String {
+    format!(
+        "{FIND_JS}\nwindow.__pywireFind.find({}, {forward}, {case_sensitive})",
+        serde_json::to_string(text).unwrap()
+    )
+}
+
+/// The match count and current match index a find script evaluated to.
+pub fn parse_result(result: &JSValue) -> Option<(u32, Option<u32>)> {
+    let JSValue::Array(values) = result else {
+        return None;
+    };
+    match values.as_slice() {
+        [JSValue::Number(count), JSValue::Number(index)] if *count >= 0.0 => {
+            let index = (*index >= 0.0).then_some(*index as u32);
+            Some((*count as u32, index))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn search_text_is_quoted() {
+        let script = find_script("it's \"quoted\"\n", false, true);
+        assert!(script.ends_with(r#"window.__pywireFind.find("it's \"quoted\"\n", false, true)"#));
+    }
+
+    #[test]
+    fn parses_match_count_and_index() {
+        let result =
+            |count, index| JSValue::Array(vec![JSValue::Number(count), JSValue::Number(index)]);
+        assert_eq!(parse_result(&result(3.0, 1.0)), Some((3, Some(1))));
+        assert_eq!(parse_result(&result(0.0, -1.0)), Some((0, None)));
+        assert_eq!(parse_result(&JSValue::Undefined), None);
+    }
+}
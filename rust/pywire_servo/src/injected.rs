@@ -0,0 +1,209 @@
+//! Scripts the shell injects into every page it loads.
+//!
+//! Injections are (re-)applied each time a document's head has been parsed,
+//! so they survive navigations. Each script is idempotent: running it again on
+//! the same document only updates its settings.
+//!
+//! User scripts from `pw_add_user_script` are different: Servo runs them at
+//! document start, before any of the page's own scripts, through its user
+//! content manager.
+
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use servo::{Servo, UserContentManager, UserScript, WebView};
+
+const BRIDGE_JS: &str = include_str!("js/bridge.js");
+const SPATIAL_NAVIGATION_JS: &str = include_str!("js/spatial_navigation.js");
+const MEDIA_CONTROL_JS: &str = include_str!("js/media_control.js");
+const AUDIO_CONTROL_JS: &str = include_str!("js/audio_control.js");
+const AUTOPLAY_JS: &str = include_str!("js/autoplay.js");
+const PRIVACY_SIGNALS_JS: &str = include_str!("js/privacy_signals.js");
+const PRINT_JS: &str = include_str!("js/print.js");
+const WINDOW_DRAG_JS: &str = include_str!("js/window_drag.js");
+
+/// Whether arrow-key spatial navigation is enabled.
+pub static SPATIAL_NAVIGATION: AtomicBool = AtomicBool::new(false);
+
+/// Whether all media in the page is held paused.
+pub static MEDIA_PAUSED: AtomicBool = AtomicBool::new(false);
+
+/// Whether the page's media is muted. Servo has no audio controls for a
+/// webview, so this and [`VOLUME`] apply to the page's media elements.
+pub static MUTED: AtomicBool = AtomicBool::new(false);
+
+/// The volume the page's media plays at, relative to its own, from 0 to 1.
+pub static VOLUME: Mutex<f32> = Mutex::new(1.0);
+
+/// Whether pages see `navigator.doNotTrack == "1"`. Off by default, as in
+/// browsers. Only scripts see it: Servo has no way for the embedder to add the
+/// `DNT` request header, so servers don't.
+pub static DO_NOT_TRACK: AtomicBool = AtomicBool::new(false);
+
+/// Whether pages see `navigator.globalPrivacyControl == true`. Like
+/// [`DO_NOT_TRACK`], no `Sec-GPC` request header is sent.
+pub static GLOBAL_PRIVACY_CONTROL: AtomicBool = AtomicBool::new(false);
+
+/// Which media may start playing before the user has interacted with the
+/// page. Servo has no autoplay policy of its own and allows everything, so
+/// the others are enforced by a script.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AutoplayPolicy {
+    Allow,
+    /// Media without sound (muted, or at volume 0) may autoplay.
+    BlockAudio,
+    Block,
+}
+
+impl AutoplayPolicy {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "allow" => Some(Self::Allow),
+            "block-audio" => Some(Self::BlockAudio),
+            "block" => Some(Self::Block),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Allow => "allow",
+            Self::BlockAudio => "block-audio",
+            Self::Block => "block",
+        }
+    }
+}
+
+/// Set from `InitParams::autoplay_policy` before the first page loads.
+pub static AUTOPLAY_POLICY: Mutex<AutoplayPolicy> = Mutex::new(AutoplayPolicy::Allow);
+
+/// High-contrast focus indicator used when the focus ring is simply enabled:
+/// a bright outline with a dark halo, visible on both light and dark pages.
+pub const DEFAULT_FOCUS_RING_CSS: &str = "outline: 3px solid #ffbf47 !important; \
+     outline-offset: 2px !important; \
+     box-shadow: 0 0 0 5px #0b0c0c !important;";
+
+/// CSS declarations forced onto focused elements, or `None` to leave the
+/// page's own focus styling alone.
+pub static FOCUS_RING_CSS: Mutex<Option<String>> = Mutex::new(None);
+
+/// Scripts registered with `pw_add_user_script`, in registration order.
+pub static USER_SCRIPTS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// A user content manager that runs every registered user script, for a new
+/// webview.
+pub fn user_content_manager(servo: &Servo) -> Rc<UserContentManager> {
+    let manager = Rc::new(UserContentManager::new(servo));
+    for script in USER_SCRIPTS.lock().unwrap().iter() {
+        manager.add_script(Rc::new(UserScript::new(script.clone(), None)));
+    }
+    manager
+}
+
+/// Register a user script, and hand it to `manager` if a webview already
+/// uses one. Runs from the next document on.
+pub fn add_user_script(manager: Option<&UserContentManager>, script: String) {
+    if let Some(manager) = manager {
+        manager.add_script(Rc::new(UserScript::new(script.clone(), None)));
+    }
+    USER_SCRIPTS.lock().unwrap().push(script);
+}
+
+/// Insert, update or remove the focus ring user stylesheet.
+fn focus_ring_script(css: Option<&str>) -> String {
+    // The selectors are separate rules so an engine without `:focus-visible`
+    // support still applies the `:focus` one.
+    let rules = css.map(|css| format!(":focus {{ {css} }}\n:focus-visible {{ {css} }}"));
+    format!(
+        "(function (rules) {{\
+           let style = document.getElementById('__pywire-focus-ring');\
+           if (rules === null) {{ if (style) style.remove(); return; }}\
+           if (!style) {{\
+             style = document.createElement('style');\
+             style.id = '__pywire-focus-ring';\
+             (document.head || document.documentElement).appendChild(style);\
+           }}\
+           style.textContent = rules;\
+         }})({});",
+        serde_json::to_string(&rules).unwrap()
+    )
+}
+
+/// Apply all injections to the document currently loaded in `webview`.
+pub fn inject_into(webview: &WebView) {
+    webview.evaluate_javascript(BRIDGE_JS, |_result| {});
+    webview.evaluate_javascript(PRINT_JS, |_result| {});
+    webview.evaluate_javascript(WINDOW_DRAG_JS, |_result| {});
+
+    if SPATIAL_NAVIGATION.load(Ordering::Relaxed) {
+        webview.evaluate_javascript(
+            SPATIAL_NAVIGATION_JS.replace("__ENABLED__", "true"),
+            |_result| {},
+        );
+    } else {
+        // Only switch off a handler a previous call installed; don't add one.
+        webview.evaluate_javascript(
+            "if (window.__pywireSpatialNavigation) {\
+               window.__pywireSpatialNavigation.enabled = false;\
+             }",
+            |_result| {},
+        );
+    }
+
+    // Injected with the head, before the body's media can start.
+    let autoplay = *AUTOPLAY_POLICY.lock().unwrap();
+    if autoplay != AutoplayPolicy::Allow {
+        let policy = serde_json::to_string(autoplay.name()).unwrap();
+        webview.evaluate_javascript(AUTOPLAY_JS.replace("__POLICY__", &policy), |_result| {});
+    }
+
+    if MEDIA_PAUSED.load(Ordering::Relaxed) {
+        webview.evaluate_javascript(MEDIA_CONTROL_JS.replace("__PAUSED__", "true"), |_result| {});
+    } else {
+        // Resuming only matters on a page that was paused.
+        webview.evaluate_javascript(
+            format!(
+                "if (window.__pywireMedia) {}",
+                MEDIA_CONTROL_JS.replace("__PAUSED__", "false")
+            ),
+            |_result| {},
+        );
+    }
+
+    let muted = MUTED.load(Ordering::Relaxed);
+    let volume = *VOLUME.lock().unwrap();
+    let script = AUDIO_CONTROL_JS
+        .replace("__MUTED__", &muted.to_string())
+        .replace("__VOLUME__", &volume.to_string());
+    if muted || volume < 1.0 {
+        webview.evaluate_javascript(script, |_result| {});
+    } else {
+        // Restoring full volume only matters on a page that was turned down.
+        webview.evaluate_javascript(
+            format!("if (window.__pywireAudio) {}", script),
+            |_result| {},
+        );
+    }
+
+    let do_not_track = DO_NOT_TRACK.load(Ordering::Relaxed);
+    let global_privacy_control = GLOBAL_PRIVACY_CONTROL.load(Ordering::Relaxed);
+    let script = PRIVACY_SIGNALS_JS
+        .replace("__DO_NOT_TRACK__", &do_not_track.to_string())
+        .replace(
+            "__GLOBAL_PRIVACY_CONTROL__",
+            &global_privacy_control.to_string(),
+        );
+    if do_not_track || global_privacy_control {
+        webview.evaluate_javascript(script, |_result| {});
+    } else {
+        // Leave the engine's own values alone unless we overrode them.
+        webview.evaluate_javascript(
+            format!("if (window.__pywirePrivacySignals) {}", script),
+            |_result| {},
+        );
+    }
+
+    let css = FOCUS_RING_CSS.lock().unwrap().clone();
+    webview.evaluate_javascript(focus_ring_script(css.as_deref()), |_result| {});
+}
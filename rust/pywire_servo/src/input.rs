@@ -0,0 +1,146 @@
+//! Translating window input into Servo's input events.
+//!
+//! The window's mouse, touch and keyboard events and the synthetic input
+//! from `pw_send_*` go through the same functions here, so both reach the
+//! page alike. Keys are translated by `keyutils` and wheel distances by
+//! `scroll`; this module covers the rest.
+
+use servo::{EditingActionEvent, KeyboardEvent, MouseButton as ServoMouseButton};
+use servo::{MouseButtonAction, TouchEventType};
+use winit::event::{ElementState, MouseButton, TouchPhase};
+
+pub fn mouse_button_from_winit(button: MouseButton) -> ServoMouseButton {
+    match button {
+        MouseButton::Left => ServoMouseButton::Left,
+        MouseButton::Right => ServoMouseButton::Right,
+        MouseButton::Middle => ServoMouseButton::Middle,
+        MouseButton::Back => ServoMouseButton::Back,
+        MouseButton::Forward => ServoMouseButton::Forward,
+        MouseButton::Other(v) => ServoMouseButton::Other(v),
+    }
+}
+
+pub fn mouse_button_action_from_winit(state: ElementState) -> MouseButtonAction {
+    match state {
+        ElementState::Pressed => MouseButtonAction::Down,
+        ElementState::Released => MouseButtonAction::Up,
+    }
+}
+
+/// The button called `name` in `pw_send_mouse_click`.
+pub fn mouse_button_from_name(name: &str) -> Option<ServoMouseButton> {
+    match name {
+        "left" => Some(ServoMouseButton::Left),
+        "middle" => Some(ServoMouseButton::Middle),
+        "right" => Some(ServoMouseButton::Right),
+        "back" => Some(ServoMouseButton::Back),
+        "forward" => Some(ServoMouseButton::Forward),
+        _ => None,
+    }
+}
+
+/// The buttons held after `action` on `button`, as a DOM `MouseEvent.buttons`
+/// mask, given those held before. Buttons the DOM has no bit for are left
+/// out.
+pub fn pressed_buttons(held: u16, action: MouseButtonAction, button: ServoMouseButton) -> u16 {
+    let mask = match button {
+        ServoMouseButton::Left => 1,
+        ServoMouseButton::Right => 2,
+        ServoMouseButton::Middle => 4,
+        ServoMouseButton::Back => 8,
+        ServoMouseButton::Forward => 16,
+        _ => 0,
+    };
+    if action == MouseButtonAction::Down {
+        held | mask
+    } else {
+        held & !mask
+    }
+}
+
+pub fn touch_event_type_from_winit(phase: TouchPhase) -> TouchEventType {
+    match phase {
+        TouchPhase::Started => TouchEventType::Down,
+        TouchPhase::Moved => TouchEventType::Move,
+        TouchPhase::Ended => TouchEventType::Up,
+        TouchPhase::Cancelled => TouchEventType::Cancel,
+    }
+}
+
+/// The clipboard action Cmd/Ctrl+C, X or V stand for, which the page gets
+/// instead of the key press.
+pub fn editing_action(event: &KeyboardEvent) -> Option<EditingActionEvent> {
+    let key = &event.event;
+    let cmd_or_ctrl = key.modifiers.contains(servo::Modifiers::CONTROL)
+        || key.modifiers.contains(servo::Modifiers::META);
+    if key.state != servo::KeyState::Down || !cmd_or_ctrl {
+        return None;
+    }
+    match &key.key {
+        servo::Key::Character(c) if c.eq_ignore_ascii_case("c") => Some(EditingActionEvent::Copy),
+        servo::Key::Character(c) if c.eq_ignore_ascii_case("x") => Some(EditingActionEvent::Cut),
+        servo::Key::Character(c) if c.eq_ignore_ascii_case("v") => Some(EditingActionEvent::Paste),
+        _ => None,
+    }
+}
+
+/// The wheel delta for scrolling `(dx, dy)` CSS pixels, positive down and
+/// right as with `window.scrollBy`, at `scale` device pixels per CSS pixel.
+/// Servo takes wheel deltas the way winit reports them, with positive values
+/// scrolling up and left.
+pub fn wheel_delta_from_css(dx: f64, dy: f64, scale: f64) -> (f64, f64) {
+    (-dx * scale, -dy * scale)
+}
+
+#[cfg(test)]
+mod tests {
+    use keyboard_types::{Code, Key, KeyState, Modifiers};
+
+    use super::*;
+    use crate::keyutils::synthetic_keyboard_event;
+
+    #[test]
+    fn tracks_held_buttons() {
+        let held = pressed_buttons(0, MouseButtonAction::Down, ServoMouseButton::Left);
+        let held = pressed_buttons(held, MouseButtonAction::Down, ServoMouseButton::Right);
+        assert_eq!(held, 3);
+        let held = pressed_buttons(held, MouseButtonAction::Up, ServoMouseButton::Left);
+        assert_eq!(held, 2);
+        assert_eq!(
+            pressed_buttons(held, MouseButtonAction::Down, ServoMouseButton::Other(9)),
+            2
+        );
+    }
+
+    #[test]
+    fn maps_mouse_buttons() {
+        assert_eq!(
+            mouse_button_from_winit(MouseButton::Middle),
+            ServoMouseButton::Middle
+        );
+        assert_eq!(
+            mouse_button_from_name("forward"),
+            Some(ServoMouseButton::Forward)
+        );
+        assert_eq!(mouse_button_from_name("Left"), None);
+    }
+
+    #[test]
+    fn clipboard_shortcuts_become_editing_actions() {
+        let press = |state, key: &str, modifiers| {
+            let key = Key::Character(key.to_string());
+            editing_action(&synthetic_keyboard_event(state, key, Code::KeyV, modifiers))
+        };
+        assert!(matches!(
+            press(KeyState::Down, "V", Modifiers::CONTROL),
+            Some(EditingActionEvent::Paste)
+        ));
+        assert!(press(KeyState::Up, "v", Modifiers::CONTROL).is_none());
+        assert!(press(KeyState::Down, "v", Modifiers::SHIFT).is_none());
+    }
+
+    #[test]
+    fn css_scroll_distances_become_wheel_deltas() {
+        assert_eq!(wheel_delta_from_css(0.0, 100.0, 2.0), (0.0, -200.0));
+    }
+}
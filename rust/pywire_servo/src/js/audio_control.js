@@ -0,0 +1,79 @@
+// Mute the page's media elements, or turn them down, including media added
+// later. The page keeps seeing its own `volume` and `muted` settings, and the
+// element plays at those combined with the app's: muted if either mutes it,
+// at the product of both volumes. So a page can't unmute what the app muted,
+// and its own volume slider keeps working.
+(function (muted, volume) {
+  let state = window.__pywireAudio;
+  if (!state) {
+    const proto = HTMLMediaElement.prototype;
+    const volumeProperty = Object.getOwnPropertyDescriptor(proto, "volume");
+    const mutedProperty = Object.getOwnPropertyDescriptor(proto, "muted");
+    const own = new WeakMap();
+
+    // The page's settings for `media`, recorded before the first change.
+    const ownSettings = (media) => {
+      let settings = own.get(media);
+      if (!settings) {
+        settings = {
+          volume: volumeProperty.get.call(media),
+          muted: mutedProperty.get.call(media),
+        };
+        own.set(media, settings);
+      }
+      return settings;
+    };
+    const apply = (media) => {
+      const settings = ownSettings(media);
+      mutedProperty.set.call(media, settings.muted || state.muted);
+      volumeProperty.set.call(media, settings.volume * state.volume);
+    };
+
+    Object.defineProperty(proto, "volume", {
+      configurable: true,
+      enumerable: volumeProperty.enumerable,
+      get() {
+        return ownSettings(this).volume;
+      },
+      set(value) {
+        const settings = ownSettings(this);
+        // Throws for volumes out of range, as the real setter does.
+        volumeProperty.set.call(this, value);
+        settings.volume = volumeProperty.get.call(this);
+        apply(this);
+      },
+    });
+    Object.defineProperty(proto, "muted", {
+      configurable: true,
+      enumerable: mutedProperty.enumerable,
+      get() {
+        return ownSettings(this).muted;
+      },
+      set(value) {
+        ownSettings(this).muted = Boolean(value);
+        apply(this);
+      },
+    });
+
+    state = window.__pywireAudio = { muted: false, volume: 1, apply: apply };
+    // Media in the page announces itself when it starts loading or playing;
+    // capturing listeners on the document see both for every element.
+    for (const type of ["loadstart", "play"]) {
+      document.addEventListener(
+        type,
+        (event) => {
+          if (event.target instanceof HTMLMediaElement) {
+            state.apply(event.target);
+          }
+        },
+        true,
+      );
+    }
+  }
+
+  state.muted = muted;
+  state.volume = volume;
+  for (const media of document.querySelectorAll("audio, video")) {
+    state.apply(media);
+  }
+})(__MUTED__, __VOLUME__);
@@ -0,0 +1,47 @@
+// Autoplay policy for `InitParams::autoplay_policy`. Servo lets every page
+// autoplay, so media the page starts before the user has interacted with it
+// is stopped here: all of it under "block", media with sound under
+// "block-audio". `play()` then rejects with `NotAllowedError`, as in
+// browsers, so pages can offer a play button instead.
+(function (policy) {
+  if (window.__pywireAutoplay) {
+    return;
+  }
+  const state = (window.__pywireAutoplay = { policy: policy, activated: false });
+  for (const type of ["pointerdown", "keydown", "touchstart"]) {
+    window.addEventListener(
+      type,
+      (event) => {
+        if (event.isTrusted) {
+          state.activated = true;
+        }
+      },
+      true,
+    );
+  }
+
+  const blocked = (media) =>
+    !state.activated &&
+    (state.policy === "block" ||
+      (state.policy === "block-audio" && !media.muted && media.volume > 0));
+
+  const play = HTMLMediaElement.prototype.play;
+  HTMLMediaElement.prototype.play = function () {
+    if (blocked(this)) {
+      return Promise.reject(
+        new DOMException("Autoplay is not allowed before the user interacts", "NotAllowedError"),
+      );
+    }
+    return play.apply(this, arguments);
+  };
+  // The `autoplay` attribute starts media without calling `play()`.
+  document.addEventListener(
+    "play",
+    (event) => {
+      if (event.target instanceof HTMLMediaElement && blocked(event.target)) {
+        event.target.pause();
+      }
+    },
+    true,
+  );
+})(__POLICY__);
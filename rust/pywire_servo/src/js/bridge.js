@@ -0,0 +1,17 @@
+// The page side of the message bridge: `window.pywire.send(channel, data)`
+// delivers `data` (anything JSON can encode) to the app's event listeners.
+// Messages from the app arrive as `pywire-message` events and through
+// `window.pywire.onMessage` (see `post_message.js`).
+(function () {
+  if (window.pywire && window.pywire.send) {
+    return;
+  }
+  window.pywire = window.pywire || {};
+  window.pywire.send = function (channel, data) {
+    if (typeof channel !== "string" || channel === "") {
+      throw new TypeError("pywire.send: channel must be a non-empty string");
+    }
+    const message = { channel: channel, data: data === undefined ? null : data };
+    console.log("PW_MSG:" + JSON.stringify(message));
+  };
+})();
@@ -0,0 +1,18 @@
+// Describe what was right-clicked, for the `context_menu` event. The point is
+// in device pixels. Evaluates to a JSON string.
+(function (x, y) {
+  const ratio = window.devicePixelRatio || 1;
+  const target = document.elementFromPoint(x / ratio, y / ratio);
+  const closest = (selector) =>
+    target && target.closest ? target.closest(selector) : null;
+  const link = closest("a[href], area[href]");
+  const image = closest("img");
+  const editable = closest("input, textarea, [contenteditable]");
+  const selection = window.getSelection ? String(window.getSelection()) : "";
+  return JSON.stringify({
+    link_url: link ? link.href : null,
+    image_url: image ? image.currentSrc || image.src : null,
+    selection: selection || null,
+    editable: !!(editable && (editable.isContentEditable || !editable.readOnly)),
+  });
+})(__X__, __Y__);
@@ -0,0 +1,82 @@
+// Serialise the DOM with layout information for automated layout assertions.
+// Evaluates to a JSON string so the embedder gets it back in one piece.
+(function (maxDepth, maxNodes) {
+  const STYLE_PROPERTIES = [
+    "display",
+    "position",
+    "visibility",
+    "opacity",
+    "z-index",
+    "overflow",
+    "color",
+    "background-color",
+    "font-family",
+    "font-size",
+    "font-weight",
+    "margin-top",
+    "margin-right",
+    "margin-bottom",
+    "margin-left",
+    "padding-top",
+    "padding-right",
+    "padding-bottom",
+    "padding-left",
+    "border-top-width",
+    "border-right-width",
+    "border-bottom-width",
+    "border-left-width",
+  ];
+
+  let nodes = 0;
+  let truncated = false;
+
+  function snapshot(el, depth) {
+    nodes += 1;
+    const rect = el.getBoundingClientRect();
+    const computed = window.getComputedStyle(el);
+    const style = {};
+    for (const property of STYLE_PROPERTIES) {
+      style[property] = computed.getPropertyValue(property);
+    }
+    const attributes = {};
+    for (const attr of Array.from(el.attributes)) {
+      attributes[attr.name] = attr.value;
+    }
+
+    const node = {
+      tag: el.tagName.toLowerCase(),
+      attributes,
+      rect: { x: rect.x, y: rect.y, width: rect.width, height: rect.height },
+      style,
+      text: "",
+      children: [],
+    };
+    for (const child of Array.from(el.childNodes)) {
+      if (child.nodeType === Node.TEXT_NODE) {
+        node.text += child.textContent;
+      }
+    }
+    node.text = node.text.trim();
+
+    if (maxDepth > 0 && depth >= maxDepth) {
+      truncated = truncated || el.children.length > 0;
+      return node;
+    }
+    for (const child of Array.from(el.children)) {
+      if (nodes >= maxNodes) {
+        truncated = true;
+        break;
+      }
+      node.children.push(snapshot(child, depth + 1));
+    }
+    return node;
+  }
+
+  return JSON.stringify({
+    url: document.location.href,
+    viewport: { width: window.innerWidth, height: window.innerHeight },
+    scroll: { x: window.scrollX, y: window.scrollY },
+    truncated,
+    root: document.documentElement ? snapshot(document.documentElement, 0) : null,
+  });
+})(__MAX_DEPTH__, __MAX_NODES__);
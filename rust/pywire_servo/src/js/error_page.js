@@ -0,0 +1,33 @@
+// Show the app's own page for an error of `kind` (`pw_set_error_page`) in
+// place of the shell's, with `{{url}}` and `{{error}}` filled in. Returns
+// whether the app has one. The document keeps the URL that failed, so
+// reloading it retries the original request.
+function __pywireCustomErrorPage(pages, kind, error, after) {
+  var html = pages[kind];
+  if (typeof html !== "string") {
+    return false;
+  }
+  var escape = function (text) {
+    return String(text)
+      .replace(/&/g, "&amp;")
+      .replace(/</g, "&lt;")
+      .replace(/>/g, "&gt;")
+      .replace(/"/g, "&quot;");
+  };
+  html = html
+    .split("{{url}}")
+    .join(escape(location.href))
+    .split("{{error}}")
+    .join(escape(error));
+  document.documentElement.style.visibility = "hidden";
+  // A document can only be replaced once it has been parsed.
+  document.addEventListener("DOMContentLoaded", function () {
+    document.open();
+    document.write(html);
+    document.close();
+    if (after) {
+      after();
+    }
+  });
+  return true;
+}
@@ -0,0 +1,220 @@
+// Find-in-page for `pw_find`; Servo has none of its own. Matches are
+// highlighted by boxes drawn in an overlay rather than by wrapping them in
+// elements, so the page's DOM keeps its structure: the only addition is the
+// overlay's host element, whose contents sit in a closed shadow root.
+(function () {
+  if (window.__pywireFind) {
+    return;
+  }
+
+  // Drawing more boxes than this only slows the page down.
+  const MAX_HIGHLIGHTS = 1000;
+  const SKIPPED = new Set(["SCRIPT", "STYLE", "NOSCRIPT", "TEMPLATE", "TEXTAREA", "SELECT"]);
+  const MATCH_COLOR = "rgba(255, 230, 0, 0.45)";
+  const CURRENT_COLOR = "rgba(255, 140, 0, 0.65)";
+
+  const state = {
+    text: null,
+    caseSensitive: false,
+    ranges: [],
+    index: -1,
+    host: null,
+    root: null,
+  };
+
+  function textNodes() {
+    const nodes = [];
+    const walker = document.createTreeWalker(
+      document.body || document.documentElement,
+      NodeFilter.SHOW_TEXT,
+      {
+        acceptNode(node) {
+          const parent = node.parentElement;
+          return parent && !SKIPPED.has(parent.tagName)
+            ? NodeFilter.FILTER_ACCEPT
+            : NodeFilter.FILTER_REJECT;
+        },
+      },
+    );
+    while (walker.nextNode()) {
+      nodes.push(walker.currentNode);
+    }
+    return nodes;
+  }
+
+  // Ranges over every rendered occurrence of `text`, in document order.
+  // Text is searched across node boundaries, so `foo <b>bar</b>` matches
+  // "foo bar".
+  function search(text, caseSensitive) {
+    const nodes = textNodes();
+    const starts = [];
+    let content = "";
+    for (const node of nodes) {
+      starts.push(content.length);
+      content += node.data;
+    }
+    let haystack = content;
+    let needle = text;
+    if (!caseSensitive) {
+      haystack = content.toLowerCase();
+      needle = text.toLowerCase();
+      // A few characters change length when lowercased, which would throw
+      // the offsets off; search those pages case-sensitively.
+      if (haystack.length !== content.length) {
+        haystack = content;
+        needle = text;
+      }
+    }
+
+    // Matches come in order, so the node cursor only moves forward.
+    let cursor = 0;
+    function locate(offset, isEnd) {
+      while (cursor < nodes.length - 1) {
+        const end = starts[cursor] + nodes[cursor].data.length;
+        if (isEnd ? end >= offset : end > offset) {
+          break;
+        }
+        cursor += 1;
+      }
+      return [nodes[cursor], offset - starts[cursor]];
+    }
+
+    const ranges = [];
+    let at = haystack.indexOf(needle);
+    while (at !== -1) {
+      const range = document.createRange();
+      range.setStart(...locate(at, false));
+      range.setEnd(...locate(at + needle.length, true));
+      // Text under `display: none` has no boxes and can't be shown.
+      if (range.getClientRects().length > 0) {
+        ranges.push(range);
+      }
+      at = haystack.indexOf(needle, at + needle.length);
+    }
+    return ranges;
+  }
+
+  // The match to start a new search at: the first one in view or below it
+  // going forward, the last one in view or above it going back.
+  function startIndex(ranges, forward) {
+    if (forward) {
+      const index = ranges.findIndex((range) => range.getBoundingClientRect().bottom >= 0);
+      return index === -1 ? 0 : index;
+    }
+    for (let index = ranges.length - 1; index >= 0; index -= 1) {
+      if (ranges[index].getBoundingClientRect().top < window.innerHeight) {
+        return index;
+      }
+    }
+    return ranges.length - 1;
+  }
+
+  function overlay() {
+    if (state.host && state.host.isConnected) {
+      return state.root;
+    }
+    const host = document.createElement("pywire-find");
+    const style = {
+      display: "block",
+      position: "absolute",
+      top: "0",
+      left: "0",
+      width: "0",
+      height: "0",
+      margin: "0",
+      padding: "0",
+      border: "0",
+      overflow: "visible",
+      "pointer-events": "none",
+      "z-index": "2147483647",
+    };
+    for (const [name, value] of Object.entries(style)) {
+      host.style.setProperty(name, value, "important");
+    }
+    state.root = host.attachShadow ? host.attachShadow({ mode: "closed" }) : host;
+    // Outside <body>, where the page's own queries rarely look.
+    document.documentElement.appendChild(host);
+    state.host = host;
+    return state.root;
+  }
+
+  function draw() {
+    const root = overlay();
+    root.textContent = "";
+    const origin = state.host.getBoundingClientRect();
+    const boxes = document.createDocumentFragment();
+    state.ranges.forEach((range, index) => {
+      if (index >= MAX_HIGHLIGHTS && index !== state.index) {
+        return;
+      }
+      for (const rect of range.getClientRects()) {
+        const box = document.createElement("div");
+        box.style.cssText =
+          "position: absolute; border-radius: 2px;" +
+          `left: ${rect.left - origin.left}px; top: ${rect.top - origin.top}px;` +
+          `width: ${rect.width}px; height: ${rect.height}px;` +
+          `background: ${index === state.index ? CURRENT_COLOR : MATCH_COLOR};`;
+        boxes.appendChild(box);
+      }
+    });
+    root.appendChild(boxes);
+  }
+
+  function reveal(range) {
+    const rect = range.getBoundingClientRect();
+    const visible =
+      rect.top >= 0 &&
+      rect.bottom <= window.innerHeight &&
+      rect.left >= 0 &&
+      rect.right <= window.innerWidth;
+    const element = range.startContainer.parentElement;
+    if (!visible && element) {
+      element.scrollIntoView({ block: "center", inline: "nearest" });
+    }
+  }
+
+  function find(text, forward, caseSensitive) {
+    if (!text) {
+      clear();
+      return [0, -1];
+    }
+    const repeated =
+      text === state.text && caseSensitive === state.caseSensitive && state.index >= 0;
+    // Search again each time, so matches follow changes to the page.
+    const ranges = search(text, caseSensitive);
+    let index = repeated ? state.index + (forward ? 1 : -1) : startIndex(ranges, forward);
+    index = ranges.length ? (index + ranges.length) % ranges.length : -1;
+
+    state.text = text;
+    state.caseSensitive = caseSensitive;
+    state.ranges = ranges;
+    state.index = index;
+    if (index >= 0) {
+      reveal(ranges[index]);
+    }
+    draw();
+    return [ranges.length, index];
+  }
+
+  // Evaluates to whether there was a search to clear.
+  function clear() {
+    const active = state.text !== null;
+    if (state.host) {
+      state.host.remove();
+    }
+    state.text = null;
+    state.ranges = [];
+    state.index = -1;
+    state.host = null;
+    state.root = null;
+    return active;
+  }
+
+  window.addEventListener("resize", () => {
+    if (state.text !== null) {
+      draw();
+    }
+  });
+
+  window.__pywireFind = { find: find, clear: clear };
+})();
@@ -0,0 +1,8 @@
+// The HTTP status of the page's response, from its navigation timing entry.
+// Evaluates to 0 where the engine doesn't report it.
+(function () {
+  const entry = performance.getEntriesByType
+    ? performance.getEntriesByType("navigation")[0]
+    : null;
+  return entry && typeof entry.responseStatus === "number" ? entry.responseStatus : 0;
+})()
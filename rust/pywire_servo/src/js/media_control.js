@@ -0,0 +1,43 @@
+// Pause every media element in the page, and keep media that starts playing
+// later paused, until resumed.
+(function (paused) {
+  const state =
+    window.__pywireMedia ||
+    (window.__pywireMedia = { paused: false, installed: false, resumable: new Set() });
+  if (!state.installed) {
+    state.installed = true;
+    // `play` doesn't bubble, but a capturing listener on the document sees it
+    // for every element in the page, including ones added after the pause.
+    document.addEventListener(
+      "play",
+      (event) => {
+        if (state.paused && event.target instanceof HTMLMediaElement) {
+          state.resumable.add(event.target);
+          event.target.pause();
+        }
+      },
+      true,
+    );
+  }
+  if (paused === state.paused) {
+    return;
+  }
+  state.paused = paused;
+
+  if (paused) {
+    for (const media of document.querySelectorAll("audio, video")) {
+      if (!media.paused) {
+        state.resumable.add(media);
+        media.pause();
+      }
+    }
+  } else {
+    // Only restart what the pause interrupted, not media the user paused.
+    for (const media of state.resumable) {
+      if (media.isConnected) {
+        media.play().catch(() => {});
+      }
+    }
+    state.resumable.clear();
+  }
+})(__PAUSED__);
@@ -0,0 +1,12 @@
+// The app side of the message bridge: delivers `pw_post_message(channel,
+// data)` to the page as a `pywire-message` event on `window`, whose `detail`
+// is `{channel, data}`, and to `window.pywire.onMessage(channel, data)` if the
+// page set one. The shell calls this with the channel and data JSON-encoded.
+(function (channel, data) {
+  window.dispatchEvent(
+    new CustomEvent("pywire-message", { detail: { channel: channel, data: data } }),
+  );
+  if (window.pywire && typeof window.pywire.onMessage === "function") {
+    window.pywire.onMessage(channel, data);
+  }
+})
@@ -0,0 +1,11 @@
+// Route `window.print()` to the shell, which saves the page as a PDF. Servo
+// has no print dialog of its own, so the call would otherwise do nothing.
+(function () {
+  if (window.__pywirePrint) {
+    return;
+  }
+  window.__pywirePrint = true;
+  window.print = function () {
+    console.log("PW_PRINT:");
+  };
+})();
@@ -0,0 +1,13 @@
+// Report the privacy preferences the shell signals, the way browsers expose
+// them to pages.
+(function (doNotTrack, globalPrivacyControl) {
+  window.__pywirePrivacySignals = true;
+  const define = (name, value) =>
+    Object.defineProperty(Navigator.prototype, name, {
+      configurable: true,
+      enumerable: true,
+      get: () => value,
+    });
+  define("doNotTrack", doNotTrack ? "1" : null);
+  define("globalPrivacyControl", globalPrivacyControl);
+})(__DO_NOT_TRACK__, __GLOBAL_PRIVACY_CONTROL__);
@@ -0,0 +1,140 @@
+// Spatial navigation for remote-control UIs: arrow keys move focus to the
+// nearest focusable element in that direction, Enter activates it.
+(function (enabled) {
+  const state =
+    window.__pywireSpatialNavigation ||
+    (window.__pywireSpatialNavigation = { enabled: false, installed: false });
+  state.enabled = enabled;
+  if (state.installed) {
+    return;
+  }
+  state.installed = true;
+
+  const FOCUSABLE = [
+    "a[href]",
+    "area[href]",
+    "button:not([disabled])",
+    "input:not([disabled]):not([type=hidden])",
+    "select:not([disabled])",
+    "textarea:not([disabled])",
+    "[tabindex]:not([tabindex='-1'])",
+    "[contenteditable='']",
+    "[contenteditable='true']",
+  ].join(",");
+
+  const DIRECTIONS = {
+    ArrowUp: { x: 0, y: -1 },
+    ArrowDown: { x: 0, y: 1 },
+    ArrowLeft: { x: -1, y: 0 },
+    ArrowRight: { x: 1, y: 0 },
+  };
+
+  function candidates() {
+    return Array.from(document.querySelectorAll(FOCUSABLE)).filter((el) => {
+      const rect = el.getBoundingClientRect();
+      return rect.width > 0 && rect.height > 0;
+    });
+  }
+
+  function center(el) {
+    const rect = el.getBoundingClientRect();
+    return { x: rect.left + rect.width / 2, y: rect.top + rect.height / 2 };
+  }
+
+  // Distance along the direction of travel, with sideways drift penalised so
+  // that moving "down" prefers the element directly below.
+  function score(from, to, dir) {
+    const dx = to.x - from.x;
+    const dy = to.y - from.y;
+    const along = dx * dir.x + dy * dir.y;
+    const across = Math.abs(dx * dir.y) + Math.abs(dy * dir.x);
+    return along + across * 2;
+  }
+
+  function next(current, dir) {
+    const elements = candidates().filter((el) => el !== current);
+    if (elements.length === 0) {
+      return null;
+    }
+    if (!current) {
+      return elements[0];
+    }
+    const from = center(current);
+    let best = null;
+    let bestScore = Infinity;
+    for (const el of elements) {
+      const to = center(el);
+      const along = (to.x - from.x) * dir.x + (to.y - from.y) * dir.y;
+      if (along <= 0) {
+        continue;
+      }
+      const s = score(from, to, dir);
+      if (s < bestScore) {
+        best = el;
+        bestScore = s;
+      }
+    }
+    if (best) {
+      return best;
+    }
+    // Nothing further in this direction: wrap around to the element furthest
+    // away in the opposite direction.
+    let wrapped = null;
+    let furthest = -Infinity;
+    for (const el of elements) {
+      const to = center(el);
+      const behind = (from.x - to.x) * dir.x + (from.y - to.y) * dir.y;
+      if (behind > furthest) {
+        wrapped = el;
+        furthest = behind;
+      }
+    }
+    return wrapped;
+  }
+
+  function isTextEntry(el) {
+    return (
+      el &&
+      (el.tagName === "TEXTAREA" ||
+        el.isContentEditable ||
+        (el.tagName === "INPUT" &&
+          !["button", "checkbox", "radio", "submit", "reset"].includes(el.type)))
+    );
+  }
+
+  document.addEventListener(
+    "keydown",
+    (event) => {
+      if (!state.enabled || event.altKey || event.ctrlKey || event.metaKey) {
+        return;
+      }
+      const current = document.activeElement === document.body ? null : document.activeElement;
+
+      if (event.key === "Enter") {
+        // Links and buttons activate natively; give other focusables a click.
+        if (current && !isTextEntry(current) && !current.matches("a[href], button, input, select")) {
+          current.click();
+          event.preventDefault();
+        }
+        return;
+      }
+
+      const dir = DIRECTIONS[event.key];
+      if (!dir) {
+        return;
+      }
+      // Left/right move the caret inside text fields.
+      if (isTextEntry(current) && dir.y === 0) {
+        return;
+      }
+      const target = next(current, dir);
+      if (target) {
+        target.focus();
+        target.scrollIntoView({ block: "nearest", inline: "nearest" });
+      }
+      // Arrow keys would otherwise scroll the page.
+      event.preventDefault();
+    },
+    true,
+  );
+})(__ENABLED__);
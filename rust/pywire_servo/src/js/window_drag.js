@@ -0,0 +1,47 @@
+// Moving and resizing the window from the page, for windows without
+// decorations (`InitParams::decorations` off) whose title bar and borders the
+// page draws itself.
+// Call `pywire.startDrag()` or `pywire.startResize(edge)` from a mousedown
+// handler, or mark elements: `data-pywire-drag` for a title bar (its buttons,
+// links and fields, and anything marked `data-pywire-no-drag`, still get
+// their clicks) and `data-pywire-resize="se"` for an edge or corner grip.
+(function () {
+  if (window.pywire && window.pywire.startDrag) {
+    return;
+  }
+  const EDGES = ["n", "s", "e", "w", "ne", "nw", "se", "sw"];
+  window.pywire = window.pywire || {};
+  window.pywire.startDrag = function () {
+    console.log("PW_WINDOW:drag");
+  };
+  window.pywire.startResize = function (edge) {
+    if (!EDGES.includes(edge)) {
+      throw new TypeError("pywire.startResize: edge must be one of " + EDGES.join(", "));
+    }
+    console.log("PW_WINDOW:resize:" + edge);
+  };
+
+  document.addEventListener(
+    "mousedown",
+    (event) => {
+      if (event.button !== 0 || !(event.target instanceof Element)) {
+        return;
+      }
+      const grip = event.target.closest("[data-pywire-resize]");
+      if (grip) {
+        const edge = grip.getAttribute("data-pywire-resize");
+        if (EDGES.includes(edge)) {
+          event.preventDefault();
+          window.pywire.startResize(edge);
+        }
+        return;
+      }
+      const interactive = "[data-pywire-no-drag], a, button, input, select, textarea, label";
+      if (event.target.closest("[data-pywire-drag]") && !event.target.closest(interactive)) {
+        event.preventDefault();
+        window.pywire.startDrag();
+      }
+    },
+    true,
+  );
+})();
@@ -2,9 +2,11 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
-use keyboard_types::{Code, Key, KeyState, Location, Modifiers, NamedKey};
-use servo::KeyboardEvent;
-use winit::event::{ElementState, KeyEvent};
+use keyboard_types::{
+    Code, CompositionEvent, CompositionState, Key, KeyState, Location, Modifiers, NamedKey,
+};
+use servo::{ImeEvent, KeyboardEvent};
+use winit::event::{ElementState, Ime, KeyEvent};
 use winit::keyboard::{
     Key as WinitKey, KeyCode, KeyLocation as WinitKeyLocation, ModifiersState,
     NamedKey as WinitNamedKey, PhysicalKey,
@@ -603,3 +605,80 @@ pub fn keyboard_event_from_winit(key_event: &KeyEvent, state: ModifiersState) ->
         false,
     )
 }
+
+/// A key event made up by the app rather than typed, for `pw_send_key`.
+pub fn synthetic_keyboard_event(
+    state: KeyState,
+    key: Key,
+    code: Code,
+    modifiers: Modifiers,
+) -> KeyboardEvent {
+    KeyboardEvent::new_without_event(
+        state,
+        key,
+        code,
+        Location::Standard,
+        modifiers,
+        false,
+        false,
+    )
+}
+
+/// Translate a winit IME event into a Servo composition event: the page sees
+/// `compositionstart` when the IME engages, `compositionupdate` as the
+/// preedit text changes and `compositionend` with the committed text.
+pub fn ime_event_from_winit(ime: &Ime) -> ImeEvent {
+    let composition = |state, data: &str| {
+        ImeEvent::Composition(CompositionEvent {
+            state,
+            data: data.to_owned(),
+        })
+    };
+    match ime {
+        Ime::Enabled => composition(CompositionState::Start, ""),
+        Ime::Preedit(text, _cursor) => composition(CompositionState::Update, text),
+        Ime::Commit(text) => composition(CompositionState::End, text),
+        Ime::Disabled => ImeEvent::Dismissed,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn composition(event: ImeEvent) -> Option<CompositionEvent> {
+        match event {
+            ImeEvent::Composition(composition) => Some(composition),
+            ImeEvent::Dismissed => None,
+        }
+    }
+
+    #[test]
+    fn commit_ends_the_composition_with_the_committed_text() {
+        assert_eq!(
+            composition(ime_event_from_winit(&Ime::Commit("日本語".to_owned()))),
+            Some(CompositionEvent {
+                state: CompositionState::End,
+                data: "日本語".to_owned(),
+            })
+        );
+    }
+
+    #[test]
+    fn preedit_updates_the_composition() {
+        assert_eq!(
+            composition(ime_event_from_winit(&Ime::Preedit(
+                "にほ".to_owned(),
+                Some((6, 6))
+            ))),
+            Some(CompositionEvent {
+                state: CompositionState::Update,
+                data: "にほ".to_owned(),
+            })
+        );
+        assert!(matches!(
+            ime_event_from_winit(&Ime::Disabled),
+            ImeEvent::Dismissed
+        ));
+    }
+}
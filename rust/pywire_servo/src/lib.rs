@@ -1,18 +1,60 @@
+//! Native runtime for pywire-shell: a Servo webview in a winit window, driven
+//! through the `pw_*` C functions.
+//!
+//! # Threading
+//!
+//! `pw_start_app` runs the event loop on the calling thread and blocks until
+//! the app exits. It must be called on the main thread on macOS, where it
+//! fails with `-13` on any other (headless apps excepted); other platforms
+//! allow any thread. It may only be called once per process (`-7` after that).
+//! Servo, the window and all page state live on that thread.
+//!
+//! A caller whose main thread has other work, such as its own loop, can set
+//! `InitParams::pump_events`: `pw_start_app` then returns once the app is set
+//! up, and the caller runs the event loop a slice at a time with
+//! `pw_poll_events` on the same thread.
+//!
+//! Every other `pw_*` function may be called from any thread, before, during
+//! or after `pw_start_app`. Each one either updates shared settings behind a
+//! lock or atomic, or queues a request for the event loop and returns. None of
+//! them touch Servo directly. Functions documented as "before `pw_start_app`"
+//! are checked atomically against startup, so a call that races it from
+//! another thread is either applied or refused with `-7`, never lost.
+//!
+//! Event listeners run on the event loop thread and must return quickly. They
+//! may call any `pw_*` function except the ones that wait for the loop to
+//! answer (such as `pw_get_window_position` or `pw_capture_png`): the loop
+//! can't answer while it is busy running the listener, so those time out
+//! with `-5`.
+//!
+//! # Strings
+//!
+//! Strings passed in are borrowed for the duration of the call. A string
+//! returned as `*mut c_char` belongs to the caller, who must release it with
+//! `pw_free_string`; null means there is no value. The only `*const c_char`,
+//! `pw_version`, is static.
+
 use euclid::{Point2D, Rect, Scale, Size2D};
 use raw_window_handle::{HasDisplayHandle, HasWindowHandle};
-use std::cell::Cell;
+use std::cell::{Cell, RefCell};
+use std::collections::HashSet;
 use std::env;
-use std::ffi::CStr;
-use std::fs;
+use std::ffi::{CStr, CString};
 use std::os::raw::c_char;
-use std::path::PathBuf;
-use std::rc::Rc;
-use std::sync::Arc;
+use std::path::{Path, PathBuf};
+use std::rc::{Rc, Weak};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
 use url::Url;
 use winit::application::ApplicationHandler;
-use winit::event::{ElementState, MouseButton, MouseScrollDelta, WindowEvent};
+use winit::event::{MouseScrollDelta, TouchPhase, WindowEvent};
 use winit::event_loop::{ActiveEventLoop, ControlFlow, EventLoop, EventLoopProxy};
-use winit::window::{CursorIcon, Window, WindowAttributes, WindowId};
+use winit::platform::pump_events::{EventLoopExtPumpEvents, PumpStatus};
+use winit::window::{
+    CursorIcon, Fullscreen, Icon, ResizeDirection, Window, WindowAttributes, WindowId, WindowLevel,
+};
 
 #[cfg(target_os = "macos")]
 use {
@@ -20,29 +62,292 @@ use {
     objc2_foundation::{MainThreadMarker, NSString},
 };
 
+use servo::ipc_channel::ipc;
 use servo::{
-    resources::{self, Resource, ResourceReaderMethods},
-    ConsoleLogLevel, ContextMenu, ContextMenuAction, Cursor, DevicePixel, DevicePoint,
-    EditingActionEvent, EmbedderControl, EventLoopWaker, InputEvent, InputEventId,
-    InputEventResult, LoadStatus, MouseButton as ServoMouseButton, MouseButtonAction,
-    MouseButtonEvent, MouseMoveEvent, OffscreenRenderingContext, RenderingContext, Servo,
-    ServoBuilder, WebView, WebViewBuilder, WebViewDelegate, WheelDelta, WheelEvent, WheelMode,
-    WindowRenderingContext,
+    resources, AuthenticationRequest, ConsoleLogLevel, ContextMenu, ContextMenuAction, Cursor,
+    DevicePixel, DevicePoint, EmbedderControl, EmbedderControlId, EventLoopWaker, InputEvent,
+    InputEventId, InputEventResult, JSValue, KeyboardEvent, LoadStatus,
+    MouseButton as ServoMouseButton, MouseButtonAction, MouseButtonEvent, MouseMoveEvent,
+    NavigationRequest, NetworkManager, OffscreenRenderingContext, PermissionRequest,
+    RenderingContext, Servo, ServoBuilder, SoftwareRenderingContext, TouchEvent, TouchId,
+    UserContentManager, WebResourceLoad, WebView, WebViewBuilder, WebViewDelegate, WheelDelta,
+    WheelEvent, WheelMode, WindowRenderingContext,
 };
 
+mod accelerators;
+mod auth;
+mod capture;
+mod cert;
+mod clipboard;
+mod color_scheme;
+mod crash;
+mod devtools;
+mod diagnostics;
+mod dialog;
+mod encoding;
+mod errors;
+mod events;
+mod favicon;
+mod file_drop;
+mod file_picker;
+mod find;
+mod injected;
+mod input;
 mod keyutils;
-use keyutils::keyboard_event_from_winit;
+mod logging;
+mod memory;
+mod navigation;
+mod net_error;
+mod network;
+mod pdf;
+mod permissions;
+mod platform;
+mod prefs;
+mod print;
+mod protocol;
+mod resource_reader;
+mod scroll;
+mod single_instance;
+mod static_files;
+mod status_text;
+mod thumbnail;
+mod watchdog;
+mod zoom;
+use auth::{PendingAuthentications, AUTH_TIMEOUT_MS};
+use capture::{CaptureCallback, Frame, FrameCopy};
+use cert::PendingCertErrors;
+use errors::ErrorCode;
+use events::{emit_bridge_message, emit_event, EventCallback, EventType, TypedEventCallback};
+use file_drop::FileBatch;
+use keyutils::{ime_event_from_winit, keyboard_event_from_winit, synthetic_keyboard_event};
+use navigation::{NavigationCallback, NewWindowPolicy, PendingNavigation, NEW_WINDOW_POLICY};
+use network::{CacheMode, MixedContentPolicy, UrlRewrite, NETWORK_POLICY};
+use pdf::PageSize;
+use permissions::{PendingPermissions, PERMISSION_TIMEOUT_MS};
+use platform::ProgressState;
+use prefs::ENGINE_SETTINGS;
+use print::PrintJob;
+use protocol::{ProtocolCallback, ProtocolSource};
+use resource_reader::{PyWireResourceReader, ResourceCallback};
+use scroll::{SmoothScroll, SCROLL_SETTINGS};
+use status_text::StatusText;
+use thumbnail::ThumbnailSource;
+use watchdog::Watchdog;
+use zoom::ZoomLevels;
 
 #[derive(Debug)]
 enum UserEvent {
     Wake,
+    RunDiagnostics(mpsc::Sender<serde_json::Value>),
+    /// A thread panicked, with what it said.
+    Crash(String),
+    /// Ask Servo for a memory report, for the request with this id.
+    CreateMemoryReport(u32),
+    /// Servo's serialized memory report for a request, or why there is none.
+    MemoryReport(u32, Result<serde_json::Value, String>),
     ExecuteJs(String),
+    AddUserScript(String, bool),
     SetTitle(String),
     Resize(u32, u32),
+    StopLoading,
+    ReapplyInjections,
+    ApplyColorScheme,
+    GetScrollPosition(mpsc::Sender<Option<(f64, f64)>>),
+    SetScrollPosition(f64, f64),
+    /// Synthetic input from `pw_send_*`, at CSS pixel positions and
+    /// distances.
+    SendMouseClick(f64, f64, ServoMouseButton),
+    SendKey(
+        keyboard_types::Key,
+        keyboard_types::Code,
+        keyboard_types::Modifiers,
+    ),
+    SendScroll(f64, f64),
+    CaptureDomSnapshot(u32, mpsc::Sender<Option<String>>),
+    /// Search the page for the text, forward, case-sensitively.
+    Find(String, bool, bool),
+    ClearFind,
+    SetDefaultZoom(f32),
+    SetOriginZoom(f32),
+    ClearZoomOverrides,
+    StepZoom(bool),
+    ResetZoom,
+    SetPinchZoom(f32),
+    ClearCookies,
+    CapturePng(PathBuf, mpsc::Sender<i32>),
+    PrintToPdf(PathBuf, PageSize, mpsc::Sender<i32>),
+    ShowPrintDialog,
+    /// The document measurements of the print job, or `None` if the page
+    /// couldn't be measured.
+    PrintMeasured(Option<[f64; 4]>),
+    PrintScrolled(f64),
+    PrintNext,
+    NavigateWithCallback(Url, PendingNavigation),
+    ApplyWindowChrome,
+    UpdateThumbnail,
+    SetTaskbarProgress(ProgressState, f32),
+    SetBadgeCount(u32),
+    SecondInstance,
+    LoadUrl(Url),
+    Reload,
+    GoBack(mpsc::Sender<bool>),
+    GoForward(mpsc::Sender<bool>),
+    /// Whether the active webview can go back and forward.
+    HistoryState(mpsc::Sender<(bool, bool)>),
+    CreateWebView(Url, mpsc::Sender<Option<u32>>),
+    ActivateWebView(u32, mpsc::Sender<bool>),
+    CloseWebView(u32, mpsc::Sender<bool>),
+    AnswerAuthentication(u32, Option<(String, String)>, mpsc::Sender<bool>),
+    AnswerCertError(u32, bool, mpsc::Sender<bool>),
+    RespondPermission(u32, bool, mpsc::Sender<bool>),
+    Shutdown,
+    CancelClose,
+    SetVisible(bool),
+    SetFullscreen(bool),
+    /// Maximize or minimize the window, or restore it from either.
+    SetWindowState(WindowState, mpsc::Sender<bool>),
+    /// Replies whether the window is maximized and whether it is minimized,
+    /// or `None` without a window.
+    GetWindowState(mpsc::Sender<Option<(bool, bool)>>),
+    /// Replies whether the platform honors window levels.
+    SetWindowLevel(WindowLevel, mpsc::Sender<bool>),
+    SetWindowPosition(i32, i32),
+    ApplySizeLimits,
+    SetWindowIcon(Icon),
+    SetClipboardText(String),
+    GetClipboardText(mpsc::Sender<Option<String>>),
+    GetUrl(mpsc::Sender<Option<String>>),
+    GetWindowPosition(mpsc::Sender<Option<(i32, i32)>>),
+}
+
+/// Delivers `UserEvent`s to whichever loop runs the app: winit's, or the
+/// headless loop's channel.
+#[derive(Clone)]
+enum LoopProxy {
+    Winit(EventLoopProxy<UserEvent>),
+    Headless(mpsc::Sender<UserEvent>),
+}
+
+impl LoopProxy {
+    /// Fails once the loop has exited.
+    fn send_event(&self, event: UserEvent) -> Result<(), ()> {
+        match self {
+            LoopProxy::Winit(proxy) => proxy.send_event(event).map_err(|_| ()),
+            LoopProxy::Headless(sender) => sender.send(event).map_err(|_| ()),
+        }
+    }
+}
+
+/// Set once by `pw_start_app`; `pw_*` functions on any thread read it to reach
+/// the event loop. Its presence is what "started" means.
+static PROXY: std::sync::OnceLock<LoopProxy> = std::sync::OnceLock::new();
+
+/// Held while `pw_start_app` sets up and publishes [`PROXY`] and while a
+/// pre-start setter checks it and stores its value, so a setting racing
+/// startup from another thread is either picked up by the new app or refused,
+/// never dropped. The log and resource callbacks, which can run during
+/// startup, must not call pre-start setters.
+static START_LOCK: Mutex<()> = Mutex::new(());
+
+/// Run `configure` only if `pw_start_app` hasn't published the event loop yet.
+/// Returns false (and skips `configure`) once it has.
+fn before_start(configure: impl FnOnce()) -> bool {
+    let _guard = START_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    if PROXY.get().is_some() {
+        return false;
+    }
+    configure();
+    true
+}
+
+/// Scripts from `pw_execute_javascript` and `pw_post_message` calls made
+/// before `pw_start_app`, queued for the first page.
+static EARLY_SCRIPTS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Milliseconds a navigation may take to reach `LoadStatus::Complete` before it
+/// is aborted. `0` disables the timeout.
+static LOAD_TIMEOUT_MS: AtomicU64 = AtomicU64::new(0);
+
+/// The longest a resize holds back painting while waiting for Servo to lay
+/// the page out at the new size, so a page that is slow to do so still
+/// catches up. Meanwhile the window shows its last frame stretched.
+const RESIZE_HOLD: Duration = Duration::from_millis(100);
+
+/// How often the event loop spins Servo while the active page animates, when
+/// there is no frame rate cap.
+const ANIMATION_TICK: Duration = Duration::from_millis(16);
+
+/// Where to place the window when it is created, set by
+/// `pw_set_window_position` before `pw_start_app`.
+static INITIAL_POSITION: Mutex<Option<(i32, i32)>> = Mutex::new(None);
+
+/// What `UserEvent::SetWindowState` does to the window.
+#[derive(Clone, Copy, Debug)]
+enum WindowState {
+    Maximized,
+    Minimized,
+    Restored,
+}
+
+/// The window's level when it is created, set by `pw_set_window_level`
+/// before `pw_start_app`.
+static INITIAL_WINDOW_LEVEL: Mutex<WindowLevel> = Mutex::new(WindowLevel::Normal);
+
+/// Bounds on the window's inner size in logical pixels, from
+/// `pw_set_min_window_size` / `pw_set_max_window_size`.
+#[derive(Clone, Copy)]
+struct SizeLimits {
+    min: Option<(u32, u32)>,
+    max: Option<(u32, u32)>,
+}
+
+impl SizeLimits {
+    /// Clamp `size` into the limits, so `pw_resize_window` can't bypass them
+    /// on platforms that honor an explicit resize regardless.
+    fn clamp(&self, (mut width, mut height): (u32, u32)) -> (u32, u32) {
+        if let Some((max_width, max_height)) = self.max {
+            width = width.min(max_width);
+            height = height.min(max_height);
+        }
+        if let Some((min_width, min_height)) = self.min {
+            width = width.max(min_width);
+            height = height.max(min_height);
+        }
+        (width, height)
+    }
 }
 
-static mut ON_EVENT_CALLBACK: Option<extern "C" fn(*const c_char)> = None;
-static PROXY: std::sync::OnceLock<EventLoopProxy<UserEvent>> = std::sync::OnceLock::new();
+static SIZE_LIMITS: Mutex<SizeLimits> = Mutex::new(SizeLimits {
+    min: None,
+    max: None,
+});
+
+/// Whether the active webview has a navigation in flight, readable from any
+/// thread.
+static LOADING: AtomicBool = AtomicBool::new(false);
+
+/// The window's scale factor as `f64` bits, readable from any thread; `0`
+/// until the window exists.
+static SCALE_FACTOR: AtomicU64 = AtomicU64::new(0);
+
+const DOM_SNAPSHOT_JS: &str = include_str!("js/dom_snapshot.js");
+const CONTEXT_MENU_TARGET_JS: &str = include_str!("js/context_menu_target.js");
+
+/// Whether right-clicks show the built-in native menu (macOS only) rather
+/// than a `context_menu` event.
+static NATIVE_CONTEXT_MENU: AtomicBool = AtomicBool::new(true);
+
+/// How long after the last touch ends mouse events are taken to be the
+/// platform's emulation of that touch. Servo turns taps into clicks itself, so
+/// passing both on would click twice.
+const TOUCH_MOUSE_SUPPRESSION: Duration = Duration::from_millis(500);
+
+/// How long a close request waits for `pw_confirm_close` or `pw_cancel_close`
+/// before the window closes anyway.
+const CLOSE_CONFIRM_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Upper bound on the elements in a DOM snapshot, whatever the depth limit.
+const DOM_SNAPSHOT_MAX_NODES: u32 = 10_000;
 
 #[repr(C)]
 pub struct InitParams {
@@ -50,11 +355,251 @@ pub struct InitParams {
     pub url: *const c_char,
     pub width: u32,
     pub height: i32,
-    pub on_event: Option<extern "C" fn(*const c_char)>,
+    pub on_event: Option<EventCallback>,
+    /// Create the window hidden; show it later with `pw_show_window`, e.g.
+    /// once the first `load_status` `complete` event arrives.
+    pub start_hidden: bool,
+    /// Keep the window title as set from Python instead of following the
+    /// page's `document.title`. `title` events are still emitted.
+    pub manual_title: bool,
+    /// User-Agent for every request and `navigator.userAgent`. Null keeps
+    /// Servo's default.
+    pub user_agent: *const c_char,
+    /// Directory to keep cookies, `localStorage` and IndexedDB in across runs,
+    /// created if missing. Null, or a directory that can't be written (which
+    /// emits a `profile_unavailable` event), keeps them in memory.
+    pub profile_dir: *const c_char,
+    /// Render without a window or display, into an offscreen surface of
+    /// `width` x `height` physical pixels, e.g. for screenshots in CI. The
+    /// bridge, `pw_capture_png` and navigation work as usual; window calls
+    /// are ignored. Stop it with `pw_shutdown`.
+    pub headless: bool,
+    /// Run Servo's devtools server on this port (`0` disables it), falling
+    /// back to a free port if it is taken. A `devtools_started` event reports
+    /// the port it listens on. Every connection is accepted, so only enable it
+    /// in development.
+    pub devtools_port: u16,
+    /// Don't close the window when the user asks to. Emit `close_requested`
+    /// instead and close once Python calls `pw_confirm_close`. A second close
+    /// request, or no answer within 30 seconds, closes the window anyway.
+    pub intercept_close: bool,
+    /// Serve the files under this directory as `pywire://app/<path>`, with
+    /// MIME types from their extensions and `404` for missing files. Paths
+    /// can't reach outside it. With a null `url` the app opens
+    /// `pywire://app/index.html`. Null serves nothing.
+    pub app_dir: *const c_char,
+    /// Never open a file dialog for `<input type=file>`, so pages can't read
+    /// local files, e.g. for sandboxed apps. File inputs then stay empty.
+    pub disable_file_uploads: bool,
+    /// Paint at most this many frames per second (`0` for no cap). Frames
+    /// Servo produces in between are coalesced: the latest one is painted
+    /// once the interval has passed.
+    pub max_fps: u32,
+    /// Scroll mouse wheel notches smoothly over a few frames instead of
+    /// jumping. Touchpad scrolling is unaffected.
+    pub smooth_scrolling: bool,
+    /// The window's color before the first frame and wherever the page draws
+    /// no background, as `0xRRGGBBAA`. `0` keeps the default: opaque white,
+    /// or fully transparent with `transparent`.
+    pub background_color: u32,
+    /// Let the desktop show through where the window's content is
+    /// transparent, for custom-shaped or overlay windows. The page must give
+    /// itself a transparent background too. Not supported on every platform
+    /// and compositor: where the OpenGL surface has no alpha channel the
+    /// window stays opaque and a `transparency_unavailable` event says so.
+    pub transparent: bool,
+    /// How much Servo and the shell log: `"off"`, `"error"`, `"warn"`,
+    /// `"info"`, `"debug"` or `"trace"`. Null logs warnings and errors. Logs
+    /// go to stderr, or to the callback from `pw_set_log_callback`.
+    pub log_level: *const c_char,
+    /// The directory with Servo's engine resources. Null falls back to the
+    /// `SERVO_RESOURCES_PATH` environment variable. One of them must be set
+    /// unless `pw_add_resource` or `pw_set_resource_callback` supply the
+    /// resources.
+    pub resources_path: *const c_char,
+    /// The HTTP proxy for all requests, e.g. `"http://proxy.corp:3128"`;
+    /// `http://` and `https://` proxies are supported. Null connects
+    /// directly. A malformed URL fails `pw_start_app` with `-4`.
+    pub proxy_url: *const c_char,
+    /// Hosts that bypass `proxy_url`, separated by commas: host names,
+    /// domains (`.corp.example`) or IP ranges. Null bypasses none.
+    pub no_proxy: *const c_char,
+    /// A PEM file with the certificate authorities to trust, e.g. a company
+    /// CA. They replace the built-in roots, so an app that also loads public
+    /// sites must include their CAs too. Null trusts the built-in roots. A
+    /// missing file fails `pw_start_app` with `-4`.
+    pub ca_certificates: *const c_char,
+    /// Start with the page's media muted, e.g. for kiosks; `pw_set_muted`
+    /// unmutes it.
+    pub start_muted: bool,
+    /// Which media may play before the user interacts with the page:
+    /// `"allow"` (all of it), `"block-audio"` (only media without sound) or
+    /// `"block"` (none). Applies from the first page on. Null allows all, as
+    /// Servo does. An unknown policy fails `pw_start_app` with `-4`.
+    pub autoplay_policy: *const c_char,
+    /// Report the page as `unresponsive` once it has loaded but painted
+    /// nothing, or left a script unanswered, for this many milliseconds, so
+    /// the app can offer a reload. Time spent in an `alert()`, `confirm()`
+    /// or file dialog doesn't count. `0` turns the watchdog off.
+    pub watchdog_timeout_ms: u32,
+    /// Give the window the OS title bar and borders. Without them, apps draw
+    /// their own in HTML, and the page moves and resizes the window with
+    /// `pywire.startDrag()` / `pywire.startResize(edge)` or the
+    /// `data-pywire-drag` / `data-pywire-resize` attributes.
+    pub decorations: bool,
+    /// Leave the page empty when a load fails, instead of showing Servo's
+    /// error page, for apps that show their own error UI on the
+    /// `navigation_failed` event.
+    pub hide_error_pages: bool,
+    /// Run `pw_execute_javascript` and `pw_post_message` calls right away, on
+    /// whatever is loaded, instead of holding those made before the first
+    /// page has loaded until it has. Calls made before `pw_start_app` are
+    /// then dropped.
+    pub disable_script_queue: bool,
+    /// Return from `pw_start_app` once the app is set up instead of running
+    /// its event loop until it closes. The caller then drives the loop with
+    /// `pw_poll_events` from the same thread, e.g. to keep its own main loop
+    /// on the main thread, which macOS requires of the window's event loop.
+    pub pump_events: bool,
+}
+
+/// The window background for `InitParams::background_color`, as RGBA from 0
+/// to 1.
+fn background_rgba(color: u32, transparent: bool) -> [f32; 4] {
+    match (color, transparent) {
+        (0, false) => [1.0, 1.0, 1.0, 1.0],
+        (0, true) => [0.0, 0.0, 0.0, 0.0],
+        _ => color.to_be_bytes().map(|channel| channel as f32 / 255.0),
+    }
+}
+
+/// Fill the framebuffer bound on `gl` with `color`.
+fn clear_framebuffer(gl: &glow::Context, color: [f32; 4]) {
+    use glow::HasContext;
+    unsafe {
+        gl.clear_color(color[0], color[1], color[2], color[3]);
+        gl.clear(glow::COLOR_BUFFER_BIT);
+    }
+}
+
+/// How many bits of alpha the framebuffer bound on `gl` has. Without any,
+/// the desktop can't show through a transparent window.
+fn framebuffer_alpha_bits(gl: &glow::Context) -> i32 {
+    use glow::HasContext;
+    unsafe {
+        let attachment = match gl.get_parameter_framebuffer(glow::FRAMEBUFFER_BINDING) {
+            Some(_) => glow::COLOR_ATTACHMENT0,
+            None if gl.version().is_embedded => glow::BACK,
+            None => glow::BACK_LEFT,
+        };
+        gl.get_framebuffer_attachment_parameter_i32(
+            glow::FRAMEBUFFER,
+            attachment,
+            glow::FRAMEBUFFER_ATTACHMENT_ALPHA_SIZE,
+        )
+    }
+}
+
+/// The OS cursor for a cursor the page asked for, or `None` to hide it
+/// (`cursor: none`).
+fn cursor_icon(cursor: Cursor) -> Option<CursorIcon> {
+    let icon = match cursor {
+        Cursor::None => return None,
+        Cursor::Default => CursorIcon::Default,
+        Cursor::Pointer => CursorIcon::Pointer,
+        Cursor::Text => CursorIcon::Text,
+        Cursor::Wait => CursorIcon::Wait,
+        Cursor::Help => CursorIcon::Help,
+        Cursor::Progress => CursorIcon::Progress,
+        Cursor::NotAllowed => CursorIcon::NotAllowed,
+        Cursor::ContextMenu => CursorIcon::ContextMenu,
+        Cursor::Cell => CursorIcon::Cell,
+        Cursor::Crosshair => CursorIcon::Crosshair,
+        Cursor::VerticalText => CursorIcon::VerticalText,
+        Cursor::Alias => CursorIcon::Alias,
+        Cursor::Copy => CursorIcon::Copy,
+        Cursor::Move => CursorIcon::Move,
+        Cursor::NoDrop => CursorIcon::NoDrop,
+        Cursor::Grab => CursorIcon::Grab,
+        Cursor::Grabbing => CursorIcon::Grabbing,
+        Cursor::AllScroll => CursorIcon::AllScroll,
+        Cursor::ColResize => CursorIcon::ColResize,
+        Cursor::RowResize => CursorIcon::RowResize,
+        Cursor::NResize => CursorIcon::NResize,
+        Cursor::EResize => CursorIcon::EResize,
+        Cursor::SResize => CursorIcon::SResize,
+        Cursor::WResize => CursorIcon::WResize,
+        Cursor::NeResize => CursorIcon::NeResize,
+        Cursor::NwResize => CursorIcon::NwResize,
+        Cursor::SeResize => CursorIcon::SeResize,
+        Cursor::SwResize => CursorIcon::SwResize,
+        Cursor::EwResize => CursorIcon::EwResize,
+        Cursor::NsResize => CursorIcon::NsResize,
+        Cursor::NeswResize => CursorIcon::NeswResize,
+        Cursor::NwseResize => CursorIcon::NwseResize,
+        Cursor::ZoomIn => CursorIcon::ZoomIn,
+        Cursor::ZoomOut => CursorIcon::ZoomOut,
+        // Kinds a newer Servo may add get the arrow until they are mapped.
+        #[allow(unreachable_patterns)]
+        _ => CursorIcon::Default,
+    };
+    Some(icon)
+}
+
+/// Start moving or resizing `window` with the mouse for the page's
+/// `pywire.startDrag()` (`"drag"`) or `pywire.startResize(edge)`
+/// (`"resize:<edge>"`). Must happen while the mouse button is still down.
+fn drag_window(window: &Window, action: &str) {
+    let result = match action.strip_prefix("resize:") {
+        None if action == "drag" => window.drag_window(),
+        None => return,
+        Some(edge) => {
+            let direction = match edge {
+                "n" => ResizeDirection::North,
+                "s" => ResizeDirection::South,
+                "e" => ResizeDirection::East,
+                "w" => ResizeDirection::West,
+                "ne" => ResizeDirection::NorthEast,
+                "nw" => ResizeDirection::NorthWest,
+                "se" => ResizeDirection::SouthEast,
+                "sw" => ResizeDirection::SouthWest,
+                _ => return,
+            };
+            window.drag_resize_window(direction)
+        }
+    };
+    if let Err(e) = result {
+        log::warn!("Could not {} the window: {}", action, e);
+    }
+}
+
+/// Build a `data:` URL for a minimal error page shown in place of a failed load.
+fn error_page_url(title: &str, detail: &str) -> Url {
+    let escape = |text: &str| {
+        text.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+    };
+    let html = format!(
+        "<!DOCTYPE html><html><head><title>{title}</title></head>\
+         <body style=\"font-family: sans-serif; padding: 2em\">\
+         <h1>{title}</h1><p>{detail}</p></body></html>",
+        title = escape(title),
+        detail = escape(detail),
+    );
+    let mut encoded = String::from("data:text/html;charset=utf-8,");
+    for byte in html.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-_.~".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    Url::parse(&encoded).unwrap_or_else(|_| Url::parse("about:blank").unwrap())
 }
 
 struct JsonWaker {
-    proxy: EventLoopProxy<UserEvent>,
+    proxy: LoopProxy,
 }
 
 impl EventLoopWaker for JsonWaker {
@@ -68,47 +613,318 @@ impl EventLoopWaker for JsonWaker {
     }
 }
 
-struct PyWireResourceReader {
-    path: PathBuf,
+struct PyWireWebViewDelegate {
+    /// The id Python knows this delegate's webview by.
+    webview_id: u32,
+    /// The id of the webview currently shown. Only that one drives the
+    /// window: its title, cursor, load progress and repaints.
+    active_webview: Rc<Cell<Option<u32>>>,
+    /// `None` when running headless.
+    window: Option<Arc<Window>>,
+    needs_repaint: Rc<Cell<bool>>,
+    /// When the in-flight navigation started (or last made progress).
+    load_started: Rc<Cell<Option<Instant>>>,
+    network_manager: NetworkManager,
+    /// Scroll offset to restore once the in-flight load completes.
+    pending_scroll: Rc<Cell<Option<(f64, f64)>>>,
+    zoom: Rc<RefCell<ZoomLevels>>,
+    pending_navigation: Rc<RefCell<Option<PendingNavigation>>>,
+    /// Whether the window title follows `document.title`.
+    sync_title: bool,
+    /// The text input the IME is composing into, while one has focus.
+    ime_control: Cell<Option<EmbedderControlId>>,
+    /// The keyboard modifiers currently held.
+    modifiers: Rc<Cell<winit::keyboard::ModifiersState>>,
+    /// The mouse buttons currently held, as a DOM `buttons` mask.
+    pressed_mouse_buttons: Rc<Cell<u16>>,
+    /// Whether `<input type=file>` may open a file dialog.
+    file_uploads: bool,
+    authentications: Rc<RefCell<PendingAuthentications>>,
+    cert_errors: Rc<RefCell<PendingCertErrors>>,
+    permissions: Rc<RefCell<PendingPermissions<PermissionRequest>>>,
+    /// Webviews showing find-in-page highlights.
+    find_webviews: Rc<RefCell<HashSet<u32>>>,
+    /// The webviews with CSS animations or `requestAnimationFrame` callbacks
+    /// running.
+    animating_webviews: Rc<RefCell<HashSet<u32>>>,
+    /// The webviews with a load in flight.
+    loading_webviews: Rc<RefCell<HashSet<u32>>>,
+    /// Whether `pw_stop_loading` stopped the active page's load, which is
+    /// reported once the load has ended.
+    stop_requested: Rc<Cell<bool>>,
+    /// For opening webviews on a page's behalf (`window.open`).
+    servo: Weak<Servo>,
+    rendering_context: Rc<dyn RenderingContext>,
+    user_content_manager: Option<Rc<UserContentManager>>,
+    next_webview_id: Rc<Cell<u32>>,
+    /// Webviews pages opened or declined, for the app to pick up.
+    webview_requests: Rc<RefCell<Vec<WebViewRequest>>>,
+    /// The webview that opened this one, until its first navigation has been
+    /// reported as `new_window`.
+    opener: Cell<Option<u32>>,
+    /// When this webview's page last crashed, to hold off reloading one that
+    /// keeps crashing.
+    last_crash: Cell<Option<Instant>>,
+    /// Watches the active page for a blank window or a hung script.
+    watchdog: Rc<RefCell<Watchdog>>,
+    /// The active page's status bar text.
+    status_text: Rc<RefCell<StatusText>>,
+    /// Scripts from `pw_execute_javascript` and `pw_post_message` held until
+    /// the first page has loaded, or `None` once it has (or with
+    /// `InitParams::disable_script_queue`).
+    queued_scripts: Rc<RefCell<Option<Vec<String>>>>,
 }
 
-impl ResourceReaderMethods for PyWireResourceReader {
-    fn read(&self, res: Resource) -> Vec<u8> {
-        let mut path = self.path.clone();
-        path.push(res.filename());
-        match fs::read(&path) {
-            Ok(bytes) => bytes,
-            Err(e) => {
-                eprintln!(
-                    "[pw_servo] Error reading resource {:?} from {:?}: {}",
-                    res.filename(),
-                    path,
-                    e
-                );
-                vec![]
+/// A change to the app's webviews made on a page's behalf.
+enum WebViewRequest {
+    /// Add a webview opened by a page.
+    Adopt(u32, WebView),
+    /// Close a page-opened webview the app declined.
+    Close(u32),
+}
+
+impl PyWireWebViewDelegate {
+    /// A delegate for another webview, sharing this one's state.
+    fn for_webview(&self, webview_id: u32, opener: Option<u32>) -> Self {
+        PyWireWebViewDelegate {
+            webview_id,
+            active_webview: self.active_webview.clone(),
+            window: self.window.clone(),
+            needs_repaint: self.needs_repaint.clone(),
+            load_started: self.load_started.clone(),
+            network_manager: self.network_manager.clone(),
+            pending_scroll: self.pending_scroll.clone(),
+            zoom: self.zoom.clone(),
+            pending_navigation: self.pending_navigation.clone(),
+            sync_title: self.sync_title,
+            ime_control: Cell::new(None),
+            modifiers: self.modifiers.clone(),
+            pressed_mouse_buttons: self.pressed_mouse_buttons.clone(),
+            file_uploads: self.file_uploads,
+            authentications: self.authentications.clone(),
+            cert_errors: self.cert_errors.clone(),
+            permissions: self.permissions.clone(),
+            find_webviews: self.find_webviews.clone(),
+            animating_webviews: self.animating_webviews.clone(),
+            loading_webviews: self.loading_webviews.clone(),
+            stop_requested: self.stop_requested.clone(),
+            servo: self.servo.clone(),
+            rendering_context: self.rendering_context.clone(),
+            user_content_manager: self.user_content_manager.clone(),
+            next_webview_id: self.next_webview_id.clone(),
+            webview_requests: self.webview_requests.clone(),
+            opener: Cell::new(opener),
+            last_crash: Cell::new(None),
+            watchdog: self.watchdog.clone(),
+            status_text: self.status_text.clone(),
+            queued_scripts: self.queued_scripts.clone(),
+        }
+    }
+
+    fn is_active(&self) -> bool {
+        self.active_webview.get() == Some(self.webview_id)
+    }
+
+    /// Emit `event` tagged with the id of the webview it comes from.
+    fn emit(&self, event_type: EventType, mut event: serde_json::Value) {
+        event["webview_id"] = self.webview_id.into();
+        emit_event(event_type, event);
+    }
+
+    /// Hold a certificate error the error page reported and tell Python.
+    /// Only the top-level page can accept its certificate, so errors in
+    /// frames are reported as not allowable. Reports without the page's
+    /// nonce are ignored, as any page could log the marker.
+    fn report_cert_error(&self, webview: &WebView, payload: &str) {
+        let Some(mut error) = cert::parse_report(payload) else {
+            log::warn!("Malformed certificate error report: {}", payload);
+            return;
+        };
+        if !net_error::take_nonce(&error.nonce) {
+            log::warn!("Ignoring a certificate error report not from the error page");
+            return;
+        }
+        log::warn!("Certificate error for {}: {}", error.host, error.error);
+        let top_level = webview.url().is_some_and(|url| url.as_str() == error.url);
+        error.allowable &= top_level;
+        let id = self
+            .cert_errors
+            .borrow_mut()
+            .add(self.webview_id, error.url.clone());
+        self.emit(
+            EventType::Network,
+            serde_json::json!({
+                "type": "cert_error",
+                "request_id": id,
+                "url": error.url,
+                "host": error.host,
+                "error": error.error,
+                "allowable": error.allowable,
+            }),
+        );
+    }
+
+    /// Report a right-click at `(x, y)` (device pixels) as a `context_menu`
+    /// event, with what the page has under the pointer.
+    fn emit_context_menu(&self, webview: &WebView, x: i32, y: i32) {
+        let modifiers = self.modifiers.get();
+        let mut event = serde_json::json!({
+            "type": "context_menu",
+            "webview_id": self.webview_id,
+            "x": x,
+            "y": y,
+            "modifiers": {
+                "shift": modifiers.shift_key(),
+                "ctrl": modifiers.control_key(),
+                "alt": modifiers.alt_key(),
+                "meta": modifiers.super_key(),
+            },
+            "link_url": null,
+            "image_url": null,
+            "selection": null,
+            "editable": false,
+        });
+        let script = CONTEXT_MENU_TARGET_JS
+            .replace("__X__", &x.to_string())
+            .replace("__Y__", &y.to_string());
+        webview.evaluate_javascript(script, move |result| {
+            // Without the page's details Python still gets the position.
+            if let Ok(JSValue::String(json)) = result {
+                if let Ok(serde_json::Value::Object(target)) = serde_json::from_str(&json) {
+                    for (key, value) in target {
+                        event[key] = value;
+                    }
+                }
             }
+            emit_event(EventType::Input, event);
+        });
+    }
+
+    /// Report the end of a load `pw_stop_loading` stopped, ending its
+    /// `pw_navigate_with_callback` navigation as stopped rather than complete.
+    fn report_load_stopped(&self, webview: &WebView) {
+        let navigation = self.pending_navigation.borrow_mut().take();
+        if let Some(navigation) = navigation {
+            navigation.finish(false, "stopped");
         }
+        self.emit(
+            EventType::Load,
+            serde_json::json!({
+                "type": "load_stopped",
+                "url": webview.url().map(|url| url.to_string()),
+            }),
+        );
     }
-    fn sandbox_access_files(&self) -> Vec<PathBuf> {
-        vec![]
+
+    /// Note when a `pw_navigate_with_callback` navigation's load starts, and
+    /// end it as superseded if another load starts before it completes.
+    /// [`Self::check_http_status`] ends it once its load completes.
+    fn track_pending_navigation(&self, status: LoadStatus) {
+        let finished = {
+            let mut pending = self.pending_navigation.borrow_mut();
+            match (pending.as_mut(), status) {
+                (Some(navigation), LoadStatus::Started) if navigation.started => pending
+                    .take()
+                    .map(|navigation| (navigation, false, "superseded")),
+                (Some(navigation), LoadStatus::Started) => {
+                    navigation.started = true;
+                    None
+                }
+                _ => None,
+            }
+        };
+        // Run the callback without holding the borrow, in case it calls back in.
+        if let Some((navigation, success, status)) = finished {
+            navigation.finish(success, status);
+        }
     }
-    fn sandbox_access_files_dirs(&self) -> Vec<PathBuf> {
-        vec![self.path.clone()]
+
+    /// Once `webview` has finished loading a page, report it as failed if the
+    /// server answered with an HTTP error status. The pending
+    /// `pw_navigate_with_callback` navigation, if this load was its own, ends
+    /// when the status is known.
+    fn check_http_status(&self, webview: &WebView) {
+        let navigation = if self.is_active() {
+            let mut pending = self.pending_navigation.borrow_mut();
+            match pending.as_ref() {
+                Some(navigation) if navigation.started => pending.take(),
+                _ => None,
+            }
+        } else {
+            None
+        };
+        let webview_id = self.webview_id;
+        let url = webview.url().map(|url| url.to_string());
+        webview.evaluate_javascript(net_error::HTTP_STATUS_JS, move |result| {
+            let status = match result {
+                Ok(JSValue::Number(status)) => status as u16,
+                _ => 0,
+            };
+            if !net_error::is_http_error(status) {
+                if let Some(navigation) = navigation {
+                    navigation.finish(true, "complete");
+                }
+                return;
+            }
+            log::warn!("Failed to load {:?}: HTTP {}", url, status);
+            emit_event(
+                EventType::Navigation,
+                serde_json::json!({
+                    "type": "navigation_failed",
+                    "webview_id": webview_id,
+                    "url": url,
+                    "error": format!("HTTP {}", status),
+                    "kind": "http",
+                    "status": status,
+                }),
+            );
+            if let Some(navigation) = navigation {
+                navigation.finish(false, "failed");
+            }
+        });
     }
-}
 
-struct PyWireWebViewDelegate {
-    window: Arc<Window>,
-    needs_repaint: Rc<Cell<bool>>,
-}
+    /// Report a load the network error page says failed, and end the pending
+    /// `pw_navigate_with_callback` navigation as failed. Reports without the
+    /// page's nonce, or from a webview no longer showing the failed URL, are
+    /// ignored, as any page could log the marker.
+    fn report_load_failure(&self, webview: &WebView, payload: &str) {
+        let Some(failure) = net_error::parse_report(payload) else {
+            log::warn!("Malformed load failure report: {}", payload);
+            return;
+        };
+        let current = webview.url().is_some_and(|url| url.as_str() == failure.url);
+        if !net_error::take_nonce(&failure.nonce) || !current {
+            log::warn!("Ignoring a load failure report not from the error page");
+            return;
+        }
+        log::warn!("Failed to load {}: {}", failure.url, failure.error);
+        self.emit(
+            EventType::Navigation,
+            serde_json::json!({
+                "type": "navigation_failed",
+                "url": failure.url,
+                "error": failure.error,
+                "kind": failure.kind,
+            }),
+        );
+        if !self.is_active() {
+            return;
+        }
+        // The error page still completes loading, but the navigation failed.
+        let navigation = self.pending_navigation.borrow_mut().take();
+        if let Some(navigation) = navigation {
+            navigation.finish(false, "failed");
+        }
+    }
 
-impl PyWireWebViewDelegate {
     #[cfg(target_os = "macos")]
     fn show_native_context_menu(&self, mtm: MainThreadMarker, menu: ContextMenu) {
-        let window_handle = self
-            .window
-            .window_handle()
-            .expect("Failed to get window handle");
+        let Some(window) = &self.window else {
+            menu.dismiss();
+            return;
+        };
+        let window_handle = window.window_handle().expect("Failed to get window handle");
         if let raw_window_handle::RawWindowHandle::AppKit(handle) = window_handle.as_raw() {
             unsafe {
                 let view_ptr = handle.ns_view.as_ptr() as *mut NSView;
@@ -121,7 +937,7 @@ impl PyWireWebViewDelegate {
                 // Get position from Servo's context menu data.
                 let pos = menu.position();
                 // Convert from DevicePixels (Servo) to Logical Points (AppKit).
-                let scale = self.window.scale_factor();
+                let scale = window.scale_factor();
                 let logical_x = pos.min.x as f64 / scale;
                 let logical_y = pos.min.y as f64 / scale;
 
@@ -129,9 +945,12 @@ impl PyWireWebViewDelegate {
                 // so we don't need the view_height - y flip.
                 let ns_point = objc2_foundation::NSPoint::new(logical_x, logical_y);
 
-                println!(
-                    "[pw_servo] Context menu: pos=({:?}), scale={}, ns_point=({}, {})",
-                    pos, scale, ns_point.x, ns_point.y
+                log::debug!(
+                    "Context menu: pos=({:?}), scale={}, ns_point=({}, {})",
+                    pos,
+                    scale,
+                    ns_point.x,
+                    ns_point.y
                 );
 
                 let ns_menu = NSMenu::new(mtm);
@@ -196,91 +1015,435 @@ impl PyWireWebViewDelegate {
 }
 
 impl WebViewDelegate for PyWireWebViewDelegate {
-    fn show_console_message(&self, _webview: WebView, level: ConsoleLogLevel, message: String) {
+    fn show_console_message(&self, webview: WebView, level: ConsoleLogLevel, message: String) {
         // Intercept PW_MSG: prefix for JS -> Python bridge
         if let Some(payload) = message.strip_prefix("PW_MSG:") {
-            unsafe {
-                if let Some(cb) = ON_EVENT_CALLBACK {
-                    use std::ffi::CString;
-                    if let Ok(c_payload) = CString::new(payload) {
-                        cb(c_payload.as_ptr());
-                    }
-                }
+            emit_bridge_message(payload);
+        } else if let Some(payload) = message.strip_prefix("PW_CERT:") {
+            self.report_cert_error(&webview, payload);
+        } else if let Some(payload) = message.strip_prefix("PW_NETERR:") {
+            self.report_load_failure(&webview, payload);
+        } else if let Some(action) = message.strip_prefix("PW_WINDOW:") {
+            // Only the page shown in the window may move it.
+            if let (true, Some(window)) = (self.is_active(), &self.window) {
+                drag_window(window, action);
+                // The OS takes over the mouse and the button's release never
+                // reaches the page, so don't count it as held afterwards.
+                self.pressed_mouse_buttons.set(0);
+            }
+        } else if message == "PW_PRINT:" {
+            // Only the active webview is painted, so only it can be captured.
+            if self.is_active() {
+                send_user_event(UserEvent::ShowPrintDialog);
             }
         } else {
-            println!("[console] {:?}: {}", level, message);
+            log::debug!(target: "console", "{:?}: {}", level, message);
+            self.emit(
+                EventType::Console,
+                serde_json::json!({
+                    "type": "console",
+                    "level": format!("{:?}", level).to_lowercase(),
+                    "message": message,
+                }),
+            );
         }
     }
 
     fn notify_new_frame_ready(&self, _webview: WebView) {
         // println!("[pw_servo] New frame ready, requesting redraw");
+        if !self.is_active() {
+            return;
+        }
         self.needs_repaint.set(true);
-        self.window.request_redraw();
+        if let Some(window) = &self.window {
+            window.request_redraw();
+        }
+        if let Some(change) = self.watchdog.borrow_mut().frame_painted() {
+            self.emit(EventType::Crash, watchdog::event(&change));
+        }
+    }
+
+    fn notify_animating_changed(&self, _webview: WebView, animating: bool) {
+        let mut animating_webviews = self.animating_webviews.borrow_mut();
+        if animating {
+            animating_webviews.insert(self.webview_id);
+        } else {
+            animating_webviews.remove(&self.webview_id);
+        }
+    }
+
+    fn notify_load_status_changed(&self, webview: WebView, status: LoadStatus) {
+        log::debug!("Load status changed: {:?}", status);
+        // The delegate is attached before the initial URL starts loading, so
+        // listeners registered before `pw_start_app` see the first load too.
+        self.emit(
+            EventType::Load,
+            serde_json::json!({
+                "type": "load_status",
+                "status": match status {
+                    LoadStatus::Started => "started",
+                    LoadStatus::HeadParsed => "head_parsed",
+                    LoadStatus::Complete => "complete",
+                },
+                "url": webview.url().map(|url| url.to_string()),
+                "can_go_back": webview.can_go_back(),
+                "can_go_forward": webview.can_go_forward(),
+            }),
+        );
+        if matches!(status, LoadStatus::HeadParsed) {
+            injected::inject_into(&webview);
+        }
+        if matches!(status, LoadStatus::Complete) {
+            self.loading_webviews.borrow_mut().remove(&self.webview_id);
+        } else {
+            self.loading_webviews.borrow_mut().insert(self.webview_id);
+        }
+        if matches!(status, LoadStatus::Complete) && self.is_active() && self.stop_requested.take()
+        {
+            self.report_load_stopped(&webview);
+        }
+        if matches!(status, LoadStatus::Complete) {
+            self.check_http_status(&webview);
+        }
+        if !self.is_active() {
+            return;
+        }
+        if matches!(status, LoadStatus::Complete)
+            && NETWORK_POLICY
+                .lock()
+                .unwrap()
+                .cache_mode
+                .clears_after_load()
+        {
+            self.network_manager.clear_cache();
+        }
+        // The load timeout is measured from the last sign of progress, so a slow
+        // page that has at least parsed its head gets a fresh budget.
+        match status {
+            LoadStatus::Started | LoadStatus::HeadParsed => {
+                self.load_started.set(Some(Instant::now()))
+            }
+            LoadStatus::Complete => self.load_started.set(None),
+        }
+        if matches!(status, LoadStatus::Started) {
+            // A navigation that starts after the stop isn't affected by it.
+            self.stop_requested.set(false);
+        }
+        match status {
+            LoadStatus::Started => self.watchdog.borrow_mut().reset(),
+            LoadStatus::HeadParsed => {}
+            LoadStatus::Complete => self.watchdog.borrow_mut().load_complete(Instant::now()),
+        }
+        LOADING.store(!matches!(status, LoadStatus::Complete), Ordering::Relaxed);
+        self.track_pending_navigation(status);
+        if platform::AUTO_TASKBAR_PROGRESS.load(Ordering::Relaxed) {
+            // Servo reports load milestones rather than bytes, so the bar
+            // moves in steps.
+            let (state, fraction) = match status {
+                LoadStatus::Started => (ProgressState::Normal, 0.1),
+                LoadStatus::HeadParsed => (ProgressState::Normal, 0.5),
+                LoadStatus::Complete => (ProgressState::None, 0.0),
+            };
+            if let Some(window) = &self.window {
+                platform::set_taskbar_progress(window, state, fraction);
+            }
+        }
+        if matches!(status, LoadStatus::Complete) {
+            if let Some((x, y)) = self.pending_scroll.take() {
+                scroll_to(&webview, x, y);
+            }
+            let queued_scripts = self.queued_scripts.take();
+            for script in queued_scripts.into_iter().flatten() {
+                webview.evaluate_javascript(script, |_result| {});
+            }
+        }
+        if let Some(window) = &self.window {
+            window.request_redraw();
+        }
+    }
+
+    fn notify_page_title_changed(&self, _webview: WebView, title: Option<String>) {
+        if let (true, true, Some(window), Some(title)) =
+            (self.sync_title, self.is_active(), &self.window, &title)
+        {
+            window.set_title(title);
+        }
+        self.emit(
+            EventType::Title,
+            serde_json::json!({"type": "title", "title": title}),
+        );
     }
 
-    fn notify_load_status_changed(&self, _webview: WebView, status: LoadStatus) {
-        println!("[pw_servo] Load status changed: {:?}", status);
-        self.window.request_redraw();
+    fn notify_status_text_changed(&self, _webview: WebView, status: Option<String>) {
+        if !self.is_active() {
+            return;
+        }
+        let cleared = self
+            .status_text
+            .borrow_mut()
+            .update(status.unwrap_or_default(), Instant::now());
+        if let Some(text) = cleared {
+            self.emit(
+                EventType::Title,
+                serde_json::json!({"type": "status_text", "text": text}),
+            );
+        }
+    }
+
+    fn notify_favicon_changed(&self, webview: WebView) {
+        self.emit(
+            EventType::Title,
+            favicon::event(webview.favicon().as_deref()),
+        );
+    }
+
+    fn notify_url_changed(&self, webview: WebView, url: Url) {
+        // Entering a different origin may bring a different remembered zoom.
+        self.zoom.borrow_mut().apply(&webview);
+        // A search doesn't carry over to another page. A new document starts
+        // without highlights, but a same-document navigation keeps them.
+        if self.find_webviews.borrow_mut().remove(&self.webview_id) {
+            webview.evaluate_javascript(find::CLEAR_JS, |_result| {});
+            self.emit(EventType::Find, serde_json::json!({"type": "find_cleared"}));
+        }
+        self.emit(
+            EventType::Navigation,
+            serde_json::json!({"type": "url_changed", "url": url.as_str()}),
+        );
+    }
+
+    fn notify_history_changed(&self, _webview: WebView, entries: Vec<Url>, current: usize) {
+        // Also called for `history.pushState` and fragment navigations, which
+        // never change the load status.
+        self.emit(
+            EventType::Navigation,
+            serde_json::json!({
+                "type": "history_changed",
+                "index": current,
+                "length": entries.len(),
+                "can_go_back": current > 0,
+                "can_go_forward": current + 1 < entries.len(),
+            }),
+        );
+    }
+
+    fn notify_crashed(&self, webview: WebView, reason: String, backtrace: Option<String>) {
+        log::error!("The page crashed: {}", reason);
+        let now = Instant::now();
+        let reloading = crash::CRASH_RECOVERY.load(Ordering::Relaxed)
+            && crash::should_reload(self.last_crash.replace(Some(now)), now);
+        self.emit(
+            EventType::Crash,
+            serde_json::json!({
+                "type": "crash",
+                "detail": reason,
+                "backtrace": backtrace,
+                "reloading": reloading,
+            }),
+        );
+        if reloading {
+            webview.reload();
+        }
     }
 
     fn notify_cursor_changed(&self, _webview: WebView, cursor: Cursor) {
         // println!("[pw_servo] Cursor changed: {:?}", cursor);
-        match cursor {
-            Cursor::Default => self.window.set_cursor(CursorIcon::Default),
-            Cursor::Pointer => self.window.set_cursor(CursorIcon::Pointer),
-            Cursor::Text => self.window.set_cursor(CursorIcon::Text),
-            Cursor::Wait => self.window.set_cursor(CursorIcon::Wait),
-            Cursor::Help => self.window.set_cursor(CursorIcon::Help),
-            Cursor::Progress => self.window.set_cursor(CursorIcon::Progress),
-            Cursor::NotAllowed => self.window.set_cursor(CursorIcon::NotAllowed),
-            Cursor::ContextMenu => self.window.set_cursor(CursorIcon::ContextMenu),
-            Cursor::Cell => self.window.set_cursor(CursorIcon::Cell),
-            Cursor::Crosshair => self.window.set_cursor(CursorIcon::Crosshair),
-            Cursor::VerticalText => self.window.set_cursor(CursorIcon::VerticalText),
-            Cursor::Alias => self.window.set_cursor(CursorIcon::Alias),
-            Cursor::Copy => self.window.set_cursor(CursorIcon::Copy),
-            Cursor::NoDrop => self.window.set_cursor(CursorIcon::NoDrop),
-            Cursor::Grab => self.window.set_cursor(CursorIcon::Grab),
-            Cursor::Grabbing => self.window.set_cursor(CursorIcon::Grabbing),
-            Cursor::AllScroll => self.window.set_cursor(CursorIcon::AllScroll),
-            Cursor::ColResize => self.window.set_cursor(CursorIcon::ColResize),
-            Cursor::RowResize => self.window.set_cursor(CursorIcon::RowResize),
-            Cursor::NResize => self.window.set_cursor(CursorIcon::NResize),
-            Cursor::EResize => self.window.set_cursor(CursorIcon::EResize),
-            Cursor::SResize => self.window.set_cursor(CursorIcon::SResize),
-            Cursor::WResize => self.window.set_cursor(CursorIcon::WResize),
-            Cursor::NeResize => self.window.set_cursor(CursorIcon::NeResize),
-            Cursor::NwResize => self.window.set_cursor(CursorIcon::NwResize),
-            Cursor::SeResize => self.window.set_cursor(CursorIcon::SeResize),
-            Cursor::SwResize => self.window.set_cursor(CursorIcon::SwResize),
-            Cursor::EwResize => self.window.set_cursor(CursorIcon::EwResize),
-            Cursor::NsResize => self.window.set_cursor(CursorIcon::NsResize),
-            Cursor::NeswResize => self.window.set_cursor(CursorIcon::NeswResize),
-            Cursor::NwseResize => self.window.set_cursor(CursorIcon::NwseResize),
-            _ => self.window.set_cursor(CursorIcon::Default),
+        if !self.is_active() {
+            return;
+        }
+        if let Some(window) = &self.window {
+            match cursor_icon(cursor) {
+                Some(icon) => {
+                    window.set_cursor(icon);
+                    window.set_cursor_visible(true);
+                }
+                None => window.set_cursor_visible(false),
+            }
+        }
+    }
+
+    fn notify_fullscreen_state_changed(&self, _webview: WebView, fullscreen: bool) {
+        // The page called `requestFullscreen()` or left fullscreen (including
+        // with Escape). The `fullscreen` event goes out once the window's
+        // state actually changes.
+        if !self.is_active() {
+            return;
+        }
+        if let Some(window) = &self.window {
+            set_window_fullscreen(window, fullscreen);
         }
     }
 
     fn notify_focus_changed(&self, _webview: WebView, focused: bool) {
-        println!("[pw_servo] Servo notified focus changed: {}", focused);
+        log::debug!("Servo notified focus changed: {}", focused);
     }
 
-    fn show_embedder_control(&self, _webview: WebView, control: EmbedderControl) {
+    fn show_embedder_control(&self, webview: WebView, control: EmbedderControl) {
         match control {
             EmbedderControl::ContextMenu(menu) => {
                 #[cfg(target_os = "macos")]
                 {
-                    if let Some(mtm) = MainThreadMarker::new() {
-                        self.show_native_context_menu(mtm, menu);
-                        return;
+                    if NATIVE_CONTEXT_MENU.load(Ordering::Relaxed) {
+                        if let Some(mtm) = MainThreadMarker::new() {
+                            self.show_native_context_menu(mtm, menu);
+                            return;
+                        }
                     }
                 }
+                // Servo's own menu is empty; Python shows one from the event.
+                let position = menu.position().min;
                 menu.dismiss();
+                self.emit_context_menu(&webview, position.x, position.y);
+            }
+            EmbedderControl::FilePicker(picker) => {
+                if self.file_uploads {
+                    // The page waits on the dialog, which doesn't make it hung.
+                    self.watchdog.borrow_mut().pause();
+                    file_picker::show(picker, self.window.as_deref());
+                    self.watchdog.borrow_mut().resume(Instant::now());
+                } else {
+                    picker.dismiss();
+                }
+            }
+            EmbedderControl::SimpleDialog(dialog) => {
+                let title = webview.page_title().unwrap_or_default();
+                self.watchdog.borrow_mut().pause();
+                dialog::show(dialog, self.window.as_deref(), &title);
+                self.watchdog.borrow_mut().resume(Instant::now());
+            }
+            EmbedderControl::InputMethod(input_method) => {
+                // A text field has focus: let the OS IME compose into it and
+                // anchor its candidate window at the caret.
+                if let Some(window) = &self.window {
+                    let area = input_method.position();
+                    window.set_ime_allowed(true);
+                    window.set_ime_cursor_area(
+                        winit::dpi::PhysicalPosition::new(area.min.x, area.min.y),
+                        winit::dpi::PhysicalSize::new(area.width(), area.height()),
+                    );
+                    self.ime_control.set(Some(input_method.id()));
+                }
             }
             _ => {
-                println!("[pw_servo] Unhandled embedder control: {:?}", control.id());
+                log::info!("Unhandled embedder control: {:?}", control.id());
+            }
+        }
+    }
+
+    fn hide_embedder_control(&self, _webview: WebView, control_id: EmbedderControlId) {
+        if self.ime_control.get() == Some(control_id) {
+            self.ime_control.set(None);
+            if let Some(window) = &self.window {
+                window.set_ime_allowed(false);
+            }
+        }
+    }
+
+    fn request_open_auxiliary_webview(&self, _parent_webview: WebView) -> Option<WebView> {
+        let servo = self.servo.upgrade()?;
+        let id = self.next_webview_id.get();
+        self.next_webview_id.set(id + 1);
+        log::info!(
+            "WebView {} opened a new window, WebView {}",
+            self.webview_id,
+            id
+        );
+        let delegate = Rc::new(self.for_webview(id, Some(self.webview_id)));
+        let scale_factor = self
+            .window
+            .as_ref()
+            .map_or(1.0, |window| window.scale_factor() as f32);
+        let mut builder = WebViewBuilder::new_auxiliary(&servo, self.rendering_context.clone())
+            .delegate(delegate)
+            .hidpi_scale_factor(Scale::new(scale_factor));
+        if let Some(user_content_manager) = &self.user_content_manager {
+            builder = builder.user_content_manager(user_content_manager.clone());
+        }
+        let webview = builder.build();
+        webview.set_clipboard_delegate(Rc::new(clipboard::PyWireClipboardDelegate));
+        self.webview_requests
+            .borrow_mut()
+            .push(WebViewRequest::Adopt(id, webview.clone()));
+        Some(webview)
+    }
+
+    fn request_authentication(&self, _webview: WebView, request: AuthenticationRequest) {
+        let url = request.url().clone();
+        let for_proxy = request.for_proxy();
+        let deadline = Instant::now() + auth::timeout();
+        let id = self
+            .authentications
+            .borrow_mut()
+            .add(self.webview_id, request, deadline);
+        log::info!("Authentication required for {}", url);
+        self.emit(
+            EventType::Network,
+            serde_json::json!({
+                "type": "auth_required",
+                "request_id": id,
+                "url": url.as_str(),
+                "host": url.host_str(),
+                // Servo doesn't pass on the `WWW-Authenticate` challenge, so
+                // the realm is unknown until it does.
+                "realm": serde_json::Value::Null,
+                "for_proxy": for_proxy,
+            }),
+        );
+    }
+
+    fn request_permission(&self, _webview: WebView, request: PermissionRequest) {
+        let kind = permissions::feature_name(request.feature());
+        let deadline = Instant::now() + permissions::timeout();
+        let id = self
+            .permissions
+            .borrow_mut()
+            .add(self.webview_id, request, deadline);
+        log::info!("Permission requested: {}", kind);
+        self.emit(
+            EventType::Permission,
+            serde_json::json!({
+                "type": "permission",
+                "request_id": id,
+                "kind": kind,
+            }),
+        );
+    }
+
+    fn request_navigation(&self, _webview: WebView, navigation_request: NavigationRequest) {
+        // Servo reports where a new window goes with its first navigation.
+        if let Some(opener) = self.opener.take() {
+            let policy = *NEW_WINDOW_POLICY.lock().unwrap();
+            self.emit(
+                EventType::Navigation,
+                serde_json::json!({
+                    "type": "new_window",
+                    "url": navigation_request.url.as_str(),
+                    "opener_webview_id": opener,
+                    "opened": policy == NewWindowPolicy::Tab,
+                }),
+            );
+            if policy == NewWindowPolicy::Event {
+                navigation_request.deny();
+                self.webview_requests
+                    .borrow_mut()
+                    .push(WebViewRequest::Close(self.webview_id));
+                return;
             }
         }
+        if self.is_active()
+            && NETWORK_POLICY
+                .lock()
+                .unwrap()
+                .cache_mode
+                .clears_before_navigation()
+        {
+            self.network_manager.clear_cache();
+        }
+        navigation_request.allow();
+    }
+
+    fn load_web_resource(&self, webview: WebView, load: WebResourceLoad) {
+        network::handle_load(load, webview.url());
     }
 
     fn notify_input_event_handled(
@@ -298,28 +1461,130 @@ impl WebViewDelegate for PyWireWebViewDelegate {
 }
 
 struct AppState {
-    servo: Option<Servo>,
-    webview: Option<WebView>,
+    servo: Option<Rc<Servo>>,
+    /// Every open webview with the id Python knows it by, in creation order.
+    webviews: Vec<(u32, WebView)>,
+    /// The id of the webview that is shown, painted and receives input.
+    active_webview: Rc<Cell<Option<u32>>>,
+    next_webview_id: Rc<Cell<u32>>,
+    webview_requests: Rc<RefCell<Vec<WebViewRequest>>>,
+    /// Runs the `pw_add_user_script` scripts in every webview.
+    user_content_manager: Option<Rc<UserContentManager>>,
     window: Option<Arc<Window>>,
     window_rendering_context: Option<Rc<WindowRenderingContext>>,
     offscreen_rendering_context: Option<Rc<OffscreenRenderingContext>>,
     needs_repaint: Rc<Cell<bool>>,
-    proxy: EventLoopProxy<UserEvent>,
+    load_started: Rc<Cell<Option<Instant>>>,
+    pending_scroll: Rc<Cell<Option<(f64, f64)>>>,
+    zoom: Rc<RefCell<ZoomLevels>>,
+    pending_navigation: Rc<RefCell<Option<PendingNavigation>>>,
+    /// Served with the next frame Servo paints, unless their deadline passes
+    /// first.
+    pending_captures: RefCell<Vec<(CaptureCallback, Option<Instant>)>>,
+    /// The `pw_print_to_pdf` in progress, if any.
+    print_job: Rc<RefCell<Option<PrintJob>>>,
+    /// Whether Servo has painted a frame yet.
+    frame_painted: Cell<bool>,
+    /// The shortest time between two paints, with `InitParams::max_fps` set.
+    frame_interval: Option<Duration>,
+    /// Until when the window keeps showing its last frame after a resize,
+    /// unless Servo produces one at the new size first.
+    resize_hold: Cell<Option<Instant>>,
+    /// That last frame, copied before the window's surface was resized.
+    resize_frame: RefCell<Option<FrameCopy>>,
+    /// When Servo was last spun for the active page's animations.
+    last_animation_tick: Cell<Option<Instant>>,
+    last_paint: Cell<Option<Instant>>,
+    /// Frames painted since startup, for `pw_run_diagnostics`.
+    frames_painted: Cell<u64>,
+    next_thumbnail_refresh: Cell<Option<Instant>>,
+    /// Servo's rendering target when running without a window.
+    headless_rendering_context: Option<Rc<SoftwareRenderingContext>>,
+    exit_requested: Cell<bool>,
+    /// The window, or headless surface, couldn't be created, so
+    /// `pw_start_app` fails.
+    startup_failed: Cell<bool>,
+    proxy: LoopProxy,
     initial_url: String,
     initial_title: String,
     initial_size: (u32, i32),
+    start_hidden: bool,
+    manual_title: bool,
+    intercept_close: bool,
+    disable_file_uploads: bool,
+    /// HTTP authentication challenges waiting for `pw_provide_credentials`.
+    authentications: Rc<RefCell<PendingAuthentications>>,
+    /// Certificate errors waiting for `pw_allow_cert` or `pw_reject_cert`.
+    cert_errors: Rc<RefCell<PendingCertErrors>>,
+    /// Permission prompts waiting for `pw_respond_permission`.
+    permissions: Rc<RefCell<PendingPermissions<PermissionRequest>>>,
+    /// Webviews showing find-in-page highlights.
+    find_webviews: Rc<RefCell<HashSet<u32>>>,
+    /// The webviews with CSS animations or `requestAnimationFrame` callbacks
+    /// running.
+    animating_webviews: Rc<RefCell<HashSet<u32>>>,
+    /// The webviews with a load in flight.
+    loading_webviews: Rc<RefCell<HashSet<u32>>>,
+    /// Whether `pw_stop_loading` stopped the active page's load, which is
+    /// reported once the load has ended.
+    stop_requested: Rc<Cell<bool>>,
+    /// When the unanswered `close_requested` event was sent.
+    close_requested_at: Cell<Option<Instant>>,
+    /// Last fullscreen state reported to Python.
+    fullscreen: Cell<bool>,
+    /// The window's RGBA color where nothing has been painted.
+    background: [f32; 4],
+    transparent: bool,
+    decorations: bool,
+    /// Keys held down that triggered an accelerator the page doesn't get.
+    accelerator_keys: RefCell<HashSet<keyboard_types::Code>>,
+    /// The OS theme, where the platform reports one.
+    os_theme: Cell<Option<winit::window::Theme>>,
+    /// Files being dragged over the window or dropped on it, gathered into
+    /// one event per drag.
+    hovered_files: RefCell<FileBatch>,
+    dropped_files: RefCell<FileBatch>,
+    /// Whether Python was told files are being dragged over the window.
+    file_hover_reported: Cell<bool>,
+    /// Whether wheel notches scroll over several frames.
+    smooth_scrolling: bool,
+    smooth_scroll: RefCell<SmoothScroll>,
+    /// Whether the window is fully covered by other windows.
+    occluded: Cell<bool>,
+    minimized: Cell<bool>,
+    /// Whether nothing of the window can be seen, so painting is paused.
+    window_hidden: Cell<bool>,
     last_mouse_position: Cell<Point2D<f32, DevicePixel>>,
-    modifiers_state: Cell<winit::keyboard::ModifiersState>,
-    pressed_mouse_buttons: Cell<u16>,
+    modifiers_state: Rc<Cell<winit::keyboard::ModifiersState>>,
+    pressed_mouse_buttons: Rc<Cell<u16>>,
+    /// Ids of the fingers currently on the screen.
+    active_touches: RefCell<HashSet<u64>>,
+    last_touch_end: Cell<Option<Instant>>,
+    watchdog: Rc<RefCell<Watchdog>>,
+    status_text: Rc<RefCell<StatusText>>,
+    /// Scripts from `pw_execute_javascript` and `pw_post_message` held until
+    /// the first page has loaded, or `None` once it has (or with
+    /// `InitParams::disable_script_queue`).
+    queued_scripts: Rc<RefCell<Option<Vec<String>>>>,
 }
 
 impl AppState {
+    /// The webview that is shown and receives input, if any is open.
+    fn webview(&self) -> Option<&WebView> {
+        let active = self.active_webview.get()?;
+        self.webviews
+            .iter()
+            .find(|(id, _)| *id == active)
+            .map(|(_, webview)| webview)
+    }
+
     /// Drive servo forward and repaint if needed.
     /// This mirrors servoshell's pattern: spin events, then repaint.
     fn pump_servo(&mut self) {
         if let Some(servo) = &self.servo {
             servo.spin_event_loop();
         }
+        self.handle_webview_requests();
 
         // After spinning, check if we need to repaint
         if self.needs_repaint.take() {
@@ -327,438 +1592,4312 @@ impl AppState {
         }
     }
 
-    fn repaint(&self) {
-        if let (Some(webview), Some(window_rc), Some(offscreen_rc), Some(window)) = (
-            &self.webview,
-            &self.window_rendering_context,
-            &self.offscreen_rendering_context,
-            &self.window,
-        ) {
-            // 1. Make offscreen context current (ensure Servo renders to FBO)
-            offscreen_rc
-                .make_current()
-                .expect("Failed to make offscreen context current");
-            offscreen_rc.prepare_for_rendering();
+    /// When the in-flight load will time out, if a timeout is configured.
+    fn load_deadline(&self) -> Option<Instant> {
+        let timeout_ms = LOAD_TIMEOUT_MS.load(Ordering::Relaxed);
+        if timeout_ms == 0 {
+            return None;
+        }
+        self.load_started
+            .get()
+            .map(|started| started + Duration::from_millis(timeout_ms))
+    }
 
-            // 2. Servo paints to FBO
-            webview.paint();
+    /// Run `callback` with the next frame Servo paints, and make sure one is
+    /// painted soon. With a `deadline`, the callback is dropped without a
+    /// frame once it passes, e.g. when the caller has stopped waiting.
+    fn capture_next_frame(&self, callback: CaptureCallback, deadline: Option<Instant>) {
+        self.pending_captures
+            .borrow_mut()
+            .push((callback, deadline));
+        match &self.window {
+            Some(window) => window.request_redraw(),
+            // The headless loop repaints on its next tick.
+            None => self.needs_repaint.set(true),
+        }
+    }
 
-            // 3. Blit Servo output
-            window_rc
-                .make_current()
-                .expect("Failed to make window context current");
-            window_rc.prepare_for_rendering(); // Bind window FBO
+    /// Start printing the active webview to a PDF at `path`. Returns `-6`
+    /// if there is nothing to print yet, the page is still loading or
+    /// another print is running.
+    fn start_print(&self, path: PathBuf, page_size: PageSize) -> i32 {
+        let refusal = match self.webview() {
+            None => Some("There is no page to print"),
+            Some(_) if !self.frame_painted.get() => Some("Nothing has been rendered yet"),
+            Some(_) if LOADING.load(Ordering::Relaxed) => Some("The page is still loading"),
+            Some(_) if self.print_job.borrow().is_some() => Some("A print is already running"),
+            Some(_) => None,
+        };
+        let (None, Some(webview)) = (refusal, self.webview()) else {
+            errors::set_last_error(refusal.unwrap_or("There is no page to print"));
+            return ErrorCode::Unavailable.into();
+        };
+        log::info!("Printing to {:?}", path);
+        *self.print_job.borrow_mut() = Some(PrintJob::new(path, page_size));
+        webview.evaluate_javascript(print::MEASURE_JS, |result| {
+            let measurements = match result {
+                Ok(JSValue::Array(values)) => match values.as_slice() {
+                    [JSValue::Number(document_height), JSValue::Number(viewport_height), JSValue::Number(x), JSValue::Number(y)] => {
+                        Some([*document_height, *viewport_height, *x, *y])
+                    }
+                    _ => None,
+                },
+                _ => None,
+            };
+            send_user_event(UserEvent::PrintMeasured(measurements));
+        });
+        0
+    }
 
-            let gl = window_rc.glow_gl_api();
+    fn print_measured(&self, measurements: Option<[f64; 4]>) {
+        let Some([document_height, viewport_height, x, y]) = measurements else {
+            self.finish_print(Err("Cannot measure the page".to_string()));
+            return;
+        };
+        if let Some(job) = self.print_job.borrow_mut().as_mut() {
+            job.restore_scroll = (x, y);
+            job.measured(document_height, viewport_height);
+        }
+        self.print_next();
+    }
 
-            if let Some(cb) = offscreen_rc.render_to_parent_callback() {
-                let size = window.inner_size();
-                let rect = Rect::new(
-                    Point2D::origin(),
-                    Size2D::new(size.width as i32, size.height as i32),
+    /// Scroll to the next part of the page to print, or write the PDF once
+    /// every part has been captured.
+    fn print_next(&self) {
+        let next = match self.print_job.borrow_mut().as_mut() {
+            Some(job) => job.next_offset(),
+            None => return,
+        };
+        let Some(offset) = next else {
+            self.finish_print(Ok(()));
+            return;
+        };
+        let Some(webview) = self.webview() else {
+            self.finish_print(Err("The page was closed while printing".to_string()));
+            return;
+        };
+        // Capture only once the scroll has been applied.
+        webview.evaluate_javascript(format!("window.scrollTo(0, {offset})"), move |_result| {
+            send_user_event(UserEvent::PrintScrolled(offset));
+        });
+    }
+
+    fn print_scrolled(&self, offset: f64) {
+        let job = self.print_job.clone();
+        self.capture_next_frame(
+            Box::new(move |frame| {
+                if let Some(job) = job.borrow_mut().as_mut() {
+                    job.add_capture(offset, frame);
+                }
+                send_user_event(UserEvent::PrintNext);
+            }),
+            None,
+        );
+    }
+
+    /// Write the print job's PDF, or report why it failed, and scroll the
+    /// page back to where it was.
+    fn finish_print(&self, result: Result<(), String>) {
+        let Some(job) = self.print_job.borrow_mut().take() else {
+            return;
+        };
+        if let Some(webview) = self.webview() {
+            let (x, y) = job.restore_scroll;
+            scroll_to(webview, x, y);
+        }
+        let written = result.and_then(|()| {
+            let pages = job.pages();
+            std::fs::write(&job.path, pdf::encode(&pages, job.page_size))
+                .map(|()| pages.len())
+                .map_err(|e| format!("Cannot write PDF to {:?}: {}", job.path, e))
+        });
+        let path = job.path.to_string_lossy();
+        match written {
+            Ok(pages) => {
+                log::info!("Printed {} page(s) to {}", pages, path);
+                emit_event(
+                    EventType::Print,
+                    serde_json::json!({"type": "pdf_printed", "path": path, "pages": pages}),
+                );
+            }
+            Err(error) => {
+                errors::set_last_error(error.clone());
+                emit_event(
+                    EventType::Print,
+                    serde_json::json!({"type": "print_failed", "path": path, "error": error}),
                 );
-                cb(&gl, rect);
             }
-
-            // 4. Present
-            window_rc.present();
         }
     }
-}
 
-#[cfg(target_os = "macos")]
-fn force_srgb_color_space(window_handle: raw_window_handle::RawWindowHandle) {
-    if let raw_window_handle::RawWindowHandle::AppKit(handle) = window_handle {
-        // Safety: We are on main thread (winit event loop)
-        unsafe {
-            if let Some(_mtm) = MainThreadMarker::new() {
-                let view_ptr = handle.ns_view.as_ptr() as *mut NSView;
-                if !view_ptr.is_null() {
-                    let view = &*view_ptr;
-                    if let Some(window) = view.window() {
-                        window.setColorSpace(Some(&NSColorSpace::sRGBColorSpace()));
-                    }
-                }
+    /// Show the configured thumbnail, capturing a fresh one in auto mode.
+    fn update_thumbnail(&self) {
+        let image = match &*thumbnail::THUMBNAIL.lock().unwrap() {
+            ThumbnailSource::Default => None,
+            ThumbnailSource::Image(image) => Some(image.clone()),
+            ThumbnailSource::Auto => {
+                self.next_thumbnail_refresh
+                    .set(Some(Instant::now() + thumbnail::AUTO_REFRESH_INTERVAL));
+                self.capture_next_frame(
+                    Box::new(|frame| {
+                        match frame
+                            .scaled_to_fit(thumbnail::AUTO_THUMBNAIL_SIZE)
+                            .encode_png()
+                        {
+                            Ok(png) => platform::set_dock_image(Some(&png)),
+                            Err(e) => log::warn!("Failed to encode thumbnail: {}", e),
+                        }
+                    }),
+                    None,
+                );
+                return;
             }
+        };
+        self.next_thumbnail_refresh.set(None);
+        platform::set_dock_image(image.as_deref());
+    }
+
+    /// Recapture an automatic thumbnail once it is due.
+    fn check_thumbnail_refresh(&self) {
+        if self
+            .next_thumbnail_refresh
+            .get()
+            .is_some_and(|due| Instant::now() >= due)
+        {
+            self.update_thumbnail();
         }
     }
-}
 
-#[cfg(not(target_os = "macos"))]
-fn force_srgb_color_space(_window_handle: raw_window_handle::RawWindowHandle) {
-    // No-op
-}
+    /// Bring the window to the front for each launch a later instance handed
+    /// over, and report the launch's arguments.
+    fn handle_second_instances(&self) {
+        let launches = single_instance::take_pending();
+        if launches.is_empty() {
+            return;
+        }
+        if let Some(window) = &self.window {
+            window.set_minimized(false);
+            window.set_visible(true);
+            window.focus_window();
+        }
+        for launch in launches {
+            emit_event(
+                EventType::Instance,
+                serde_json::json!({
+                    "type": "second_instance",
+                    "args": launch.args,
+                    "cwd": launch.cwd,
+                }),
+            );
+        }
+    }
 
-impl ApplicationHandler<UserEvent> for AppState {
-    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
-        if self.window.is_some() {
+    fn close_deadline(&self) -> Option<Instant> {
+        self.close_requested_at
+            .get()
+            .map(|requested| requested + CLOSE_CONFIRM_TIMEOUT)
+    }
+
+    /// Close the window if a close request has gone unanswered for too long.
+    fn check_close_timeout(&mut self) {
+        let Some(deadline) = self.close_deadline() else {
             return;
+        };
+        if Instant::now() >= deadline {
+            log::warn!("Close request unanswered, exiting...");
+            self.shut_down();
         }
+    }
 
-        println!("[pw_servo] App resumed, creating window...");
-        let window_attributes = WindowAttributes::default()
-            .with_title(&self.initial_title)
-            .with_inner_size(winit::dpi::LogicalSize::new(
-                self.initial_size.0 as f64,
-                self.initial_size.1 as f64,
-            ))
-            .with_visible(true);
+    /// Deny the permission prompts nobody answered in time.
+    fn check_permission_timeouts(&self) {
+        let expired = self.permissions.borrow_mut().expire(Instant::now());
+        for id in expired {
+            log::info!("Permission request {} unanswered, denied", id);
+            emit_event(
+                EventType::Permission,
+                serde_json::json!({"type": "permission_timeout", "request_id": id}),
+            );
+        }
+    }
 
-        let window = Arc::new(
-            event_loop
-                .create_window(window_attributes)
-                .expect("Failed to create window"),
-        );
-        self.window = Some(window.clone());
+    /// Cancel the authentication challenges nobody answered in time.
+    fn check_auth_timeouts(&self) {
+        let expired = self.authentications.borrow_mut().expire(Instant::now());
+        for id in expired {
+            log::info!("Authentication request {} unanswered, cancelled", id);
+            emit_event(
+                EventType::Network,
+                serde_json::json!({"type": "auth_timeout", "request_id": id}),
+            );
+        }
+    }
 
-        let window_handle = window.window_handle().expect("Failed to get window handle");
-        force_srgb_color_space(window_handle.as_raw());
+    /// Abort the in-flight load if it has exceeded the configured timeout.
+    fn check_load_timeout(&mut self) {
+        let Some(deadline) = self.load_deadline() else {
+            return;
+        };
+        if Instant::now() < deadline {
+            return;
+        }
+        self.load_started.set(None);
+        self.stop_requested.set(false);
 
-        println!(
-            "[pw_servo] Window created. Physical size: {:?}, Scale factor: {}",
-            window.inner_size(),
-            window.scale_factor()
-        );
+        if let Some(webview) = self.webview() {
+            let url = webview.url().map(|url| url.to_string()).unwrap_or_default();
+            let timeout_ms = LOAD_TIMEOUT_MS.load(Ordering::Relaxed);
+            log::warn!("Load of {} timed out after {}ms", url, timeout_ms);
+            emit_event(
+                EventType::Load,
+                serde_json::json!({
+                    "type": "load_timeout",
+                    "url": url,
+                    "timeout_ms": timeout_ms,
+                }),
+            );
+            self.finish_pending_navigation("timeout");
+            webview.load(error_page_url(
+                "Page load timed out",
+                &format!("{} did not finish loading within {}ms.", url, timeout_ms),
+            ));
+        }
+    }
 
-        println!("[pw_servo] Creating WindowRenderingContext...");
-        let display_handle = event_loop
-            .display_handle()
-            .expect("Failed to get display handle");
+    /// Whether mouse events are currently synthesized from touches.
+    fn mouse_emulated_by_touch(&self) -> bool {
+        !self.active_touches.borrow().is_empty()
+            || self
+                .last_touch_end
+                .get()
+                .is_some_and(|ended| ended.elapsed() < TOUCH_MOUSE_SUPPRESSION)
+    }
 
-        let window_rc = Rc::new(
-            WindowRenderingContext::new(display_handle, window_handle, window.inner_size())
-                .expect("Failed to create WindowRenderingContext"),
+    /// Emit a `fullscreen` event if the window entered or left fullscreen,
+    /// whether through `pw_set_fullscreen`, the page or the OS.
+    fn check_fullscreen_change(&self) {
+        let Some(window) = &self.window else {
+            return;
+        };
+        let active = window.fullscreen().is_some();
+        if self.fullscreen.replace(active) == active {
+            return;
+        }
+        // On macOS, AppKit may reset the window's color space when moving it
+        // into or out of its fullscreen space, and its opacity with it.
+        if let Ok(handle) = window.window_handle() {
+            force_srgb_color_space(handle.as_raw());
+        }
+        if platform::TRANSPARENT_WINDOW.load(Ordering::Relaxed) {
+            platform::make_transparent(window);
+        }
+        emit_event(
+            EventType::Window,
+            serde_json::json!({"type": "fullscreen", "active": active}),
         );
-        window_rc
-            .make_current()
-            .expect("Failed to make window context current");
-
-        println!("[pw_servo] Creating OffscreenRenderingContext...");
-        let offscreen_rc = Rc::new(window_rc.offscreen_context(window.inner_size()));
+    }
 
-        self.window_rendering_context = Some(window_rc.clone());
-        self.offscreen_rendering_context = Some(offscreen_rc.clone());
+    /// Report a `pw_navigate_with_callback` navigation as failed with `status`.
+    fn finish_pending_navigation(&self, status: &str) {
+        let navigation = self.pending_navigation.borrow_mut().take();
+        if let Some(navigation) = navigation {
+            navigation.finish(false, status);
+        }
+    }
 
-        println!("[pw_servo] Creating Servo instance...");
+    /// Create Servo and the first webview, painting into the offscreen
+    /// framebuffer behind the window or, headless, a software surface.
+    fn build_webview(&mut self) -> WebView {
+        log::info!("Creating Servo instance...");
         let waker = Box::new(JsonWaker {
             proxy: self.proxy.clone(),
         });
 
-        let servo = ServoBuilder::default().event_loop_waker(waker).build();
+        let servo = ServoBuilder::default()
+            .preferences(prefs::build_preferences())
+            .opts(prefs::build_opts())
+            .event_loop_waker(waker)
+            .protocol_registry(protocol::protocol_registry())
+            .build();
 
-        servo.setup_logging();
+        // Logging is set up by `pw_start_app` instead of `setup_logging`,
+        // which would print everything to stdout.
+        servo.set_delegate(Rc::new(devtools::PyWireServoDelegate));
+        self.user_content_manager = Some(injected::user_content_manager(&servo));
+        self.servo = Some(Rc::new(servo));
 
-        println!("[pw_servo] Creating WebView for: {}", self.initial_url);
         let url =
             Url::parse(&self.initial_url).unwrap_or_else(|_| Url::parse("about:blank").unwrap());
+        let (id, webview) = self.open_webview(url).expect("Servo was just created");
+        self.active_webview.set(Some(id));
+        webview
+    }
+
+    /// Open a webview loading `url`, in the background. `None` before Servo
+    /// runs or after it has shut down.
+    fn open_webview(&mut self, url: Url) -> Option<(u32, WebView)> {
+        let servo = self.servo.as_ref()?;
+        let rendering_context: Rc<dyn RenderingContext> = match (
+            &self.offscreen_rendering_context,
+            &self.headless_rendering_context,
+        ) {
+            (Some(offscreen), _) => offscreen.clone(),
+            (None, Some(headless)) => headless.clone(),
+            (None, None) => return None,
+        };
+        let scale_factor = self
+            .window
+            .as_ref()
+            .map_or(1.0, |window| window.scale_factor() as f32);
+
+        let id = self.next_webview_id.get();
+        self.next_webview_id.set(id + 1);
+        log::info!("Creating WebView {} for: {}", id, url);
 
         let delegate = Rc::new(PyWireWebViewDelegate {
-            window: window.clone(),
+            webview_id: id,
+            active_webview: self.active_webview.clone(),
+            window: self.window.clone(),
             needs_repaint: self.needs_repaint.clone(),
+            load_started: self.load_started.clone(),
+            network_manager: servo.network_manager().clone(),
+            pending_scroll: self.pending_scroll.clone(),
+            zoom: self.zoom.clone(),
+            pending_navigation: self.pending_navigation.clone(),
+            sync_title: !self.manual_title,
+            ime_control: Cell::new(None),
+            modifiers: self.modifiers_state.clone(),
+            pressed_mouse_buttons: self.pressed_mouse_buttons.clone(),
+            file_uploads: !self.disable_file_uploads,
+            authentications: self.authentications.clone(),
+            cert_errors: self.cert_errors.clone(),
+            permissions: self.permissions.clone(),
+            find_webviews: self.find_webviews.clone(),
+            animating_webviews: self.animating_webviews.clone(),
+            loading_webviews: self.loading_webviews.clone(),
+            stop_requested: self.stop_requested.clone(),
+            servo: Rc::downgrade(servo),
+            rendering_context: rendering_context.clone(),
+            user_content_manager: self.user_content_manager.clone(),
+            next_webview_id: self.next_webview_id.clone(),
+            webview_requests: self.webview_requests.clone(),
+            opener: Cell::new(None),
+            last_crash: Cell::new(None),
+            watchdog: self.watchdog.clone(),
+            status_text: self.status_text.clone(),
+            queued_scripts: self.queued_scripts.clone(),
         });
 
-        // Pass the offscreen context to the WebView
-        let webview = WebViewBuilder::new(&servo, offscreen_rc.clone())
+        let mut builder = WebViewBuilder::new(servo, rendering_context)
             .delegate(delegate)
             .url(url)
-            .hidpi_scale_factor(Scale::new(window.scale_factor() as f32))
-            .build();
-
-        self.servo = Some(servo);
-        self.webview = Some(webview.clone());
-
-        webview.show();
-        webview.focus();
-        window.focus_window();
+            .hidpi_scale_factor(Scale::new(scale_factor));
+        if let Some(user_content_manager) = &self.user_content_manager {
+            builder = builder.user_content_manager(user_content_manager.clone());
+        }
+        let webview = builder.build();
 
-        // Kick off the first spin to start loading
-        self.pump_servo();
+        webview.set_clipboard_delegate(Rc::new(clipboard::PyWireClipboardDelegate));
+        webview.notify_theme_change(color_scheme::current(self.os_theme.get()));
 
-        window.request_redraw();
-        event_loop.set_control_flow(ControlFlow::Wait);
+        self.webviews.push((id, webview.clone()));
+        Some((id, webview))
     }
 
-    fn window_event(&mut self, event_loop: &ActiveEventLoop, _id: WindowId, event: WindowEvent) {
-        match event {
-            WindowEvent::CloseRequested => {
-                println!("[pw_servo] Close requested, exiting...");
-                event_loop.exit();
-                return;
-            }
-            WindowEvent::Focused(focused) => {
-                println!("[pw_servo] Window focused: {}", focused);
-                if let Some(webview) = &self.webview {
-                    if focused {
-                        webview.focus();
-                    } else {
-                        webview.blur();
-                        println!("[pw_servo] Window lost focus, blurring webview");
+    /// Take in the webviews pages opened while Servo ran, showing them as
+    /// tabs, and close the ones the app declined.
+    fn handle_webview_requests(&mut self) {
+        let requests = self.webview_requests.take();
+        for request in requests {
+            match request {
+                WebViewRequest::Adopt(id, webview) => {
+                    webview.notify_theme_change(color_scheme::current(self.os_theme.get()));
+                    self.webviews.push((id, webview));
+                    if *NEW_WINDOW_POLICY.lock().unwrap() == NewWindowPolicy::Tab {
+                        self.activate_webview(id);
                     }
                 }
-            }
-            WindowEvent::Resized(size) => {
-                println!("[pw_servo] Resized to {:?}", size);
-                // Resize both contexts
-                if let Some(rc) = &self.window_rendering_context {
-                    rc.resize(size);
-                }
-                // Offscreen context resize logic might need to check if webview resizes internally?
-                // Actually webview.resize will call resize on its context (offscreen_rc)
-                if let Some(webview) = &self.webview {
-                    webview.resize(size);
-                }
-            }
-            WindowEvent::ScaleFactorChanged {
-                scale_factor,
-                inner_size_writer: _,
-            } => {
-                println!("[pw_servo] Scale factor changed to {}", scale_factor);
-                if let Some(webview) = &self.webview {
-                    webview.set_hidpi_scale_factor(Scale::new(scale_factor as f32));
+                WebViewRequest::Close(id) => {
+                    self.close_webview(id);
                 }
             }
-            WindowEvent::CursorLeft { .. } => {
-                if let Some(webview) = &self.webview {
-                    webview.notify_input_event(InputEvent::MouseLeftViewport(Default::default()));
-                }
+        }
+    }
+
+    /// Show the webview `id` in place of the active one and give it the
+    /// focus. Returns false for an unknown id.
+    fn activate_webview(&mut self, id: u32) -> bool {
+        let Some(webview) = self
+            .webviews
+            .iter()
+            .find(|(webview_id, _)| *webview_id == id)
+            .map(|(_, webview)| webview.clone())
+        else {
+            return false;
+        };
+        if self.active_webview.get() == Some(id) {
+            return true;
+        }
+        // Navigation state and load tracking belong to the webview being left.
+        self.finish_pending_navigation("superseded");
+        self.load_started.set(None);
+        self.pending_scroll.set(None);
+        self.watchdog.borrow_mut().reset();
+        self.clear_status_text();
+        if let Some(previous) = self.webview() {
+            previous.blur();
+            previous.hide();
+        }
+
+        self.active_webview.set(Some(id));
+        self.stop_requested.set(false);
+        LOADING.store(
+            self.loading_webviews.borrow().contains(&id),
+            Ordering::Relaxed,
+        );
+        if !self.window_hidden.get() {
+            webview.show();
+        }
+        webview.focus();
+        self.zoom.borrow_mut().apply(&webview);
+        // The cursor the previous page set stays until this one sets its own
+        // on the next mouse move.
+        if let Some(window) = &self.window {
+            window.set_cursor(CursorIcon::Default);
+            window.set_cursor_visible(true);
+        }
+        if let (Some(window), false) = (&self.window, self.manual_title) {
+            if let Some(title) = webview.page_title() {
+                window.set_title(&title);
             }
-            WindowEvent::CursorMoved { position, .. } => {
-                let point = Point2D::new(position.x as f32, position.y as f32);
-                self.last_mouse_position.set(point);
-                if let Some(webview) = &self.webview {
-                    let servo_point = DevicePoint::new(point.x, point.y);
-                    let buttons = self.pressed_mouse_buttons.get();
-                    if buttons != 0 {
-                        println!(
-                            "[pw_servo] MouseMove at {:?} with buttons={}",
-                            point, buttons
-                        );
-                    }
-                    webview.notify_input_event(InputEvent::MouseMove(
-                        MouseMoveEvent::new_with_buttons(servo_point.into(), buttons),
-                    ));
-                }
+        }
+        emit_event(
+            EventType::Window,
+            serde_json::json!({"type": "webview_activated", "webview_id": id}),
+        );
+        self.needs_repaint.set(true);
+        if let Some(window) = &self.window {
+            window.request_redraw();
+        }
+        true
+    }
+
+    /// Close the webview `id`. Closing the active one activates its
+    /// neighbour, if any is left. Returns false for an unknown id.
+    fn close_webview(&mut self, id: u32) -> bool {
+        let Some(index) = self
+            .webviews
+            .iter()
+            .position(|(webview_id, _)| *webview_id == id)
+        else {
+            return false;
+        };
+        let was_active = self.active_webview.get() == Some(id);
+        if was_active {
+            self.finish_pending_navigation("closed");
+            self.load_started.set(None);
+            self.pending_scroll.set(None);
+            self.watchdog.borrow_mut().reset();
+            self.clear_status_text();
+            self.stop_requested.set(false);
+            LOADING.store(false, Ordering::Relaxed);
+            self.active_webview.set(None);
+        }
+        // Dropping the last handle closes the webview in Servo.
+        self.webviews.remove(index);
+        self.authentications.borrow_mut().cancel_for_webview(id);
+        self.cert_errors.borrow_mut().cancel_for_webview(id);
+        self.permissions.borrow_mut().deny_for_webview(id);
+        self.find_webviews.borrow_mut().remove(&id);
+        self.animating_webviews.borrow_mut().remove(&id);
+        self.loading_webviews.borrow_mut().remove(&id);
+        emit_event(
+            EventType::Window,
+            serde_json::json!({"type": "webview_closed", "webview_id": id}),
+        );
+        if was_active {
+            let neighbour = self
+                .webviews
+                .get(index)
+                .or_else(|| self.webviews.last())
+                .map(|(id, _)| *id);
+            if let Some(neighbour) = neighbour {
+                self.activate_webview(neighbour);
             }
-            WindowEvent::MouseInput { state, button, .. } => {
-                let action = match state {
-                    ElementState::Pressed => MouseButtonAction::Down,
-                    ElementState::Released => MouseButtonAction::Up,
-                };
-                let servo_button = match button {
-                    MouseButton::Left => ServoMouseButton::Left,
-                    MouseButton::Right => ServoMouseButton::Right,
-                    MouseButton::Middle => ServoMouseButton::Middle,
-                    MouseButton::Back => ServoMouseButton::Back,
-                    MouseButton::Forward => ServoMouseButton::Forward,
-                    MouseButton::Other(v) => ServoMouseButton::Other(v),
-                };
+        }
+        self.needs_repaint.set(true);
+        if let Some(window) = &self.window {
+            window.request_redraw();
+        }
+        true
+    }
 
-                let button_mask = match servo_button {
-                    ServoMouseButton::Left => 1,
-                    ServoMouseButton::Right => 2,
-                    ServoMouseButton::Middle => 4,
-                    ServoMouseButton::Back => 8,
-                    ServoMouseButton::Forward => 16,
-                    _ => 0,
-                };
-                let mut current_buttons = self.pressed_mouse_buttons.get();
-                if action == MouseButtonAction::Down {
-                    current_buttons |= button_mask;
-                } else {
-                    current_buttons &= !button_mask;
-                }
-                self.pressed_mouse_buttons.set(current_buttons);
+    /// Render without a window: Servo paints into an offscreen surface of the
+    /// requested size, which needs no display connection. Servo's
+    /// `OffscreenRenderingContext` hangs off a window's context, so this uses
+    /// its standalone `SoftwareRenderingContext`. Fails if that surface can't
+    /// be created, e.g. without a software OpenGL implementation.
+    fn start_headless(&mut self) -> Result<(), String> {
+        log::info!("Starting headless...");
+        SCALE_FACTOR.store(1.0f64.to_bits(), Ordering::Relaxed);
+        let size = winit::dpi::PhysicalSize::new(
+            self.initial_size.0.max(1),
+            self.initial_size.1.max(1) as u32,
+        );
+        let rendering_context = match SoftwareRenderingContext::new(size) {
+            Ok(rendering_context) => Rc::new(rendering_context),
+            Err(e) => {
+                return Err(format!(
+                    "Could not create the headless rendering context: {:?}",
+                    e
+                ))
+            }
+        };
+        self.headless_rendering_context = Some(rendering_context.clone());
 
-                println!(
-                    "[pw_servo] MouseInput {:?} button={:?} mask={} total_buttons={}",
-                    action, servo_button, button_mask, current_buttons
-                );
+        let webview = self.build_webview();
+        webview.show();
+        webview.focus();
+        self.pump_servo();
+        Ok(())
+    }
 
-                let point = self.last_mouse_position.get();
-                if let Some(webview) = &self.webview {
-                    let servo_point = DevicePoint::new(point.x, point.y);
-                    webview.notify_input_event(InputEvent::MouseButton(MouseButtonEvent::new(
-                        action,
-                        servo_button,
-                        servo_point.into(),
-                    )));
-                }
+    /// Start headless, or record why that failed so that `pw_start_app`
+    /// returns `ErrorCode::WindowFailed`, as when the window can't be opened.
+    /// Returns whether the app started.
+    fn start_headless_or_fail(&mut self) -> bool {
+        match self.start_headless() {
+            Ok(()) => true,
+            Err(message) => {
+                errors::set_last_error(message);
+                self.startup_failed.set(true);
+                false
             }
-            WindowEvent::ModifiersChanged(modifiers) => {
-                self.modifiers_state.set(modifiers.state());
+        }
+    }
+
+    /// Tear down the embedding and ask the event loop to exit. The webview
+    /// holds references into Servo and Servo into the rendering contexts, so
+    /// they are dropped in that order, before the window that owns the surface.
+    fn shut_down(&mut self) {
+        self.finish_pending_navigation("shutdown");
+        self.pending_captures.borrow_mut().clear();
+        self.print_job.borrow_mut().take();
+        self.active_webview.set(None);
+        self.webviews.clear();
+        self.webview_requests.borrow_mut().clear();
+        self.authentications.borrow_mut().cancel_all();
+        self.cert_errors.borrow_mut().cancel_all();
+        self.permissions.borrow_mut().deny_all();
+        self.user_content_manager = None;
+        if let Some(servo) = &self.servo {
+            // Let Servo process the webview closing before it goes away.
+            servo.spin_event_loop();
+        }
+        self.servo = None;
+        self.offscreen_rendering_context = None;
+        self.window_rendering_context = None;
+        self.window = None;
+        self.headless_rendering_context = None;
+        LOADING.store(false, Ordering::Relaxed);
+        self.exit_requested.set(true);
+    }
+
+    /// Hand the frame just painted into the framebuffer bound on `gl` to the
+    /// pending captures.
+    fn serve_captures(&self, gl: &glow::Context, width: u32, height: u32) {
+        let captures = self.pending_captures.take();
+        if !captures.is_empty() {
+            let frame = Frame::read(gl, width, height);
+            for (capture, _) in captures {
+                capture(&frame);
             }
-            WindowEvent::KeyboardInput { event, .. } => {
-                if let Some(webview) = &self.webview {
-                    let servo_event = keyboard_event_from_winit(&event, self.modifiers_state.get());
-                    let mut handled = false;
-
-                    // Intercept clipboard shortcuts (Cmd+C/X/V)
-                    if servo_event.event.state == servo::KeyState::Down {
-                        let mods = servo_event.event.modifiers;
-                        let cmd_or_ctrl = mods.contains(servo::Modifiers::CONTROL)
-                            || mods.contains(servo::Modifiers::META);
-
-                        if cmd_or_ctrl {
-                            match servo_event.event.key {
-                                servo::Key::Character(ref c) if c == "c" || c == "C" => {
-                                    webview.notify_input_event(InputEvent::EditingAction(
-                                        EditingActionEvent::Copy,
-                                    ));
-                                    handled = true;
-                                }
-                                servo::Key::Character(ref c) if c == "x" || c == "X" => {
-                                    webview.notify_input_event(InputEvent::EditingAction(
-                                        EditingActionEvent::Cut,
-                                    ));
-                                    handled = true;
-                                }
-                                servo::Key::Character(ref c) if c == "v" || c == "V" => {
-                                    webview.notify_input_event(InputEvent::EditingAction(
-                                        EditingActionEvent::Paste,
-                                    ));
-                                    handled = true;
-                                }
-                                _ => {}
-                            }
-                        }
-                    }
+        }
+    }
 
-                    if !handled {
-                        webview.notify_input_event(InputEvent::Keyboard(servo_event));
-                    }
-                }
+    /// When the frame rate cap next allows a paint, if it holds one back.
+    /// Frames after a resize aren't held back, so the window catches up with
+    /// its new size right away.
+    fn next_paint_due(&self) -> Option<Instant> {
+        if self.resize_hold.get().is_some() {
+            return None;
+        }
+        Some(self.last_paint.get()? + self.frame_interval?)
+    }
+
+    /// When the next capture with a deadline gives up on its frame.
+    fn capture_deadline(&self) -> Option<Instant> {
+        self.pending_captures
+            .borrow()
+            .iter()
+            .filter_map(|(_, deadline)| *deadline)
+            .min()
+    }
+
+    /// Drop the captures whose caller has stopped waiting for a frame, such
+    /// as those of a hidden window, so they don't run when one comes later.
+    fn check_capture_timeouts(&self) {
+        let now = Instant::now();
+        self.pending_captures
+            .borrow_mut()
+            .retain(|(_, deadline)| !deadline.is_some_and(|deadline| deadline <= now));
+    }
+
+    /// When a frame held back by the frame rate cap is to be painted.
+    fn held_frame_deadline(&self) -> Option<Instant> {
+        if self.needs_repaint.get() {
+            self.next_paint_due()
+        } else {
+            None
+        }
+    }
+
+    /// Copy the frame on screen before the window's surface is resized, as
+    /// its contents are undefined afterwards, to show until Servo paints the
+    /// page at the new size. A copy from an earlier resize in the same hold
+    /// is kept, as Servo hasn't painted anything newer since.
+    fn keep_frame_for_resize(&self) {
+        if self.resize_frame.borrow().is_some() || !self.frame_painted.get() {
+            return;
+        }
+        let (Some(window_rc), Some(offscreen_rc)) = (
+            &self.window_rendering_context,
+            &self.offscreen_rendering_context,
+        ) else {
+            return;
+        };
+        let Some(cb) = offscreen_rc.render_to_parent_callback() else {
+            return;
+        };
+        if window_rc.make_current().is_err() {
+            return;
+        }
+        // Draw the last frame into the window's framebuffer, still at the
+        // old size, and copy it from there.
+        window_rc.prepare_for_rendering();
+        let gl = window_rc.glow_gl_api();
+        let size = offscreen_rc.size();
+        let rect = Rect::new(
+            Point2D::origin(),
+            Size2D::new(size.width as i32, size.height as i32),
+        );
+        cb(&gl, rect);
+        *self.resize_frame.borrow_mut() = FrameCopy::copy(&gl, rect.size.width, rect.size.height);
+    }
+
+    /// Present the frame kept by [`Self::keep_frame_for_resize`], stretched
+    /// over the resized window.
+    fn draw_resize_frame(&self) {
+        let resize_frame = self.resize_frame.borrow();
+        let (Some(frame), Some(window_rc), Some(window)) = (
+            resize_frame.as_ref(),
+            &self.window_rendering_context,
+            &self.window,
+        ) else {
+            return;
+        };
+        if window_rc.make_current().is_err() {
+            return;
+        }
+        window_rc.prepare_for_rendering();
+        let gl = window_rc.glow_gl_api();
+        clear_framebuffer(&gl, self.background);
+        let size = window.inner_size();
+        frame.draw(&gl, size.width as i32, size.height as i32);
+        window_rc.present();
+    }
+
+    fn release_resize_frame(&self) {
+        let Some(frame) = self.resize_frame.take() else {
+            return;
+        };
+        if let Some(window_rc) = &self.window_rendering_context {
+            if window_rc.make_current().is_ok() {
+                frame.delete(&window_rc.glow_gl_api());
             }
-            WindowEvent::MouseWheel { delta, .. } => {
-                println!("[pw_servo] MouseWheel: {:?}", delta);
-                const LINE_HEIGHT: f32 = 76.0;
-                const LINE_WIDTH: f32 = 76.0;
+        }
+    }
+
+    /// Paint the latest frame if Servo didn't produce one at the new size
+    /// within [`RESIZE_HOLD`] of a resize.
+    fn check_resize_hold(&self) {
+        let expired = self
+            .resize_hold
+            .get()
+            .is_some_and(|until| Instant::now() >= until);
+        if let (true, Some(window)) = (expired, &self.window) {
+            self.resize_hold.set(None);
+            self.needs_repaint.set(true);
+            window.request_redraw();
+        }
+    }
+
+    /// Paint a frame held back by the frame rate cap once it is due. The
+    /// headless loop gets there on its own, as it repaints every tick.
+    fn check_held_frame(&self) {
+        let due = self
+            .held_frame_deadline()
+            .is_some_and(|due| Instant::now() >= due);
+        if let (true, Some(window)) = (due, &self.window) {
+            window.request_redraw();
+        }
+    }
+
+    /// When Servo is next spun for the active page's animations, if it has
+    /// any running. Without input, only Servo's own wake-ups would spin it,
+    /// so the loop ticks at the frame rate (or the cap) while they run to keep
+    /// `requestAnimationFrame` pages moving. Hidden windows don't tick.
+    fn animation_deadline(&self) -> Option<Instant> {
+        let active = self.active_webview.get()?;
+        if self.window_hidden.get() || !self.animating_webviews.borrow().contains(&active) {
+            return None;
+        }
+        let interval = self.frame_interval.unwrap_or(ANIMATION_TICK);
+        Some(
+            self.last_animation_tick
+                .get()
+                .map_or_else(Instant::now, |tick| tick + interval),
+        )
+    }
+
+    /// Spin Servo for the active page's animations once a tick is due.
+    fn check_animation_tick(&mut self) {
+        let now = Instant::now();
+        if self.animation_deadline().is_some_and(|due| now >= due) {
+            self.last_animation_tick.set(Some(now));
+            self.pump_servo();
+        }
+    }
+
+    fn record_paint(&self) {
+        self.frame_painted.set(true);
+        self.last_paint.set(Some(Instant::now()));
+        self.frames_painted.set(self.frames_painted.get() + 1);
+    }
+
+    /// Report a key press that matches a `pw_register_accelerator` combo.
+    /// Returns true if the key is the app's alone and must not reach the
+    /// page, which also goes for its release.
+    fn handle_accelerator(&self, event: &KeyboardEvent) -> bool {
+        let key = &event.event;
+        if key.state == servo::KeyState::Up {
+            return self.accelerator_keys.borrow_mut().remove(&key.code);
+        }
+        let Some(accelerator) = accelerators::find(&key.key, key.code, key.modifiers) else {
+            return false;
+        };
+        emit_event(
+            EventType::Input,
+            serde_json::json!({
+                "type": "accelerator",
+                "action_id": accelerator.action_id,
+                "combo": accelerator.combo,
+                "repeat": key.repeat,
+            }),
+        );
+        if accelerator.deliver_to_page {
+            return false;
+        }
+        self.accelerator_keys.borrow_mut().insert(key.code);
+        true
+    }
+
+    /// Search the active webview for `text` and report the matches as a
+    /// `find_result` event. An empty `text` clears the search.
+    fn find_in_page(&self, text: String, forward: bool, case_sensitive: bool) {
+        if text.is_empty() {
+            self.clear_find();
+            return;
+        }
+        let (Some(id), Some(webview)) = (self.active_webview.get(), self.webview()) else {
+            return;
+        };
+        self.find_webviews.borrow_mut().insert(id);
+        let script = find::find_script(&text, forward, case_sensitive);
+        webview.evaluate_javascript(script, move |result| {
+            // A page that fails the search has nothing to show.
+            let (matches, index) = result
+                .ok()
+                .as_ref()
+                .and_then(find::parse_result)
+                .unwrap_or((0, None));
+            emit_event(
+                EventType::Find,
+                serde_json::json!({
+                    "type": "find_result",
+                    "webview_id": id,
+                    "text": text,
+                    "matches": matches,
+                    "index": index,
+                }),
+            );
+        });
+    }
+
+    /// Remove the active webview's find-in-page highlights.
+    fn clear_find(&self) {
+        let (Some(id), Some(webview)) = (self.active_webview.get(), self.webview()) else {
+            return;
+        };
+        if self.find_webviews.borrow_mut().remove(&id) {
+            webview.evaluate_javascript(find::CLEAR_JS, |_result| {});
+        }
+    }
+
+    /// The device pixels a CSS pixel of the page takes up, at its zoom.
+    fn device_pixels_per_css_pixel(&self) -> f64 {
+        f64::from_bits(SCALE_FACTOR.load(Ordering::Relaxed)) * self.zoom.borrow().effective() as f64
+    }
+
+    /// Move the mouse to `point` in the active webview.
+    fn send_mouse_move(&self, point: Point2D<f32, DevicePixel>) {
+        self.last_mouse_position.set(point);
+        if let Some(webview) = self.webview() {
+            let servo_point = DevicePoint::new(point.x, point.y);
+            let buttons = self.pressed_mouse_buttons.get();
+            if buttons != 0 {
+                log::trace!("MouseMove at {:?} with buttons={}", point, buttons);
+            }
+            webview.notify_input_event(InputEvent::MouseMove(MouseMoveEvent::new_with_buttons(
+                servo_point.into(),
+                buttons,
+            )));
+        }
+    }
+
+    /// Press or release `servo_button` at the mouse position in the active
+    /// webview.
+    fn send_mouse_button(&self, action: MouseButtonAction, servo_button: ServoMouseButton) {
+        let current_buttons =
+            input::pressed_buttons(self.pressed_mouse_buttons.get(), action, servo_button);
+        self.pressed_mouse_buttons.set(current_buttons);
+
+        log::trace!(
+            "MouseInput {:?} button={:?} total_buttons={}",
+            action,
+            servo_button,
+            current_buttons
+        );
+
+        let point = self.last_mouse_position.get();
+        if let Some(webview) = self.webview() {
+            let servo_point = DevicePoint::new(point.x, point.y);
+            webview.notify_input_event(InputEvent::MouseButton(MouseButtonEvent::new(
+                action,
+                servo_button,
+                servo_point.into(),
+            )));
+        }
+    }
+
+    /// Deliver a key event to the active webview, unless it triggers an
+    /// accelerator. Cmd/Ctrl+C, X and V become editing actions.
+    fn send_key(&self, servo_event: KeyboardEvent) {
+        let Some(webview) = self.webview() else {
+            return;
+        };
+        if self.handle_accelerator(&servo_event) {
+            return;
+        }
+        let event = match input::editing_action(&servo_event) {
+            Some(action) => InputEvent::EditingAction(action),
+            None => InputEvent::Keyboard(servo_event),
+        };
+        webview.notify_input_event(event);
+    }
+
+    /// Scroll the active webview by `(x, y)` at the mouse position.
+    fn send_wheel(&self, x: f64, y: f64, mode: WheelMode) {
+        let point = self.last_mouse_position.get();
+        if let Some(webview) = self.webview() {
+            webview.notify_input_event(InputEvent::Wheel(WheelEvent::new(
+                WheelDelta { x, y, z: 0.0, mode },
+                DevicePoint::new(point.x, point.y).into(),
+            )));
+        }
+    }
+
+    /// Send the running smooth scroll's next step once it is due.
+    fn step_smooth_scroll(&self) {
+        let now = Instant::now();
+        let due = self
+            .smooth_scroll
+            .borrow()
+            .next_step()
+            .is_some_and(|due| now >= due);
+        if !due {
+            return;
+        }
+        let step = self.smooth_scroll.borrow_mut().step(now);
+        if let Some((x, y)) = step {
+            self.send_wheel(x, y, WheelMode::DeltaPixel);
+        }
+    }
+
+    /// Give up on opening the window: `pw_start_app` returns
+    /// `ErrorCode::WindowFailed` with `message` as the last error.
+    fn fail_startup(&self, event_loop: &ActiveEventLoop, message: String) {
+        errors::set_last_error(message);
+        self.startup_failed.set(true);
+        event_loop.exit();
+    }
+
+    /// Tell every webview the theme `prefers-color-scheme` should report.
+    fn apply_color_scheme(&self) {
+        let theme = color_scheme::current(self.os_theme.get());
+        for (_, webview) in &self.webviews {
+            webview.notify_theme_change(theme);
+        }
+    }
+
+    /// Report the files dragged onto the window once all of a drag's files
+    /// have arrived.
+    fn check_file_batches(&self) {
+        let now = Instant::now();
+        let hovered = self.hovered_files.borrow_mut().take_complete(now);
+        if let Some(paths) = hovered {
+            self.file_hover_reported.set(true);
+            emit_event(
+                EventType::Input,
+                serde_json::json!({
+                    "type": "file_hover",
+                    "webview_id": self.active_webview.get(),
+                    "paths": file_drop::path_strings(&paths),
+                    "hovering": true,
+                }),
+            );
+        }
+        let dropped = self.dropped_files.borrow_mut().take_complete(now);
+        if let Some(paths) = dropped {
+            let paths = file_drop::path_strings(&paths);
+            emit_event(
+                EventType::Input,
+                serde_json::json!({
+                    "type": "file_dropped",
+                    "webview_id": self.active_webview.get(),
+                    "path": paths.first(),
+                    "paths": paths,
+                }),
+            );
+        }
+    }
+
+    /// Have Servo measure its memory use. The processes answer asynchronously,
+    /// so the report is awaited on another thread and handed back to the
+    /// event loop to be emitted. Every request ends in an event: a report
+    /// that can't be made or doesn't arrive in time is a failure.
+    fn create_memory_report(&self, id: u32) {
+        let fail = |error: String| {
+            let _ = self
+                .proxy
+                .send_event(UserEvent::MemoryReport(id, Err(error)));
+        };
+        let Some(servo) = &self.servo else {
+            fail("the engine isn't running".to_string());
+            return;
+        };
+        let (sender, receiver) = match ipc::channel() {
+            Ok(channel) => channel,
+            Err(e) => {
+                fail(format!("can't create a channel for the report: {}", e));
+                return;
+            }
+        };
+        servo.create_memory_report(sender);
+        let proxy = self.proxy.clone();
+        thread::spawn(move || {
+            let report = receiver
+                .try_recv_timeout(memory::REPORT_TIMEOUT)
+                .map_err(|e| format!("the engine didn't report: {:?}", e))
+                .and_then(|report| serde_json::to_value(report).map_err(|e| e.to_string()));
+            let _ = proxy.send_event(UserEvent::MemoryReport(id, report));
+        });
+    }
+
+    /// Report the engine resources that couldn't be read since the last check.
+    fn check_resource_failures(&self) {
+        for failure in resource_reader::take_failed_reads() {
+            emit_event(
+                EventType::Resource,
+                serde_json::json!({
+                    "type": "resource_missing",
+                    "name": failure.name,
+                    "required": failure.required,
+                    "error": failure.error,
+                }),
+            );
+        }
+    }
+
+    /// Report the active page's status text once it has settled.
+    fn check_status_text(&self) {
+        let settled = self.status_text.borrow_mut().take_settled(Instant::now());
+        if let Some(text) = settled {
+            self.emit_status_text(text);
+        }
+    }
+
+    /// Empty the status bar of the webview being left.
+    fn clear_status_text(&self) {
+        let cleared = self
+            .status_text
+            .borrow_mut()
+            .update(String::new(), Instant::now());
+        if let Some(text) = cleared {
+            self.emit_status_text(text);
+        }
+    }
+
+    fn emit_status_text(&self, text: String) {
+        emit_event(
+            EventType::Title,
+            serde_json::json!({
+                "type": "status_text",
+                "webview_id": self.active_webview.get(),
+                "text": text,
+            }),
+        );
+    }
+
+    /// Report the active page once it is stuck or has recovered, and send it
+    /// the script that shows whether it still responds when one is due.
+    fn check_watchdog(&self) {
+        let (Some(id), Some(webview)) = (self.active_webview.get(), self.webview()) else {
+            return;
+        };
+        let now = Instant::now();
+        let change = self.watchdog.borrow_mut().check(now);
+        if let Some(change) = change {
+            if let watchdog::Change::Stuck(stuck) = &change {
+                log::warn!("The page is unresponsive: {}", stuck.reason());
+            }
+            let mut event = watchdog::event(&change);
+            event["webview_id"] = id.into();
+            emit_event(EventType::Crash, event);
+        }
+        if !self.watchdog.borrow_mut().ping_due(now) {
+            return;
+        }
+        let state = self.watchdog.clone();
+        // Any answer will do, even an error: it comes from the page's script
+        // thread, which is what a hung page doesn't get back to.
+        webview.evaluate_javascript("0", move |_result| {
+            if let Some(change) = state.borrow_mut().pong(now) {
+                let mut event = watchdog::event(&change);
+                event["webview_id"] = id.into();
+                emit_event(EventType::Crash, event);
+            }
+        });
+    }
+
+    /// Pause painting while the window is minimized or covered, and let the
+    /// page know it is hidden so Servo throttles its animations. When the
+    /// window is revealed, paint the latest frame straight away.
+    fn update_window_visibility(&self) {
+        let hidden = self.occluded.get() || self.minimized.get();
+        if hidden == self.window_hidden.replace(hidden) {
+            return;
+        }
+        log::debug!(
+            "Window {}",
+            if hidden {
+                "hidden, pausing painting"
+            } else {
+                "revealed"
+            }
+        );
+        if let Some(webview) = self.webview() {
+            if hidden {
+                webview.hide();
+            } else {
+                webview.show();
+            }
+        }
+        if !hidden {
+            self.needs_repaint.set(true);
+            if let Some(window) = &self.window {
+                window.request_redraw();
+            }
+        }
+    }
+
+    fn repaint(&self) {
+        // Nothing can be seen of a hidden window: keep the frame pending for
+        // when it is revealed, unless a capture is waiting for it.
+        if self.window_hidden.get() && self.pending_captures.borrow().is_empty() {
+            self.needs_repaint.set(true);
+            return;
+        }
+        // Under a frame rate cap, hold back frames that come too soon and
+        // paint the latest one when the interval is up.
+        if self
+            .next_paint_due()
+            .is_some_and(|due| Instant::now() < due)
+        {
+            self.needs_repaint.set(true);
+            return;
+        }
+        // After a resize, painting before Servo has laid the page out at the
+        // new size would show a blank frame or the old layout cut off. Until
+        // a new frame comes, show the last one stretched to the new size.
+        if self
+            .resize_hold
+            .get()
+            .is_some_and(|until| Instant::now() < until)
+            && !self.needs_repaint.get()
+        {
+            self.draw_resize_frame();
+            return;
+        }
+        self.resize_hold.set(None);
+        self.release_resize_frame();
+        self.needs_repaint.set(false);
+
+        if let (Some(webview), Some(rendering_context)) =
+            (self.webview(), &self.headless_rendering_context)
+        {
+            rendering_context
+                .make_current()
+                .expect("Failed to make headless context current");
+            rendering_context.prepare_for_rendering();
+            webview.paint();
+            self.record_paint();
+            let size = rendering_context.size();
+            self.serve_captures(&rendering_context.glow_gl_api(), size.width, size.height);
+            return;
+        }
+
+        if let (Some(webview), Some(window_rc), Some(offscreen_rc), Some(window)) = (
+            self.webview(),
+            &self.window_rendering_context,
+            &self.offscreen_rendering_context,
+            &self.window,
+        ) {
+            // 1. Make offscreen context current (ensure Servo renders to FBO)
+            offscreen_rc
+                .make_current()
+                .expect("Failed to make offscreen context current");
+            offscreen_rc.prepare_for_rendering();
+
+            // 2. Servo paints to FBO
+            webview.paint();
+            self.record_paint();
+
+            let size = window.inner_size();
+            self.serve_captures(&offscreen_rc.glow_gl_api(), size.width, size.height);
+
+            // 3. Blit Servo output
+            window_rc
+                .make_current()
+                .expect("Failed to make window context current");
+            window_rc.prepare_for_rendering(); // Bind window FBO
+
+            let gl = window_rc.glow_gl_api();
+            clear_framebuffer(&gl, self.background);
+
+            if let Some(cb) = offscreen_rc.render_to_parent_callback() {
+                let size = window.inner_size();
+                let rect = Rect::new(
+                    Point2D::origin(),
+                    Size2D::new(size.width as i32, size.height as i32),
+                );
+                cb(&gl, rect);
+            }
+
+            // 4. Present
+            window_rc.present();
+        }
+    }
+}
+
+/// Borderless fullscreen on the window's current monitor. On macOS this uses a
+/// native fullscreen space.
+fn set_window_fullscreen(window: &Window, fullscreen: bool) {
+    if fullscreen {
+        window.set_fullscreen(Some(Fullscreen::Borderless(None)));
+    } else {
+        window.set_fullscreen(None);
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn force_srgb_color_space(window_handle: raw_window_handle::RawWindowHandle) {
+    if let raw_window_handle::RawWindowHandle::AppKit(handle) = window_handle {
+        // Safety: We are on main thread (winit event loop)
+        unsafe {
+            if let Some(_mtm) = MainThreadMarker::new() {
+                let view_ptr = handle.ns_view.as_ptr() as *mut NSView;
+                if !view_ptr.is_null() {
+                    let view = &*view_ptr;
+                    if let Some(window) = view.window() {
+                        window.setColorSpace(Some(&NSColorSpace::sRGBColorSpace()));
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+fn force_srgb_color_space(_window_handle: raw_window_handle::RawWindowHandle) {
+    // No-op
+}
+
+impl ApplicationHandler<UserEvent> for AppState {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        if self.window.is_some() {
+            return;
+        }
+
+        log::info!("App resumed, creating window...");
+        let mut window_attributes = WindowAttributes::default()
+            .with_title(&self.initial_title)
+            .with_inner_size(winit::dpi::LogicalSize::new(
+                self.initial_size.0 as f64,
+                self.initial_size.1 as f64,
+            ))
+            .with_visible(!self.start_hidden)
+            .with_transparent(self.transparent)
+            .with_decorations(self.decorations);
+        let limits = *SIZE_LIMITS.lock().unwrap();
+        if let Some((width, height)) = limits.min {
+            window_attributes = window_attributes
+                .with_min_inner_size(winit::dpi::LogicalSize::new(width as f64, height as f64));
+        }
+        if let Some((width, height)) = limits.max {
+            window_attributes = window_attributes
+                .with_max_inner_size(winit::dpi::LogicalSize::new(width as f64, height as f64));
+        }
+        if let Some((x, y)) = *INITIAL_POSITION.lock().unwrap() {
+            window_attributes =
+                window_attributes.with_position(winit::dpi::PhysicalPosition::new(x, y));
+        }
+        window_attributes =
+            window_attributes.with_window_level(*INITIAL_WINDOW_LEVEL.lock().unwrap());
+
+        let window = match event_loop.create_window(window_attributes) {
+            Ok(window) => Arc::new(window),
+            Err(e) => {
+                self.fail_startup(event_loop, format!("Could not create the window: {}", e));
+                return;
+            }
+        };
+        self.window = Some(window.clone());
+        SCALE_FACTOR.store(window.scale_factor().to_bits(), Ordering::Relaxed);
+        self.os_theme.set(window.theme());
+
+        let window_handle = window.window_handle().expect("Failed to get window handle");
+        force_srgb_color_space(window_handle.as_raw());
+        platform::apply_window_chrome(&window);
+
+        log::info!(
+            "Window created. Physical size: {:?}, Scale factor: {}",
+            window.inner_size(),
+            window.scale_factor()
+        );
+
+        log::debug!("Creating WindowRenderingContext...");
+        let display_handle = event_loop
+            .display_handle()
+            .expect("Failed to get display handle");
+
+        let window_rc =
+            match WindowRenderingContext::new(display_handle, window_handle, window.inner_size()) {
+                Ok(window_rc) => Rc::new(window_rc),
+                Err(e) => {
+                    let message = format!("Could not create the OpenGL context: {:?}", e);
+                    self.fail_startup(event_loop, message);
+                    return;
+                }
+            };
+        if let Err(e) = window_rc.make_current() {
+            let message = format!("Could not use the OpenGL context: {:?}", e);
+            self.fail_startup(event_loop, message);
+            return;
+        }
+        // Show the background color, not uninitialized memory, until the
+        // first frame is painted.
+        window_rc.prepare_for_rendering();
+        if self.transparent {
+            // Servo asks for a GL config with an alpha channel, but the
+            // platform may only offer opaque ones for the window.
+            if framebuffer_alpha_bits(&window_rc.glow_gl_api()) > 0 {
+                platform::make_transparent(&window);
+            } else {
+                log::warn!("The window's OpenGL surface has no alpha channel, it stays opaque");
+                emit_event(
+                    EventType::Window,
+                    serde_json::json!({"type": "transparency_unavailable"}),
+                );
+            }
+        }
+        clear_framebuffer(&window_rc.glow_gl_api(), self.background);
+        window_rc.present();
+
+        log::debug!("Creating OffscreenRenderingContext...");
+        let offscreen_rc = Rc::new(window_rc.offscreen_context(window.inner_size()));
+
+        self.window_rendering_context = Some(window_rc.clone());
+        self.offscreen_rendering_context = Some(offscreen_rc.clone());
+
+        let webview = self.build_webview();
+
+        // The webview is shown even when the window starts hidden, so the page
+        // loads and lays out before the window appears.
+        webview.show();
+        webview.focus();
+        if !self.start_hidden {
+            window.focus_window();
+        }
+
+        // Kick off the first spin to start loading
+        self.pump_servo();
+
+        // Launches forwarded before the window existed.
+        self.handle_second_instances();
+
+        if !matches!(
+            *thumbnail::THUMBNAIL.lock().unwrap(),
+            ThumbnailSource::Default
+        ) {
+            self.update_thumbnail();
+        }
+
+        window.request_redraw();
+        event_loop.set_control_flow(ControlFlow::Wait);
+    }
+
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, _id: WindowId, event: WindowEvent) {
+        match event {
+            WindowEvent::CloseRequested => {
+                if self.intercept_close && self.close_requested_at.get().is_none() {
+                    log::info!("Close requested, waiting for confirmation");
+                    self.close_requested_at.set(Some(Instant::now()));
+                    emit_event(
+                        EventType::Close,
+                        serde_json::json!({"type": "close_requested"}),
+                    );
+                    return;
+                }
+                log::info!("Close requested, exiting...");
+                self.shut_down();
+                event_loop.exit();
+                return;
+            }
+            WindowEvent::Focused(focused) => {
+                log::debug!("Window focused: {}", focused);
+                if let Some(webview) = self.webview() {
+                    if focused {
+                        webview.focus();
+                    } else {
+                        webview.blur();
+                        log::debug!("Window lost focus, blurring webview");
+                    }
+                }
+            }
+            WindowEvent::Resized(size) => {
+                log::debug!("Resized to {:?}", size);
+                self.check_fullscreen_change();
+                // Some platforms only report minimizing as a resize to 0x0.
+                let minimized = size.width == 0
+                    || size.height == 0
+                    || self
+                        .window
+                        .as_ref()
+                        .and_then(|window| window.is_minimized())
+                        .unwrap_or(false);
+                self.minimized.set(minimized);
+                self.update_window_visibility();
+                if !minimized {
+                    self.keep_frame_for_resize();
+                }
+                // Resize both contexts
+                if let Some(rc) = &self.window_rendering_context {
+                    rc.resize(size);
+                }
+                // Offscreen context resize logic might need to check if webview resizes internally?
+                // Actually webview.resize will call resize on its context (offscreen_rc)
+                // Background webviews are resized too, so they are laid out
+                // for the window by the time they are shown.
+                for (_, webview) in &self.webviews {
+                    webview.resize(size);
+                }
+                // A frame Servo had ready is for the old size; wait for the
+                // one at the new size instead.
+                self.needs_repaint.set(false);
+                self.resize_hold.set(Some(Instant::now() + RESIZE_HOLD));
+            }
+            WindowEvent::ScaleFactorChanged {
+                scale_factor,
+                inner_size_writer: _,
+            } => {
+                log::info!("Scale factor changed to {}", scale_factor);
+                for (_, webview) in &self.webviews {
+                    webview.set_hidpi_scale_factor(Scale::new(scale_factor as f32));
+                }
+                let previous =
+                    f64::from_bits(SCALE_FACTOR.swap(scale_factor.to_bits(), Ordering::Relaxed));
+                if let Some(window) = &self.window {
+                    // The window keeps its logical size, so its physical size
+                    // scales along; the resize itself is reported afterwards.
+                    let size = window
+                        .inner_size()
+                        .to_logical::<f64>(previous)
+                        .to_physical::<u32>(scale_factor);
+                    emit_event(
+                        EventType::Window,
+                        serde_json::json!({
+                            "type": "scale_factor",
+                            "value": scale_factor,
+                            "width": size.width,
+                            "height": size.height,
+                        }),
+                    );
+                }
+            }
+            WindowEvent::CursorLeft { .. } => {
+                if let Some(webview) = self.webview() {
+                    webview.notify_input_event(InputEvent::MouseLeftViewport(Default::default()));
+                }
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                if self.mouse_emulated_by_touch() {
+                    return;
+                }
+                self.send_mouse_move(Point2D::new(position.x as f32, position.y as f32));
+            }
+            WindowEvent::MouseInput { state, button, .. } => {
+                if self.mouse_emulated_by_touch() {
+                    return;
+                }
+                self.send_mouse_button(
+                    input::mouse_button_action_from_winit(state),
+                    input::mouse_button_from_winit(button),
+                );
+            }
+            WindowEvent::Touch(touch) => {
+                match touch.phase {
+                    TouchPhase::Started => {
+                        self.active_touches.borrow_mut().insert(touch.id);
+                    }
+                    TouchPhase::Moved => {}
+                    TouchPhase::Ended | TouchPhase::Cancelled => {
+                        self.active_touches.borrow_mut().remove(&touch.id);
+                    }
+                }
+                let event_type = input::touch_event_type_from_winit(touch.phase);
+                if self.active_touches.borrow().is_empty() {
+                    self.last_touch_end.set(Some(Instant::now()));
+                }
+                if let Some(webview) = self.webview() {
+                    let point = DevicePoint::new(touch.location.x as f32, touch.location.y as f32);
+                    webview.notify_input_event(InputEvent::Touch(TouchEvent::new(
+                        event_type,
+                        TouchId(touch.id as i32),
+                        point.into(),
+                    )));
+                }
+            }
+            WindowEvent::PinchGesture { delta, .. } => {
+                // Trackpad pinch zooms the page like Ctrl+scroll in browsers,
+                // remembered for the page's origin.
+                if let Some(webview) = self.webview() {
+                    let mut zoom = self.zoom.borrow_mut();
+                    let level = zoom.effective() * (1.0 + delta as f32);
+                    zoom.zoom_page(webview, level);
+                }
+            }
+            WindowEvent::Ime(ime) => {
+                if let Some(webview) = self.webview() {
+                    webview.notify_input_event(InputEvent::Ime(ime_event_from_winit(&ime)));
+                }
+            }
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.modifiers_state.set(modifiers.state());
+            }
+            WindowEvent::KeyboardInput { event, .. } => {
+                self.send_key(keyboard_event_from_winit(
+                    &event,
+                    self.modifiers_state.get(),
+                ));
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                log::trace!("MouseWheel: {:?}", delta);
+                let scale_factor = self
+                    .window
+                    .as_ref()
+                    .map_or(1.0, |window| window.scale_factor());
+                let (delta_x, delta_y, mode) = scroll::wheel_delta(delta, scale_factor);
+                // Touchpads already scroll smoothly, and page steps stay steps.
+                let notch = matches!(delta, MouseScrollDelta::LineDelta(..))
+                    && matches!(mode, WheelMode::DeltaPixel);
+                if self.smooth_scrolling && notch {
+                    self.smooth_scroll
+                        .borrow_mut()
+                        .add(delta_x, delta_y, Instant::now());
+                    self.step_smooth_scroll();
+                } else {
+                    self.send_wheel(delta_x, delta_y, mode);
+                }
+            }
+            WindowEvent::ThemeChanged(theme) => {
+                log::info!("OS theme changed to {:?}", theme);
+                self.os_theme.set(Some(theme));
+                self.apply_color_scheme();
+            }
+            WindowEvent::HoveredFile(path) => {
+                self.hovered_files.borrow_mut().add(path, Instant::now());
+            }
+            WindowEvent::HoveredFileCancelled => {
+                self.hovered_files.borrow_mut().discard();
+                if self.file_hover_reported.replace(false) {
+                    emit_event(
+                        EventType::Input,
+                        serde_json::json!({
+                            "type": "file_hover",
+                            "webview_id": self.active_webview.get(),
+                            "paths": [],
+                            "hovering": false,
+                        }),
+                    );
+                }
+            }
+            WindowEvent::DroppedFile(path) => {
+                // The drop ends the hover; `file_dropped` says as much.
+                self.hovered_files.borrow_mut().discard();
+                self.file_hover_reported.set(false);
+                self.dropped_files.borrow_mut().add(path, Instant::now());
+            }
+            WindowEvent::Occluded(occluded) => {
+                self.occluded.set(occluded);
+                self.update_window_visibility();
+            }
+            WindowEvent::RedrawRequested => {
+                log::trace!("RedrawRequested");
+                self.repaint();
+            }
+            _ => (),
+        }
+
+        // Critical: pump Servo on EVERY window event, just like servoshell does.
+        self.pump_servo();
+    }
+
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        self.check_load_timeout();
+        self.check_thumbnail_refresh();
+        self.check_close_timeout();
+        self.check_permission_timeouts();
+        self.check_auth_timeouts();
+        self.check_capture_timeouts();
+        self.check_held_frame();
+        self.check_resize_hold();
+        self.step_smooth_scroll();
+        self.check_file_batches();
+        self.check_resource_failures();
+        self.check_watchdog();
+        self.check_status_text();
+        self.check_animation_tick();
+        if self.exit_requested.get() {
+            event_loop.exit();
+            return;
+        }
+
+        // Only wake on a timer while there is a deadline to enforce.
+        let deadline = [
+            self.load_deadline(),
+            self.next_thumbnail_refresh.get(),
+            self.close_deadline(),
+            self.permissions.borrow().next_deadline(),
+            self.authentications.borrow().next_deadline(),
+            self.capture_deadline(),
+            self.held_frame_deadline(),
+            self.resize_hold.get(),
+            self.smooth_scroll.borrow().next_step(),
+            self.hovered_files.borrow().deadline(),
+            self.dropped_files.borrow().deadline(),
+            self.watchdog.borrow().deadline(),
+            self.status_text.borrow().deadline(),
+            self.animation_deadline(),
+        ]
+        .into_iter()
+        .flatten()
+        .min();
+        match deadline {
+            Some(deadline) => event_loop.set_control_flow(ControlFlow::WaitUntil(deadline)),
+            None => event_loop.set_control_flow(ControlFlow::Wait),
+        }
+    }
+
+    fn user_event(&mut self, event_loop: &ActiveEventLoop, event: UserEvent) {
+        self.handle_user_event(event);
+        if self.exit_requested.get() {
+            event_loop.exit();
+        }
+    }
+}
+
+impl AppState {
+    fn handle_user_event(&mut self, event: UserEvent) {
+        match event {
+            UserEvent::Shutdown => self.shut_down(),
+            UserEvent::CancelClose => self.close_requested_at.set(None),
+            UserEvent::SetClipboardText(text) => {
+                if let Err(e) = clipboard::set_text(text) {
+                    errors::set_last_error(format!("Clipboard write failed: {}", e));
+                }
+            }
+            UserEvent::GetClipboardText(reply) => {
+                let text = clipboard::get_text().unwrap_or_else(|e| {
+                    errors::set_last_error(format!("Clipboard read failed: {}", e));
+                    None
+                });
+                let _ = reply.send(text);
+            }
+            UserEvent::SetWindowIcon(icon) => {
+                if let Some(window) = &self.window {
+                    window.set_window_icon(Some(icon));
+                }
+            }
+            UserEvent::ApplySizeLimits => {
+                if let Some(window) = &self.window {
+                    let limits = *SIZE_LIMITS.lock().unwrap();
+                    let logical = |(width, height): (u32, u32)| {
+                        winit::dpi::LogicalSize::new(width as f64, height as f64)
+                    };
+                    window.set_min_inner_size(limits.min.map(logical));
+                    window.set_max_inner_size(limits.max.map(logical));
+                }
+            }
+            UserEvent::SetWindowPosition(x, y) => {
+                if let Some(window) = &self.window {
+                    window.set_outer_position(winit::dpi::PhysicalPosition::new(x, y));
+                }
+            }
+            UserEvent::GetUrl(reply) => {
+                let url = self
+                    .webview()
+                    .and_then(|webview| webview.url())
+                    .map(|url| url.to_string());
+                let _ = reply.send(url);
+            }
+            UserEvent::GetWindowPosition(reply) => {
+                let position = self
+                    .window
+                    .as_ref()
+                    .and_then(|window| window.outer_position().ok())
+                    .map(|position| (position.x, position.y));
+                let _ = reply.send(position);
+            }
+            UserEvent::SetFullscreen(fullscreen) => {
+                if let Some(window) = &self.window {
+                    set_window_fullscreen(window, fullscreen);
+                }
+            }
+            UserEvent::SetWindowState(state, reply) => {
+                let _ = reply.send(self.window.is_some());
+                if let Some(window) = &self.window {
+                    match state {
+                        WindowState::Maximized => {
+                            window.set_minimized(false);
+                            window.set_maximized(true);
+                        }
+                        WindowState::Minimized => window.set_minimized(true),
+                        WindowState::Restored => {
+                            window.set_minimized(false);
+                            window.set_maximized(false);
+                            window.focus_window();
+                        }
+                    }
+                }
+            }
+            UserEvent::GetWindowState(reply) => {
+                let state = self.window.as_ref().map(|window| {
+                    let minimized = window.is_minimized().unwrap_or(self.minimized.get());
+                    (window.is_maximized(), minimized)
+                });
+                let _ = reply.send(state);
+            }
+            UserEvent::SetWindowLevel(level, reply) => {
+                let supported = match &self.window {
+                    Some(window) if platform::supports_window_level(window) => {
+                        window.set_window_level(level);
+                        true
+                    }
+                    _ => false,
+                };
+                let _ = reply.send(supported);
+            }
+            UserEvent::SetVisible(visible) => {
+                if let Some(window) = &self.window {
+                    window.set_visible(visible);
+                    if visible {
+                        window.focus_window();
+                        self.needs_repaint.set(true);
+                        self.pump_servo();
+                    }
+                }
+            }
+            UserEvent::Wake => {
+                self.pump_servo();
+            }
+            UserEvent::Crash(detail) => emit_event(
+                EventType::Crash,
+                serde_json::json!({
+                    "type": "crash",
+                    "webview_id": null,
+                    "detail": detail,
+                    "reloading": false,
+                }),
+            ),
+            UserEvent::CreateMemoryReport(id) => self.create_memory_report(id),
+            UserEvent::MemoryReport(id, report) => {
+                let event = match report {
+                    Ok(report) => serde_json::json!({
+                        "type": "memory_report",
+                        "request_id": id,
+                        "summary": memory::summarize(&report),
+                        "report": report,
+                    }),
+                    Err(error) => serde_json::json!({
+                        "type": "memory_report_failed",
+                        "request_id": id,
+                        "error": error,
+                    }),
+                };
+                emit_event(EventType::Memory, event);
+            }
+            UserEvent::RunDiagnostics(reply) => {
+                let mut report = match (&self.window, &self.window_rendering_context) {
+                    (Some(window), Some(window_rc)) => {
+                        // Servo may have left another context current.
+                        let gl = window_rc
+                            .make_current()
+                            .is_ok()
+                            .then(|| window_rc.glow_gl_api());
+                        diagnostics::runtime_report(window, gl.as_deref())
+                    }
+                    _ => serde_json::json!({}),
+                };
+                report["rendering"] = serde_json::json!({
+                    "frames_painted": self.frames_painted.get(),
+                    "max_fps": self
+                        .frame_interval
+                        .map(|interval| (1.0 / interval.as_secs_f64()).round()),
+                });
+                let _ = reply.send(report);
+            }
+            UserEvent::AddUserScript(script, run_now) => {
+                if run_now {
+                    if let Some(webview) = self.webview() {
+                        webview.evaluate_javascript(script.clone(), |_result| {});
+                    }
+                }
+                injected::add_user_script(self.user_content_manager.as_deref(), script);
+            }
+            UserEvent::ExecuteJs(script) => {
+                if let Some(queue) = self.queued_scripts.borrow_mut().as_mut() {
+                    queue.push(script);
+                } else if let Some(webview) = self.webview() {
+                    webview.evaluate_javascript(script, |_result| {
+                        // For now we don't handle the result back to Python
+                    });
+                }
+            }
+            UserEvent::SetTitle(title) => {
+                if let Some(window) = &self.window {
+                    window.set_title(&title);
+                }
+            }
+            UserEvent::Resize(width, height) => {
+                if let Some(window) = &self.window {
+                    let (width, height) = SIZE_LIMITS.lock().unwrap().clamp((width, height));
+                    let _ = window.request_inner_size(winit::dpi::LogicalSize::new(
+                        width as f64,
+                        height as f64,
+                    ));
+                }
+            }
+            UserEvent::ApplyColorScheme => self.apply_color_scheme(),
+            UserEvent::ReapplyInjections => {
+                if let Some(webview) = self.webview() {
+                    injected::inject_into(webview);
+                }
+            }
+            UserEvent::GetScrollPosition(reply) => match self.webview() {
+                Some(webview) => {
+                    webview.evaluate_javascript(
+                        "[window.scrollX, window.scrollY]",
+                        move |result| {
+                            let position = match result {
+                                Ok(JSValue::Array(values)) => match values.as_slice() {
+                                    [JSValue::Number(x), JSValue::Number(y)] => Some((*x, *y)),
+                                    _ => None,
+                                },
+                                _ => None,
+                            };
+                            let _ = reply.send(position);
+                        },
+                    );
+                }
+                None => {
+                    let _ = reply.send(None);
+                }
+            },
+            UserEvent::SendMouseClick(x, y, button) => {
+                let scale = self.device_pixels_per_css_pixel();
+                self.send_mouse_move(Point2D::new((x * scale) as f32, (y * scale) as f32));
+                self.send_mouse_button(MouseButtonAction::Down, button);
+                self.send_mouse_button(MouseButtonAction::Up, button);
+            }
+            UserEvent::SendKey(key, code, modifiers) => {
+                for state in [servo::KeyState::Down, servo::KeyState::Up] {
+                    self.send_key(synthetic_keyboard_event(
+                        state,
+                        key.clone(),
+                        code,
+                        modifiers,
+                    ));
+                }
+            }
+            UserEvent::SendScroll(x, y) => {
+                let (x, y) = input::wheel_delta_from_css(x, y, self.device_pixels_per_css_pixel());
+                self.send_wheel(x, y, WheelMode::DeltaPixel);
+            }
+            UserEvent::SetScrollPosition(x, y) => {
+                if let Some(webview) = self.webview() {
+                    // Until the page has loaded its layout may still grow, so a
+                    // restored offset would be clamped short; apply it on completion.
+                    if LOADING.load(Ordering::Relaxed) {
+                        self.pending_scroll.set(Some((x, y)));
+                    } else {
+                        scroll_to(webview, x, y);
+                    }
+                }
+            }
+            UserEvent::CaptureDomSnapshot(max_depth, reply) => match self.webview() {
+                Some(webview) => {
+                    let script = DOM_SNAPSHOT_JS
+                        .replace("__MAX_DEPTH__", &max_depth.to_string())
+                        .replace("__MAX_NODES__", &DOM_SNAPSHOT_MAX_NODES.to_string());
+                    webview.evaluate_javascript(script, move |result| {
+                        let snapshot = match result {
+                            Ok(JSValue::String(json)) => Some(json),
+                            _ => None,
+                        };
+                        let _ = reply.send(snapshot);
+                    });
+                }
+                None => {
+                    let _ = reply.send(None);
+                }
+            },
+            UserEvent::Find(text, forward, case_sensitive) => {
+                self.find_in_page(text, forward, case_sensitive)
+            }
+            UserEvent::ClearFind => self.clear_find(),
+            UserEvent::SetDefaultZoom(level) => {
+                let mut zoom = self.zoom.borrow_mut();
+                zoom.set_default(level);
+                if let Some(webview) = self.webview() {
+                    zoom.apply(webview);
+                }
+            }
+            UserEvent::SetOriginZoom(level) => {
+                if let Some(webview) = self.webview() {
+                    self.zoom.borrow_mut().zoom_page(webview, level);
+                }
+            }
+            UserEvent::StepZoom(zoom_in) => {
+                if let Some(webview) = self.webview() {
+                    let mut zoom = self.zoom.borrow_mut();
+                    let level = zoom::step_zoom(zoom.effective(), zoom_in);
+                    zoom.zoom_page(webview, level);
+                }
+            }
+            UserEvent::ResetZoom => {
+                if let Some(webview) = self.webview() {
+                    self.zoom.borrow_mut().reset_page(webview);
+                }
+            }
+            UserEvent::SetPinchZoom(level) => {
+                if let Some(webview) = self.webview() {
+                    let level = zoom::clamp_pinch_zoom(level);
+                    webview.set_pinch_zoom(level);
+                    emit_event(
+                        EventType::Zoom,
+                        serde_json::json!({"type": "pinch_zoom", "level": level}),
+                    );
+                }
+            }
+            UserEvent::PrintToPdf(path, page_size, reply) => {
+                let _ = reply.send(self.start_print(path, page_size));
+            }
+            UserEvent::ShowPrintDialog => {
+                let title = self
+                    .webview()
+                    .and_then(|webview| webview.page_title())
+                    .filter(|title| !title.trim().is_empty())
+                    .unwrap_or_else(|| "page".to_string());
+                let file_name = format!("{}.pdf", title.trim());
+                if let Some(path) = file_picker::save_pdf(self.window.as_deref(), &file_name) {
+                    self.start_print(path, PageSize::A4);
+                }
+            }
+            UserEvent::PrintMeasured(measurements) => self.print_measured(measurements),
+            UserEvent::PrintScrolled(offset) => self.print_scrolled(offset),
+            UserEvent::PrintNext => self.print_next(),
+            UserEvent::CapturePng(path, reply) => {
+                if self.frame_painted.get() {
+                    // The caller gives up after `QUERY_TIMEOUT`.
+                    let deadline = Instant::now() + QUERY_TIMEOUT;
+                    self.capture_next_frame(
+                        Box::new(move |frame| {
+                            let written =
+                                frame
+                                    .encode_png()
+                                    .map_err(|e| e.to_string())
+                                    .and_then(|png| {
+                                        std::fs::write(&path, png).map_err(|e| e.to_string())
+                                    });
+                            let code = match written {
+                                Ok(()) => 0,
+                                Err(e) => {
+                                    errors::set_last_error(format!(
+                                        "Cannot write capture to {:?}: {}",
+                                        path, e
+                                    ));
+                                    ErrorCode::WriteFailed.into()
+                                }
+                            };
+                            let _ = reply.send(code);
+                        }),
+                        Some(deadline),
+                    );
+                } else {
+                    errors::set_last_error_quietly("Nothing has been rendered yet");
+                    let _ = reply.send(ErrorCode::Unavailable.into());
+                }
+            }
+            UserEvent::ClearCookies => {
+                if let Some(servo) = &self.servo {
+                    servo.site_data_manager().clear_cookies();
+                }
+            }
+            UserEvent::ClearZoomOverrides => {
+                let mut zoom = self.zoom.borrow_mut();
+                zoom.clear_overrides();
+                if let Some(webview) = self.webview() {
+                    zoom.apply(webview);
+                }
+            }
+            UserEvent::NavigateWithCallback(url, navigation) => {
+                // Only one navigation can be in flight; the previous one loses.
+                self.finish_pending_navigation("superseded");
+                match self.webview() {
+                    Some(webview) => {
+                        *self.pending_navigation.borrow_mut() = Some(navigation);
+                        webview.load(url);
+                    }
+                    None => navigation.finish(false, "no webview"),
+                }
+            }
+            UserEvent::LoadUrl(url) => {
+                if let Some(webview) = self.webview() {
+                    self.finish_pending_navigation("superseded");
+                    webview.load(url);
+                }
+            }
+            UserEvent::Reload => {
+                if let Some(webview) = self.webview() {
+                    self.finish_pending_navigation("superseded");
+                    webview.reload();
+                }
+            }
+            UserEvent::GoBack(reply) => {
+                let moved = match self.webview() {
+                    Some(webview) if webview.can_go_back() => {
+                        self.finish_pending_navigation("superseded");
+                        webview.go_back(1);
+                        true
+                    }
+                    _ => false,
+                };
+                let _ = reply.send(moved);
+            }
+            UserEvent::GoForward(reply) => {
+                let moved = match self.webview() {
+                    Some(webview) if webview.can_go_forward() => {
+                        self.finish_pending_navigation("superseded");
+                        webview.go_forward(1);
+                        true
+                    }
+                    _ => false,
+                };
+                let _ = reply.send(moved);
+            }
+            UserEvent::HistoryState(reply) => {
+                let state = self.webview().map_or((false, false), |webview| {
+                    (webview.can_go_back(), webview.can_go_forward())
+                });
+                let _ = reply.send(state);
+            }
+            UserEvent::CreateWebView(url, reply) => {
+                let id = self.open_webview(url).map(|(id, _)| id);
+                // A window left empty by closing its last webview shows the
+                // next one right away.
+                if let (Some(id), None) = (id, self.active_webview.get()) {
+                    self.activate_webview(id);
+                }
+                let _ = reply.send(id);
+            }
+            UserEvent::ActivateWebView(id, reply) => {
+                let _ = reply.send(self.activate_webview(id));
+            }
+            UserEvent::CloseWebView(id, reply) => {
+                let _ = reply.send(self.close_webview(id));
+            }
+            UserEvent::AnswerAuthentication(id, credentials, reply) => {
+                let request = self.authentications.borrow_mut().take(id);
+                let found = request.is_some();
+                if let (Some(request), Some((username, password))) = (request, credentials) {
+                    request.authenticate(username, password);
+                }
+                let _ = reply.send(found);
+            }
+            UserEvent::AnswerCertError(id, allow, reply) => {
+                let pending = self.cert_errors.borrow_mut().take(id);
+                // The error page must still be showing: once the webview has
+                // navigated away there is nothing left to accept.
+                let webview = pending.as_ref().and_then(|(webview_id, url)| {
+                    self.webviews
+                        .iter()
+                        .find(|(id, _)| id == webview_id)
+                        .map(|(_, webview)| webview)
+                        .filter(|webview| {
+                            webview.url().is_some_and(|current| current.as_str() == url)
+                        })
+                });
+                match (allow, webview) {
+                    (true, Some(webview)) => {
+                        webview.evaluate_javascript(cert::ALLOW_JS, move |result| {
+                            let _ = reply.send(matches!(result, Ok(JSValue::Boolean(true))));
+                        })
+                    }
+                    (true, None) => {
+                        let _ = reply.send(false);
+                    }
+                    (false, _) => {
+                        let _ = reply.send(pending.is_some());
+                    }
+                }
+            }
+            UserEvent::RespondPermission(id, allow, reply) => {
+                let _ = reply.send(self.permissions.borrow_mut().respond(id, allow));
+            }
+            UserEvent::ApplyWindowChrome => {
+                if let Some(window) = &self.window {
+                    platform::apply_window_chrome(window);
+                }
+            }
+            UserEvent::UpdateThumbnail => self.update_thumbnail(),
+            UserEvent::SecondInstance => self.handle_second_instances(),
+            UserEvent::SetBadgeCount(count) => {
+                if let Some(window) = &self.window {
+                    platform::set_badge_count(window, count);
+                }
+            }
+            UserEvent::SetTaskbarProgress(state, fraction) => {
+                if let Some(window) = &self.window {
+                    platform::set_taskbar_progress(window, state, fraction);
+                }
+            }
+            UserEvent::StopLoading => {
+                let loading = self
+                    .active_webview
+                    .get()
+                    .is_some_and(|id| self.loading_webviews.borrow().contains(&id));
+                if let (true, Some(webview)) = (loading, self.webview()) {
+                    // Servo has no way to cancel a navigation, so this aborts
+                    // the current document's fetches. The load is reported as
+                    // stopped when it ends.
+                    webview.evaluate_javascript("window.stop()", |_result| {});
+                    self.stop_requested.set(true);
+                }
+            }
+        }
+    }
+}
+
+/// Scroll the root of the page in `webview` to the document offset `(x, y)`.
+fn scroll_to(webview: &WebView, x: f64, y: f64) {
+    webview.evaluate_javascript(format!("window.scrollTo({x}, {y})"), |_result| {});
+}
+
+/// How long a blocking query waits for the event loop to answer.
+const QUERY_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Ask the event loop for a value and block until it answers, or fail with the
+/// FFI return code. Times out with `-5` rather than deadlocking when called
+/// from the event loop thread itself (e.g. from inside an event callback).
+fn query<T>(make_event: impl FnOnce(mpsc::Sender<T>) -> UserEvent) -> Result<T, i32> {
+    let (sender, receiver) = mpsc::channel();
+    match send_user_event(make_event(sender)) {
+        0 => receiver.recv_timeout(QUERY_TIMEOUT).map_err(|_| {
+            errors::set_last_error_quietly(
+                "The event loop didn't answer in time; was this called from an event callback?",
+            );
+            ErrorCode::Timeout.into()
+        }),
+        code => Err(code),
+    }
+}
+
+/// Queue `event` for the event loop, mapping the result to the FFI return codes:
+/// `0` on success, `-2` if the loop has exited, `-3` if it was never started.
+fn send_user_event(event: UserEvent) -> i32 {
+    let Some(proxy) = PROXY.get() else {
+        errors::set_last_error_quietly("The app hasn't been started with pw_start_app");
+        return ErrorCode::NotStarted.into();
+    };
+    if proxy.send_event(event).is_ok() {
+        0
+    } else {
+        errors::set_last_error_quietly("The app's event loop has exited");
+        ErrorCode::LoopExited.into()
+    }
+}
+
+/// Wake the event loop from any thread so it runs its checks, if it is running.
+fn wake_event_loop() {
+    if let Some(proxy) = PROXY.get() {
+        let _ = proxy.send_event(UserEvent::Wake);
+    }
+}
+
+/// Report a panic on any thread as a `crash` event, from the event loop.
+fn report_panic(detail: String) {
+    log::error!("{}", detail);
+    if let Some(proxy) = PROXY.get() {
+        let _ = proxy.send_event(UserEvent::Crash(detail));
+    }
+}
+
+/// Re-run the page injections so a changed setting applies to the current
+/// document. Before `pw_start_app` the first page picks the setting up anyway,
+/// so that counts as success.
+fn reapply_injections() -> i32 {
+    match send_user_event(UserEvent::ReapplyInjections) {
+        code if code == i32::from(ErrorCode::NotStarted) => 0,
+        code => code,
+    }
+}
+
+/// Run `script` in the page shown. Calls made before the first page has
+/// loaded, even before `pw_start_app`, are queued and run in order once it has,
+/// unless `InitParams::disable_script_queue` is set.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn pw_execute_javascript(script: *const c_char) -> i32 {
+    let script = unsafe {
+        if script.is_null() {
+            return errors::null_argument("script");
+        }
+        CStr::from_ptr(script).to_string_lossy().into_owned()
+    };
+
+    execute_script(script)
+}
+
+/// Run `script` in the page shown, or queue it for the first page if it
+/// hasn't loaded yet.
+fn execute_script(script: String) -> i32 {
+    if before_start(|| EARLY_SCRIPTS.lock().unwrap().push(script.clone())) {
+        return 0;
+    }
+    send_user_event(UserEvent::ExecuteJs(script))
+}
+
+/// Send a message to the page shown: `data` is JSON, which the page receives
+/// as `{channel, data}` in a `pywire-message` event on `window`, and through
+/// `window.pywire.onMessage(channel, data)` if it set one. The counterpart of
+/// `pywire.send`. Queued like `pw_execute_javascript` until the first page
+/// has loaded. Returns `-4` for an empty channel or invalid JSON.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn pw_post_message(channel: *const c_char, json: *const c_char) -> i32 {
+    if channel.is_null() || json.is_null() {
+        return errors::null_argument("channel or json");
+    }
+    let channel = unsafe { CStr::from_ptr(channel) }.to_string_lossy();
+    if channel.is_empty() {
+        errors::set_last_error("Message channel must not be empty");
+        return ErrorCode::InvalidArgument.into();
+    }
+    let json = unsafe { CStr::from_ptr(json) }.to_string_lossy();
+    let data: serde_json::Value = match serde_json::from_str(&json) {
+        Ok(data) => data,
+        Err(error) => {
+            errors::set_last_error(format!("Invalid message JSON: {}", error));
+            return ErrorCode::InvalidArgument.into();
+        }
+    };
+    execute_script(events::post_message_script(&channel, &data))
+}
+
+/// Run `script` at document start on every page loaded from now on, before the
+/// page's own scripts, e.g. to set up globals or polyfills. Scripts run in the
+/// order they were added. The page already shown doesn't get it unless
+/// `run_on_current_page` is set, in which case it runs there right away, after
+/// the page's scripts. May be called before `pw_start_app`.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn pw_add_user_script(script: *const c_char, run_on_current_page: bool) -> i32 {
+    if script.is_null() {
+        return errors::null_argument("script");
+    }
+    let script = unsafe { CStr::from_ptr(script) }
+        .to_string_lossy()
+        .into_owned();
+    // Until the loop runs there is no page or webview; the first one created
+    // picks the script up from the list.
+    let mut pending = Some(script);
+    if before_start(|| injected::add_user_script(None, pending.take().unwrap())) {
+        return 0;
+    }
+    send_user_event(UserEvent::AddUserScript(
+        pending.unwrap(),
+        run_on_current_page,
+    ))
+}
+
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn pw_set_title(title: *const c_char) -> i32 {
+    let title = unsafe {
+        if title.is_null() {
+            return errors::null_argument("title");
+        }
+        CStr::from_ptr(title).to_string_lossy().into_owned()
+    };
+
+    send_user_event(UserEvent::SetTitle(title))
+}
+
+#[no_mangle]
+pub extern "C" fn pw_resize_window(width: u32, height: u32) -> i32 {
+    send_user_event(UserEvent::Resize(width, height))
+}
+
+/// Set the title bar and taskbar icon from `len` bytes of `width` x `height`
+/// RGBA pixels, row by row from the top. Returns `-4` if `len` doesn't match
+/// the dimensions.
+///
+/// macOS windows have no icon of their own (use `pw_set_thumbnail` to change
+/// the Dock icon), so the call is ignored there and returns `2`.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn pw_set_window_icon(rgba: *const u8, len: usize, width: u32, height: u32) -> i32 {
+    if rgba.is_null() {
+        return errors::null_argument("rgba");
+    }
+    if (width as usize)
+        .checked_mul(height as usize)
+        .and_then(|pixels| pixels.checked_mul(4))
+        != Some(len)
+    {
+        errors::set_last_error(format!(
+            "Icon buffer is {} bytes, expected {}x{}x4",
+            len, width, height
+        ));
+        return ErrorCode::InvalidArgument.into();
+    }
+    if cfg!(target_os = "macos") {
+        return 2;
+    }
+    let rgba = unsafe { std::slice::from_raw_parts(rgba, len) }.to_vec();
+    match Icon::from_rgba(rgba, width, height) {
+        Ok(icon) => send_user_event(UserEvent::SetWindowIcon(icon)),
+        Err(err) => {
+            errors::set_last_error(format!("Invalid icon: {}", err));
+            ErrorCode::InvalidArgument.into()
+        }
+    }
+}
+
+/// Put `text` on the system clipboard, as if the user had copied it.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn pw_set_clipboard_text(text: *const c_char) -> i32 {
+    if text.is_null() {
+        return errors::null_argument("text");
+    }
+    let text = unsafe { CStr::from_ptr(text) }
+        .to_string_lossy()
+        .into_owned();
+    send_user_event(UserEvent::SetClipboardText(text))
+}
+
+/// Read the system clipboard's text. Returns null if the clipboard is empty,
+/// holds something other than text, or can't be read (see `pw_last_error`).
+/// The string is owned by the caller and must be released with
+/// [`pw_free_string`].
+#[no_mangle]
+pub extern "C" fn pw_get_clipboard_text() -> *mut c_char {
+    match query(UserEvent::GetClipboardText) {
+        Ok(Some(text)) => into_c_string(text),
+        _ => std::ptr::null_mut(),
+    }
+}
+
+/// Store a size limit and apply it to the window. Before `pw_start_app` the
+/// window is created with it, so that counts as success.
+fn set_size_limit(limit: Option<(u32, u32)>, max: bool) -> i32 {
+    {
+        let mut limits = SIZE_LIMITS.lock().unwrap();
+        if max {
+            limits.max = limit;
+        } else {
+            limits.min = limit;
+        }
+    }
+    match send_user_event(UserEvent::ApplySizeLimits) {
+        code if code == i32::from(ErrorCode::NotStarted) => 0,
+        code => code,
+    }
+}
+
+/// `0 x 0` clears a size limit.
+fn size_limit(width: u32, height: u32) -> Option<(u32, u32)> {
+    if width == 0 && height == 0 {
+        None
+    } else {
+        Some((width, height))
+    }
+}
+
+/// Stands in for a maximum width or height of `0`, which winit can't take on
+/// its own: larger than any display, yet small enough for the platforms' size
+/// hints once scaled to physical pixels.
+const UNBOUNDED_SIZE: u32 = u16::MAX as u32;
+
+/// Like [`size_limit`], but a `0` width or height leaves that side unbounded
+/// rather than limiting it to nothing.
+fn max_size_limit(width: u32, height: u32) -> Option<(u32, u32)> {
+    let unbounded = |side: u32| if side == 0 { UNBOUNDED_SIZE } else { side };
+    size_limit(width, height).map(|(width, height)| (unbounded(width), unbounded(height)))
+}
+
+/// Keep the window's inner size at least `width` x `height` logical pixels,
+/// whether the user or [`pw_resize_window`] resizes it. Pass `0, 0` to
+/// remove the limit. May be called before `pw_start_app`.
+#[no_mangle]
+pub extern "C" fn pw_set_min_window_size(width: u32, height: u32) -> i32 {
+    set_size_limit(size_limit(width, height), false)
+}
+
+/// Keep the window's inner size at most `width` x `height` logical pixels.
+/// A `0` width or height leaves that side unbounded, so `0, 0` removes the
+/// limit. May be called before `pw_start_app`.
+#[no_mangle]
+pub extern "C" fn pw_set_max_window_size(width: u32, height: u32) -> i32 {
+    set_size_limit(max_size_limit(width, height), true)
+}
+
+/// Move the window so its outer top-left corner (including decorations) is at
+/// `(x, y)` in physical pixels on the virtual desktop. Before `pw_start_app`
+/// this sets where the window is created. Ignored on Wayland, where clients
+/// can't position their windows.
+#[no_mangle]
+pub extern "C" fn pw_set_window_position(x: i32, y: i32) -> i32 {
+    if before_start(|| *INITIAL_POSITION.lock().unwrap() = Some((x, y))) {
+        return 0;
+    }
+    send_user_event(UserEvent::SetWindowPosition(x, y))
+}
+
+/// Read the window's outer top-left corner into `x` and `y`, in the same
+/// physical-pixel desktop coordinates as [`pw_set_window_position`]. Returns
+/// `-6` if the platform doesn't report it (Wayland).
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn pw_get_window_position(x: *mut i32, y: *mut i32) -> i32 {
+    if x.is_null() || y.is_null() {
+        return errors::null_argument("x or y");
+    }
+    match query(UserEvent::GetWindowPosition) {
+        Ok(Some((window_x, window_y))) => {
+            unsafe {
+                *x = window_x;
+                *y = window_y;
+            }
+            0
+        }
+        Ok(None) => {
+            errors::set_last_error_quietly("A headless app has no window");
+            ErrorCode::Unavailable.into()
+        }
+        Err(code) => code,
+    }
+}
+
+/// The window's scale factor: physical pixels per logical pixel, the page's
+/// `devicePixelRatio` at 100% zoom. A `scale_factor` event reports changes,
+/// e.g. when the window moves to another monitor. Doesn't wait for the event
+/// loop, so it may be called from event listeners. Returns `1.0` when
+/// headless and `0.0` before the window exists.
+#[no_mangle]
+pub extern "C" fn pw_get_scale_factor() -> f64 {
+    f64::from_bits(SCALE_FACTOR.load(Ordering::Relaxed))
+}
+
+/// Abort navigations that don't reach `LoadStatus::Complete` within `ms`
+/// milliseconds, showing an error page and emitting a `load_timeout` event.
+/// The timer restarts when the page makes progress (its head is parsed).
+/// Pass `0` to disable. May be called before `pw_start_app`.
+#[no_mangle]
+pub extern "C" fn pw_set_load_timeout(ms: u64) -> i32 {
+    LOAD_TIMEOUT_MS.store(ms, Ordering::Relaxed);
+    0
+}
+
+/// Close the webview, shut Servo down and leave the event loop, so
+/// `pw_start_app` returns. Returns `0` once the shutdown has been queued.
+#[no_mangle]
+pub extern "C" fn pw_shutdown() -> i32 {
+    send_user_event(UserEvent::Shutdown)
+}
+
+/// Answer a `close_requested` event (see `InitParams::intercept_close`) by
+/// closing the window, like [`pw_shutdown`].
+#[no_mangle]
+pub extern "C" fn pw_confirm_close() -> i32 {
+    send_user_event(UserEvent::Shutdown)
+}
+
+/// Answer a `close_requested` event by keeping the window open. The next
+/// close request asks Python again instead of forcing the close.
+#[no_mangle]
+pub extern "C" fn pw_cancel_close() -> i32 {
+    send_user_event(UserEvent::CancelClose)
+}
+
+/// Show the window, e.g. one created with `InitParams::start_hidden`.
+#[no_mangle]
+pub extern "C" fn pw_show_window() -> i32 {
+    send_user_event(UserEvent::SetVisible(true))
+}
+
+/// Hide the window without closing it. The page keeps running.
+#[no_mangle]
+pub extern "C" fn pw_hide_window() -> i32 {
+    send_user_event(UserEvent::SetVisible(false))
+}
+
+/// Enter or leave borderless fullscreen. A `fullscreen` event reports the
+/// change, as it does when the page or the OS changes the state.
+#[no_mangle]
+pub extern "C" fn pw_set_fullscreen(fullscreen: bool) -> i32 {
+    send_user_event(UserEvent::SetFullscreen(fullscreen))
+}
+
+/// Maximize the window, e.g. for a custom title bar's maximize button. A
+/// minimized window is restored first. With a maximum size set
+/// (`pw_set_max_window_size`) the window grows only up to it. Returns `-6`
+/// when headless.
+#[no_mangle]
+pub extern "C" fn pw_maximize_window() -> i32 {
+    set_window_state(WindowState::Maximized)
+}
+
+/// Minimize the window to the taskbar or Dock. The page keeps running, but
+/// painting pauses until the window is restored. Returns `-6` when headless.
+#[no_mangle]
+pub extern "C" fn pw_minimize_window() -> i32 {
+    set_window_state(WindowState::Minimized)
+}
+
+/// Bring the window back from being maximized or minimized to its previous
+/// size, within the size limits. Not for fullscreen; see
+/// [`pw_set_fullscreen`]. Returns `-6` when headless.
+#[no_mangle]
+pub extern "C" fn pw_restore_window() -> i32 {
+    set_window_state(WindowState::Restored)
+}
+
+/// Apply `state` to the window. `-6` when headless, like the queries.
+fn set_window_state(state: WindowState) -> i32 {
+    match query(|reply| UserEvent::SetWindowState(state, reply)) {
+        Ok(true) => 0,
+        Ok(false) => {
+            errors::set_last_error_quietly("A headless app has no window");
+            ErrorCode::Unavailable.into()
+        }
+        Err(code) => code,
+    }
+}
+
+/// Whether the window is maximized: `1` if it is, `0` if not, `-6` when
+/// headless.
+#[no_mangle]
+pub extern "C" fn pw_is_maximized() -> i32 {
+    match query(UserEvent::GetWindowState) {
+        Ok(Some((maximized, _))) => maximized.into(),
+        Ok(None) => {
+            errors::set_last_error_quietly("A headless app has no window");
+            ErrorCode::Unavailable.into()
+        }
+        Err(code) => code,
+    }
+}
+
+/// Whether the window is minimized: `1` if it is, `0` if not, `-6` when
+/// headless.
+#[no_mangle]
+pub extern "C" fn pw_is_minimized() -> i32 {
+    match query(UserEvent::GetWindowState) {
+        Ok(Some((_, minimized))) => minimized.into(),
+        Ok(None) => {
+            errors::set_last_error_quietly("A headless app has no window");
+            ErrorCode::Unavailable.into()
+        }
+        Err(code) => code,
+    }
+}
+
+/// Keep the window above all others (`"always-on-top"`), e.g. for overlays and
+/// HUDs, below all others (`"always-on-bottom"`), e.g. for desktop widgets, or
+/// stacked as usual (`"normal"`). Before `pw_start_app` this sets the level the
+/// window is created with, and returns `0` whether or not it will be honoured.
+/// Returns `-4` for an unknown level and, once started, `-6` if the level was
+/// ignored: Wayland has no way for a window to set its own level, and a
+/// headless app has no window.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn pw_set_window_level(level: *const c_char) -> i32 {
+    if level.is_null() {
+        return errors::null_argument("level");
+    }
+    let name = unsafe { CStr::from_ptr(level) }.to_string_lossy();
+    let level = match name.as_ref() {
+        "normal" => WindowLevel::Normal,
+        "always-on-top" => WindowLevel::AlwaysOnTop,
+        "always-on-bottom" => WindowLevel::AlwaysOnBottom,
+        _ => {
+            errors::set_last_error(format!("Unknown window level: {:?}", name));
+            return ErrorCode::InvalidArgument.into();
+        }
+    };
+    set_window_level(level)
+}
+
+/// Keep the window above all others, or stop doing so. Shorthand for
+/// [`pw_set_window_level`] with `"always-on-top"` or `"normal"`, with the same
+/// return codes.
+#[no_mangle]
+pub extern "C" fn pw_set_always_on_top(enabled: bool) -> i32 {
+    set_window_level(if enabled {
+        WindowLevel::AlwaysOnTop
+    } else {
+        WindowLevel::Normal
+    })
+}
+
+fn set_window_level(level: WindowLevel) -> i32 {
+    if before_start(|| *INITIAL_WINDOW_LEVEL.lock().unwrap() = level) {
+        return 0;
+    }
+    match query(|reply| UserEvent::SetWindowLevel(level, reply)) {
+        Ok(true) => 0,
+        Ok(false) => {
+            errors::set_last_error_quietly("Window levels aren't supported here");
+            ErrorCode::Unavailable.into()
+        }
+        Err(code) => code,
+    }
+}
+
+/// Stop the active page's load, like a browser's stop button. Emits a
+/// `load_stopped` event once the load has ended, which also ends a
+/// `pw_navigate_with_callback` navigation as `"stopped"`. Servo can't cancel a
+/// navigation, so this aborts the current document's fetches; a navigation that
+/// hasn't committed yet may still load. Returns `1` if nothing was loading.
+#[no_mangle]
+pub extern "C" fn pw_stop_loading() -> i32 {
+    if PROXY.get().is_some() && !LOADING.load(Ordering::Relaxed) {
+        return 1;
+    }
+    send_user_event(UserEvent::StopLoading)
+}
+
+/// Navigate to `url`. Like the initial URL in `InitParams`, a URL that fails to
+/// parse loads `about:blank` instead.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn pw_load_url(url: *const c_char) -> i32 {
+    let url = unsafe {
+        if url.is_null() {
+            return errors::null_argument("url");
+        }
+        CStr::from_ptr(url).to_string_lossy().into_owned()
+    };
+    let url = Url::parse(&url).unwrap_or_else(|_| Url::parse("about:blank").unwrap());
+    send_user_event(UserEvent::LoadUrl(url))
+}
+
+/// Reload the current page.
+#[no_mangle]
+pub extern "C" fn pw_reload() -> i32 {
+    send_user_event(UserEvent::Reload)
+}
+
+/// Go back one entry in the session history. Returns `1`, without navigating,
+/// if there is no earlier entry, so a back button can be disabled.
+#[no_mangle]
+pub extern "C" fn pw_go_back() -> i32 {
+    match query(UserEvent::GoBack) {
+        Ok(true) => 0,
+        Ok(false) => 1,
+        Err(code) => code,
+    }
+}
+
+/// Go forward one entry in the session history. Returns `1`, without
+/// navigating, if there is no later entry.
+#[no_mangle]
+pub extern "C" fn pw_go_forward() -> i32 {
+    match query(UserEvent::GoForward) {
+        Ok(true) => 0,
+        Ok(false) => 1,
+        Err(code) => code,
+    }
+}
+
+/// Whether the active webview has an earlier session history entry to go
+/// back to: `1` if it has, `0` if not (or no webview is open). The
+/// `load_status` and `history_changed` events carry the same state as
+/// `can_go_back`, so a toolbar can follow it without asking.
+#[no_mangle]
+pub extern "C" fn pw_can_go_back() -> i32 {
+    match query(UserEvent::HistoryState) {
+        Ok((can_go_back, _)) => can_go_back.into(),
+        Err(code) => code,
+    }
+}
+
+/// Whether the active webview has a later session history entry to go
+/// forward to: `1` if it has, `0` if not. See [`pw_can_go_back`].
+#[no_mangle]
+pub extern "C" fn pw_can_go_forward() -> i32 {
+    match query(UserEvent::HistoryState) {
+        Ok((_, can_go_forward)) => can_go_forward.into(),
+        Err(code) => code,
+    }
+}
+
+/// Answer the `auth_required` challenge `request_id` with a user name and
+/// password. The server decides whether they are right: wrong ones usually
+/// bring another challenge. Challenges left unanswered for the
+/// authentication timeout are cancelled and reported as `auth_timeout`.
+/// Returns `-4` for an unknown, already answered or timed out challenge.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn pw_provide_credentials(
+    request_id: u32,
+    username: *const c_char,
+    password: *const c_char,
+) -> i32 {
+    if username.is_null() || password.is_null() {
+        return errors::null_argument("username or password");
+    }
+    let (username, password) = unsafe {
+        (
+            CStr::from_ptr(username).to_string_lossy().into_owned(),
+            CStr::from_ptr(password).to_string_lossy().into_owned(),
+        )
+    };
+    answer_authentication(request_id, Some((username, password)))
+}
+
+/// Decline the `auth_required` challenge `request_id`. The page then shows
+/// the server's `401` response, as a browser does when its login dialog is
+/// cancelled. Returns `-4` for an unknown or already answered challenge.
+#[no_mangle]
+pub extern "C" fn pw_cancel_authentication(request_id: u32) -> i32 {
+    answer_authentication(request_id, None)
+}
+
+fn answer_authentication(request_id: u32, credentials: Option<(String, String)>) -> i32 {
+    match query(|reply| UserEvent::AnswerAuthentication(request_id, credentials, reply)) {
+        Ok(true) => 0,
+        Ok(false) => {
+            errors::set_last_error(format!(
+                "Unknown or already answered authentication request: {}",
+                request_id
+            ));
+            ErrorCode::InvalidArgument.into()
+        }
+        Err(code) => code,
+    }
+}
+
+/// Trust the certificate behind the `cert_error` `request_id` until the app
+/// exits, and load the page it was shown for. Only for hosts the app knows,
+/// such as internal servers with self-signed certificates. Returns `-4` for an
+/// unknown or already answered error, one that isn't `allowable`, or one whose
+/// page the webview has since navigated away from.
+#[no_mangle]
+pub extern "C" fn pw_allow_cert(request_id: u32) -> i32 {
+    answer_cert_error(request_id, true)
+}
+
+/// Keep showing the certificate error page for the `cert_error`
+/// `request_id`, which is also what happens to errors nobody answers.
+/// Returns `-4` for an unknown or already answered error.
+#[no_mangle]
+pub extern "C" fn pw_reject_cert(request_id: u32) -> i32 {
+    answer_cert_error(request_id, false)
+}
+
+/// Show `html` instead of the shell's error page when a load fails with
+/// `kind`: `"network"` (any network failure; also used for DNS failures
+/// without a page of their own), `"dns"` (the host name didn't resolve) or
+/// `"tls"` (the certificate failed verification). `{{url}}` and `{{error}}` in
+/// `html` are replaced with the failed URL and the reason, HTML-escaped. The
+/// page is shown at the failed URL, so reloading retries it. Null or `""`
+/// restores the shell's page. Returns `-4` for an unknown kind. May be called
+/// before `pw_start_app`.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn pw_set_error_page(kind: *const c_char, html: *const c_char) -> i32 {
+    if kind.is_null() {
+        return errors::null_argument("kind");
+    }
+    let name = unsafe { CStr::from_ptr(kind) }.to_string_lossy();
+    let Some(kind) = net_error::ErrorKind::from_name(&name) else {
+        errors::set_last_error(format!("Unknown error page kind: {:?}", name));
+        return ErrorCode::InvalidArgument.into();
+    };
+    let html = (!html.is_null())
+        .then(|| {
+            unsafe { CStr::from_ptr(html) }
+                .to_string_lossy()
+                .into_owned()
+        })
+        .filter(|html| !html.is_empty());
+    net_error::set_error_page(kind, html);
+    0
+}
+
+fn answer_cert_error(request_id: u32, allow: bool) -> i32 {
+    match query(|reply| UserEvent::AnswerCertError(request_id, allow, reply)) {
+        Ok(true) => 0,
+        Ok(false) => {
+            errors::set_last_error(format!(
+                "Unknown or already answered certificate error: {}",
+                request_id
+            ));
+            ErrorCode::InvalidArgument.into()
+        }
+        Err(code) => code,
+    }
+}
+
+/// Answer the `permission` request `request_id`. Requests left unanswered
+/// for the permission timeout are denied and reported as
+/// `permission_timeout`. Returns `-4` for an unknown, already answered or
+/// timed out request.
+#[no_mangle]
+pub extern "C" fn pw_respond_permission(request_id: u32, allow: bool) -> i32 {
+    match query(|reply| UserEvent::RespondPermission(request_id, allow, reply)) {
+        Ok(true) => 0,
+        Ok(false) => {
+            errors::set_last_error(format!(
+                "Unknown, answered or timed out permission request: {}",
+                request_id
+            ));
+            ErrorCode::InvalidArgument.into()
+        }
+        Err(code) => code,
+    }
+}
+
+/// Deny `permission` requests that go unanswered for `ms` milliseconds
+/// (30 seconds by default). Applies to requests made after the call. May be
+/// called before `pw_start_app`.
+#[no_mangle]
+pub extern "C" fn pw_set_permission_timeout(ms: u64) -> i32 {
+    PERMISSION_TIMEOUT_MS.store(ms, Ordering::Relaxed);
+    0
+}
+
+/// Cancel `auth_required` challenges that go unanswered for `ms`
+/// milliseconds (60 seconds by default). Applies to challenges made after the
+/// call. May be called before `pw_start_app`.
+#[no_mangle]
+pub extern "C" fn pw_set_auth_timeout(ms: u64) -> i32 {
+    AUTH_TIMEOUT_MS.store(ms, Ordering::Relaxed);
+    0
+}
+
+/// Open another webview in the window, like a browser tab, loading `url` in
+/// the background. Returns its id, for `pw_activate_webview` and
+/// `pw_close_webview`; the initial webview has id `1`. Events from a webview
+/// carry its id as `webview_id`. Only the active webview is painted and gets
+/// input, and the other `pw_*` functions act on it. Returns `-6` if Servo
+/// isn't running.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn pw_create_webview(url: *const c_char) -> i32 {
+    let url = unsafe {
+        if url.is_null() {
+            return errors::null_argument("url");
+        }
+        CStr::from_ptr(url).to_string_lossy().into_owned()
+    };
+    let url = Url::parse(&url).unwrap_or_else(|_| Url::parse("about:blank").unwrap());
+    match query(|reply| UserEvent::CreateWebView(url, reply)) {
+        Ok(Some(id)) => id as i32,
+        Ok(None) => {
+            errors::set_last_error("Servo isn't running");
+            ErrorCode::Unavailable.into()
+        }
+        Err(code) => code,
+    }
+}
+
+/// Show the webview `id` instead of the active one, and emit
+/// `webview_activated`. A navigation started with `pw_navigate_with_callback`
+/// in the webview left behind is reported as `superseded`. Returns `-4` for an
+/// unknown id.
+#[no_mangle]
+pub extern "C" fn pw_activate_webview(id: u32) -> i32 {
+    match query(|reply| UserEvent::ActivateWebView(id, reply)) {
+        Ok(true) => 0,
+        Ok(false) => {
+            errors::set_last_error(format!("Unknown webview: {}", id));
+            ErrorCode::InvalidArgument.into()
+        }
+        Err(code) => code,
+    }
+}
+
+/// Close the webview `id` and emit `webview_closed`. Closing the active
+/// webview activates the next one, or the previous one if it was last. Once
+/// none is left the window stays blank until `pw_create_webview`. Returns `-4`
+/// for an unknown id.
+#[no_mangle]
+pub extern "C" fn pw_close_webview(id: u32) -> i32 {
+    match query(|reply| UserEvent::CloseWebView(id, reply)) {
+        Ok(true) => 0,
+        Ok(false) => {
+            errors::set_last_error(format!("Unknown webview: {}", id));
+            ErrorCode::InvalidArgument.into()
+        }
+        Err(code) => code,
+    }
+}
+
+/// Set how `http:` subresources on `https:` pages are handled: `"block"`
+/// (the default, matching browsers), `"allow"`, or `"upgrade"` to retry them
+/// over `https:`. Blocked requests emit a `mixed_content_blocked` event.
+///
+/// Loosening this to `"allow"` lets a network attacker tamper with scripts and
+/// styles on otherwise secure pages; only use it for trusted intranet hosts.
+/// Requests the engine blocks itself (active content such as scripts) are not
+/// affected by `"allow"`. Returns `-4` for an unknown policy name.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn pw_set_mixed_content_policy(policy: *const c_char) -> i32 {
+    let policy = unsafe {
+        if policy.is_null() {
+            return errors::null_argument("policy");
+        }
+        CStr::from_ptr(policy).to_string_lossy().into_owned()
+    };
+
+    match MixedContentPolicy::from_name(&policy) {
+        Some(policy) => {
+            NETWORK_POLICY.lock().unwrap().mixed_content = policy;
+            0
+        }
+        None => {
+            errors::set_last_error(format!("Unknown mixed content policy: {:?}", policy));
+            ErrorCode::InvalidArgument.into()
+        }
+    }
+}
+
+/// Set what happens when a page opens a new window with `window.open` or a
+/// `target="_blank"` link: `"tab"` (the default) opens it in a new webview and
+/// activates it, as `pw_create_webview` and `pw_activate_webview` would;
+/// `"event"` opens nothing, so the app can e.g. hand the URL to the system
+/// browser. Either way a `new_window` event reports the `url`, the
+/// `opener_webview_id` and, as `webview_id`, the new webview's id, with
+/// `opened` saying whether it was kept. Servo doesn't pass on the `features`
+/// argument of `window.open`. Returns `-4` for an unknown policy name.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn pw_set_new_window_policy(policy: *const c_char) -> i32 {
+    let policy = unsafe {
+        if policy.is_null() {
+            return errors::null_argument("policy");
+        }
+        CStr::from_ptr(policy).to_string_lossy().into_owned()
+    };
+
+    match NewWindowPolicy::from_name(&policy) {
+        Some(policy) => {
+            *NEW_WINDOW_POLICY.lock().unwrap() = policy;
+            0
+        }
+        None => {
+            errors::set_last_error(format!("Unknown new window policy: {:?}", policy));
+            ErrorCode::InvalidArgument.into()
+        }
+    }
+}
+
+/// Choose whether right-clicks on macOS show the built-in native menu (Back,
+/// Forward, Reload, Copy, Paste), the default. Otherwise, and always on other
+/// platforms, a right-click emits a `context_menu` event with the position in
+/// device pixels (`x`, `y`), the held `modifiers`, and the `link_url`,
+/// `image_url`, `selection` and `editable` state of what is under the
+/// pointer, so Python can show its own menu.
+#[no_mangle]
+pub extern "C" fn pw_set_native_context_menu(native: bool) -> i32 {
+    NATIVE_CONTEXT_MENU.store(native, Ordering::Relaxed);
+    0
+}
+
+/// Set the caching strategy for navigations: `"default"`, or `"no-store"` to
+/// clear the HTTP cache around the active webview's navigations. See
+/// [`CacheMode`] for how each mode interacts with the page's own cache headers
+/// and what `"no-store"` can't prevent. Returns `-4` for any other
+/// name, including `"reload"` and `"force-cache"`, which Servo gives the
+/// embedder no way to apply to a single request.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn pw_set_cache_mode(mode: *const c_char) -> i32 {
+    let mode = unsafe {
+        if mode.is_null() {
+            return errors::null_argument("mode");
+        }
+        CStr::from_ptr(mode).to_string_lossy().into_owned()
+    };
+
+    match CacheMode::from_name(&mode) {
+        Some(mode) => {
+            NETWORK_POLICY.lock().unwrap().cache_mode = mode;
+            0
+        }
+        None => {
+            errors::set_last_error(format!("Unsupported cache mode: {:?}", mode));
+            ErrorCode::InvalidArgument.into()
+        }
+    }
+}
+
+/// Set the color scheme pages see through `prefers-color-scheme`: `"light"`,
+/// `"dark"`, or `"auto"` to follow the OS theme (the default), which is light
+/// where the platform doesn't report a theme. Returns `-4` for anything else.
+/// May be called before `pw_start_app`.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn pw_set_color_scheme(scheme: *const c_char) -> i32 {
+    if scheme.is_null() {
+        return errors::null_argument("scheme");
+    }
+    let scheme = unsafe { CStr::from_ptr(scheme) }.to_string_lossy();
+    let Some(scheme) = color_scheme::ColorScheme::parse(&scheme) else {
+        errors::set_last_error(format!("Unknown color scheme: {:?}", scheme));
+        return ErrorCode::InvalidArgument.into();
+    };
+    *color_scheme::COLOR_SCHEME.lock().unwrap() = scheme;
+    // Webviews created later pick the scheme up themselves.
+    match send_user_event(UserEvent::ApplyColorScheme) {
+        code if code == i32::from(ErrorCode::NotStarted) => 0,
+        code => code,
+    }
+}
+
+/// Enable arrow-key spatial navigation for remote-control UIs: arrow keys move
+/// focus to the nearest focusable element in that direction (wrapping around at
+/// the edges) instead of scrolling, and Enter activates it. Pair with a focus
+/// ring so the focused element is visible. May be called before `pw_start_app`.
+#[no_mangle]
+pub extern "C" fn pw_set_spatial_navigation(enabled: bool) -> i32 {
+    injected::SPATIAL_NAVIGATION.store(enabled, Ordering::Relaxed);
+    reapply_injections()
+}
+
+/// Handle the key combo `combo` (such as `"CmdOrCtrl+R"` or `"F11"`) in the
+/// app: pressing it emits an `accelerator` event with `action_id`, before the
+/// page sees the key. Unless `deliver_to_page` is set, the page never gets
+/// the key at all. Registering an `action_id` again replaces its combo.
+/// Combos are modifiers (`Ctrl`, `Shift`, `Alt`, `Cmd`, `CmdOrCtrl`) and one
+/// key joined by `+`: a character or a key name like `Enter`, `ArrowUp` or
+/// `F5`. Returns `-4` for a combo that can't be parsed. May be called before
+/// `pw_start_app`.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn pw_register_accelerator(
+    combo: *const c_char,
+    action_id: u32,
+    deliver_to_page: bool,
+) -> i32 {
+    if combo.is_null() {
+        return errors::null_argument("combo");
+    }
+    let combo = unsafe { CStr::from_ptr(combo) }.to_string_lossy();
+    match accelerators::Accelerator::parse(&combo, action_id, deliver_to_page) {
+        Some(accelerator) => {
+            accelerators::register(accelerator);
+            0
+        }
+        None => {
+            errors::set_last_error(format!("Invalid accelerator: {:?}", combo));
+            ErrorCode::InvalidArgument.into()
+        }
+    }
+}
+
+/// Stop handling the accelerator registered for `action_id`; its combo
+/// reaches the page again. Returns `-4` if none is registered.
+#[no_mangle]
+pub extern "C" fn pw_unregister_accelerator(action_id: u32) -> i32 {
+    if accelerators::unregister(action_id) {
+        0
+    } else {
+        errors::set_last_error(format!(
+            "No accelerator is registered for action {}",
+            action_id
+        ));
+        ErrorCode::InvalidArgument.into()
+    }
+}
+
+/// Scroll `line_height` CSS pixels per line for each mouse wheel notch, times
+/// the lines per notch the OS is set to (three where it has no setting).
+/// Pass `0` for the default of 20 pixels. Touchpad scrolling is unaffected.
+/// Returns `-4` for a negative or non-finite height. May be called before
+/// `pw_start_app`.
+#[no_mangle]
+pub extern "C" fn pw_set_scroll_speed(line_height: f32) -> i32 {
+    if !line_height.is_finite() || line_height < 0.0 {
+        errors::set_last_error(format!("Invalid scroll line height: {}", line_height));
+        return ErrorCode::InvalidArgument.into();
+    }
+    SCROLL_SETTINGS.lock().unwrap().line_height = if line_height == 0.0 {
+        scroll::DEFAULT_LINE_HEIGHT
+    } else {
+        line_height
+    };
+    0
+}
+
+/// Invert the scroll direction of wheels and touchpads, so content follows
+/// the fingers. This comes on top of the OS: macOS already applies its own
+/// natural scrolling setting to the deltas it reports. May be called before
+/// `pw_start_app`.
+#[no_mangle]
+pub extern "C" fn pw_set_natural_scrolling(enabled: bool) -> i32 {
+    SCROLL_SETTINGS.lock().unwrap().natural = enabled;
+    0
+}
+
+/// Force a visible focus indicator regardless of the page's `:focus` styling.
+/// `style` is `"on"` for a built-in high-contrast ring, CSS declarations such
+/// as `"outline: 2px solid red !important"` for a custom one (use
+/// `!important` to win over page styles), or null/`""`/`"off"` to
+/// restore the page's own styling (the default). Re-applied on every
+/// navigation. May be called before `pw_start_app`.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn pw_set_focus_ring_style(style: *const c_char) -> i32 {
+    let style = unsafe {
+        if style.is_null() {
+            String::new()
+        } else {
+            CStr::from_ptr(style).to_string_lossy().into_owned()
+        }
+    };
+
+    let css = match style.trim() {
+        "" | "off" => None,
+        "on" => Some(injected::DEFAULT_FOCUS_RING_CSS.to_string()),
+        css => Some(css.to_string()),
+    };
+    *injected::FOCUS_RING_CSS.lock().unwrap() = css;
+    reapply_injections()
+}
+
+/// Read the page's root scroll offset (`window.scrollX`/`scrollY`, in CSS
+/// pixels) into `x` and `y`. Blocks until the event loop answers. Returns `-6`
+/// if the page can't report a position (e.g. no document yet).
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn pw_get_scroll_position(x: *mut f64, y: *mut f64) -> i32 {
+    if x.is_null() || y.is_null() {
+        return errors::null_argument("x or y");
+    }
+    match query(UserEvent::GetScrollPosition) {
+        Ok(Some((scroll_x, scroll_y))) => {
+            unsafe {
+                *x = scroll_x;
+                *y = scroll_y;
+            }
+            0
+        }
+        Ok(None) => {
+            errors::set_last_error_quietly("The page can't report its scroll position");
+            ErrorCode::Unavailable.into()
+        }
+        Err(code) => code,
+    }
+}
+
+/// Scroll the page's root to `(x, y)` in CSS pixels. While a load is in
+/// flight the offset is held back and applied once it completes, since the
+/// layout may not have reached its final height yet; a restore issued right
+/// after navigating therefore lands where expected.
+#[no_mangle]
+pub extern "C" fn pw_set_scroll_position(x: f64, y: f64) -> i32 {
+    send_user_event(UserEvent::SetScrollPosition(x, y))
+}
+
+/// Click `button` (`"left"`, `"middle"`, `"right"`, `"back"` or `"forward"`)
+/// at `(x, y)` in CSS pixels from the page's top left, the coordinates of
+/// `getBoundingClientRect()`, e.g. to drive the page in tests. The page gets
+/// the same mouse events as for a real click, and the mouse stays there.
+/// Returns `-4` for an unknown button.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn pw_send_mouse_click(x: f64, y: f64, button: *const c_char) -> i32 {
+    if button.is_null() {
+        return errors::null_argument("button");
+    }
+    let name = unsafe { CStr::from_ptr(button) }.to_string_lossy();
+    let Some(button) = input::mouse_button_from_name(&name) else {
+        errors::set_last_error(format!("Unknown mouse button: {:?}", name));
+        return ErrorCode::InvalidArgument.into();
+    };
+    send_user_event(UserEvent::SendMouseClick(x, y, button))
+}
+
+/// Press and release `combo`, written like an accelerator (`"Enter"`,
+/// `"Shift+A"`, `"CmdOrCtrl+V"`), in the page. The key goes through the same
+/// handling as a typed one, so accelerators and the clipboard shortcuts apply.
+/// Returns `-4` for a malformed combo.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn pw_send_key(combo: *const c_char) -> i32 {
+    if combo.is_null() {
+        return errors::null_argument("combo");
+    }
+    let combo = unsafe { CStr::from_ptr(combo) }.to_string_lossy();
+    let Some((key, code, modifiers)) = accelerators::key_press(&combo) else {
+        errors::set_last_error(format!("Invalid key combo: {:?}", combo));
+        return ErrorCode::InvalidArgument.into();
+    };
+    send_user_event(UserEvent::SendKey(key, code, modifiers))
+}
+
+/// Scroll the page by `(dx, dy)` CSS pixels with a mouse wheel event at the
+/// mouse position; positive values scroll down and right, as with
+/// `window.scrollBy`. Smooth and natural scrolling don't apply.
+#[no_mangle]
+pub extern "C" fn pw_send_scroll(dx: f64, dy: f64) -> i32 {
+    send_user_event(UserEvent::SendScroll(dx, dy))
+}
+
+/// Capture a JSON snapshot of the DOM for layout assertions: every element's
+/// tag, attributes, own text, bounding box and a set of computed styles.
+/// Elements deeper than `max_depth` are omitted (`0` means no limit), and the
+/// snapshot never exceeds 10,000 elements; `"truncated"` reports whether
+/// anything was cut. Blocks until the page answers.
+///
+/// Returns null on failure. The string is owned by the caller and must be
+/// released with [`pw_free_string`].
+#[no_mangle]
+pub extern "C" fn pw_capture_dom_snapshot(max_depth: u32) -> *mut c_char {
+    match query(|reply| UserEvent::CaptureDomSnapshot(max_depth, reply)) {
+        Ok(Some(json)) => into_c_string(json),
+        _ => std::ptr::null_mut(),
+    }
+}
+
+/// Find `text` in the page, highlighting every match and scrolling the
+/// current one into view. Calling it again with the same text moves to the
+/// next match, or the previous one unless `forward`; other text starts a new
+/// search from the part of the page in view. Each call reports a
+/// `find_result` event with the number of `matches` and the current match's
+/// `index` (`null` without matches). An empty `text` clears the search.
+///
+/// Servo has no find-in-page of its own, so a script searches the page's
+/// text and draws the highlights over it. Highlights go away with
+/// `pw_find_clear` or when the page navigates, which reports `find_cleared`.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn pw_find(text: *const c_char, forward: bool, case_sensitive: bool) -> i32 {
+    if text.is_null() {
+        return errors::null_argument("text");
+    }
+    let text = unsafe { CStr::from_ptr(text) }
+        .to_string_lossy()
+        .into_owned();
+    send_user_event(UserEvent::Find(text, forward, case_sensitive))
+}
+
+/// End the search started by `pw_find`, removing its highlights.
+#[no_mangle]
+pub extern "C" fn pw_find_clear() -> i32 {
+    send_user_event(UserEvent::ClearFind)
+}
+
+/// Save the page as rendered to a PNG file at `path`, at the window's size in
+/// physical pixels. The pixels are read back from Servo's framebuffer as soon
+/// as it next paints, and tagged sRGB, the color space they are displayed in.
+/// Blocks until the file is written.
+///
+/// Returns `-6` if nothing has been rendered yet, `-5` if no frame is painted
+/// in time (as for a hidden window), in which case nothing is written later,
+/// and `-10` if the file can't be written (see `pw_last_error`).
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn pw_capture_png(path: *const c_char) -> i32 {
+    if path.is_null() {
+        return errors::null_argument("path");
+    }
+    let path = PathBuf::from(
+        unsafe { CStr::from_ptr(path) }
+            .to_string_lossy()
+            .into_owned(),
+    );
+    query(|reply| UserEvent::CapturePng(path, reply)).unwrap_or_else(|code| code)
+}
+
+/// Save the page to a PDF file at `path`, on `page_size` paper: `"a4"` (the
+/// default when null) or `"letter"`. Servo can't print, so the pages are
+/// screenshots: the page is scrolled through one viewport at a time, and the
+/// captures are stitched and cut to the paper's proportions at the window's
+/// width. Text in the PDF is not selectable. `window.print()` in the page
+/// does the same on A4, after asking where to save.
+///
+/// Returns `0` once printing has started; a `pdf_printed` event reports the
+/// file written, or `print_failed` why not. Returns `-4` for an unknown page
+/// size, and `-6` if the page hasn't finished loading, nothing has been
+/// rendered yet or another print is running (see `pw_last_error`).
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn pw_print_to_pdf(path: *const c_char, page_size: *const c_char) -> i32 {
+    if path.is_null() {
+        return errors::null_argument("path");
+    }
+    let path = PathBuf::from(
+        unsafe { CStr::from_ptr(path) }
+            .to_string_lossy()
+            .into_owned(),
+    );
+    let page_size = if page_size.is_null() {
+        PageSize::A4
+    } else {
+        let name = unsafe { CStr::from_ptr(page_size) }.to_string_lossy();
+        match PageSize::parse(&name) {
+            Some(page_size) => page_size,
+            None => {
+                errors::set_last_error(format!("Unknown page size: {:?}", name));
+                return ErrorCode::InvalidArgument.into();
+            }
+        }
+    };
+    query(|reply| UserEvent::PrintToPdf(path, page_size, reply)).unwrap_or_else(|code| code)
+}
+
+/// Delete every cookie, e.g. for a logout button. Stored cookies in the
+/// profile directory are replaced when the engine next saves them.
+#[no_mangle]
+pub extern "C" fn pw_clear_cookies() -> i32 {
+    send_user_event(UserEvent::ClearCookies)
+}
+
+/// The User-Agent the engine sends: the one from `InitParams::user_agent`, or
+/// Servo's default. Owned by the caller; release it with [`pw_free_string`].
+#[no_mangle]
+pub extern "C" fn pw_get_user_agent() -> *mut c_char {
+    into_c_string(prefs::build_preferences().user_agent)
+}
+
+/// Describe the most recent failure, e.g. why `pw_start_app` returned an
+/// error or why a call returned `-3`, or null if nothing has failed yet. The
+/// codes themselves are listed in [`ErrorCode`]. The caller owns the returned
+/// string and must release it with [`pw_free_string`].
+#[no_mangle]
+pub extern "C" fn pw_last_error() -> *mut c_char {
+    errors::last_error()
+        .map(into_c_string)
+        .unwrap_or(std::ptr::null_mut())
+}
+
+/// Hand `value` to the caller as a string they release with [`pw_free_string`].
+/// Every `pw_*` function that returns a `*mut c_char` builds it here. A value
+/// containing a NUL byte can't be represented and comes back as null.
+fn into_c_string(value: String) -> *mut c_char {
+    CString::new(value)
+        .map(CString::into_raw)
+        .unwrap_or(std::ptr::null_mut())
+}
+
+/// Release a string returned as `*mut c_char` by a `pw_*` function, such as
+/// [`pw_get_url`] or [`pw_last_error`]. The string was allocated by this
+/// library, so it must come back here rather than to the C allocator, and
+/// exactly once. Passing null is a no-op. Don't pass the static `*const c_char`
+/// from [`pw_version`].
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn pw_free_string(string: *mut c_char) {
+    if !string.is_null() {
+        unsafe {
+            drop(CString::from_raw(string));
+        }
+    }
+}
+
+/// Enable or disable WebGL. Disabling it makes `getContext("webgl")` and
+/// `getContext("webgl2")` fail, which avoids crashes on unstable GPU drivers or
+/// remote desktops; pages that require WebGL will show their fallback or
+/// break. Must be called before `pw_start_app` (`-7` afterwards).
+#[no_mangle]
+pub extern "C" fn pw_set_webgl_enabled(enabled: bool) -> i32 {
+    if before_start(|| ENGINE_SETTINGS.lock().unwrap().webgl_enabled = Some(enabled)) {
+        0
+    } else {
+        errors::set_last_error("pw_set_webgl_enabled must be called before pw_start_app");
+        ErrorCode::AlreadyStarted.into()
+    }
+}
+
+/// Choose between the GPU (`true`) and software (`false`) 2D canvas backend.
+/// Software rendering is slower for canvas-heavy pages but sidesteps GPU
+/// driver problems. Must be called before `pw_start_app` (`-7` afterwards).
+#[no_mangle]
+pub extern "C" fn pw_set_accelerated_canvas(enabled: bool) -> i32 {
+    if before_start(|| ENGINE_SETTINGS.lock().unwrap().accelerated_canvas = Some(enabled)) {
+        0
+    } else {
+        errors::set_last_error("pw_set_accelerated_canvas must be called before pw_start_app");
+        ErrorCode::AlreadyStarted.into()
+    }
+}
+
+/// Apply changed window chrome settings to the open window. Before
+/// `pw_start_app` the window picks them up when it is created.
+fn apply_window_chrome() -> i32 {
+    match send_user_event(UserEvent::ApplyWindowChrome) {
+        code if code == i32::from(ErrorCode::NotStarted) => 0,
+        code => code,
+    }
+}
+
+/// Show or hide the window's drop shadow, so a frameless window still looks
+/// native. Uses `NSWindow.hasShadow` on macOS and the undecorated-window shadow
+/// on Windows (decorated Windows windows always have one); a logged no-op
+/// elsewhere. May be called before `pw_start_app`.
+#[no_mangle]
+pub extern "C" fn pw_set_window_shadow(enabled: bool) -> i32 {
+    platform::WINDOW_CHROME.lock().unwrap().shadow = Some(enabled);
+    apply_window_chrome()
+}
+
+/// Round the window's corners with `radius` logical pixels (`0` for square
+/// corners). macOS rounds the content view's layer to the exact radius.
+/// Windows 11 only has fixed styles, so `radius` picks square, small (up to 4)
+/// or standard rounding; earlier Windows versions and other platforms log a
+/// note and keep their corners. May be called before `pw_start_app`.
+#[no_mangle]
+pub extern "C" fn pw_set_corner_radius(radius: f64) -> i32 {
+    if !radius.is_finite() || radius < 0.0 {
+        errors::set_last_error(format!("Invalid corner radius: {}", radius));
+        return ErrorCode::InvalidArgument.into();
+    }
+    platform::WINDOW_CHROME.lock().unwrap().corner_radius = Some(radius);
+    apply_window_chrome()
+}
+
+/// Set the image shown for the app in the macOS Dock: a path to an image file
+/// (PNG, JPEG, ICNS, ...), `"auto"` for a scaled-down capture of the page that
+/// refreshes every 30 seconds and on [`pw_refresh_thumbnail`], or `""`/null
+/// for the app icon. Windows taskbar previews need DWM iconic-thumbnail
+/// messages that winit doesn't expose, so other platforms keep the system
+/// preview and get `-6`. Returns `-4` if the file can't be read. May be called
+/// before `pw_start_app`.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn pw_set_thumbnail(source: *const c_char) -> i32 {
+    let source = unsafe {
+        if source.is_null() {
+            String::new()
+        } else {
+            CStr::from_ptr(source).to_string_lossy().into_owned()
+        }
+    };
+    if !cfg!(target_os = "macos") {
+        errors::set_last_error_quietly("Thumbnails are only supported in the macOS Dock");
+        return ErrorCode::Unavailable.into();
+    }
+
+    let thumbnail = match source.as_str() {
+        "" => ThumbnailSource::Default,
+        "auto" => ThumbnailSource::Auto,
+        path => match std::fs::read(path) {
+            Ok(image) => ThumbnailSource::Image(image),
+            Err(e) => {
+                errors::set_last_error(format!("Cannot read thumbnail {:?}: {}", path, e));
+                return ErrorCode::InvalidArgument.into();
+            }
+        },
+    };
+    *thumbnail::THUMBNAIL.lock().unwrap() = thumbnail;
+    pw_refresh_thumbnail()
+}
+
+/// Show the current thumbnail again, recapturing the page in `"auto"` mode,
+/// e.g. right after the page changed visibly. Returns `-6` outside macOS,
+/// like [`pw_set_thumbnail`].
+#[no_mangle]
+pub extern "C" fn pw_refresh_thumbnail() -> i32 {
+    if !cfg!(target_os = "macos") {
+        errors::set_last_error_quietly("Thumbnails are only supported in the macOS Dock");
+        return ErrorCode::Unavailable.into();
+    }
+    match send_user_event(UserEvent::UpdateThumbnail) {
+        code if code == i32::from(ErrorCode::NotStarted) => 0,
+        code => code,
+    }
+}
+
+/// Show progress on the app's taskbar button (Windows) or Dock icon (macOS,
+/// as a percentage badge). `state` is `"normal"`, `"paused"` or `"error"` for
+/// a bar at `fraction` (clamped to 0.0–1.0), `"indeterminate"` for activity of
+/// unknown length, or `"none"` to remove the indicator. A logged no-op on
+/// other platforms. Returns `-4` for an unknown state.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn pw_set_taskbar_progress(fraction: f32, state: *const c_char) -> i32 {
+    let state = unsafe {
+        if state.is_null() {
+            return errors::null_argument("state");
+        }
+        CStr::from_ptr(state).to_string_lossy().into_owned()
+    };
+    let Some(state) = ProgressState::from_name(&state) else {
+        errors::set_last_error(format!("Unknown taskbar progress state: {:?}", state));
+        return ErrorCode::InvalidArgument.into();
+    };
+    let fraction = if fraction.is_nan() {
+        0.0
+    } else {
+        fraction.clamp(0.0, 1.0)
+    };
+    send_user_event(UserEvent::SetTaskbarProgress(state, fraction))
+}
+
+/// Show `count` as a badge on the Dock icon (macOS) or as an overlay on the
+/// taskbar button (Windows), e.g. for unread messages; `0` clears it. Large
+/// counts are capped (`999+` in the Dock, `9+` in the 16-pixel taskbar
+/// overlay). While Dock progress is shown it takes the badge's place. A logged
+/// no-op on other platforms.
+#[no_mangle]
+pub extern "C" fn pw_set_badge_count(count: u32) -> i32 {
+    send_user_event(UserEvent::SetBadgeCount(count))
+}
+
+/// Remove the badge set by [`pw_set_badge_count`].
+#[no_mangle]
+pub extern "C" fn pw_clear_badge() -> i32 {
+    pw_set_badge_count(0)
+}
+
+/// Make the taskbar progress follow page loads: a bar while a page loads,
+/// cleared once it completes. Progress set with [`pw_set_taskbar_progress`]
+/// shows until the next load. May be called before `pw_start_app`.
+#[no_mangle]
+pub extern "C" fn pw_set_auto_taskbar_progress(enabled: bool) -> i32 {
+    platform::AUTO_TASKBAR_PROGRESS.store(enabled, Ordering::Relaxed);
+    0
+}
+
+/// Make the app single-instance. Call it first thing at startup, before
+/// `pw_start_app`, with an id unique to the app (letters, digits, `.`, `_`
+/// and `-`). Returns `0` if this is the only instance; it then keeps the id
+/// until it exits. Returns `1` if another instance already runs: that one is
+/// handed this process's arguments and working directory, comes to the front
+/// and emits a `second_instance` event, and this process should exit.
+///
+/// The running instance listens on a loopback port recorded, with a secret
+/// token, in `pywire-<app_id>-<user>.lock` in the temp directory. A lock left
+/// by a crashed instance no longer answers and is replaced automatically.
+/// Returns `-4` for an invalid id and `-9` if the lock can't be created
+/// (`pw_last_error` says why).
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn pw_request_single_instance(app_id: *const c_char) -> i32 {
+    let app_id = unsafe {
+        if app_id.is_null() {
+            return errors::null_argument("app_id");
+        }
+        CStr::from_ptr(app_id).to_string_lossy().into_owned()
+    };
+    if !single_instance::is_valid_app_id(&app_id) {
+        errors::set_last_error(format!("Invalid app id: {:?}", app_id));
+        return ErrorCode::InvalidArgument.into();
+    }
+
+    match single_instance::request(&app_id, || {
+        let _ = send_user_event(UserEvent::SecondInstance);
+    }) {
+        Ok(single_instance::Outcome::Primary) => 0,
+        Ok(single_instance::Outcome::Forwarded) => 1,
+        Err(e) => {
+            errors::set_last_error(format!("Single-instance lock failed: {}", e));
+            ErrorCode::LockFailed.into()
+        }
+    }
+}
+
+/// Send the log records of Servo and the shell to `callback` (see
+/// [`logging::LogCallback`]) instead of stderr, e.g. for apps without a
+/// console. The callback runs on whichever thread logged. Pass null to log
+/// to stderr again. Call it before `pw_start_app` to catch the startup
+/// messages too.
+#[no_mangle]
+pub extern "C" fn pw_set_log_callback(callback: Option<logging::LogCallback>) -> i32 {
+    logging::set_callback(callback);
+    0
+}
+
+/// Supply Servo's engine resources from `callback` (see [`ResourceCallback`])
+/// instead of loose files, e.g. from a single-file app bundle. Resources the
+/// callback declines are still read from the resources directory if one is
+/// set (`InitParams::resources_path` or `SERVO_RESOURCES_PATH`); with a
+/// callback installed the directory is optional. Pass null to remove the
+/// callback. Must be called before `pw_start_app` (`-7` afterwards).
+#[no_mangle]
+pub extern "C" fn pw_set_resource_callback(callback: Option<ResourceCallback>) -> i32 {
+    if before_start(|| *resource_reader::RESOURCE_CALLBACK.lock().unwrap() = callback) {
+        0
+    } else {
+        errors::set_last_error("pw_set_resource_callback must be called before pw_start_app");
+        ErrorCode::AlreadyStarted.into()
+    }
+}
+
+/// Add `len` bytes at `data` to the in-memory resource bundle as the engine
+/// resource named `name` (its file name, e.g. `"user-agent.css"`), replacing
+/// any earlier contents. Bundled resources are served before the resource
+/// callback and the resources directory are consulted, so an app that bundles
+/// them all needs neither. The bytes are copied. Must be called before
+/// `pw_start_app` (`-7` afterwards); `-4` for an empty or non-UTF-8 name.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn pw_add_resource(name: *const c_char, data: *const u8, len: usize) -> i32 {
+    if name.is_null() || (data.is_null() && len > 0) {
+        return errors::null_argument("name or data");
+    }
+    let Ok(name) = unsafe { CStr::from_ptr(name) }.to_str() else {
+        errors::set_last_error("Resource names must be UTF-8");
+        return ErrorCode::InvalidArgument.into();
+    };
+    if name.is_empty() {
+        errors::set_last_error("Resource names must not be empty");
+        return ErrorCode::InvalidArgument.into();
+    }
+    let contents = if len == 0 {
+        Vec::new()
+    } else {
+        unsafe { std::slice::from_raw_parts(data, len) }.to_vec()
+    };
+    let added = before_start(|| {
+        resource_reader::RESOURCE_BUNDLE
+            .lock()
+            .unwrap()
+            .insert(name.to_string(), contents);
+    });
+    if added {
+        0
+    } else {
+        errors::set_last_error("Resources must be added before pw_start_app");
+        ErrorCode::AlreadyStarted.into()
+    }
+}
+
+/// Set one of Servo's preferences, for settings the shell has no call of its
+/// own for. `key` is the preference's name, e.g. `"dom.webgl2.enabled"` (or
+/// `dom_webgl2_enabled`), and `value_json` its value as JSON, of the type of
+/// the preference's default: `true`/`false`, an integer, a number, a string
+/// or a list. Applied when the engine is built, after the shell's own
+/// settings, so it wins over them. Returns `-4` for an unknown preference,
+/// invalid JSON or a value of the wrong type (`pw_last_error` says which),
+/// and `-7` after `pw_start_app`.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn pw_set_preference(key: *const c_char, value_json: *const c_char) -> i32 {
+    if key.is_null() || value_json.is_null() {
+        return errors::null_argument("key or value_json");
+    }
+    let (key, value_json) = unsafe {
+        (
+            CStr::from_ptr(key).to_string_lossy(),
+            CStr::from_ptr(value_json).to_string_lossy(),
+        )
+    };
+    let mut result = Ok(());
+    if !before_start(|| result = prefs::set_preference(&key, &value_json)) {
+        errors::set_last_error("Preferences must be set before pw_start_app");
+        return ErrorCode::AlreadyStarted.into();
+    }
+    match result {
+        Ok(()) => 0,
+        Err(message) => {
+            errors::set_last_error(message);
+            ErrorCode::InvalidArgument.into()
+        }
+    }
+}
 
-                let (delta_x, delta_y, mode) = match delta {
-                    MouseScrollDelta::LineDelta(x, y) => {
-                        (x * LINE_WIDTH, y * LINE_HEIGHT, WheelMode::DeltaPixel)
-                    }
-                    MouseScrollDelta::PixelDelta(pos) => {
-                        (pos.x as f32, pos.y as f32, WheelMode::DeltaPixel)
-                    }
-                };
+/// Check at startup that every required engine resource is available, and
+/// fail `pw_start_app` with `-8` if any is missing, instead of running with
+/// empty stand-ins (which tends to show up as a blank window or TLS errors).
+/// `pw_last_error` then names the missing files. Without it, each resource
+/// that can't be read is reported as a `resource_missing` event instead.
+/// Must be called before `pw_start_app` (`-7` afterwards).
+#[no_mangle]
+pub extern "C" fn pw_set_strict_resources(strict: bool) -> i32 {
+    if before_start(|| resource_reader::STRICT_RESOURCES.store(strict, Ordering::Relaxed)) {
+        0
+    } else {
+        errors::set_last_error("pw_set_strict_resources must be called before pw_start_app");
+        ErrorCode::AlreadyStarted.into()
+    }
+}
 
-                let point = self.last_mouse_position.get();
-                if let Some(webview) = &self.webview {
-                    webview.notify_input_event(InputEvent::Wheel(WheelEvent::new(
-                        WheelDelta {
-                            x: delta_x as f64,
-                            y: delta_y as f64,
-                            z: 0.0,
-                            mode,
-                        },
-                        DevicePoint::new(point.x, point.y).into(),
-                    )));
-                }
-            }
-            WindowEvent::RedrawRequested => {
-                println!("[pw_servo] RedrawRequested");
-                self.repaint();
-            }
-            _ => (),
-        }
+/// Answer `scheme://` URLs (e.g. `app://index.html`) from `callback` (see
+/// [`ProtocolCallback`]) instead of the network, so an app can serve its UI
+/// without a local HTTP server. The callback gets the path without the scheme
+/// and returns the full contents; `Range` requests are sliced from them. The
+/// scheme counts as secure and can be fetched from scripts. Registering a
+/// scheme again replaces its handler.
+///
+/// Returns `-4` for an invalid scheme or one the engine handles itself
+/// (`http`, `file`, ...). Must be called before `pw_start_app` (`-7`
+/// afterwards).
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn pw_register_protocol(
+    scheme: *const c_char,
+    callback: Option<ProtocolCallback>,
+) -> i32 {
+    let Some(callback) = callback else {
+        return errors::null_argument("callback");
+    };
+    register_protocol(scheme, ProtocolSource::Callback(callback))
+}
 
-        // Critical: pump Servo on EVERY window event, just like servoshell does.
-        self.pump_servo();
+/// Like `pw_register_protocol`, but serve `scheme://` URLs from the files under
+/// `directory`. Paths naming a directory serve its `index.html`, and `.br` or
+/// `.gz` siblings are sent precompressed when the request accepts them. Returns
+/// `-4` if `directory` doesn't exist.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn pw_register_protocol_directory(
+    scheme: *const c_char,
+    directory: *const c_char,
+) -> i32 {
+    if directory.is_null() {
+        return errors::null_argument("directory");
+    }
+    let directory = unsafe { CStr::from_ptr(directory) }
+        .to_string_lossy()
+        .into_owned();
+    let directory = PathBuf::from(directory);
+    if !directory.is_dir() {
+        errors::set_last_error(format!(
+            "Protocol directory does not exist: {:?}",
+            directory
+        ));
+        return ErrorCode::InvalidArgument.into();
     }
+    register_protocol(scheme, ProtocolSource::Directory(directory))
+}
 
-    fn user_event(&mut self, _event_loop: &ActiveEventLoop, event: UserEvent) {
-        match event {
-            UserEvent::Wake => {
-                self.pump_servo();
-            }
-            UserEvent::ExecuteJs(script) => {
-                if let Some(webview) = &self.webview {
-                    webview.evaluate_javascript(script, |_result| {
-                        // For now we don't handle the result back to Python
-                    });
-                }
-            }
-            UserEvent::SetTitle(title) => {
-                if let Some(window) = &self.window {
-                    window.set_title(&title);
-                }
-            }
-            UserEvent::Resize(width, height) => {
-                if let Some(window) = &self.window {
-                    let _ = window.request_inner_size(winit::dpi::LogicalSize::new(
-                        width as f64,
-                        height as f64,
-                    ));
-                }
-            }
-        }
+fn register_protocol(scheme: *const c_char, source: ProtocolSource) -> i32 {
+    if scheme.is_null() {
+        return errors::null_argument("scheme");
+    }
+    let scheme = unsafe { CStr::from_ptr(scheme) }.to_string_lossy();
+    if !protocol::is_valid_scheme(&scheme) {
+        errors::set_last_error(format!("Invalid or reserved scheme: {:?}", scheme));
+        return ErrorCode::InvalidArgument.into();
+    }
+    if before_start(|| protocol::register(&scheme, source)) {
+        0
+    } else {
+        errors::set_last_error("Protocols must be registered before pw_start_app");
+        ErrorCode::AlreadyStarted.into()
     }
 }
 
+/// Expose a Do Not Track preference to pages' scripts as
+/// `navigator.doNotTrack == "1"`. This is not a request header: Servo doesn't
+/// let the embedder add headers to the requests it issues, so no `DNT: 1`
+/// reaches servers, and deployments that need servers to see the signal must
+/// add it in a proxy (`InitParams::proxy_url`). Off by default. May be called
+/// before `pw_start_app`.
+#[no_mangle]
+pub extern "C" fn pw_set_do_not_track(enabled: bool) -> i32 {
+    injected::DO_NOT_TRACK.store(enabled, Ordering::Relaxed);
+    reapply_injections()
+}
+
+/// Expose Global Privacy Control, the opt-out of data sale and sharing that
+/// some privacy laws recognise, to pages' scripts as
+/// `navigator.globalPrivacyControl == true`. As with [`pw_set_do_not_track`]
+/// this is script-only: no `Sec-GPC: 1` request header is sent. Off by
+/// default. May be called before `pw_start_app`.
+#[no_mangle]
+pub extern "C" fn pw_set_global_privacy_control(enabled: bool) -> i32 {
+    injected::GLOBAL_PRIVACY_CONTROL.store(enabled, Ordering::Relaxed);
+    reapply_injections()
+}
+
+/// Decode HTML and text documents that don't declare a charset with `charset`
+/// (any WHATWG label, e.g. `"shift_jis"` or `"windows-1251"`), for legacy
+/// pages that were saved in a regional encoding. Applies to `file:` URLs and
+/// to the schemes the app serves itself (`pw_register_protocol`,
+/// `InitParams::app_dir`). Documents with a byte order mark, a `charset` in
+/// their MIME type or a `<meta>` declaration in their first 1024 bytes keep
+/// their own encoding. Servo has no fallback-encoding preference, so responses
+/// from the network are decoded as their headers say, or UTF-8 with
+/// auto-detection. `""` or null restores that default for the app's documents
+/// too. Can be called before `pw_start_app`. Returns `-4` for an unknown label.
 #[no_mangle]
 #[allow(clippy::not_unsafe_ptr_arg_deref)]
-pub extern "C" fn pw_execute_javascript(script: *const c_char) -> i32 {
-    let script = unsafe {
-        if script.is_null() {
-            return -1;
+pub extern "C" fn pw_set_default_encoding(charset: *const c_char) -> i32 {
+    let charset = unsafe {
+        if charset.is_null() {
+            String::new()
+        } else {
+            CStr::from_ptr(charset).to_string_lossy().into_owned()
         }
-        CStr::from_ptr(script).to_string_lossy().into_owned()
     };
 
-    if let Some(proxy) = PROXY.get() {
-        if proxy.send_event(UserEvent::ExecuteJs(script)).is_ok() {
+    match encoding::parse_label(&charset) {
+        Ok(encoding) => {
+            *encoding::DEFAULT_ENCODING.lock().unwrap() = encoding;
             0
-        } else {
-            -2
         }
-    } else {
-        -3
+        Err(()) => {
+            errors::set_last_error(format!("Unknown encoding: {:?}", charset));
+            ErrorCode::InvalidArgument.into()
+        }
     }
 }
 
+/// Rewrite request and navigation URLs matching the regular expression
+/// `pattern` to `replacement` before they go out, e.g. to redirect a CDN host
+/// to a local mirror. `replacement` may use capture groups (`$1`, `${name}`).
+///
+/// Rules are tried in the order they were added and only the first match
+/// applies. The rewrite is delivered as a redirect, so the rewritten request
+/// is checked again: a replacement that matches its own pattern would loop.
+/// Every request is tested against every rule until one matches, so keep the
+/// list short and anchor patterns (`^https://cdn\.example\.com/`) where
+/// possible. Returns `-4` if `pattern` is not a valid regular expression
+/// (`pw_last_error` says why).
 #[no_mangle]
 #[allow(clippy::not_unsafe_ptr_arg_deref)]
-pub extern "C" fn pw_set_title(title: *const c_char) -> i32 {
-    let title = unsafe {
-        if title.is_null() {
-            return -1;
+pub extern "C" fn pw_add_url_rewrite(pattern: *const c_char, replacement: *const c_char) -> i32 {
+    let (pattern, replacement) = unsafe {
+        if pattern.is_null() || replacement.is_null() {
+            return errors::null_argument("pattern or replacement");
         }
-        CStr::from_ptr(title).to_string_lossy().into_owned()
+        (
+            CStr::from_ptr(pattern).to_string_lossy().into_owned(),
+            CStr::from_ptr(replacement).to_string_lossy().into_owned(),
+        )
     };
 
-    if let Some(proxy) = PROXY.get() {
-        if proxy.send_event(UserEvent::SetTitle(title)).is_ok() {
+    match regex::Regex::new(&pattern) {
+        Ok(pattern) => {
+            NETWORK_POLICY
+                .lock()
+                .unwrap()
+                .url_rewrites
+                .push(UrlRewrite {
+                    pattern,
+                    replacement,
+                });
             0
-        } else {
-            -2
         }
-    } else {
-        -3
+        Err(e) => {
+            errors::set_last_error(format!("Invalid URL rewrite pattern {:?}: {}", pattern, e));
+            ErrorCode::InvalidArgument.into()
+        }
     }
 }
 
+/// Remove every rule added with [`pw_add_url_rewrite`].
 #[no_mangle]
-pub extern "C" fn pw_resize_window(width: u32, height: u32) -> i32 {
-    if let Some(proxy) = PROXY.get() {
-        if proxy.send_event(UserEvent::Resize(width, height)).is_ok() {
-            0
-        } else {
-            -2
+pub extern "C" fn pw_clear_url_rewrites() -> i32 {
+    NETWORK_POLICY.lock().unwrap().url_rewrites.clear();
+    0
+}
+
+/// Set the default page zoom (clamped to 0.25–5.0), used for every origin
+/// without its own override. Emits a `zoom` event if the current page's
+/// effective zoom changes.
+#[no_mangle]
+pub extern "C" fn pw_set_zoom(level: f32) -> i32 {
+    send_user_event(UserEvent::SetDefaultZoom(level))
+}
+
+/// Zoom the current page and remember `level` for its origin, so it is
+/// re-applied whenever the webview returns to that origin. Overrides take
+/// precedence over the default from [`pw_set_zoom`].
+#[no_mangle]
+pub extern "C" fn pw_set_origin_zoom(level: f32) -> i32 {
+    send_user_event(UserEvent::SetOriginZoom(level))
+}
+
+/// Zoom the current page in one step (e.g. 100% to 110%), remembered for its
+/// origin like [`pw_set_origin_zoom`]. Emits a `zoom` event with the new level.
+#[no_mangle]
+pub extern "C" fn pw_zoom_in() -> i32 {
+    send_user_event(UserEvent::StepZoom(true))
+}
+
+/// Zoom the current page out one step. See [`pw_zoom_in`].
+#[no_mangle]
+pub extern "C" fn pw_zoom_out() -> i32 {
+    send_user_event(UserEvent::StepZoom(false))
+}
+
+/// Return the current page to the default zoom, forgetting its origin's
+/// override.
+#[no_mangle]
+pub extern "C" fn pw_reset_zoom() -> i32 {
+    send_user_event(UserEvent::ResetZoom)
+}
+
+/// Set the pinch (visual) zoom, clamped to 1.0–5.0. Unlike page zoom, which
+/// reflows the layout at a larger size, pinch zoom magnifies the rendered page
+/// without relayout, as pinching a touchscreen does. It is not remembered
+/// across navigations. Emits a `pinch_zoom` event.
+#[no_mangle]
+pub extern "C" fn pw_set_pinch_zoom(level: f32) -> i32 {
+    send_user_event(UserEvent::SetPinchZoom(level))
+}
+
+/// Forget all per-origin zoom levels, returning every origin to the default.
+#[no_mangle]
+pub extern "C" fn pw_clear_zoom_overrides() -> i32 {
+    send_user_event(UserEvent::ClearZoomOverrides)
+}
+
+/// The URL of the page currently shown, following redirects and in-page
+/// navigations (`history.pushState`, fragment changes). A `url_changed` event
+/// reports each change. Returns null before the first navigation or if the app
+/// isn't running. The string is owned by the caller and must be released with
+/// [`pw_free_string`].
+#[no_mangle]
+pub extern "C" fn pw_get_url() -> *mut c_char {
+    match query(UserEvent::GetUrl) {
+        Ok(Some(url)) => into_c_string(url),
+        _ => std::ptr::null_mut(),
+    }
+}
+
+/// Navigate to `url` and invoke `callback` exactly once when the navigation
+/// ends: `(true, "complete")` once the page has loaded, or `false` with
+/// `"superseded"` (another navigation started first), `"failed"` (the page
+/// couldn't be loaded, or the server answered with a 4xx or 5xx status),
+/// `"timeout"`, `"stopped"`, `"invalid url"` or
+/// `"cancelled"` (the app shut down, or was never started). The callback
+/// usually runs on the event loop thread and must stay valid until it fires.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn pw_navigate_with_callback(
+    url: *const c_char,
+    callback: Option<NavigationCallback>,
+) -> i32 {
+    let Some(callback) = callback else {
+        return errors::null_argument("callback");
+    };
+    let url = unsafe {
+        if url.is_null() {
+            return errors::null_argument("url");
+        }
+        CStr::from_ptr(url).to_string_lossy().into_owned()
+    };
+
+    let navigation = PendingNavigation::new(callback);
+    match Url::parse(&url) {
+        // If the event can't be delivered, dropping it reports "cancelled".
+        Ok(url) => send_user_event(UserEvent::NavigateWithCallback(url, navigation)),
+        Err(_) => {
+            navigation.finish(false, "invalid url");
+            errors::set_last_error(format!("Invalid URL: {:?}", url));
+            ErrorCode::InvalidArgument.into()
         }
+    }
+}
+
+/// Subscribe `callback` to every bridge event, alongside `InitParams.on_event`
+/// and any other listeners. Returns a token for
+/// [`pw_remove_event_listener_native`], or `0` if `callback` is null.
+///
+/// Listeners are called in registration order, synchronously on the event
+/// loop thread: a slow listener blocks the loop, so handlers must be fast.
+#[no_mangle]
+pub extern "C" fn pw_add_event_listener_native(callback: Option<EventCallback>) -> u32 {
+    callback.map(events::add_listener).unwrap_or(0)
+}
+
+/// Like [`pw_add_event_listener_native`], but `callback` also receives the
+/// event's numeric type (see `EventType`: 0 bridge message, 1 load, 2 title,
+/// 3 console, 4 navigation, 5 close, 6 zoom, 7 network, ...), so it can
+/// dispatch without parsing the JSON payload. Remove it with
+/// [`pw_remove_event_listener_native`].
+#[no_mangle]
+pub extern "C" fn pw_add_typed_event_listener_native(callback: Option<TypedEventCallback>) -> u32 {
+    callback.map(events::add_typed_listener).unwrap_or(0)
+}
+
+/// Subscribe `callback` to one kind of event only: a category such as
+/// `"bridge"`, `"load"`, `"title"` or `"console"`, or an event's `type`
+/// such as `"load_status"`. Same token and threading rules as
+/// [`pw_add_event_listener_native`]. Returns `0` if an argument is null.
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn pw_register_callback(
+    event_type: *const c_char,
+    callback: Option<EventCallback>,
+) -> u32 {
+    let Some(callback) = callback else {
+        return 0;
+    };
+    if event_type.is_null() {
+        return 0;
+    }
+    let name = unsafe { CStr::from_ptr(event_type) }
+        .to_string_lossy()
+        .into_owned();
+    events::add_named_listener(name, callback)
+}
+
+/// Unsubscribe the listener registered under `token`. Returns `-4` for an
+/// unknown token.
+#[no_mangle]
+pub extern "C" fn pw_remove_event_listener_native(token: u32) -> i32 {
+    if events::remove_listener(token) {
+        0
     } else {
-        -3
+        errors::set_last_error(format!("Unknown event listener token: {}", token));
+        ErrorCode::InvalidArgument.into()
+    }
+}
+
+/// Pause every playing `<audio>`/`<video>` in the page, e.g. when the app goes
+/// to the background. Media that starts playing afterwards (including on
+/// later pages) is paused as soon as it starts, until
+/// [`pw_resume_all_media`]. Media elements never attached to the document
+/// (`new Audio()`) are not covered.
+#[no_mangle]
+pub extern "C" fn pw_pause_all_media() -> i32 {
+    injected::MEDIA_PAUSED.store(true, Ordering::Relaxed);
+    reapply_injections()
+}
+
+/// Resume the media that [`pw_pause_all_media`] paused. Media the user had
+/// paused themselves stays paused.
+#[no_mangle]
+pub extern "C" fn pw_resume_all_media() -> i32 {
+    injected::MEDIA_PAUSED.store(false, Ordering::Relaxed);
+    reapply_injections()
+}
+
+/// Mute the page's audio and video, or unmute it. Servo has no audio
+/// controls for a webview, so this applies to the `<audio>` and `<video>`
+/// elements in the page, including ones added later; the page keeps seeing
+/// its own `muted` setting and can't unmute them. Media that isn't in the
+/// document (`new Audio()`) and Web Audio are not covered. May be called
+/// before `pw_start_app`; see also `InitParams::start_muted`.
+#[no_mangle]
+pub extern "C" fn pw_set_muted(muted: bool) -> i32 {
+    injected::MUTED.store(muted, Ordering::Relaxed);
+    reapply_injections()
+}
+
+/// Play the page's media at `volume` (0 to 1) times its own volume, which
+/// the page keeps seeing unchanged. Covers the same media as
+/// [`pw_set_muted`]. Returns `-4` for a volume outside 0 to 1. May be called
+/// before `pw_start_app`.
+#[no_mangle]
+pub extern "C" fn pw_set_volume(volume: f32) -> i32 {
+    if !(0.0..=1.0).contains(&volume) {
+        errors::set_last_error(format!("Volume must be between 0 and 1: {}", volume));
+        return ErrorCode::InvalidArgument.into();
+    }
+    *injected::VOLUME.lock().unwrap() = volume;
+    reapply_injections()
+}
+
+/// Reload pages whose content crashed, like a browser's "sad tab" button,
+/// unless the page crashed again within 30 seconds of the last time. Either
+/// way the crash is reported as a `crash` event, whose `reloading` says
+/// whether the page is being reloaded. Off by default. May be called before
+/// `pw_start_app`.
+#[no_mangle]
+pub extern "C" fn pw_set_crash_recovery(enabled: bool) -> i32 {
+    crash::CRASH_RECOVERY.store(enabled, Ordering::Relaxed);
+    0
+}
+
+/// Ids of `pw_get_memory_report` requests. Start at 1 so that callers can
+/// tell them from error codes.
+static NEXT_MEMORY_REPORT: AtomicU32 = AtomicU32::new(1);
+
+/// Ask Servo how much memory it uses, e.g. to watch a long-running app for
+/// leaks. Returns a request id right away; the report arrives as a
+/// `memory_report` event with that `request_id`, holding Servo's own `report`
+/// (each process's measurements: a `path` naming what was measured, its
+/// `kind` and `size` in bytes) and a `summary` with the `explicit` total and
+/// the same split `by_category`. A report that can't be made, or that the
+/// engine doesn't deliver within 10 seconds, comes as `memory_report_failed`.
+/// Returns `-3` before `pw_start_app`.
+#[no_mangle]
+pub extern "C" fn pw_get_memory_report() -> i32 {
+    let id = NEXT_MEMORY_REPORT.fetch_add(1, Ordering::Relaxed);
+    match send_user_event(UserEvent::CreateMemoryReport(id)) {
+        0 => id as i32,
+        code => code,
     }
 }
 
+/// A JSON report for bug reports: versions, OS, the engine resources
+/// directory and any missing required resources, and how crypto was set up.
+/// Once the app runs it also has a `runtime` section with the GL vendor,
+/// renderer and version (flagging software rendering), the monitors, and the
+/// window size, plus a `rendering` entry counting the frames painted so far,
+/// which stays put while the page is idle. Safe to call before
+/// `pw_start_app`, when `runtime` is null. The caller owns the returned string
+/// and must release it with [`pw_free_string`].
+#[no_mangle]
+pub extern "C" fn pw_run_diagnostics() -> *mut c_char {
+    let mut report = diagnostics::environment_report();
+    report["runtime"] = match query(UserEvent::RunDiagnostics) {
+        Ok(runtime) => runtime,
+        Err(code) if code == i32::from(ErrorCode::NotStarted) => serde_json::Value::Null,
+        Err(code) => serde_json::json!({ "error": code }),
+    };
+    into_c_string(report.to_string())
+}
+
+/// The runtime version reported by `pw_version`.
+const VERSION: &CStr = c"0.2.0";
+
+/// The runtime version, e.g. `"0.2.0"`. The string is static: don't free it.
 #[no_mangle]
 pub extern "C" fn pw_version() -> *const c_char {
-    c"0.2.0".as_ptr()
+    VERSION.as_ptr()
+}
+
+/// The loop `pw_start_app` runs the app on.
+enum MainLoop {
+    Winit(EventLoop<UserEvent>),
+    Headless(mpsc::Receiver<UserEvent>),
+}
+
+/// How often the headless loop spins Servo while no events arrive. Without a
+/// window nothing else would drive timers, animations and painting.
+const HEADLESS_TICK: Duration = Duration::from_millis(16);
+
+/// Run `app` without a window until `pw_shutdown`.
+fn run_headless(app: &mut AppState, events: mpsc::Receiver<UserEvent>) {
+    if !app.start_headless_or_fail() {
+        return;
+    }
+    while step_headless(app, &events, HEADLESS_TICK) {}
+}
+
+/// Run one turn of the headless loop: wait up to `max_wait` for an event
+/// (less if a deadline comes sooner), then run the checks and spin Servo.
+/// Returns false once the app should stop.
+fn step_headless(
+    app: &mut AppState,
+    events: &mpsc::Receiver<UserEvent>,
+    max_wait: Duration,
+) -> bool {
+    if app.exit_requested.get() {
+        return false;
+    }
+    let deadline = [
+        app.load_deadline(),
+        app.permissions.borrow().next_deadline(),
+        app.authentications.borrow().next_deadline(),
+        app.capture_deadline(),
+        app.held_frame_deadline(),
+        app.watchdog.borrow().deadline(),
+        app.status_text.borrow().deadline(),
+    ]
+    .into_iter()
+    .flatten()
+    .min();
+    let wait = deadline.map_or(max_wait, |deadline| {
+        deadline
+            .saturating_duration_since(Instant::now())
+            .min(max_wait)
+    });
+    match events.recv_timeout(wait) {
+        Ok(event) => app.handle_user_event(event),
+        Err(mpsc::RecvTimeoutError::Timeout) => {}
+        Err(mpsc::RecvTimeoutError::Disconnected) => return false,
+    }
+    app.check_load_timeout();
+    app.check_permission_timeouts();
+    app.check_auth_timeouts();
+    app.check_capture_timeouts();
+    app.check_resource_failures();
+    app.check_watchdog();
+    app.check_status_text();
+    app.pump_servo();
+    !app.exit_requested.get()
+}
+
+/// An app started with `InitParams::pump_events`, which `pw_poll_events`
+/// drives.
+struct PumpedApp {
+    main_loop: MainLoop,
+    app: AppState,
+    on_event_token: Option<u32>,
+}
+
+thread_local! {
+    /// The pumped app, on the thread that started it.
+    static PUMPED_APP: RefCell<Option<PumpedApp>> = const { RefCell::new(None) };
+}
+
+/// Tear down after the app's event loop has ended, returning `pw_start_app`'s
+/// result: `code`, or `-11` if the window or headless surface couldn't be
+/// created.
+fn finish_app(app: &AppState, on_event_token: Option<u32>, code: i32) -> i32 {
+    if let Some(token) = on_event_token {
+        events::remove_listener(token);
+    }
+    single_instance::release();
+    if app.startup_failed.get() {
+        ErrorCode::WindowFailed.into()
+    } else {
+        code
+    }
+}
+
+/// Run the event loop of an app started with `InitParams::pump_events` for
+/// up to `timeout_ms` milliseconds, or only handle what is pending with `0`.
+/// Call it over and over on the thread that called `pw_start_app`, e.g. from
+/// the app's own main loop. Returns `1` while the app runs, then what
+/// `pw_start_app` would have once it has closed: `0`, or `-11` if the window
+/// couldn't be created. Returns `-3` if no app is pumped, `-13` if it is
+/// pumped on another thread, `-6` from inside an event callback, and `-12` if
+/// the runtime panicked, which ends the app.
+#[no_mangle]
+pub extern "C" fn pw_poll_events(timeout_ms: u32) -> i32 {
+    let timeout = Duration::from_millis(timeout_ms.into());
+    let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        PUMPED_APP.with(|pumped| {
+            let Ok(mut pumped) = pumped.try_borrow_mut() else {
+                errors::set_last_error("pw_poll_events can't be called from an event callback");
+                return ErrorCode::Unavailable.into();
+            };
+            let Some(running) = pumped.as_mut() else {
+                if PROXY.get().is_none() {
+                    errors::set_last_error_quietly("The app hasn't been started with pw_start_app");
+                    return ErrorCode::NotStarted.into();
+                }
+                errors::set_last_error(
+                    "No app is pumped on this thread: call pw_poll_events on the thread that \
+                     called pw_start_app with InitParams.pump_events, until the app closes",
+                );
+                return ErrorCode::WrongThread.into();
+            };
+            let mut code = 0;
+            let running_on = match &mut running.main_loop {
+                MainLoop::Winit(event_loop) => {
+                    match event_loop.pump_app_events(Some(timeout), &mut running.app) {
+                        PumpStatus::Continue => true,
+                        PumpStatus::Exit(exit_code) => {
+                            if exit_code != 0 {
+                                errors::set_last_error(format!(
+                                    "The event loop exited with {}",
+                                    exit_code
+                                ));
+                                code = ErrorCode::WindowFailed.into();
+                            }
+                            false
+                        }
+                    }
+                }
+                MainLoop::Headless(events) => {
+                    step_headless(&mut running.app, events, timeout.min(HEADLESS_TICK))
+                }
+            };
+            if running_on {
+                return 1;
+            }
+            let running = pumped.take().unwrap();
+            finish_app(&running.app, running.on_event_token, code)
+        })
+    }));
+    res.unwrap_or_else(|panic| {
+        // The app can't be trusted to carry on after a panic.
+        PUMPED_APP.with(|pumped| {
+            if let Ok(mut pumped) = pumped.try_borrow_mut() {
+                pumped.take();
+            }
+        });
+        errors::set_last_error(format!("pw_poll_events failed: {}", panic_message(&*panic)));
+        ErrorCode::Panicked.into()
+    })
 }
 
+/// Open the window and run the app, blocking until it closes (or, with
+/// `InitParams::pump_events`, until it is set up); on macOS this must be the
+/// main thread. Returns `0` once the app has closed, or an [`ErrorCode`]: `-4`
+/// for an invalid `app_dir`, `log_level`, `proxy_url`, `ca_certificates` or
+/// `autoplay_policy`, `-7` if the app was already started, `-8` for missing
+/// engine resources, `-11` if the window (or headless surface) can't be
+/// created, `-12` if the runtime panicked and `-13` off the main thread on
+/// macOS. `pw_last_error` says why.
 #[no_mangle]
 #[allow(clippy::not_unsafe_ptr_arg_deref)]
 pub extern "C" fn pw_start_app(params: InitParams) -> i32 {
     let res = std::panic::catch_unwind(|| {
+        // A second call must fail before it touches the running app's state.
+        let _start_guard = START_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        if PROXY.get().is_some() {
+            errors::set_last_error("pw_start_app can only be called once per process");
+            return ErrorCode::AlreadyStarted.into();
+        }
+        // Off the main thread on macOS, fail before setting anything up.
+        let mut builder = EventLoop::with_user_event();
+        if !params.headless && !platform::allow_event_loop_thread(&mut builder) {
+            errors::set_last_error(
+                "On macOS, pw_start_app must be called on the main thread (or with \
+                 InitParams.headless)",
+            );
+            return ErrorCode::WrongThread.into();
+        }
+
         let title = unsafe {
             if params.title.is_null() {
                 "PyWire Shell".to_string()
@@ -770,8 +5909,130 @@ pub extern "C" fn pw_start_app(params: InitParams) -> i32 {
             }
         };
 
+        // Check every parameter before changing any global state, so a call
+        // that fails leaves the process as it was and can be retried.
+        let log_level = if params.log_level.is_null() {
+            logging::DEFAULT_LEVEL
+        } else {
+            let name = unsafe { CStr::from_ptr(params.log_level) }.to_string_lossy();
+            match logging::parse_level(&name) {
+                Some(level) => level,
+                None => {
+                    errors::set_last_error(format!("Unknown log level: {:?}", name));
+                    return ErrorCode::InvalidArgument.into();
+                }
+            }
+        };
+        let app_dir = if params.app_dir.is_null() {
+            None
+        } else {
+            let app_dir = PathBuf::from(
+                unsafe { CStr::from_ptr(params.app_dir) }
+                    .to_string_lossy()
+                    .into_owned(),
+            );
+            if !app_dir.is_dir() {
+                errors::set_last_error(format!("App directory does not exist: {:?}", app_dir));
+                return ErrorCode::InvalidArgument.into();
+            }
+            Some(app_dir)
+        };
+        let proxy_url = if params.proxy_url.is_null() {
+            None
+        } else {
+            let proxy_url = unsafe { CStr::from_ptr(params.proxy_url) }.to_string_lossy();
+            match prefs::parse_proxy_url(&proxy_url) {
+                Ok(proxy_url) => Some(proxy_url),
+                Err(message) => {
+                    errors::set_last_error(message);
+                    return ErrorCode::InvalidArgument.into();
+                }
+            }
+        };
+        let autoplay_policy = if params.autoplay_policy.is_null() {
+            None
+        } else {
+            let name = unsafe { CStr::from_ptr(params.autoplay_policy) }.to_string_lossy();
+            let Some(policy) = injected::AutoplayPolicy::from_name(&name) else {
+                errors::set_last_error(format!("Unknown autoplay policy: {:?}", name));
+                return ErrorCode::InvalidArgument.into();
+            };
+            Some(policy)
+        };
+        let certificate_path = if params.ca_certificates.is_null() {
+            None
+        } else {
+            let path = unsafe { CStr::from_ptr(params.ca_certificates) }.to_string_lossy();
+            if !Path::new(path.as_ref()).is_file() {
+                errors::set_last_error(format!("CA certificates file does not exist: {:?}", path));
+                return ErrorCode::InvalidArgument.into();
+            }
+            Some(path.into_owned())
+        };
+
+        // Servo's resources. A resource bundle or callback can stand in for
+        // the directory entirely.
+        let resource_callback = *resource_reader::RESOURCE_CALLBACK.lock().unwrap();
+        let resources_bundled = !resource_reader::RESOURCE_BUNDLE.lock().unwrap().is_empty();
+        let (resources_path, source) = if params.resources_path.is_null() {
+            let path = env::var("SERVO_RESOURCES_PATH").ok().map(PathBuf::from);
+            (path, "SERVO_RESOURCES_PATH")
+        } else {
+            let path = unsafe { CStr::from_ptr(params.resources_path) }.to_string_lossy();
+            (
+                Some(PathBuf::from(path.into_owned())),
+                "InitParams.resources_path",
+            )
+        };
+        match &resources_path {
+            Some(path) if !path.is_dir() => {
+                errors::set_last_error(format!("{} is not a directory: {:?}", source, path));
+                return ErrorCode::MissingResources.into();
+            }
+            None if resource_callback.is_none() && !resources_bundled => {
+                errors::set_last_error(
+                    "No engine resources: pass InitParams.resources_path, set \
+                     SERVO_RESOURCES_PATH, add resources or install a resource callback",
+                );
+                return ErrorCode::MissingResources.into();
+            }
+            _ => {}
+        }
+        let reader = PyWireResourceReader {
+            path: resources_path.clone(),
+            callback: resource_callback,
+        };
+        // Without strict resources the app still starts, and each missing
+        // resource is reported as a `resource_missing` event once it is read,
+        // so there is nothing to check up front (and no reason to have the
+        // callback read every required resource an extra time).
+        if resource_reader::STRICT_RESOURCES.load(Ordering::Relaxed) {
+            let missing = reader.missing_required();
+            if !missing.is_empty() {
+                let location = match &reader.path {
+                    Some(path) => format!("in {:?}", path),
+                    None => "from the resource bundle and callback".to_string(),
+                };
+                errors::set_last_error(format!(
+                    "Missing required engine resources {}: {}",
+                    location,
+                    missing.join(", ")
+                ));
+                return ErrorCode::MissingResources.into();
+            }
+        }
+
+        logging::init(log_level);
+        crash::install_panic_hook(report_panic);
+
         let url = unsafe {
-            if params.url.is_null() {
+            if params.url.is_null() && app_dir.is_some() {
+                format!(
+                    "{}://{}/index.html",
+                    protocol::APP_SCHEME,
+                    protocol::APP_HOST
+                )
+            } else if params.url.is_null() {
                 "about:blank".to_string()
             } else {
                 CStr::from_ptr(params.url)
@@ -780,56 +6041,227 @@ pub extern "C" fn pw_start_app(params: InitParams) -> i32 {
                     .to_string()
             }
         };
+        if let Some(app_dir) = app_dir {
+            protocol::register_app_dir(app_dir);
+        }
 
-        // Initialize Servo resources
-        let resources_path = env::var("SERVO_RESOURCES_PATH")
-            .map(PathBuf::from)
-            .expect("SERVO_RESOURCES_PATH must be set");
-
-        if !resources_path.exists() {
-            panic!("SERVO_RESOURCES_PATH does not exist: {:?}", resources_path);
+        if !params.user_agent.is_null() {
+            let user_agent = unsafe { CStr::from_ptr(params.user_agent) }
+                .to_string_lossy()
+                .into_owned();
+            ENGINE_SETTINGS.lock().unwrap().user_agent = Some(user_agent);
+        }
+        if proxy_url.is_some() {
+            ENGINE_SETTINGS.lock().unwrap().proxy_url = proxy_url;
+        }
+        if !params.no_proxy.is_null() {
+            let no_proxy = unsafe { CStr::from_ptr(params.no_proxy) }.to_string_lossy();
+            ENGINE_SETTINGS.lock().unwrap().no_proxy = Some(prefs::parse_no_proxy(&no_proxy));
+        }
+        if let Some(policy) = autoplay_policy {
+            *injected::AUTOPLAY_POLICY.lock().unwrap() = policy;
+        }
+        if params.start_muted {
+            injected::MUTED.store(true, Ordering::Relaxed);
+        }
+        net_error::HIDE_ERROR_PAGES.store(params.hide_error_pages, Ordering::Relaxed);
+        if certificate_path.is_some() {
+            ENGINE_SETTINGS.lock().unwrap().certificate_path = certificate_path;
+        }
+        if params.background_color != 0 || params.transparent {
+            ENGINE_SETTINGS.lock().unwrap().background_color =
+                Some(background_rgba(params.background_color, params.transparent));
         }
 
-        resources::set(Box::new(PyWireResourceReader {
-            path: resources_path,
-        }));
+        *resource_reader::RESOURCES_PATH.lock().unwrap() = resources_path;
+        resources::set(Box::new(reader));
 
         // Initialize crypto
         match rustls::crypto::aws_lc_rs::default_provider().install_default() {
-            Ok(_) => (),
-            Err(_) => println!("[pw_servo] Warning: crypto provider already installed"),
+            Ok(_) => {
+                let _ = diagnostics::CRYPTO_PROVIDER.set("installed");
+            }
+            Err(_) => {
+                log::warn!("crypto provider already installed");
+                let _ = diagnostics::CRYPTO_PROVIDER.set("already_installed");
+            }
         }
 
-        let event_loop = EventLoop::with_user_event().build().unwrap();
-        let proxy = event_loop.create_proxy();
+        let (main_loop, proxy) = if params.headless {
+            let (sender, receiver) = mpsc::channel();
+            (MainLoop::Headless(receiver), LoopProxy::Headless(sender))
+        } else {
+            let event_loop = match builder.build() {
+                Ok(event_loop) => event_loop,
+                Err(e) => {
+                    errors::set_last_error(format!("Could not start the window system: {}", e));
+                    return ErrorCode::WindowFailed.into();
+                }
+            };
+            let proxy = event_loop.create_proxy();
+            (MainLoop::Winit(event_loop), LoopProxy::Winit(proxy))
+        };
         let _ = PROXY.set(proxy.clone());
+        // Calls from now on reach the loop, which queues them itself.
+        let early_scripts = std::mem::take(&mut *EARLY_SCRIPTS.lock().unwrap());
+        let queued_scripts = if params.disable_script_queue {
+            if !early_scripts.is_empty() {
+                log::warn!(
+                    "Dropping {} script(s) sent before pw_start_app",
+                    early_scripts.len()
+                );
+            }
+            None
+        } else {
+            Some(early_scripts)
+        };
+        drop(_start_guard);
 
-        unsafe {
-            ON_EVENT_CALLBACK = params.on_event;
+        let on_event_token = params.on_event.map(events::add_listener);
+
+        if params.devtools_port != 0 {
+            ENGINE_SETTINGS.lock().unwrap().devtools_port =
+                Some(devtools::choose_port(params.devtools_port));
+        }
+
+        if !params.profile_dir.is_null() {
+            let path = PathBuf::from(
+                unsafe { CStr::from_ptr(params.profile_dir) }
+                    .to_string_lossy()
+                    .into_owned(),
+            );
+            let profile_dir = match prefs::prepare_profile_dir(&path) {
+                Ok(path) => Some(path),
+                Err(e) => {
+                    log::warn!("profile storage unavailable, {}", e);
+                    emit_event(
+                        EventType::Storage,
+                        serde_json::json!({
+                            "type": "profile_unavailable",
+                            "path": path.to_string_lossy(),
+                            "error": e,
+                        }),
+                    );
+                    None
+                }
+            };
+            ENGINE_SETTINGS.lock().unwrap().profile_dir = profile_dir;
         }
 
         let mut app = AppState {
             servo: None,
-            webview: None,
+            webviews: Vec::new(),
+            active_webview: Rc::new(Cell::new(None)),
+            next_webview_id: Rc::new(Cell::new(1)),
+            webview_requests: Rc::new(RefCell::new(Vec::new())),
+            user_content_manager: None,
             window: None,
             window_rendering_context: None,
             offscreen_rendering_context: None,
             needs_repaint: Rc::new(Cell::new(false)),
+            load_started: Rc::new(Cell::new(None)),
+            pending_scroll: Rc::new(Cell::new(None)),
+            zoom: Rc::new(RefCell::new(ZoomLevels::default())),
+            pending_navigation: Rc::new(RefCell::new(None)),
+            pending_captures: RefCell::new(Vec::new()),
+            print_job: Rc::new(RefCell::new(None)),
+            frame_painted: Cell::new(false),
+            next_thumbnail_refresh: Cell::new(None),
+            headless_rendering_context: None,
+            exit_requested: Cell::new(false),
+            startup_failed: Cell::new(false),
             proxy,
             initial_url: url,
             initial_title: title,
             initial_size: (params.width, params.height),
+            start_hidden: params.start_hidden,
+            manual_title: params.manual_title,
+            intercept_close: params.intercept_close,
+            disable_file_uploads: params.disable_file_uploads,
+            frame_interval: (params.max_fps > 0)
+                .then(|| Duration::from_secs_f64(1.0 / params.max_fps as f64)),
+            resize_hold: Cell::new(None),
+            resize_frame: RefCell::new(None),
+            last_animation_tick: Cell::new(None),
+            last_paint: Cell::new(None),
+            frames_painted: Cell::new(0),
+            authentications: Rc::new(RefCell::new(PendingAuthentications::default())),
+            cert_errors: Rc::new(RefCell::new(PendingCertErrors::default())),
+            permissions: Rc::new(RefCell::new(PendingPermissions::default())),
+            find_webviews: Rc::new(RefCell::new(HashSet::new())),
+            animating_webviews: Rc::new(RefCell::new(HashSet::new())),
+            loading_webviews: Rc::new(RefCell::new(HashSet::new())),
+            stop_requested: Rc::new(Cell::new(false)),
+            close_requested_at: Cell::new(None),
+            fullscreen: Cell::new(false),
+            background: background_rgba(params.background_color, params.transparent),
+            transparent: params.transparent,
+            decorations: params.decorations,
+            accelerator_keys: RefCell::new(HashSet::new()),
+            os_theme: Cell::new(None),
+            hovered_files: RefCell::new(FileBatch::default()),
+            dropped_files: RefCell::new(FileBatch::default()),
+            file_hover_reported: Cell::new(false),
+            smooth_scrolling: params.smooth_scrolling,
+            smooth_scroll: RefCell::new(SmoothScroll::default()),
+            occluded: Cell::new(false),
+            minimized: Cell::new(false),
+            window_hidden: Cell::new(false),
             last_mouse_position: Cell::new(Point2D::origin()),
-            modifiers_state: Cell::new(Default::default()),
-            pressed_mouse_buttons: Cell::new(0),
+            modifiers_state: Rc::new(Cell::new(Default::default())),
+            pressed_mouse_buttons: Rc::new(Cell::new(0)),
+            active_touches: RefCell::new(HashSet::new()),
+            last_touch_end: Cell::new(None),
+            watchdog: Rc::new(RefCell::new(Watchdog::new(
+                (params.watchdog_timeout_ms > 0)
+                    .then(|| Duration::from_millis(params.watchdog_timeout_ms.into())),
+            ))),
+            status_text: Rc::new(RefCell::new(StatusText::default())),
+            queued_scripts: Rc::new(RefCell::new(queued_scripts)),
         };
 
+        if params.pump_events {
+            if matches!(main_loop, MainLoop::Headless(_)) && !app.start_headless_or_fail() {
+                return finish_app(&app, on_event_token, 0);
+            }
+            PUMPED_APP.with(|pumped| {
+                *pumped.borrow_mut() = Some(PumpedApp {
+                    main_loop,
+                    app,
+                    on_event_token,
+                })
+            });
+            return 0;
+        }
+
         // println!("[pw_servo] Entering event loop...");
-        event_loop.run_app(&mut app).unwrap();
+        let mut code = 0;
+        match main_loop {
+            MainLoop::Winit(event_loop) => {
+                if let Err(e) = event_loop.run_app(&mut app) {
+                    errors::set_last_error(format!("The event loop failed: {}", e));
+                    code = ErrorCode::WindowFailed.into();
+                }
+            }
+            MainLoop::Headless(events) => run_headless(&mut app, events),
+        }
+        finish_app(&app, on_event_token, code)
     });
 
     match res {
-        Ok(_) => 0,
-        Err(_) => -1,
+        Ok(code) => code,
+        Err(panic) => {
+            errors::set_last_error(format!("pw_start_app failed: {}", panic_message(&*panic)));
+            ErrorCode::Panicked.into()
+        }
     }
 }
+
+/// The message a caught panic was raised with.
+fn panic_message(panic: &(dyn std::any::Any + Send)) -> &str {
+    panic
+        .downcast_ref::<String>()
+        .map(String::as_str)
+        .or_else(|| panic.downcast_ref::<&str>().copied())
+        .unwrap_or("unknown panic")
+}
@@ -0,0 +1,101 @@
+//! Log output from Servo and from the shell itself.
+//!
+//! Both log through the `log` facade into one logger, which hands each record
+//! to the callback from `pw_set_log_callback`, or prints it to stderr when
+//! there is none. `InitParams::log_level` sets how much gets through; by
+//! default only warnings and errors do.
+
+use std::ffi::CString;
+use std::os::raw::c_char;
+use std::sync::{Mutex, Once};
+
+use log::{LevelFilter, Log, Metadata, Record};
+
+/// Receives each log record: its level (1 error, 2 warn, 3 info, 4 debug,
+/// 5 trace), its target (the module that logged it) and its message.
+pub type LogCallback = extern "C" fn(level: u32, target: *const c_char, message: *const c_char);
+
+pub const DEFAULT_LEVEL: LevelFilter = LevelFilter::Warn;
+
+static LOG_CALLBACK: Mutex<Option<LogCallback>> = Mutex::new(None);
+
+static INSTALL: Once = Once::new();
+
+struct ShellLogger;
+
+static LOGGER: ShellLogger = ShellLogger;
+
+impl Log for ShellLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let callback = *LOG_CALLBACK.lock().unwrap();
+        let Some(callback) = callback else {
+            eprintln!(
+                "[{}] {}: {}",
+                record.target(),
+                record.level(),
+                record.args()
+            );
+            return;
+        };
+        let to_c_string = |text: String| CString::new(text.replace('\0', " ")).unwrap_or_default();
+        let target = to_c_string(record.target().to_string());
+        let message = to_c_string(record.args().to_string());
+        callback(record.level() as u32, target.as_ptr(), message.as_ptr());
+    }
+
+    fn flush(&self) {}
+}
+
+/// Install the logger at the default level, if it isn't yet.
+pub fn install() {
+    INSTALL.call_once(|| {
+        if log::set_logger(&LOGGER).is_ok() {
+            log::set_max_level(DEFAULT_LEVEL);
+        }
+    });
+}
+
+/// Install the logger and let records up to `level` through.
+pub fn init(level: LevelFilter) {
+    install();
+    log::set_max_level(level);
+}
+
+/// Send records to `callback` from now on, or back to stderr with `None`.
+pub fn set_callback(callback: Option<LogCallback>) {
+    install();
+    *LOG_CALLBACK.lock().unwrap() = callback;
+}
+
+/// The level called `name` for `InitParams::log_level`.
+pub fn parse_level(name: &str) -> Option<LevelFilter> {
+    match name.to_ascii_lowercase().as_str() {
+        "off" => Some(LevelFilter::Off),
+        "error" => Some(LevelFilter::Error),
+        "warn" | "warning" => Some(LevelFilter::Warn),
+        "info" => Some(LevelFilter::Info),
+        "debug" => Some(LevelFilter::Debug),
+        "trace" => Some(LevelFilter::Trace),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_level_names() {
+        assert_eq!(parse_level("off"), Some(LevelFilter::Off));
+        assert_eq!(parse_level("Warning"), Some(LevelFilter::Warn));
+        assert_eq!(parse_level("debug"), Some(LevelFilter::Debug));
+        assert_eq!(parse_level("verbose"), None);
+    }
+}
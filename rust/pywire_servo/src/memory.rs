@@ -0,0 +1,64 @@
+//! Memory usage reports for `pw_get_memory_report`.
+//!
+//! Servo measures memory asynchronously: each of its processes reports its
+//! allocations as a list of measurements, each with a path naming what was
+//! measured (`["js", "gc-heap"]`), the kind of memory and its size in bytes.
+//! The shell passes those along and adds a summary for monitoring.
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use serde_json::{json, Value};
+
+/// How long to wait for the engine's processes to report before the request
+/// fails.
+pub const REPORT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Totals over Servo's serialized memory report: `explicit` sums the memory
+/// the engine allocated itself, `by_category` splits it by the first part of
+/// each measurement's path. Measurements of other kinds (such as the resident
+/// set size) overlap with those and are left out.
+pub fn summarize(report: &Value) -> Value {
+    let mut explicit: u64 = 0;
+    let mut by_category: BTreeMap<String, u64> = BTreeMap::new();
+    let processes = report["results"].as_array().map_or(&[][..], Vec::as_slice);
+    for process in processes {
+        let measurements = process["reports"].as_array().map_or(&[][..], Vec::as_slice);
+        for measurement in measurements {
+            let kind = measurement["kind"].as_str().unwrap_or_default();
+            if !kind.starts_with("Explicit") {
+                continue;
+            }
+            let size = measurement["size"].as_u64().unwrap_or(0);
+            explicit += size;
+            let category = measurement["path"][0].as_str().unwrap_or("other");
+            *by_category.entry(category.to_string()).or_default() += size;
+        }
+    }
+    json!({"explicit": explicit, "by_category": by_category})
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sums_explicit_memory_by_category() {
+        let report = json!({"results": [
+            {"pid": 1, "is_main_process": true, "reports": [
+                {"path": ["js", "gc-heap"], "kind": "ExplicitJemallocHeapSize", "size": 300},
+                {"path": ["js", "malloc"], "kind": "ExplicitSystemHeapSize", "size": 100},
+                {"path": ["layout"], "kind": "ExplicitUnknownLocationSize", "size": 50},
+                {"path": ["resident"], "kind": "NonExplicitSize", "size": 9000},
+            ]},
+        ]});
+        assert_eq!(
+            summarize(&report),
+            json!({"explicit": 450, "by_category": {"js": 400, "layout": 50}})
+        );
+        assert_eq!(
+            summarize(&json!({})),
+            json!({"explicit": 0, "by_category": {}})
+        );
+    }
+}
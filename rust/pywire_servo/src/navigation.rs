@@ -0,0 +1,72 @@
+//! Following one navigation to its end for `pw_navigate_with_callback`, and
+//! what to do with navigations into new windows.
+
+use std::ffi::CString;
+use std::os::raw::c_char;
+use std::sync::Mutex;
+
+/// Invoked once when the navigation ends: `success` is true when the page
+/// finished loading, otherwise `status` says why it didn't (`"timeout"`,
+/// `"stopped"`, `"superseded"`, ...). `status` is only valid for the call.
+pub type NavigationCallback = extern "C" fn(success: bool, status: *const c_char);
+
+/// A navigation whose outcome someone is waiting for. The callback fires
+/// exactly once: through [`PendingNavigation::finish`], or with
+/// `"cancelled"` if the navigation is dropped unresolved (e.g. at shutdown).
+#[derive(Debug)]
+pub struct PendingNavigation {
+    callback: Option<NavigationCallback>,
+    /// Whether the load for this navigation has started; a further start
+    /// before it completes means another navigation took over.
+    pub started: bool,
+}
+
+impl PendingNavigation {
+    pub fn new(callback: NavigationCallback) -> Self {
+        PendingNavigation {
+            callback: Some(callback),
+            started: false,
+        }
+    }
+
+    pub fn finish(mut self, success: bool, status: &str) {
+        if let Some(callback) = self.callback.take() {
+            invoke(callback, success, status);
+        }
+    }
+}
+
+impl Drop for PendingNavigation {
+    fn drop(&mut self) {
+        if let Some(callback) = self.callback.take() {
+            invoke(callback, false, "cancelled");
+        }
+    }
+}
+
+fn invoke(callback: NavigationCallback, success: bool, status: &str) {
+    let status = CString::new(status).unwrap_or_default();
+    callback(success, status.as_ptr());
+}
+
+/// What happens when a page opens a new window, with `window.open` or a
+/// `target="_blank"` link. Either way a `new_window` event reports it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NewWindowPolicy {
+    /// Open the page in a new webview and show it, like a browser tab.
+    Tab,
+    /// Don't open anything; the app decides what to do from the event.
+    Event,
+}
+
+impl NewWindowPolicy {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "tab" => Some(Self::Tab),
+            "event" => Some(Self::Event),
+            _ => None,
+        }
+    }
+}
+
+pub static NEW_WINDOW_POLICY: Mutex<NewWindowPolicy> = Mutex::new(NewWindowPolicy::Tab);
@@ -0,0 +1,230 @@
+//! Loads that fail, reported as `navigation_failed` events.
+//!
+//! Servo has no delegate hook for a load that fails to reach the server (the
+//! host doesn't resolve, the connection is refused, ...). It answers the
+//! request with its `neterror.html` resource instead, filling in the reason.
+//! The shell serves its own version of that page (`js/neterror.html`), which
+//! reports the failure, and which stays empty with
+//! `InitParams::hide_error_pages` so the app can show its own error UI.
+//!
+//! Apps can also supply their own page for each kind of error with
+//! `pw_set_error_page`, including TLS errors, whose page is
+//! `js/badcert.html`. The shell's page replaces itself with the app's once
+//! parsed, so the document keeps the URL that failed and reloading it retries
+//! the original request.
+//!
+//! Each error page carries a nonce that its report must return, and the
+//! report is only taken from the webview showing the failed URL, so a page
+//! can't fake a failure by logging the marker itself.
+//!
+//! A top-level page answered with an HTTP error status (4xx or 5xx) is shown
+//! as the server sent it, as in browsers, but is reported as a failed load
+//! too. Servo has no delegate hook for the status either, so the shell reads
+//! it from the page's navigation timing entry once the load completes.
+
+use std::collections::hash_map::RandomState;
+use std::collections::{BTreeMap, VecDeque};
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const NET_ERROR_HTML: &str = include_str!("js/neterror.html");
+const ERROR_PAGE_JS: &str = include_str!("js/error_page.js");
+/// Evaluates to the HTTP status of the page's response, or `0` where the
+/// engine doesn't report it.
+pub const HTTP_STATUS_JS: &str = include_str!("js/http_status.js");
+
+/// What kind of failure an app's error page is for.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ErrorKind {
+    /// Any network failure. Also shown for DNS failures without a page of
+    /// their own.
+    Network,
+    /// The host name didn't resolve.
+    Dns,
+    /// The server's certificate failed verification.
+    Tls,
+}
+
+impl ErrorKind {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "network" => Some(Self::Network),
+            "dns" => Some(Self::Dns),
+            "tls" => Some(Self::Tls),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Network => "network",
+            Self::Dns => "dns",
+            Self::Tls => "tls",
+        }
+    }
+}
+
+/// The app's HTML for each kind of error, from `pw_set_error_page`.
+static ERROR_PAGES: Mutex<BTreeMap<&'static str, String>> = Mutex::new(BTreeMap::new());
+
+/// Show `html` for errors of `kind` from now on, or the shell's page again
+/// for `None`.
+pub fn set_error_page(kind: ErrorKind, html: Option<String>) {
+    let mut pages = ERROR_PAGES.lock().unwrap();
+    match html {
+        Some(html) => pages.insert(kind.name(), html),
+        None => pages.remove(kind.name()),
+    };
+}
+
+/// Fill in the script that shows the app's error pages in the shell's error
+/// page `html`.
+pub fn with_custom_pages(html: &str) -> String {
+    let pages = serde_json::to_string(&*ERROR_PAGES.lock().unwrap()).unwrap();
+    // The pages are inside a <script> element, which must not end early.
+    let pages = pages.replace("</", "<\\/");
+    html.replace("__ERROR_PAGE_JS__", ERROR_PAGE_JS)
+        .replace("__PAGES__", &pages)
+}
+
+/// Whether the network error page is left empty, from
+/// `InitParams::hide_error_pages`.
+pub static HIDE_ERROR_PAGES: AtomicBool = AtomicBool::new(false);
+
+/// The nonces of error pages served but not reported yet. Bounded, since a
+/// page that is never parsed never reports.
+static ISSUED_NONCES: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+const MAX_ISSUED_NONCES: usize = 16;
+
+/// A fresh nonce for an error page, recorded as issued.
+pub fn issue_nonce() -> String {
+    // `RandomState` is keyed randomly, so its hashes can't be predicted.
+    let mut hasher = RandomState::new().build_hasher();
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    hasher.write_u128(now.as_nanos());
+    let nonce = format!("{:016x}", hasher.finish());
+    let mut issued = ISSUED_NONCES.lock().unwrap();
+    if issued.len() == MAX_ISSUED_NONCES {
+        issued.pop_front();
+    }
+    issued.push_back(nonce.clone());
+    nonce
+}
+
+/// Whether `nonce` came from an error page that hasn't reported yet. Each
+/// nonce is accepted once.
+pub fn take_nonce(nonce: &str) -> bool {
+    let mut issued = ISSUED_NONCES.lock().unwrap();
+    match issued.iter().position(|issued| issued == nonce) {
+        Some(index) => issued.remove(index).is_some(),
+        None => false,
+    }
+}
+
+/// The page Servo shows in place of one that failed to load.
+pub fn page() -> Vec<u8> {
+    let hidden = HIDE_ERROR_PAGES.load(Ordering::Relaxed);
+    let html = NET_ERROR_HTML
+        .replace("__HIDDEN__", &hidden.to_string())
+        .replace("__NONCE__", &issue_nonce());
+    with_custom_pages(&html).into_bytes()
+}
+
+/// Whether a page answered with `status` counts as a failed load.
+pub fn is_http_error(status: u16) -> bool {
+    (400..600).contains(&status)
+}
+
+/// What the network error page reports after its `PW_NETERR:` marker.
+#[derive(Debug, PartialEq)]
+pub struct LoadFailure {
+    /// The nonce the page was served with, for [`take_nonce`].
+    pub nonce: String,
+    pub url: String,
+    pub error: String,
+    /// `"dns"` or `"network"`.
+    pub kind: String,
+}
+
+pub fn parse_report(payload: &str) -> Option<LoadFailure> {
+    let report: serde_json::Value = serde_json::from_str(payload).ok()?;
+    Some(LoadFailure {
+        nonce: report.get("nonce")?.as_str()?.to_string(),
+        url: report.get("url")?.as_str()?.to_string(),
+        error: report
+            .get("error")
+            .and_then(|error| error.as_str())
+            .unwrap_or_default()
+            .trim()
+            .to_string(),
+        kind: report
+            .get("kind")
+            .and_then(|kind| kind.as_str())
+            .unwrap_or("network")
+            .to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_the_page_report() {
+        assert_eq!(
+            parse_report(
+                r#"{"nonce": "ab12", "url": "https://nx.example/", "error": " DNS failure \n", "kind": "dns"}"#
+            ),
+            Some(LoadFailure {
+                nonce: "ab12".into(),
+                url: "https://nx.example/".into(),
+                error: "DNS failure".into(),
+                kind: "dns".into(),
+            })
+        );
+        assert_eq!(parse_report(r#"{"error": "DNS failure"}"#), None);
+        assert_eq!(
+            parse_report(r#"{"url": "https://nx.example/", "error": "DNS failure"}"#),
+            None
+        );
+        assert_eq!(parse_report("not json"), None);
+    }
+
+    #[test]
+    fn accepts_each_served_nonce_once() {
+        assert!(!String::from_utf8(page()).unwrap().contains("__NONCE__"));
+        let nonce = issue_nonce();
+        assert!(!take_nonce("0000000000000000"));
+        assert!(take_nonce(&nonce));
+        assert!(!take_nonce(&nonce));
+    }
+
+    #[test]
+    fn only_error_statuses_fail() {
+        assert!(is_http_error(404));
+        assert!(is_http_error(503));
+        assert!(!is_http_error(200));
+        assert!(!is_http_error(304));
+        assert!(!is_http_error(0));
+    }
+
+    #[test]
+    fn embeds_custom_pages_in_the_script() {
+        set_error_page(
+            ErrorKind::Tls,
+            Some("<p>{{url}}</p><script></script>".into()),
+        );
+        let page = with_custom_pages("<script>show(__PAGES__)</script>");
+        set_error_page(ErrorKind::Tls, None);
+        assert_eq!(
+            page,
+            r#"<script>show({"tls":"<p>{{url}}<\/p><script><\/script>"})</script>"#
+        );
+        assert_eq!(ErrorKind::from_name("dns"), Some(ErrorKind::Dns));
+        assert_eq!(ErrorKind::from_name("http"), None);
+    }
+}
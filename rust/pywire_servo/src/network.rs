@@ -0,0 +1,211 @@
+//! Request-level policies applied to every fetch the webview makes.
+//!
+//! Servo hands each outgoing request to `WebViewDelegate::load_web_resource`
+//! before it hits the network, which lets the shell block or redirect it. The
+//! policies live in a global so they can be configured from Python before
+//! `pw_start_app` as well as while the app is running.
+//!
+//! The request itself is read-only there: Servo doesn't let the embedder add
+//! or change request headers, neither for navigations nor for subresources,
+//! so the shell has no way to send e.g. an `Authorization` header a gateway
+//! expects. Apps behind such a gateway can answer its challenges through
+//! `auth_required` and `pw_provide_credentials`, or route the traffic through
+//! a local proxy that adds the header (`InitParams::proxy_url`).
+
+use std::sync::Mutex;
+
+use http::header::LOCATION;
+use http::{HeaderMap, HeaderValue, StatusCode};
+use regex::Regex;
+use servo::{WebResourceLoad, WebResourceResponse};
+use url::Url;
+
+use crate::encoding;
+use crate::events::{emit_event, EventType};
+
+/// How insecure (`http:`) subresources on secure (`https:`) pages are treated.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MixedContentPolicy {
+    /// Cancel the request and emit a `mixed_content_blocked` event.
+    Block,
+    /// Let the request through unchanged.
+    Allow,
+    /// Redirect the request to the same URL over `https:`.
+    Upgrade,
+}
+
+impl MixedContentPolicy {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "block" => Some(Self::Block),
+            "allow" => Some(Self::Allow),
+            "upgrade" => Some(Self::Upgrade),
+            _ => None,
+        }
+    }
+}
+
+/// Caching strategy for top-level navigations.
+///
+/// Servo does not let the embedder set a request's cache mode, so only the
+/// strategies the shell can carry out from outside are offered: `"reload"`
+/// and `"force-cache"`, which would need per-request control, are rejected.
+/// A page's own `Cache-Control` headers still decide what gets stored, and
+/// whether a stored entry needs revalidation.
+///
+/// `"no-store"` is approximated by clearing the HTTP cache around the active
+/// webview's navigations. Responses are still stored while a page loads, so
+/// its subresources may be served from the cache, and as the cache is shared,
+/// clearing it also drops what other webviews stored.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CacheMode {
+    /// Normal HTTP caching, as directed by the page's headers.
+    Default,
+    /// Clear the HTTP cache when the active webview navigates, and again once
+    /// that page has finished loading.
+    NoStore,
+}
+
+impl CacheMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "default" => Some(Self::Default),
+            "no-store" => Some(Self::NoStore),
+            _ => None,
+        }
+    }
+
+    /// Whether the HTTP cache should be cleared before the active webview
+    /// navigates.
+    pub fn clears_before_navigation(self) -> bool {
+        self == CacheMode::NoStore
+    }
+
+    /// Whether the HTTP cache should be cleared once the active webview's load
+    /// completes.
+    pub fn clears_after_load(self) -> bool {
+        self == CacheMode::NoStore
+    }
+}
+
+/// Rewrites request URLs matching `pattern`, e.g. to point a CDN at a local
+/// mirror. `replacement` may refer to capture groups as `$1` or `${name}`.
+pub struct UrlRewrite {
+    pub pattern: Regex,
+    pub replacement: String,
+}
+
+pub struct NetworkPolicy {
+    pub mixed_content: MixedContentPolicy,
+    pub cache_mode: CacheMode,
+    /// Tried in the order they were added; the first matching rule wins.
+    pub url_rewrites: Vec<UrlRewrite>,
+}
+
+impl NetworkPolicy {
+    const fn new() -> Self {
+        NetworkPolicy {
+            mixed_content: MixedContentPolicy::Block,
+            cache_mode: CacheMode::Default,
+            url_rewrites: Vec::new(),
+        }
+    }
+
+    /// The rewritten form of `url`, if any rule matches and changes it.
+    fn rewrite(&self, url: &Url) -> Option<Url> {
+        let rule = self
+            .url_rewrites
+            .iter()
+            .find(|rule| rule.pattern.is_match(url.as_str()))?;
+        let rewritten = rule
+            .pattern
+            .replace(url.as_str(), rule.replacement.as_str());
+        Url::parse(&rewritten)
+            .ok()
+            .filter(|rewritten| rewritten != url)
+    }
+}
+
+pub static NETWORK_POLICY: Mutex<NetworkPolicy> = Mutex::new(NetworkPolicy::new());
+
+/// Whether `url` is an insecure subresource of the secure page at `page_url`.
+/// Loopback hosts are potentially trustworthy and never count as mixed content.
+fn is_mixed_content(url: &Url, page_url: &Url) -> bool {
+    let secure_page = matches!(page_url.scheme(), "https" | "wss");
+    let insecure_request = matches!(url.scheme(), "http" | "ws");
+    let loopback = matches!(
+        url.host_str(),
+        Some("localhost") | Some("127.0.0.1") | Some("[::1]")
+    );
+    secure_page && insecure_request && !loopback
+}
+
+/// Apply the current policies to `load`. Dropping `load` without intercepting
+/// it lets Servo fetch it normally.
+pub fn handle_load(load: WebResourceLoad, page_url: Option<Url>) {
+    let request = load.request();
+    let rewritten = NETWORK_POLICY.lock().unwrap().rewrite(&request.url);
+    if let Some(rewritten) = rewritten {
+        let url = request.url.clone();
+        log::debug!("Rewriting {} to {}", url, rewritten);
+        redirect(load, url, &rewritten);
+        return;
+    }
+    let Some(load) = encoding::handle_load(load) else {
+        return;
+    };
+    let request = load.request();
+
+    if request.is_for_main_frame {
+        return;
+    }
+    let Some(page_url) = page_url else {
+        return;
+    };
+    if !is_mixed_content(&request.url, &page_url) {
+        return;
+    }
+
+    let url = request.url.clone();
+    let policy = NETWORK_POLICY.lock().unwrap().mixed_content;
+    match policy {
+        MixedContentPolicy::Allow => {}
+        MixedContentPolicy::Block => {
+            log::info!("Blocked mixed content: {}", url);
+            emit_event(
+                EventType::Network,
+                serde_json::json!({
+                    "type": "mixed_content_blocked",
+                    "url": url.as_str(),
+                    "page_url": page_url.as_str(),
+                }),
+            );
+            load.intercept(WebResourceResponse::new(url)).cancel();
+        }
+        MixedContentPolicy::Upgrade => {
+            let mut upgraded = url.clone();
+            let scheme = if url.scheme() == "ws" { "wss" } else { "https" };
+            if upgraded.set_scheme(scheme).is_err() {
+                return;
+            }
+            // Only drop the port when it was the insecure default.
+            if upgraded.port() == Some(80) {
+                let _ = upgraded.set_port(None);
+            }
+            redirect(load, url, &upgraded);
+        }
+    }
+}
+
+/// Answer `load` with a temporary redirect to `target`.
+fn redirect(load: WebResourceLoad, url: Url, target: &Url) {
+    let Ok(location) = HeaderValue::from_str(target.as_str()) else {
+        return;
+    };
+    let mut headers = HeaderMap::new();
+    headers.insert(LOCATION, location);
+    let response = WebResourceResponse::new(url)
+        .status_code(StatusCode::TEMPORARY_REDIRECT)
+        .headers(headers);
+    load.intercept(response).finish();
+}
@@ -0,0 +1,184 @@
+//! Writing captured pages to a PDF file.
+//!
+//! Servo has no print path of its own, so a PDF is made of screenshots: each
+//! page is one image, drawn across the full page width. Text in the result
+//! is not selectable.
+
+use std::fmt::Write as _;
+
+use crate::capture::Frame;
+
+/// Paper sizes `pw_print_to_pdf` accepts.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PageSize {
+    A4,
+    Letter,
+}
+
+impl PageSize {
+    pub fn parse(name: &str) -> Option<PageSize> {
+        match name.to_ascii_lowercase().as_str() {
+            "a4" => Some(PageSize::A4),
+            "letter" => Some(PageSize::Letter),
+            _ => None,
+        }
+    }
+
+    /// Width and height in PDF points (1/72 inch).
+    pub fn points(self) -> (f64, f64) {
+        match self {
+            PageSize::A4 => (595.28, 841.89),
+            PageSize::Letter => (612.0, 792.0),
+        }
+    }
+}
+
+/// Encode `pages` as a PDF document, one image per page, each scaled to the
+/// page width and aligned to its top edge.
+pub fn encode(pages: &[Frame], size: PageSize) -> Vec<u8> {
+    let (page_width, page_height) = size.points();
+    let mut pdf = b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n".to_vec();
+    let mut offsets = Vec::new();
+
+    let mut add_object = |pdf: &mut Vec<u8>, body: &[u8]| {
+        offsets.push(pdf.len());
+        pdf.extend_from_slice(format!("{} 0 obj\n", offsets.len()).as_bytes());
+        pdf.extend_from_slice(body);
+        pdf.extend_from_slice(b"\nendobj\n");
+    };
+
+    // Objects 1 and 2 are the catalog and page tree; page `i` is object
+    // `3 + 3i`, followed by its content stream and its image.
+    let page_ids: Vec<usize> = (0..pages.len()).map(|i| 3 + 3 * i).collect();
+    let kids = page_ids.iter().fold(String::new(), |mut kids, id| {
+        let _ = write!(kids, "{} 0 R ", id);
+        kids
+    });
+    add_object(&mut pdf, b"<< /Type /Catalog /Pages 2 0 R >>");
+    add_object(
+        &mut pdf,
+        format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            kids.trim_end(),
+            pages.len()
+        )
+        .as_bytes(),
+    );
+
+    for (page, id) in pages.iter().zip(page_ids) {
+        add_object(
+            &mut pdf,
+            format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {page_width} {page_height}] \
+                 /Resources << /XObject << /Im0 {} 0 R >> >> /Contents {} 0 R >>",
+                id + 2,
+                id + 1
+            )
+            .as_bytes(),
+        );
+
+        let height = page_width * page.height as f64 / page.width.max(1) as f64;
+        let content = format!(
+            "q {:.2} 0 0 {:.2} 0 {:.2} cm /Im0 Do Q",
+            page_width,
+            height,
+            page_height - height
+        );
+        add_object(
+            &mut pdf,
+            format!(
+                "<< /Length {} >>\nstream\n{}\nendstream",
+                content.len(),
+                content
+            )
+            .as_bytes(),
+        );
+
+        let rgb: Vec<u8> = page
+            .rgba
+            .chunks_exact(4)
+            .flat_map(|pixel| [pixel[0], pixel[1], pixel[2]])
+            .collect();
+        let data = miniz_oxide::deflate::compress_to_vec_zlib(&rgb, 6);
+        let mut image = format!(
+            "<< /Type /XObject /Subtype /Image /Width {} /Height {} \
+             /ColorSpace /DeviceRGB /BitsPerComponent 8 /Filter /FlateDecode \
+             /Length {} >>\nstream\n",
+            page.width,
+            page.height,
+            data.len()
+        )
+        .into_bytes();
+        image.extend_from_slice(&data);
+        image.extend_from_slice(b"\nendstream");
+        add_object(&mut pdf, &image);
+    }
+
+    let xref = pdf.len();
+    let mut table = format!("xref\n0 {}\n0000000000 65535 f \n", offsets.len() + 1);
+    for offset in &offsets {
+        let _ = writeln!(table, "{:010} 00000 n ", offset);
+    }
+    let _ = write!(
+        table,
+        "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+        offsets.len() + 1,
+        xref
+    );
+    pdf.extend_from_slice(table.as_bytes());
+    pdf
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(width: u32, height: u32) -> Frame {
+        Frame {
+            width,
+            height,
+            rgba: vec![200; width as usize * height as usize * 4],
+        }
+    }
+
+    fn contains(haystack: &[u8], needle: &str) -> bool {
+        haystack
+            .windows(needle.len())
+            .any(|window| window == needle.as_bytes())
+    }
+
+    #[test]
+    fn parses_page_sizes() {
+        assert_eq!(PageSize::parse("A4"), Some(PageSize::A4));
+        assert_eq!(PageSize::parse("letter"), Some(PageSize::Letter));
+        assert_eq!(PageSize::parse("legal"), None);
+    }
+
+    #[test]
+    fn writes_one_page_per_frame() {
+        let pdf = encode(&[frame(4, 6), frame(4, 2)], PageSize::Letter);
+        assert!(pdf.starts_with(b"%PDF-1.4\n"));
+        assert!(pdf.ends_with(b"%%EOF\n"));
+        assert!(contains(&pdf, "/Kids [3 0 R 6 0 R] /Count 2"));
+        assert!(contains(&pdf, "/MediaBox [0 0 612 792]"));
+        assert!(contains(&pdf, "/Width 4 /Height 2"));
+    }
+
+    #[test]
+    fn cross_reference_table_points_at_objects() {
+        let pdf = encode(&[frame(2, 2)], PageSize::A4);
+        let text = String::from_utf8_lossy(&pdf);
+        let xref = text.rfind("\nxref\n").unwrap() + 1;
+        let entries: Vec<usize> = text[xref..]
+            .lines()
+            .skip(3)
+            .take_while(|line| line.ends_with(" n "))
+            .map(|line| line[..10].parse().unwrap())
+            .collect();
+        assert_eq!(entries.len(), 5);
+        for (index, offset) in entries.into_iter().enumerate() {
+            let header = format!("{} 0 obj", index + 1);
+            assert_eq!(&pdf[offset..offset + header.len()], header.as_bytes());
+        }
+    }
+}
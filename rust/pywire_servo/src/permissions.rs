@@ -0,0 +1,217 @@
+//! Permission prompts (geolocation, notifications, camera, ...) waiting for
+//! Python to answer.
+//!
+//! Every prompt is reported as a `permission` event and held until
+//! `pw_respond_permission` answers it. Prompts nobody answers in time are
+//! denied, so an app that doesn't handle the event never grants anything.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use servo::{PermissionFeature, PermissionRequest};
+
+/// How long a prompt may go unanswered before it is denied, in milliseconds.
+pub static PERMISSION_TIMEOUT_MS: AtomicU64 = AtomicU64::new(30_000);
+
+pub fn timeout() -> Duration {
+    Duration::from_millis(PERMISSION_TIMEOUT_MS.load(Ordering::Relaxed))
+}
+
+/// A prompt that can be answered once.
+pub trait Prompt {
+    fn allow(self);
+    fn deny(self);
+}
+
+impl Prompt for PermissionRequest {
+    fn allow(self) {
+        PermissionRequest::allow(self)
+    }
+
+    fn deny(self) {
+        PermissionRequest::deny(self)
+    }
+}
+
+/// The `kind` reported in `permission` events.
+pub fn feature_name(feature: PermissionFeature) -> &'static str {
+    match feature {
+        PermissionFeature::Geolocation => "geolocation",
+        PermissionFeature::Notifications => "notifications",
+        PermissionFeature::Push => "push",
+        PermissionFeature::Midi => "midi",
+        PermissionFeature::Camera => "camera",
+        PermissionFeature::Microphone => "microphone",
+        PermissionFeature::Speaker => "speaker",
+        PermissionFeature::DeviceInfo => "device_info",
+        PermissionFeature::BackgroundSync => "background_sync",
+        PermissionFeature::Bluetooth => "bluetooth",
+        PermissionFeature::PersistentStorage => "persistent_storage",
+    }
+}
+
+struct Pending<P> {
+    webview_id: u32,
+    deadline: Instant,
+    prompt: P,
+}
+
+pub struct PendingPermissions<P> {
+    /// Ids start at 1 so that 0 can signal failure across the FFI.
+    last_id: u32,
+    prompts: HashMap<u32, Pending<P>>,
+}
+
+impl<P> Default for PendingPermissions<P> {
+    fn default() -> Self {
+        PendingPermissions {
+            last_id: 0,
+            prompts: HashMap::new(),
+        }
+    }
+}
+
+impl<P: Prompt> PendingPermissions<P> {
+    /// Hold `prompt` from webview `webview_id` until `deadline`, and return
+    /// its id.
+    pub fn add(&mut self, webview_id: u32, prompt: P, deadline: Instant) -> u32 {
+        self.last_id += 1;
+        self.prompts.insert(
+            self.last_id,
+            Pending {
+                webview_id,
+                deadline,
+                prompt,
+            },
+        );
+        self.last_id
+    }
+
+    /// Answer prompt `id`. Returns false if it is unknown or already
+    /// answered.
+    pub fn respond(&mut self, id: u32, allow: bool) -> bool {
+        let Some(pending) = self.prompts.remove(&id) else {
+            return false;
+        };
+        if allow {
+            pending.prompt.allow();
+        } else {
+            pending.prompt.deny();
+        }
+        true
+    }
+
+    /// Deny the prompts whose deadline has passed, returning their ids.
+    pub fn expire(&mut self, now: Instant) -> Vec<u32> {
+        let mut expired: Vec<u32> = self
+            .prompts
+            .iter()
+            .filter(|(_, pending)| pending.deadline <= now)
+            .map(|(id, _)| *id)
+            .collect();
+        expired.sort_unstable();
+        for id in &expired {
+            self.respond(*id, false);
+        }
+        expired
+    }
+
+    /// When the next unanswered prompt expires.
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.prompts.values().map(|pending| pending.deadline).min()
+    }
+
+    /// Deny the prompts of a webview that is going away.
+    pub fn deny_for_webview(&mut self, webview_id: u32) {
+        let ids: Vec<u32> = self
+            .prompts
+            .iter()
+            .filter(|(_, pending)| pending.webview_id == webview_id)
+            .map(|(id, _)| *id)
+            .collect();
+        for id in ids {
+            self.respond(id, false);
+        }
+    }
+
+    pub fn deny_all(&mut self) {
+        for (_, pending) in self.prompts.drain() {
+            pending.prompt.deny();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    /// Records the answer it was given.
+    struct FakePrompt(Rc<Cell<Option<bool>>>);
+
+    impl Prompt for FakePrompt {
+        fn allow(self) {
+            self.0.set(Some(true));
+        }
+
+        fn deny(self) {
+            self.0.set(Some(false));
+        }
+    }
+
+    fn prompt() -> (FakePrompt, Rc<Cell<Option<bool>>>) {
+        let answer = Rc::new(Cell::new(None));
+        (FakePrompt(answer.clone()), answer)
+    }
+
+    #[test]
+    fn unanswered_prompt_is_denied_after_the_timeout() {
+        let timeout = Duration::from_millis(500);
+        let start = Instant::now();
+        let mut pending = PendingPermissions::default();
+        let (fake, answer) = prompt();
+        let id = pending.add(1, fake, start + timeout);
+
+        assert_eq!(pending.next_deadline(), Some(start + timeout));
+        assert!(pending.expire(start + timeout / 2).is_empty());
+        assert_eq!(answer.get(), None);
+
+        assert_eq!(pending.expire(start + timeout), vec![id]);
+        assert_eq!(answer.get(), Some(false));
+        assert_eq!(pending.next_deadline(), None);
+        // Too late to grant it now.
+        assert!(!pending.respond(id, true));
+    }
+
+    #[test]
+    fn answers_reach_the_prompt_once() {
+        let deadline = Instant::now() + Duration::from_secs(30);
+        let mut pending = PendingPermissions::default();
+        let (granted, granted_answer) = prompt();
+        let (refused, refused_answer) = prompt();
+        let granted_id = pending.add(1, granted, deadline);
+        let refused_id = pending.add(1, refused, deadline);
+
+        assert!(pending.respond(granted_id, true));
+        assert!(pending.respond(refused_id, false));
+        assert!(!pending.respond(granted_id, false));
+        assert_eq!(granted_answer.get(), Some(true));
+        assert_eq!(refused_answer.get(), Some(false));
+    }
+
+    #[test]
+    fn closing_a_webview_denies_its_prompts() {
+        let deadline = Instant::now() + Duration::from_secs(30);
+        let mut pending = PendingPermissions::default();
+        let (closed, closed_answer) = prompt();
+        let (open, open_answer) = prompt();
+        pending.add(1, closed, deadline);
+        pending.add(2, open, deadline);
+
+        pending.deny_for_webview(1);
+        assert_eq!(closed_answer.get(), Some(false));
+        assert_eq!(open_answer.get(), None);
+    }
+}
@@ -0,0 +1,540 @@
+//! Native window integration that winit doesn't cover.
+//!
+//! Each function takes the winit window and reaches for the platform window
+//! behind it (`NSWindow` on macOS, the `HWND` on Windows). Where a platform
+//! has no equivalent the call logs a note and does nothing, so apps can use
+//! the same code everywhere.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use winit::event_loop::EventLoopBuilder;
+use winit::window::Window;
+
+/// Shadow and corner styling for custom-chrome windows. `None` leaves the
+/// platform default alone.
+pub struct WindowChrome {
+    pub shadow: Option<bool>,
+    pub corner_radius: Option<f64>,
+}
+
+pub static WINDOW_CHROME: Mutex<WindowChrome> = Mutex::new(WindowChrome {
+    shadow: None,
+    corner_radius: None,
+});
+
+/// Apply the current [`WINDOW_CHROME`] settings to `window`.
+pub fn apply_window_chrome(window: &Window) {
+    let (shadow, corner_radius) = {
+        let chrome = WINDOW_CHROME.lock().unwrap();
+        (chrome.shadow, chrome.corner_radius)
+    };
+    if let Some(enabled) = shadow {
+        set_window_shadow(window, enabled);
+    }
+    if let Some(radius) = corner_radius {
+        set_corner_radius(window, radius);
+    }
+}
+
+/// What a taskbar progress indicator shows.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ProgressState {
+    /// No indicator.
+    None,
+    /// A bar filled to the given fraction.
+    Normal,
+    /// Activity of unknown length.
+    Indeterminate,
+    /// A bar filled to the given fraction, shown as paused (yellow on Windows).
+    Paused,
+    /// A bar filled to the given fraction, shown as failed (red on Windows).
+    Error,
+}
+
+impl ProgressState {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "none" => Some(Self::None),
+            "normal" => Some(Self::Normal),
+            "indeterminate" => Some(Self::Indeterminate),
+            "paused" => Some(Self::Paused),
+            "error" => Some(Self::Error),
+            _ => None,
+        }
+    }
+}
+
+/// Whether the window can be kept above or below other windows. Wayland
+/// leaves stacking to the compositor, so winit ignores window levels there.
+pub fn supports_window_level(window: &Window) -> bool {
+    use raw_window_handle::{HasWindowHandle, RawWindowHandle};
+
+    !matches!(
+        window.window_handle().map(|handle| handle.as_raw()),
+        Ok(RawWindowHandle::Wayland(_))
+    )
+}
+
+/// Let the event loop run on the current thread. Only macOS insists on the
+/// main thread: there this returns false when called from another one, where
+/// winit would panic. Elsewhere any thread will do.
+#[cfg(target_os = "macos")]
+pub fn allow_event_loop_thread<T>(_builder: &mut EventLoopBuilder<T>) -> bool {
+    objc2_foundation::MainThreadMarker::new().is_some()
+}
+
+#[cfg(target_os = "windows")]
+pub fn allow_event_loop_thread<T>(builder: &mut EventLoopBuilder<T>) -> bool {
+    use winit::platform::windows::EventLoopBuilderExtWindows;
+    builder.with_any_thread(true);
+    true
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+pub fn allow_event_loop_thread<T>(builder: &mut EventLoopBuilder<T>) -> bool {
+    // Sets the same flag for Wayland.
+    use winit::platform::x11::EventLoopBuilderExtX11;
+    builder.with_any_thread(true);
+    true
+}
+
+/// Whether the taskbar progress follows the page's load progress.
+pub static AUTO_TASKBAR_PROGRESS: AtomicBool = AtomicBool::new(false);
+
+#[cfg(target_os = "macos")]
+fn ns_view(window: &Window) -> Option<&objc2_app_kit::NSView> {
+    use raw_window_handle::{HasWindowHandle, RawWindowHandle};
+
+    let handle = window.window_handle().ok()?;
+    let RawWindowHandle::AppKit(handle) = handle.as_raw() else {
+        return None;
+    };
+    // Safety: winit keeps the view alive for as long as the window.
+    unsafe { (handle.ns_view.as_ptr() as *const objc2_app_kit::NSView).as_ref() }
+}
+
+#[cfg(target_os = "windows")]
+fn hwnd(window: &Window) -> Option<windows_sys::Win32::Foundation::HWND> {
+    use raw_window_handle::{HasWindowHandle, RawWindowHandle};
+
+    let handle = window.window_handle().ok()?;
+    match handle.as_raw() {
+        RawWindowHandle::Win32(handle) => Some(handle.hwnd.get() as _),
+        _ => None,
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn set_window_shadow(window: &Window, enabled: bool) {
+    unsafe {
+        if let Some(ns_window) = ns_view(window).and_then(|view| view.window()) {
+            ns_window.setHasShadow(enabled);
+            ns_window.invalidateShadow();
+        }
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn set_window_shadow(window: &Window, enabled: bool) {
+    use winit::platform::windows::WindowExtWindows;
+
+    // Decorated windows always have the system shadow; this only affects
+    // undecorated ones.
+    window.set_undecorated_shadow(enabled);
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn set_window_shadow(_window: &Window, _enabled: bool) {
+    log::warn!("Window shadow control is not supported on this platform");
+}
+
+/// Show `image` (encoded, e.g. as PNG) as the app's Dock icon, or restore
+/// the bundle icon with `None`. The Dock is the only place macOS previews an
+/// app, so that is where the thumbnail goes. Must run on the main thread.
+#[cfg(target_os = "macos")]
+pub fn set_dock_image(image: Option<&[u8]>) {
+    use objc2::ClassType;
+    use objc2_app_kit::{NSApplication, NSImage};
+    use objc2_foundation::{MainThreadMarker, NSData};
+
+    let Some(mtm) = MainThreadMarker::new() else {
+        return;
+    };
+    let app = NSApplication::sharedApplication(mtm);
+    let image = match image {
+        Some(bytes) => {
+            let data = NSData::with_bytes(bytes);
+            match unsafe { NSImage::initWithData(NSImage::alloc(), &data) } {
+                Some(image) => Some(image),
+                None => {
+                    log::warn!("Could not decode the thumbnail image");
+                    return;
+                }
+            }
+        }
+        None => None,
+    };
+    unsafe { app.setApplicationIconImage(image.as_deref()) };
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn set_dock_image(_image: Option<&[u8]>) {
+    // Windows would need the window procedure to answer DWM's iconic
+    // thumbnail requests, which winit doesn't expose.
+    log::warn!("Custom thumbnails are not supported on this platform");
+}
+
+/// Run `f` with the shell's taskbar button interface, if the system has one.
+#[cfg(target_os = "windows")]
+fn with_taskbar(
+    window: &Window,
+    f: impl FnOnce(&windows::Win32::UI::Shell::ITaskbarList3, windows::Win32::Foundation::HWND),
+) {
+    use windows::Win32::Foundation::HWND;
+    use windows::Win32::System::Com::{CoCreateInstance, CLSCTX_INPROC_SERVER};
+    use windows::Win32::UI::Shell::{ITaskbarList3, TaskbarList};
+
+    thread_local! {
+        // winit has already initialized COM on the event loop thread.
+        static TASKBAR: Option<ITaskbarList3> = unsafe {
+            CoCreateInstance::<_, ITaskbarList3>(&TaskbarList, None, CLSCTX_INPROC_SERVER)
+                .and_then(|taskbar| taskbar.HrInit().map(|()| taskbar))
+                .ok()
+        };
+    }
+
+    let Some(hwnd) = hwnd(window) else {
+        return;
+    };
+    TASKBAR.with(|taskbar| match taskbar {
+        Some(taskbar) => f(taskbar, HWND(hwnd)),
+        None => log::warn!("The taskbar is unavailable (no ITaskbarList3)"),
+    });
+}
+
+/// Show `state` at `fraction` (0.0–1.0) on the app's taskbar button or Dock
+/// icon. Must run on the main thread.
+#[cfg(target_os = "windows")]
+pub fn set_taskbar_progress(window: &Window, state: ProgressState, fraction: f32) {
+    use windows::Win32::UI::Shell::{
+        TBPF_ERROR, TBPF_INDETERMINATE, TBPF_NOPROGRESS, TBPF_NORMAL, TBPF_PAUSED,
+    };
+
+    let flags = match state {
+        ProgressState::None => TBPF_NOPROGRESS,
+        ProgressState::Normal => TBPF_NORMAL,
+        ProgressState::Indeterminate => TBPF_INDETERMINATE,
+        ProgressState::Paused => TBPF_PAUSED,
+        ProgressState::Error => TBPF_ERROR,
+    };
+    with_taskbar(window, |taskbar, hwnd| unsafe {
+        // Setting a value switches the bar to normal, so set the state last.
+        if matches!(
+            state,
+            ProgressState::Normal | ProgressState::Paused | ProgressState::Error
+        ) {
+            let _ = taskbar.SetProgressValue(hwnd, (fraction * 1000.0) as u64, 1000);
+        }
+        let _ = taskbar.SetProgressState(hwnd, flags);
+    });
+}
+
+/// Labels competing for the Dock icon's single badge: progress wins over the
+/// badge count while it is shown.
+#[cfg(target_os = "macos")]
+static DOCK_BADGE: Mutex<(Option<String>, Option<String>)> = Mutex::new((None, None));
+
+#[cfg(target_os = "macos")]
+fn update_dock_badge() {
+    use objc2_app_kit::NSApplication;
+    use objc2_foundation::{MainThreadMarker, NSString};
+
+    let Some(mtm) = MainThreadMarker::new() else {
+        return;
+    };
+    let label = {
+        let badge = DOCK_BADGE.lock().unwrap();
+        let (progress, count) = &*badge;
+        progress
+            .as_ref()
+            .or(count.as_ref())
+            .map(|label| NSString::from_str(label))
+    };
+    unsafe {
+        NSApplication::sharedApplication(mtm)
+            .dockTile()
+            .setBadgeLabel(label.as_deref());
+    }
+}
+
+/// Show `state` at `fraction` (0.0–1.0) on the app's taskbar button or Dock
+/// icon. Must run on the main thread.
+#[cfg(target_os = "macos")]
+pub fn set_taskbar_progress(_window: &Window, state: ProgressState, fraction: f32) {
+    // The Dock has no progress bar of its own; show the percentage as the
+    // icon's badge instead.
+    let label = match state {
+        ProgressState::None => None,
+        ProgressState::Indeterminate => Some("…".to_string()),
+        ProgressState::Error => Some("!".to_string()),
+        ProgressState::Normal | ProgressState::Paused => {
+            Some(format!("{}%", (fraction * 100.0).round() as u32))
+        }
+    };
+    DOCK_BADGE.lock().unwrap().0 = label;
+    update_dock_badge();
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+pub fn set_taskbar_progress(_window: &Window, _state: ProgressState, _fraction: f32) {
+    // Logged once: with automatic progress this runs on every load.
+    static NOTED: std::sync::Once = std::sync::Once::new();
+    NOTED.call_once(|| {
+        log::warn!("Taskbar progress is not supported on this platform");
+    });
+}
+
+/// The text of a badge for `count` unread items: the number itself, capped so
+/// it stays legible at badge size.
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+fn badge_label(count: u32, max: u32) -> String {
+    if count > max {
+        format!("{}+", max)
+    } else {
+        count.to_string()
+    }
+}
+
+/// Show `count` as a badge on the Dock icon, or clear it with `0`. Must run on
+/// the main thread.
+#[cfg(target_os = "macos")]
+pub fn set_badge_count(_window: &Window, count: u32) {
+    DOCK_BADGE.lock().unwrap().1 = (count > 0).then(|| badge_label(count, 999));
+    update_dock_badge();
+}
+
+/// Show `count` as an overlay on the taskbar button, or clear it with `0`.
+/// Must run on the main thread.
+#[cfg(target_os = "windows")]
+pub fn set_badge_count(window: &Window, count: u32) {
+    use windows::core::{HSTRING, PCWSTR};
+    use windows::Win32::Foundation::HINSTANCE;
+    use windows::Win32::UI::WindowsAndMessaging::{CreateIcon, DestroyIcon, HICON};
+
+    with_taskbar(window, |taskbar, hwnd| unsafe {
+        if count == 0 {
+            let _ = taskbar.SetOverlayIcon(hwnd, HICON::default(), PCWSTR::null());
+            return;
+        }
+        // CreateIcon takes BGRA rows plus a 1-bit AND mask, which the alpha
+        // channel makes redundant.
+        let mut bgra = badge_icon(&badge_label(count, 9));
+        for pixel in bgra.chunks_exact_mut(4) {
+            pixel.swap(0, 2);
+        }
+        let mask = [0u8; BADGE_ICON_SIZE * BADGE_ICON_SIZE / 8];
+        let size = BADGE_ICON_SIZE as i32;
+        let Ok(icon) = CreateIcon(
+            HINSTANCE::default(),
+            size,
+            size,
+            1,
+            32,
+            mask.as_ptr(),
+            bgra.as_ptr(),
+        ) else {
+            log::warn!("Failed to create the taskbar badge icon");
+            return;
+        };
+        let description = HSTRING::from(format!("{} notifications", count));
+        let _ = taskbar.SetOverlayIcon(hwnd, icon, &description);
+        // The taskbar keeps its own copy.
+        let _ = DestroyIcon(icon);
+    });
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+pub fn set_badge_count(_window: &Window, _count: u32) {
+    log::warn!("Badge counts are not supported on this platform");
+}
+
+/// Edge length of the Windows taskbar overlay icon, in pixels.
+#[cfg(target_os = "windows")]
+const BADGE_ICON_SIZE: usize = 16;
+
+/// 3x5 pixel glyphs for badge labels, one bit per pixel, rows top first.
+#[cfg(target_os = "windows")]
+const BADGE_GLYPHS: &[(char, [u8; 5])] = &[
+    ('0', [0b111, 0b101, 0b101, 0b101, 0b111]),
+    ('1', [0b010, 0b110, 0b010, 0b010, 0b111]),
+    ('2', [0b111, 0b001, 0b111, 0b100, 0b111]),
+    ('3', [0b111, 0b001, 0b111, 0b001, 0b111]),
+    ('4', [0b101, 0b101, 0b111, 0b001, 0b001]),
+    ('5', [0b111, 0b100, 0b111, 0b001, 0b111]),
+    ('6', [0b111, 0b100, 0b111, 0b101, 0b111]),
+    ('7', [0b111, 0b001, 0b001, 0b001, 0b001]),
+    ('8', [0b111, 0b101, 0b111, 0b101, 0b111]),
+    ('9', [0b111, 0b101, 0b111, 0b001, 0b111]),
+    ('+', [0b000, 0b010, 0b111, 0b010, 0b000]),
+];
+
+/// Draw `label` in white on a red disc, as RGBA rows of a
+/// `BADGE_ICON_SIZE`-pixel square. Single characters are drawn at double size.
+#[cfg(target_os = "windows")]
+fn badge_icon(label: &str) -> Vec<u8> {
+    const SIZE: usize = BADGE_ICON_SIZE;
+    let mut rgba = vec![0u8; SIZE * SIZE * 4];
+    let center = (SIZE as f32 - 1.0) / 2.0;
+    for y in 0..SIZE {
+        for x in 0..SIZE {
+            let distance = ((x as f32 - center).powi(2) + (y as f32 - center).powi(2)).sqrt();
+            if distance <= SIZE as f32 / 2.0 {
+                rgba[(y * SIZE + x) * 4..][..4].copy_from_slice(&[0xd9, 0x30, 0x25, 0xff]);
+            }
+        }
+    }
+
+    let glyphs: Vec<&[u8; 5]> = label
+        .chars()
+        .filter_map(|c| BADGE_GLYPHS.iter().find(|(glyph, _)| *glyph == c))
+        .map(|(_, rows)| rows)
+        .collect();
+    let scale = if glyphs.len() == 1 { 2 } else { 1 };
+    let width = glyphs.len() * 4 * scale - scale;
+    let left = SIZE.saturating_sub(width) / 2;
+    let top = (SIZE - 5 * scale) / 2;
+    for (index, rows) in glyphs.iter().enumerate() {
+        for (row, bits) in rows.iter().enumerate() {
+            for column in 0..3 {
+                if bits & (0b100 >> column) == 0 {
+                    continue;
+                }
+                for dy in 0..scale {
+                    for dx in 0..scale {
+                        let x = left + (index * 4 + column) * scale + dx;
+                        let y = top + row * scale + dy;
+                        if x < SIZE && y < SIZE {
+                            rgba[(y * SIZE + x) * 4..][..4].fill(0xff);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    rgba
+}
+
+#[cfg(target_os = "macos")]
+fn set_corner_radius(window: &Window, radius: f64) {
+    use objc2::msg_send;
+    use objc2::runtime::{AnyObject, Bool};
+    use objc2_app_kit::NSColor;
+
+    let Some(view) = ns_view(window) else {
+        return;
+    };
+    // Round the layer that backs the content view, and let the rounded-off
+    // corners show through the window background.
+    unsafe {
+        view.setWantsLayer(true);
+        let layer: *mut AnyObject = msg_send![view, layer];
+        if let Some(layer) = layer.as_ref() {
+            let _: () = msg_send![layer, setCornerRadius: radius];
+            let _: () = msg_send![layer, setMasksToBounds: Bool::new(radius > 0.0)];
+        }
+        if let Some(ns_window) = view.window() {
+            let transparent = TRANSPARENT_WINDOW.load(Ordering::Relaxed);
+            ns_window.setOpaque(radius <= 0.0 && !transparent);
+            if radius > 0.0 || transparent {
+                ns_window.setBackgroundColor(Some(&NSColor::clearColor()));
+            }
+            // The shadow follows the window's shape, so recompute it.
+            ns_window.invalidateShadow();
+        }
+    }
+}
+
+/// Whether the window was created transparent (`InitParams::transparent`).
+pub static TRANSPARENT_WINDOW: AtomicBool = AtomicBool::new(false);
+
+/// Let the desktop show through the transparent parts of the window's
+/// content. winit makes the window itself transparent; on macOS the layers
+/// the GL surface draws into are opaque too until told otherwise, which would
+/// leave a black background. Call once the rendering context exists.
+#[cfg(target_os = "macos")]
+pub fn make_transparent(window: &Window) {
+    use objc2::msg_send;
+    use objc2::runtime::{AnyObject, Bool};
+    use objc2_app_kit::NSColor;
+
+    TRANSPARENT_WINDOW.store(true, Ordering::Relaxed);
+    let Some(view) = ns_view(window) else {
+        return;
+    };
+    unsafe {
+        if let Some(ns_window) = view.window() {
+            ns_window.setOpaque(false);
+            ns_window.setBackgroundColor(Some(&NSColor::clearColor()));
+        }
+        let layer: *mut AnyObject = msg_send![view, layer];
+        if let Some(layer) = layer.as_ref() {
+            let _: () = msg_send![layer, setOpaque: Bool::NO];
+            let sublayers: *mut AnyObject = msg_send![layer, sublayers];
+            if let Some(sublayers) = sublayers.as_ref() {
+                let count: usize = msg_send![sublayers, count];
+                for index in 0..count {
+                    let sublayer: *mut AnyObject = msg_send![sublayers, objectAtIndex: index];
+                    if let Some(sublayer) = sublayer.as_ref() {
+                        let _: () = msg_send![sublayer, setOpaque: Bool::NO];
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn make_transparent(_window: &Window) {
+    TRANSPARENT_WINDOW.store(true, Ordering::Relaxed);
+}
+
+#[cfg(target_os = "windows")]
+fn set_corner_radius(window: &Window, radius: f64) {
+    use windows_sys::Win32::Graphics::Dwm::{
+        DwmSetWindowAttribute, DWMWA_WINDOW_CORNER_PREFERENCE, DWMWCP_DONOTROUND, DWMWCP_ROUND,
+        DWMWCP_ROUNDSMALL,
+    };
+
+    let Some(hwnd) = hwnd(window) else {
+        return;
+    };
+    // Windows 11 offers a fixed set of corner styles rather than an arbitrary
+    // radius; pick the closest one. Older versions ignore the attribute.
+    let preference = if radius <= 0.0 {
+        DWMWCP_DONOTROUND
+    } else if radius <= 4.0 {
+        DWMWCP_ROUNDSMALL
+    } else {
+        DWMWCP_ROUND
+    };
+    let result = unsafe {
+        DwmSetWindowAttribute(
+            hwnd,
+            DWMWA_WINDOW_CORNER_PREFERENCE as u32,
+            &preference as *const _ as *const std::ffi::c_void,
+            std::mem::size_of_val(&preference) as u32,
+        )
+    };
+    if result != 0 {
+        log::warn!(
+            "Corner preference not supported by this Windows version ({:#x})",
+            result
+        );
+    }
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn set_corner_radius(_window: &Window, _radius: f64) {
+    log::warn!("Corner radius control is not supported on this platform");
+}
@@ -0,0 +1,283 @@
+//! Engine preferences the embedder configures before `pw_start_app`.
+//!
+//! Servo reads its preferences once, when `ServoBuilder` builds the engine, so
+//! these settings are collected in a global and folded into the `Preferences`
+//! and `Opts` handed to the builder.
+//!
+//! Besides the settings the shell has its own calls for, any of Servo's
+//! preferences can be set by name with `pw_set_preference`. Those are applied
+//! last, so they win over the shell's own settings.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde_json::Value;
+use servo::opts::Opts;
+use servo::Preferences;
+use url::Url;
+
+/// Startup settings layered on top of Servo's default preferences. `None`
+/// leaves the engine default untouched.
+pub struct EngineSettings {
+    pub webgl_enabled: Option<bool>,
+    pub accelerated_canvas: Option<bool>,
+    pub user_agent: Option<String>,
+    /// Where cookies, `localStorage` and IndexedDB persist. `None` keeps them
+    /// in memory for the session.
+    pub profile_dir: Option<PathBuf>,
+    pub devtools_port: Option<u16>,
+    /// What shows where the page draws no background, as RGBA from 0 to 1.
+    pub background_color: Option<[f32; 4]>,
+    /// The proxy every request goes through, from [`parse_proxy_url`].
+    pub proxy_url: Option<String>,
+    /// Comma-separated hosts that bypass the proxy, from [`parse_no_proxy`].
+    pub no_proxy: Option<String>,
+    /// A PEM file of certificate authorities to trust instead of the built-in
+    /// roots.
+    pub certificate_path: Option<String>,
+}
+
+impl EngineSettings {
+    const fn new() -> Self {
+        EngineSettings {
+            webgl_enabled: None,
+            accelerated_canvas: None,
+            user_agent: None,
+            profile_dir: None,
+            devtools_port: None,
+            background_color: None,
+            proxy_url: None,
+            no_proxy: None,
+            certificate_path: None,
+        }
+    }
+
+    fn apply(&self, prefs: &mut Preferences) {
+        if let Some(enabled) = self.webgl_enabled {
+            // Servo has no switch for WebGL 1 itself, but failing context
+            // creation makes `getContext("webgl")` return null, which pages
+            // already handle as "WebGL unavailable".
+            prefs.dom_webgl2_enabled = enabled;
+            prefs.webgl_testing_context_creation_error = !enabled;
+        }
+        if let Some(accelerated) = self.accelerated_canvas {
+            prefs.dom_canvas_backend = if accelerated { "vello" } else { "vello_cpu" }.to_string();
+        }
+        if let Some(user_agent) = &self.user_agent {
+            prefs.user_agent = user_agent.clone();
+        }
+        if let Some(port) = self.devtools_port {
+            prefs.devtools_server_enabled = true;
+            prefs.devtools_server_port = port.into();
+        }
+        if let Some(color) = self.background_color {
+            prefs.shell_background_color_rgba = color.map(f64::from);
+        }
+        if let Some(proxy_url) = &self.proxy_url {
+            prefs.network_http_proxy_uri = proxy_url.clone();
+            prefs.network_https_proxy_uri = proxy_url.clone();
+        }
+        if let Some(no_proxy) = &self.no_proxy {
+            prefs.network_http_no_proxy = no_proxy.clone();
+        }
+    }
+}
+
+pub static ENGINE_SETTINGS: Mutex<EngineSettings> = Mutex::new(EngineSettings::new());
+
+/// Servo preferences set by name with `pw_set_preference`, already checked.
+static PREFERENCES: Mutex<BTreeMap<String, Value>> = Mutex::new(BTreeMap::new());
+
+/// Servo's preference name for `key`: its field name, so `dom.webgl2.enabled`
+/// (as Servo's own command line spells it) becomes `dom_webgl2_enabled`.
+fn preference_name(key: &str) -> String {
+    key.trim().replace(['.', '-'], "_")
+}
+
+/// Check that `value` has the type of the preference's default: a boolean,
+/// an integer, a number, a string, or a list (whose items aren't checked).
+fn check_type(key: &str, default: &Value, value: &Value) -> Result<(), String> {
+    let matches = match default {
+        Value::Bool(_) => value.is_boolean(),
+        Value::Number(number) if number.is_f64() => value.is_number(),
+        Value::Number(_) => value.is_i64() || value.is_u64(),
+        Value::String(_) => value.is_string(),
+        Value::Array(_) => value.is_array(),
+        Value::Object(_) => value.is_object(),
+        Value::Null => true,
+    };
+    if matches {
+        return Ok(());
+    }
+    let expected = match default {
+        Value::Bool(_) => "a boolean",
+        Value::Number(number) if number.is_f64() => "a number",
+        Value::Number(_) => "an integer",
+        Value::String(_) => "a string",
+        Value::Array(_) => "a list",
+        _ => "an object",
+    };
+    Err(format!(
+        "preference {:?} takes {}, not {}",
+        key, expected, value
+    ))
+}
+
+/// Remember the Servo preference `key` to be set to the JSON `value_json`
+/// when the engine is built. Fails for unknown preferences, invalid JSON and
+/// values of the wrong type.
+pub fn set_preference(key: &str, value_json: &str) -> Result<(), String> {
+    let name = preference_name(key);
+    let value: Value = serde_json::from_str(value_json)
+        .map_err(|e| format!("invalid JSON for preference {:?}: {}", key, e))?;
+    let defaults = serde_json::to_value(Preferences::default()).map_err(|e| e.to_string())?;
+    let Some(default) = defaults.get(&name) else {
+        return Err(format!("unknown preference {:?}", key));
+    };
+    check_type(key, default, &value)?;
+    PREFERENCES.lock().unwrap().insert(name, value);
+    Ok(())
+}
+
+/// `prefs` with the preferences from `pw_set_preference` applied.
+fn apply_preferences(prefs: Preferences) -> Preferences {
+    let overrides = PREFERENCES.lock().unwrap();
+    if overrides.is_empty() {
+        return prefs;
+    }
+    let Ok(Value::Object(mut fields)) = serde_json::to_value(&prefs) else {
+        return prefs;
+    };
+    for (name, value) in overrides.iter() {
+        fields.insert(name.clone(), value.clone());
+    }
+    serde_json::from_value(Value::Object(fields)).unwrap_or_else(|e| {
+        log::error!("Ignoring preferences set with pw_set_preference: {}", e);
+        prefs
+    })
+}
+
+/// The preferences to build Servo with.
+pub fn build_preferences() -> Preferences {
+    let mut prefs = Preferences::default();
+    ENGINE_SETTINGS.lock().unwrap().apply(&mut prefs);
+    apply_preferences(prefs)
+}
+
+/// The options to build Servo with.
+pub fn build_opts() -> Opts {
+    let settings = ENGINE_SETTINGS.lock().unwrap();
+    Opts {
+        config_dir: settings.profile_dir.clone(),
+        certificate_path: settings.certificate_path.clone(),
+        ..Opts::default()
+    }
+}
+
+/// Create the profile directory if needed and check that Servo can write to
+/// it, since it only finds out when saving on exit.
+pub fn prepare_profile_dir(path: &Path) -> Result<PathBuf, String> {
+    fs::create_dir_all(path).map_err(|e| format!("cannot create {:?}: {}", path, e))?;
+    let probe = path.join(".pywire-write-test");
+    fs::write(&probe, b"").map_err(|e| format!("{:?} is not writable: {}", path, e))?;
+    let _ = fs::remove_file(&probe);
+    Ok(path.to_path_buf())
+}
+
+/// Check a proxy URL for `InitParams::proxy_url`: an `http://` or `https://`
+/// URL naming a host, optionally with a port and credentials. Servo's network
+/// stack has no SOCKS support, so `socks5://` is refused rather than ignored.
+pub fn parse_proxy_url(text: &str) -> Result<String, String> {
+    let url =
+        Url::parse(text.trim()).map_err(|e| format!("invalid proxy URL {:?}: {}", text, e))?;
+    match url.scheme() {
+        "http" | "https" => {}
+        scheme if scheme.starts_with("socks") => {
+            return Err(format!("{} proxies are not supported: {:?}", scheme, text));
+        }
+        scheme => {
+            return Err(format!(
+                "unsupported proxy scheme {:?} in {:?}",
+                scheme, text
+            ));
+        }
+    }
+    if url.host_str().is_none_or(str::is_empty) {
+        return Err(format!("proxy URL {:?} has no host", text));
+    }
+    if url.path() != "/" || url.query().is_some() || url.fragment().is_some() {
+        return Err(format!(
+            "proxy URL {:?} must not have a path or query",
+            text
+        ));
+    }
+    Ok(url.as_str().trim_end_matches('/').to_string())
+}
+
+/// Normalize a no-proxy list for `InitParams::no_proxy`: hosts, domains
+/// (`.example.com`) or IP ranges, separated by commas or whitespace.
+pub fn parse_no_proxy(text: &str) -> String {
+    text.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|host| !host.is_empty())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn preference_names_accept_servo_spelling() {
+        assert_eq!(preference_name("dom.webgl2.enabled"), "dom_webgl2_enabled");
+        assert_eq!(preference_name("user_agent"), "user_agent");
+    }
+
+    #[test]
+    fn checks_preference_types() {
+        let flag = serde_json::json!(false);
+        let count = serde_json::json!(0);
+        assert!(check_type("flag", &flag, &serde_json::json!(true)).is_ok());
+        assert!(check_type("flag", &flag, &serde_json::json!("yes")).is_err());
+        assert!(check_type("count", &count, &serde_json::json!(8)).is_ok());
+        assert!(check_type("count", &count, &serde_json::json!(1.5)).is_err());
+        assert!(check_type("ratio", &serde_json::json!(0.5), &serde_json::json!(1)).is_ok());
+    }
+
+    #[test]
+    fn rejects_unknown_preferences() {
+        assert!(set_preference("dom.no_such_thing.enabled", "true").is_err());
+        assert!(set_preference("dom.webgl2.enabled", "not json").is_err());
+        assert!(set_preference("dom.webgl2.enabled", "\"yes\"").is_err());
+    }
+
+    #[test]
+    fn accepts_http_proxies() {
+        assert_eq!(
+            parse_proxy_url("http://proxy.corp:3128"),
+            Ok("http://proxy.corp:3128".to_string())
+        );
+        assert_eq!(
+            parse_proxy_url(" https://user:pw@proxy.corp/ "),
+            Ok("https://user:pw@proxy.corp".to_string())
+        );
+    }
+
+    #[test]
+    fn refuses_malformed_and_socks_proxies() {
+        assert!(parse_proxy_url("proxy.corp:3128").is_err());
+        assert!(parse_proxy_url("socks5://proxy.corp:1080").is_err());
+        assert!(parse_proxy_url("ftp://proxy.corp").is_err());
+        assert!(parse_proxy_url("http://proxy.corp/path").is_err());
+    }
+
+    #[test]
+    fn normalizes_no_proxy_lists() {
+        assert_eq!(
+            parse_no_proxy("localhost, .corp.example\t10.0.0.0/8,,"),
+            "localhost,.corp.example,10.0.0.0/8"
+        );
+    }
+}
@@ -0,0 +1,173 @@
+//! Printing the page to PDF, one viewport at a time.
+//!
+//! A print job measures the document, scrolls through it a viewport at a time
+//! capturing each frame, stitches the frames into one tall image and cuts that
+//! into pages with the paper's aspect ratio. The event loop drives the steps;
+//! this module only keeps the state.
+
+use std::path::PathBuf;
+
+use crate::capture::Frame;
+use crate::pdf::PageSize;
+
+/// Measures the document: `[scrollHeight, innerHeight, scrollX, scrollY]`.
+pub const MEASURE_JS: &str = "[document.documentElement.scrollHeight, window.innerHeight, \
+     window.scrollX, window.scrollY]";
+
+pub struct PrintJob {
+    pub path: PathBuf,
+    pub page_size: PageSize,
+    /// Where the page was scrolled to before printing, to put it back.
+    pub restore_scroll: (f64, f64),
+    document_height: f64,
+    viewport_height: f64,
+    /// Scroll offsets still to capture, in CSS pixels, in reverse order.
+    pending: Vec<f64>,
+    captures: Vec<(f64, Frame)>,
+}
+
+impl PrintJob {
+    pub fn new(path: PathBuf, page_size: PageSize) -> PrintJob {
+        PrintJob {
+            path,
+            page_size,
+            restore_scroll: (0.0, 0.0),
+            document_height: 0.0,
+            viewport_height: 0.0,
+            pending: Vec::new(),
+            captures: Vec::new(),
+        }
+    }
+
+    /// Plan the captures for a document `document_height` CSS pixels tall,
+    /// seen through a viewport `viewport_height` pixels tall.
+    pub fn measured(&mut self, document_height: f64, viewport_height: f64) {
+        let viewport_height = viewport_height.max(1.0);
+        self.document_height = document_height.max(viewport_height);
+        self.viewport_height = viewport_height;
+        self.pending = scroll_offsets(self.document_height, viewport_height);
+        self.pending.reverse();
+    }
+
+    /// The next offset to scroll to and capture, if any are left.
+    pub fn next_offset(&mut self) -> Option<f64> {
+        self.pending.pop()
+    }
+
+    pub fn add_capture(&mut self, offset: f64, frame: &Frame) {
+        self.captures.push((
+            offset,
+            Frame {
+                width: frame.width,
+                height: frame.height,
+                rgba: frame.rgba.clone(),
+            },
+        ));
+    }
+
+    /// Stitch the captures together and cut them into pages.
+    pub fn pages(&self) -> Vec<Frame> {
+        let Some((_, first)) = self.captures.first() else {
+            return Vec::new();
+        };
+        let width = first.width as usize;
+        // Device pixels per CSS pixel, including the page zoom.
+        let scale = first.height as f64 / self.viewport_height;
+        let height = (self.document_height * scale).round() as usize;
+        let stride = width * 4;
+
+        let mut canvas = vec![255u8; stride * height];
+        for (offset, frame) in &self.captures {
+            if frame.width as usize != width {
+                continue;
+            }
+            let top = ((offset * scale).round() as usize).min(height);
+            let rows = (frame.height as usize).min(height - top);
+            canvas[top * stride..(top + rows) * stride]
+                .copy_from_slice(&frame.rgba[..rows * stride]);
+        }
+
+        let (page_width, page_height) = self.page_size.points();
+        let rows_per_page = ((width as f64 * page_height / page_width).round() as usize).max(1);
+        canvas
+            .chunks(rows_per_page * stride)
+            .map(|rows| Frame {
+                width: width as u32,
+                height: (rows.len() / stride) as u32,
+                rgba: rows.to_vec(),
+            })
+            .collect()
+    }
+}
+
+/// The scroll offsets that together show a whole document: one viewport
+/// after another, with the last one flush with the bottom.
+fn scroll_offsets(document_height: f64, viewport_height: f64) -> Vec<f64> {
+    let last = (document_height - viewport_height).max(0.0);
+    let mut offsets = Vec::new();
+    let mut offset = 0.0;
+    while offset < last {
+        offsets.push(offset);
+        offset += viewport_height;
+    }
+    offsets.push(last);
+    offsets
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A `width` x `height` frame whose rows all have the color `shade`.
+    fn frame(width: u32, height: u32, shade: u8) -> Frame {
+        Frame {
+            width,
+            height,
+            rgba: vec![shade; width as usize * height as usize * 4],
+        }
+    }
+
+    #[test]
+    fn offsets_cover_the_document() {
+        assert_eq!(scroll_offsets(500.0, 800.0), vec![0.0]);
+        assert_eq!(scroll_offsets(800.0, 800.0), vec![0.0]);
+        assert_eq!(scroll_offsets(2000.0, 800.0), vec![0.0, 800.0, 1200.0]);
+    }
+
+    #[test]
+    fn captures_are_stitched_and_paginated() {
+        let mut job = PrintJob::new(PathBuf::from("out.pdf"), PageSize::A4);
+        job.measured(25.0, 10.0);
+        let mut shade = 0;
+        while let Some(offset) = job.next_offset() {
+            shade += 1;
+            // Two device pixels per CSS pixel.
+            job.add_capture(offset, &frame(10, 20, shade));
+        }
+        assert_eq!(shade, 3);
+
+        let pages = job.pages();
+        // 50 rows at 14 rows per A4 page of width 10.
+        let heights: Vec<u32> = pages.iter().map(|page| page.height).collect();
+        assert_eq!(heights, vec![14, 14, 14, 8]);
+
+        let row = |index: usize| {
+            let page = &pages[index / 14];
+            page.rgba[(index % 14) * 40]
+        };
+        assert_eq!(row(0), 1);
+        assert_eq!(row(19), 1);
+        assert_eq!(row(20), 2);
+        // The last capture is flush with the bottom and overlaps the second.
+        assert_eq!(row(30), 3);
+        assert_eq!(row(49), 3);
+    }
+
+    #[test]
+    fn a_short_page_fills_one_viewport() {
+        let mut job = PrintJob::new(PathBuf::from("out.pdf"), PageSize::Letter);
+        job.measured(100.0, 600.0);
+        assert_eq!(job.next_offset(), Some(0.0));
+        assert_eq!(job.next_offset(), None);
+    }
+}
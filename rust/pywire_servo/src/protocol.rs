@@ -0,0 +1,474 @@
+//! Custom URL schemes (e.g. `app://`) answered by the embedder instead of the
+//! network.
+//!
+//! Schemes are added to Servo's protocol registry when the engine is built, so
+//! they have to be registered before `pw_start_app`. Each one is backed either
+//! by a callback that returns the bytes for a path, or by a directory on disk.
+//! The shell answers `Range` requests itself by slicing the full contents, so
+//! media elements can seek without the callback knowing about ranges.
+//!
+//! `InitParams::app_dir` uses the same machinery to serve an app's files under
+//! `pywire://app/`.
+
+use std::ffi::{CStr, CString};
+use std::fs;
+use std::future::Future;
+use std::os::raw::c_char;
+use std::path::{Component, Path, PathBuf};
+use std::pin::Pin;
+use std::sync::Mutex;
+
+use http::header::{
+    HeaderName, ACCEPT_ENCODING, ACCEPT_RANGES, CONTENT_ENCODING, CONTENT_RANGE, CONTENT_TYPE,
+    RANGE,
+};
+use http::{HeaderValue, StatusCode};
+use percent_encoding::percent_decode_str;
+use servo::protocol_handler::{
+    DoneChannel, FetchContext, HttpStatus, ProtocolHandler, ProtocolRegistry, Request,
+    ResourceFetchTiming, Response, ResponseBody,
+};
+use url::Url;
+
+use crate::encoding;
+use crate::static_files::{self, ContentEncoding};
+
+/// Called with the requested path (see [`request_path`]). To answer, the
+/// callback points `*data` at the contents and stores their length in `*len`,
+/// optionally points `*mime_type` at a NUL-terminated MIME type, and returns
+/// `true`. Without a MIME type one is guessed from the path's extension.
+/// Returning `false` answers `404 Not Found`.
+///
+/// The shell copies both buffers right after the callback returns, so keeping
+/// them alive until the callback is next called on the same thread is enough.
+/// Servo fetches from several of its own threads, so the callback may be
+/// invoked concurrently and from threads other than the one that started the
+/// app.
+pub type ProtocolCallback =
+    extern "C" fn(*const c_char, *mut *const u8, *mut usize, *mut *const c_char) -> bool;
+
+#[derive(Clone)]
+pub enum ProtocolSource {
+    Callback(ProtocolCallback),
+    /// Files under this directory, by path.
+    Directory(PathBuf),
+    /// Files under this directory, by path below `pywire://app/`.
+    AppDirectory(PathBuf),
+}
+
+/// The scheme and host `InitParams::app_dir` is served under.
+pub const APP_SCHEME: &str = "pywire";
+pub const APP_HOST: &str = "app";
+
+/// Registered schemes, read once at startup.
+pub static PROTOCOLS: Mutex<Vec<(String, ProtocolSource)>> = Mutex::new(Vec::new());
+
+/// Schemes the engine handles itself, which can't be taken over.
+const RESERVED_SCHEMES: &[&str] = &[
+    "about",
+    "blob",
+    "data",
+    "file",
+    "http",
+    "https",
+    "javascript",
+    "ws",
+    "wss",
+];
+
+/// Whether `scheme` is a syntactically valid URL scheme the shell may serve.
+pub fn is_valid_scheme(scheme: &str) -> bool {
+    let mut chars = scheme.chars();
+    let starts_with_letter = chars.next().is_some_and(|c| c.is_ascii_alphabetic());
+    starts_with_letter
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+        && !RESERVED_SCHEMES.contains(&scheme.to_ascii_lowercase().as_str())
+}
+
+/// Serve `scheme` from `source`, replacing any earlier registration for it.
+pub fn register(scheme: &str, source: ProtocolSource) {
+    let scheme = scheme.to_ascii_lowercase();
+    let mut protocols = PROTOCOLS.lock().unwrap();
+    protocols.retain(|(registered, _)| *registered != scheme);
+    protocols.push((scheme, source));
+}
+
+/// Serve `InitParams::app_dir` under `pywire://app/`, replacing any handler
+/// registered for the `pywire` scheme.
+pub fn register_app_dir(root: PathBuf) {
+    register(APP_SCHEME, ProtocolSource::AppDirectory(root));
+}
+
+/// Servo's built-in protocols plus every registered scheme.
+pub fn protocol_registry() -> ProtocolRegistry {
+    let mut registry = ProtocolRegistry::with_internal_protocols();
+    for (scheme, source) in PROTOCOLS.lock().unwrap().iter() {
+        let handler = PyWireProtocolHandler {
+            source: source.clone(),
+        };
+        if let Err(e) = registry.register(scheme, handler) {
+            log::error!("Could not register the {}: scheme: {:?}", scheme, e);
+        }
+    }
+    registry
+}
+
+/// The path a request asks for, relative to the scheme's root: the host and
+/// path joined, percent-decoded, without leading slashes or the query. Both
+/// `app://index.html` and `app:///index.html` ask for `"index.html"`.
+pub fn request_path(url: &Url) -> String {
+    decode_path(&format!("{}{}", url.host_str().unwrap_or(""), url.path()))
+}
+
+fn decode_path(path: &str) -> String {
+    percent_decode_str(path)
+        .decode_utf8_lossy()
+        .trim_start_matches('/')
+        .to_string()
+}
+
+/// The MIME type for a file with `path`'s extension.
+pub fn guess_mime_type(path: &str) -> &'static str {
+    let extension = Path::new(path)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or("")
+        .to_ascii_lowercase();
+    match extension.as_str() {
+        "html" | "htm" => "text/html",
+        "css" => "text/css",
+        "js" | "mjs" => "text/javascript",
+        "json" | "map" => "application/json",
+        "wasm" => "application/wasm",
+        "txt" => "text/plain",
+        "xml" => "application/xml",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "ico" => "image/x-icon",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        "otf" => "font/otf",
+        "mp3" => "audio/mpeg",
+        "wav" => "audio/wav",
+        "oga" | "ogg" => "audio/ogg",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        "ogv" => "video/ogg",
+        "pdf" => "application/pdf",
+        _ => "application/octet-stream",
+    }
+}
+
+/// The part of a `len`-byte body a `Range` header asks for.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ByteRange {
+    /// No usable range: send everything with `200 OK`.
+    Full,
+    /// Bytes `start..=end`, sent with `206 Partial Content`.
+    Partial { start: usize, end: usize },
+    /// Starts past the end, answered with `416 Range Not Satisfiable`.
+    Unsatisfiable,
+}
+
+/// Interpret a `Range` header for a body of `len` bytes. Only single
+/// `bytes=` ranges are honoured; anything else is ignored, as servers may.
+pub fn byte_range(range: Option<&str>, len: usize) -> ByteRange {
+    let Some(spec) = range.and_then(|range| range.trim().strip_prefix("bytes=")) else {
+        return ByteRange::Full;
+    };
+    if spec.contains(',') {
+        return ByteRange::Full;
+    }
+    let Some((start, end)) = spec.split_once('-') else {
+        return ByteRange::Full;
+    };
+    let (start, end) = (start.trim(), end.trim());
+    if start.is_empty() {
+        // A suffix range: the last `end` bytes.
+        let Ok(suffix) = end.parse::<usize>() else {
+            return ByteRange::Full;
+        };
+        if suffix == 0 || len == 0 {
+            return ByteRange::Unsatisfiable;
+        }
+        return ByteRange::Partial {
+            start: len.saturating_sub(suffix),
+            end: len - 1,
+        };
+    }
+    let Ok(start) = start.parse::<usize>() else {
+        return ByteRange::Full;
+    };
+    let end = if end.is_empty() {
+        usize::MAX
+    } else {
+        match end.parse::<usize>() {
+            Ok(end) if end >= start => end,
+            _ => return ByteRange::Full,
+        }
+    };
+    if start >= len {
+        return ByteRange::Unsatisfiable;
+    }
+    ByteRange::Partial {
+        start,
+        end: end.min(len - 1),
+    }
+}
+
+/// A resolved response body.
+struct Asset {
+    bytes: Vec<u8>,
+    mime_type: String,
+    encoding: Option<ContentEncoding>,
+}
+
+struct PyWireProtocolHandler {
+    source: ProtocolSource,
+}
+
+impl PyWireProtocolHandler {
+    fn asset(&self, url: &Url, accept_encoding: Option<&str>) -> Option<Asset> {
+        match &self.source {
+            ProtocolSource::Callback(callback) => read_from_callback(*callback, &request_path(url)),
+            ProtocolSource::Directory(root) => {
+                read_from_directory(root, &request_path(url), accept_encoding)
+            }
+            ProtocolSource::AppDirectory(root) => {
+                if url.host_str() != Some(APP_HOST) {
+                    return None;
+                }
+                read_from_directory(root, &decode_path(url.path()), accept_encoding)
+            }
+        }
+    }
+}
+
+impl ProtocolHandler for PyWireProtocolHandler {
+    fn load(
+        &self,
+        request: &mut Request,
+        _done_chan: &mut DoneChannel,
+        _context: &FetchContext,
+    ) -> Pin<Box<dyn Future<Output = Response> + Send>> {
+        let url = request.current_url();
+        let header = |name: HeaderName| {
+            request
+                .headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+        };
+        let range = header(RANGE);
+        // A precompressed sibling can't be sliced into a byte range of the
+        // original, so ranged requests always get the plain file.
+        let accept_encoding = if range.is_some() {
+            None
+        } else {
+            header(ACCEPT_ENCODING)
+        };
+        let asset = self.asset(url.as_url(), accept_encoding);
+
+        let mut response = Response::new(url, ResourceFetchTiming::new(request.timing_type()));
+        let Some(asset) = asset else {
+            response.status = HttpStatus::from(StatusCode::NOT_FOUND);
+            response
+                .headers
+                .insert(CONTENT_TYPE, HeaderValue::from_static("text/plain"));
+            *response.body.lock().unwrap() = ResponseBody::Done(b"404 Not Found".to_vec());
+            return Box::pin(std::future::ready(response));
+        };
+
+        // Compressed bodies can't be prescanned, so only plain ones get the
+        // fallback encoding.
+        let mime_type = asset
+            .encoding
+            .is_none()
+            .then(|| encoding::content_type_with_fallback(&asset.mime_type, &asset.bytes))
+            .flatten()
+            .unwrap_or_else(|| asset.mime_type.clone());
+        let len = asset.bytes.len();
+        let (status, body) = match byte_range(range, len) {
+            ByteRange::Full => (StatusCode::OK, asset.bytes),
+            ByteRange::Partial { start, end } => {
+                let content_range = format!("bytes {}-{}/{}", start, end, len);
+                if let Ok(value) = HeaderValue::from_str(&content_range) {
+                    response.headers.insert(CONTENT_RANGE, value);
+                }
+                (
+                    StatusCode::PARTIAL_CONTENT,
+                    asset.bytes[start..=end].to_vec(),
+                )
+            }
+            ByteRange::Unsatisfiable => {
+                if let Ok(value) = HeaderValue::from_str(&format!("bytes */{}", len)) {
+                    response.headers.insert(CONTENT_RANGE, value);
+                }
+                (StatusCode::RANGE_NOT_SATISFIABLE, vec![])
+            }
+        };
+
+        let content_type = HeaderValue::from_str(&mime_type)
+            .unwrap_or_else(|_| HeaderValue::from_static("application/octet-stream"));
+        response.headers.insert(CONTENT_TYPE, content_type);
+        response
+            .headers
+            .insert(ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+        if let Some(encoding) = asset.encoding {
+            response.headers.insert(
+                CONTENT_ENCODING,
+                HeaderValue::from_static(encoding.header_value()),
+            );
+        }
+        response.status = HttpStatus::from(status);
+        *response.body.lock().unwrap() = ResponseBody::Done(body);
+        Box::pin(std::future::ready(response))
+    }
+
+    fn is_fetchable(&self) -> bool {
+        true
+    }
+
+    fn is_secure(&self) -> bool {
+        // Content comes from the app itself, never over the network.
+        true
+    }
+}
+
+fn read_from_callback(callback: ProtocolCallback, path: &str) -> Option<Asset> {
+    let c_path = CString::new(path).ok()?;
+    let mut data: *const u8 = std::ptr::null();
+    let mut len: usize = 0;
+    let mut mime_type: *const c_char = std::ptr::null();
+    if !callback(c_path.as_ptr(), &mut data, &mut len, &mut mime_type) {
+        return None;
+    }
+    let bytes = if data.is_null() || len == 0 {
+        Vec::new()
+    } else {
+        // SAFETY: the callback promised `len` readable bytes at `data` that
+        // stay valid until we have copied them.
+        unsafe { std::slice::from_raw_parts(data, len) }.to_vec()
+    };
+    let mime_type = if mime_type.is_null() {
+        guess_mime_type(path).to_string()
+    } else {
+        // SAFETY: as above, a NUL-terminated string valid until copied.
+        unsafe { CStr::from_ptr(mime_type) }
+            .to_string_lossy()
+            .into_owned()
+    };
+    Some(Asset {
+        bytes,
+        mime_type,
+        encoding: None,
+    })
+}
+
+/// The file under `root` for `path`, or `None` if `path` would escape `root`.
+/// Directories are served by their `index.html`.
+pub fn resolve_in_directory(root: &Path, path: &str) -> Option<PathBuf> {
+    let relative = Path::new(path);
+    if !relative
+        .components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+    {
+        return None;
+    }
+    let file = root.join(relative);
+    if file.is_dir() {
+        return Some(file.join("index.html"));
+    }
+    Some(file)
+}
+
+fn read_from_directory(root: &Path, path: &str, accept_encoding: Option<&str>) -> Option<Asset> {
+    let file = resolve_in_directory(root, path)?;
+    let (served, encoding) = static_files::resolve_precompressed(&file, accept_encoding);
+    let bytes = fs::read(&served).ok()?;
+    // The type of the original file, not of its `.br` or `.gz` sibling.
+    let mime_type = guess_mime_type(&file.to_string_lossy()).to_string();
+    Some(Asset {
+        bytes,
+        mime_type,
+        encoding,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validates_schemes() {
+        assert!(is_valid_scheme("app"));
+        assert!(is_valid_scheme("my-app+v1.0"));
+        assert!(!is_valid_scheme(""));
+        assert!(!is_valid_scheme("1app"));
+        assert!(!is_valid_scheme("app://"));
+        assert!(!is_valid_scheme("HTTPS"));
+        assert!(!is_valid_scheme("file"));
+    }
+
+    #[test]
+    fn request_path_joins_host_and_path() {
+        let path = |url: &str| request_path(&Url::parse(url).unwrap());
+        assert_eq!(path("app://index.html"), "index.html");
+        assert_eq!(path("app:///index.html"), "index.html");
+        assert_eq!(path("app://assets/img/a%20b.png?v=2"), "assets/img/a b.png");
+    }
+
+    #[test]
+    fn parses_byte_ranges() {
+        assert_eq!(byte_range(None, 10), ByteRange::Full);
+        assert_eq!(
+            byte_range(Some("bytes=2-5"), 10),
+            ByteRange::Partial { start: 2, end: 5 }
+        );
+        assert_eq!(
+            byte_range(Some("bytes=4-"), 10),
+            ByteRange::Partial { start: 4, end: 9 }
+        );
+        assert_eq!(
+            byte_range(Some("bytes=-3"), 10),
+            ByteRange::Partial { start: 7, end: 9 }
+        );
+        assert_eq!(
+            byte_range(Some("bytes=8-100"), 10),
+            ByteRange::Partial { start: 8, end: 9 }
+        );
+        assert_eq!(byte_range(Some("bytes=10-"), 10), ByteRange::Unsatisfiable);
+        assert_eq!(byte_range(Some("bytes=-0"), 10), ByteRange::Unsatisfiable);
+        assert_eq!(byte_range(Some("bytes=5-2"), 10), ByteRange::Full);
+        assert_eq!(byte_range(Some("bytes=0-1,4-5"), 10), ByteRange::Full);
+        assert_eq!(byte_range(Some("items=0-1"), 10), ByteRange::Full);
+    }
+
+    #[test]
+    fn directory_paths_stay_inside_the_root() {
+        let root = Path::new("/srv/app");
+        assert_eq!(
+            resolve_in_directory(root, "js/app.js"),
+            Some(root.join("js/app.js"))
+        );
+        assert_eq!(resolve_in_directory(root, "../secret"), None);
+        assert_eq!(resolve_in_directory(root, "js/../../secret"), None);
+        assert_eq!(resolve_in_directory(root, "/etc/passwd"), None);
+        let app_path = |url: &str| decode_path(Url::parse(url).unwrap().path());
+        assert_eq!(
+            resolve_in_directory(root, &app_path("pywire://app/..%2Fsecret")),
+            None
+        );
+    }
+
+    #[test]
+    fn guesses_mime_types_from_extensions() {
+        assert_eq!(guess_mime_type("index.HTML"), "text/html");
+        assert_eq!(guess_mime_type("media/clip.mp4"), "video/mp4");
+        assert_eq!(guess_mime_type("LICENSE"), "application/octet-stream");
+    }
+}
@@ -0,0 +1,225 @@
+//! Supplies Servo's engine resources (user agent stylesheets, certificates,
+//! the HSTS preload list, ...).
+//!
+//! An app bundled into a single file can serve them from memory, either as a
+//! bundle of files handed over with `pw_add_resource` before startup or from
+//! an embedder callback. Anything neither provides is read from the resources
+//! directory: `InitParams::resources_path`, or the one named by
+//! `SERVO_RESOURCES_PATH`.
+
+use std::collections::{HashMap, HashSet};
+use std::ffi::CString;
+use std::fs;
+use std::os::raw::c_char;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicBool;
+use std::sync::{LazyLock, Mutex};
+
+use servo::resources::{Resource, ResourceReaderMethods};
+
+/// Called with a resource's file name (e.g. `"user-agent.css"`). To supply it,
+/// the callback points `*data` at the contents, stores their length in `*len`
+/// and returns `true`. The shell copies the buffer right after the callback
+/// returns, so keeping it alive until the next call for the same name is
+/// enough. Returning `false` falls back to the resources directory, if set.
+/// Resources in the bundle are served from there without asking.
+///
+/// Servo reads resources from several of its own threads, so the callback may
+/// be invoked concurrently and from threads other than the one that started
+/// the app.
+pub type ResourceCallback = extern "C" fn(*const c_char, *mut *const u8, *mut usize) -> bool;
+
+/// The callback installed by `pw_set_resource_callback`, read once at startup.
+pub static RESOURCE_CALLBACK: Mutex<Option<ResourceCallback>> = Mutex::new(None);
+
+/// Resource contents by file name, added by `pw_add_resource`. Only changes
+/// before startup, so the reader can look resources up here directly.
+pub static RESOURCE_BUNDLE: LazyLock<Mutex<HashMap<String, Vec<u8>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// The resources directory the app was started with, for diagnostics.
+pub static RESOURCES_PATH: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// Whether startup fails when a required resource is missing, instead of the
+/// engine running with an empty stand-in.
+pub static STRICT_RESOURCES: AtomicBool = AtomicBool::new(false);
+
+/// Resources without which pages render wrong or secure connections fail:
+/// the user agent stylesheets and the public suffix and HSTS preload lists.
+/// The certificate and network error pages are the shell's own.
+const REQUIRED_RESOURCES: &[Resource] = &[
+    Resource::UserAgentCSS,
+    Resource::ServoCSS,
+    Resource::PresentationalHintsCSS,
+    Resource::QuirksModeCSS,
+    Resource::DomainList,
+    Resource::HstsPreloadList,
+];
+
+/// A resource that couldn't be read, and what the engine got instead: an
+/// empty file.
+#[derive(Debug, PartialEq)]
+pub struct FailedRead {
+    pub name: &'static str,
+    /// Whether it is one of the resources pages or TLS can't do without.
+    pub required: bool,
+    pub error: String,
+}
+
+/// Failed reads the event loop hasn't reported yet.
+static FAILED_READS: Mutex<Vec<FailedRead>> = Mutex::new(Vec::new());
+
+/// Names of the resources whose failure has been queued, so a resource the
+/// engine keeps asking for is reported once.
+static REPORTED_FAILURES: LazyLock<Mutex<HashSet<&'static str>>> =
+    LazyLock::new(|| Mutex::new(HashSet::new()));
+
+/// Whether pages render wrong or secure connections fail without `name`.
+pub fn is_required(name: &str) -> bool {
+    REQUIRED_RESOURCES.iter().any(|res| res.filename() == name)
+}
+
+/// The failed reads since the last call, for the event loop to report.
+pub fn take_failed_reads() -> Vec<FailedRead> {
+    std::mem::take(&mut *FAILED_READS.lock().unwrap())
+}
+
+/// Log a failed read and queue it for a `resource_missing` event, the first
+/// time `name` fails.
+fn report_failure(name: &'static str, error: String) {
+    let required = is_required(name);
+    if required {
+        log::error!("Missing required engine resource {:?}: {}", name, error);
+    } else {
+        log::warn!("Missing engine resource {:?}: {}", name, error);
+    }
+    if !REPORTED_FAILURES.lock().unwrap().insert(name) {
+        return;
+    }
+    FAILED_READS.lock().unwrap().push(FailedRead {
+        name,
+        required,
+        error,
+    });
+    crate::wake_event_loop();
+}
+
+pub struct PyWireResourceReader {
+    pub path: Option<PathBuf>,
+    pub callback: Option<ResourceCallback>,
+}
+
+impl PyWireResourceReader {
+    /// File names of the required resources that neither the bundle, the
+    /// callback nor the resources directory can provide.
+    pub fn missing_required(&self) -> Vec<&'static str> {
+        REQUIRED_RESOURCES
+            .iter()
+            .map(|res| res.filename())
+            .filter(|name| !self.provides(name))
+            .collect()
+    }
+
+    fn provides(&self, name: &str) -> bool {
+        if RESOURCE_BUNDLE.lock().unwrap().contains_key(name) {
+            return true;
+        }
+        let from_callback = self
+            .callback
+            .is_some_and(|callback| Self::read_from_callback(callback, name).is_some());
+        from_callback
+            || self
+                .path
+                .as_ref()
+                .is_some_and(|path| path.join(name).is_file())
+    }
+
+    fn read_from_callback(callback: ResourceCallback, name: &str) -> Option<Vec<u8>> {
+        let name = CString::new(name).ok()?;
+        let mut data: *const u8 = std::ptr::null();
+        let mut len: usize = 0;
+        if !callback(name.as_ptr(), &mut data, &mut len) {
+            return None;
+        }
+        if data.is_null() || len == 0 {
+            return Some(Vec::new());
+        }
+        // SAFETY: the callback promised `len` readable bytes at `data` that
+        // stay valid until we have copied them.
+        Some(unsafe { std::slice::from_raw_parts(data, len) }.to_vec())
+    }
+}
+
+impl ResourceReaderMethods for PyWireResourceReader {
+    fn read(&self, res: Resource) -> Vec<u8> {
+        if res.filename() == Resource::BadCertHTML.filename() {
+            return crate::cert::page();
+        }
+        if res.filename() == Resource::NetErrorHTML.filename() {
+            return crate::net_error::page();
+        }
+        if let Some(bytes) = RESOURCE_BUNDLE.lock().unwrap().get(res.filename()) {
+            return bytes.clone();
+        }
+        if let Some(bytes) = self
+            .callback
+            .and_then(|callback| Self::read_from_callback(callback, res.filename()))
+        {
+            return bytes;
+        }
+
+        let Some(mut path) = self.path.clone() else {
+            report_failure(
+                res.filename(),
+                "not in the resource bundle, not provided by the resource callback \
+                 and no resources directory is set"
+                    .to_string(),
+            );
+            return vec![];
+        };
+        path.push(res.filename());
+        match fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                report_failure(res.filename(), format!("can't read {:?}: {}", path, e));
+                vec![]
+            }
+        }
+    }
+    fn sandbox_access_files(&self) -> Vec<PathBuf> {
+        vec![]
+    }
+    fn sandbox_access_files_dirs(&self) -> Vec<PathBuf> {
+        self.path.iter().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tells_required_resources_apart() {
+        assert!(is_required("user-agent.css"));
+        assert!(is_required(Resource::HstsPreloadList.filename()));
+        assert!(!is_required("rippy.png"));
+    }
+
+    #[test]
+    fn reports_each_failure_once() {
+        report_failure("test-resource.txt", "gone".to_string());
+        report_failure("test-resource.txt", "still gone".to_string());
+        let failed: Vec<FailedRead> = take_failed_reads()
+            .into_iter()
+            .filter(|failure| failure.name == "test-resource.txt")
+            .collect();
+        assert_eq!(
+            failed,
+            vec![FailedRead {
+                name: "test-resource.txt",
+                required: false,
+                error: "gone".to_string(),
+            }]
+        );
+    }
+}
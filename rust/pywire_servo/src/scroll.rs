@@ -0,0 +1,266 @@
+//! Turning mouse wheel notches into scroll distances.
+//!
+//! Touchpads report pixel deltas, which are used as they are. Wheels report
+//! notches ("lines"); each one scrolls the number of lines the OS is set to
+//! (three where it has no setting), at [`ScrollSettings::line_height`] CSS
+//! pixels a line. With smooth scrolling a notch's distance is spread over a
+//! few frames by [`SmoothScroll`] instead of jumping at once.
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use servo::WheelMode;
+use winit::event::MouseScrollDelta;
+
+/// CSS pixels per line when the app sets none, so a notch scrolls 60 pixels
+/// at the usual three lines, close to what browsers do.
+pub const DEFAULT_LINE_HEIGHT: f32 = 20.0;
+
+/// Lines per notch on platforms without a setting for it.
+const DEFAULT_LINES_PER_NOTCH: f32 = 3.0;
+
+pub struct ScrollSettings {
+    /// CSS pixels scrolled per line.
+    pub line_height: f32,
+    /// Scroll the other way from what the device reports.
+    pub natural: bool,
+}
+
+pub static SCROLL_SETTINGS: Mutex<ScrollSettings> = Mutex::new(ScrollSettings {
+    line_height: DEFAULT_LINE_HEIGHT,
+    natural: false,
+});
+
+/// How far one notch scrolls, as the OS is set up.
+enum NotchDistance {
+    Lines {
+        x: f32,
+        y: f32,
+    },
+    /// Windows' "one screen at a time" setting.
+    Page,
+}
+
+#[cfg(target_os = "windows")]
+fn notch_distance() -> NotchDistance {
+    use windows::Win32::UI::WindowsAndMessaging::{
+        SystemParametersInfoW, SPI_GETWHEELSCROLLCHARS, SPI_GETWHEELSCROLLLINES,
+        SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS, WHEEL_PAGESCROLL,
+    };
+
+    let query = |action| {
+        let mut value = 0u32;
+        unsafe {
+            SystemParametersInfoW(
+                action,
+                0,
+                Some(&mut value as *mut u32 as *mut _),
+                SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS(0),
+            )
+        }
+        .ok()
+        .map(|()| value)
+    };
+    let lines = query(SPI_GETWHEELSCROLLLINES).unwrap_or(DEFAULT_LINES_PER_NOTCH as u32);
+    if lines == WHEEL_PAGESCROLL {
+        return NotchDistance::Page;
+    }
+    let chars = query(SPI_GETWHEELSCROLLCHARS).unwrap_or(DEFAULT_LINES_PER_NOTCH as u32);
+    NotchDistance::Lines {
+        x: chars as f32,
+        y: lines as f32,
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+fn notch_distance() -> NotchDistance {
+    NotchDistance::Lines {
+        x: DEFAULT_LINES_PER_NOTCH,
+        y: DEFAULT_LINES_PER_NOTCH,
+    }
+}
+
+/// The wheel delta to send Servo for `delta`, on a display with
+/// `scale_factor` device pixels per CSS pixel.
+pub fn wheel_delta(delta: MouseScrollDelta, scale_factor: f64) -> (f64, f64, WheelMode) {
+    let settings = SCROLL_SETTINGS.lock().unwrap();
+    let notch = match delta {
+        MouseScrollDelta::LineDelta(..) => notch_distance(),
+        // Not consulted for pixel deltas.
+        MouseScrollDelta::PixelDelta(_) => NotchDistance::Page,
+    };
+    scaled_delta(delta, scale_factor, &settings, notch)
+}
+
+fn scaled_delta(
+    delta: MouseScrollDelta,
+    scale_factor: f64,
+    settings: &ScrollSettings,
+    notch: NotchDistance,
+) -> (f64, f64, WheelMode) {
+    let (x, y, mode) = match (delta, notch) {
+        (
+            MouseScrollDelta::LineDelta(x, y),
+            NotchDistance::Lines {
+                x: lines_x,
+                y: lines_y,
+            },
+        ) => {
+            let line = settings.line_height as f64 * scale_factor;
+            (
+                (x * lines_x) as f64 * line,
+                (y * lines_y) as f64 * line,
+                WheelMode::DeltaPixel,
+            )
+        }
+        (MouseScrollDelta::LineDelta(x, y), NotchDistance::Page) => {
+            (x as f64, y as f64, WheelMode::DeltaPage)
+        }
+        (MouseScrollDelta::PixelDelta(position), _) => {
+            (position.x, position.y, WheelMode::DeltaPixel)
+        }
+    };
+    if settings.natural {
+        (-x, -y, mode)
+    } else {
+        (x, y, mode)
+    }
+}
+
+/// How often a smooth scroll sends the page its next step.
+pub const SMOOTH_SCROLL_FRAME: Duration = Duration::from_millis(16);
+
+/// How quickly a smooth scroll eases out: about two thirds of the distance
+/// left is covered in this time, so a notch settles within ~150 ms.
+const SMOOTH_SCROLL_TIME_CONSTANT: f64 = 0.05;
+
+/// Below this many device pixels left, a smooth scroll finishes in one step.
+const SMOOTH_SCROLL_SNAP: f64 = 1.0;
+
+/// A wheel scroll being played out over several frames.
+#[derive(Default)]
+pub struct SmoothScroll {
+    /// Device pixels still to scroll.
+    remaining: (f64, f64),
+    /// When the last step was sent, while the scroll is running.
+    last_step: Option<Instant>,
+}
+
+impl SmoothScroll {
+    /// Add a notch's `(x, y)` pixel delta. Turning the wheel the other way
+    /// drops what was left of the previous direction, as browsers do.
+    pub fn add(&mut self, x: f64, y: f64, now: Instant) {
+        let combine = |remaining: f64, delta: f64| {
+            if remaining * delta < 0.0 {
+                delta
+            } else {
+                remaining + delta
+            }
+        };
+        self.remaining = (combine(self.remaining.0, x), combine(self.remaining.1, y));
+        // The first step goes out straight away, as a frame's worth.
+        self.last_step
+            .get_or_insert(now.checked_sub(SMOOTH_SCROLL_FRAME).unwrap_or(now));
+    }
+
+    /// When the next step is due, while the scroll is running.
+    pub fn next_step(&self) -> Option<Instant> {
+        self.last_step.map(|last| last + SMOOTH_SCROLL_FRAME)
+    }
+
+    /// The delta to send for the time elapsed since the last step, if the
+    /// scroll is running.
+    pub fn step(&mut self, now: Instant) -> Option<(f64, f64)> {
+        let last = self.last_step?;
+        let elapsed = now.saturating_duration_since(last).as_secs_f64();
+        let fraction = 1.0 - (-elapsed / SMOOTH_SCROLL_TIME_CONSTANT).exp();
+        let (x, y) = self.remaining;
+        let left = (x * (1.0 - fraction), y * (1.0 - fraction));
+        if left.0.abs() < SMOOTH_SCROLL_SNAP && left.1.abs() < SMOOTH_SCROLL_SNAP {
+            *self = SmoothScroll::default();
+            return Some((x, y));
+        }
+        self.remaining = left;
+        self.last_step = Some(now);
+        Some((x - left.0, y - left.1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use winit::dpi::PhysicalPosition;
+
+    const THREE_LINES: NotchDistance = NotchDistance::Lines { x: 3.0, y: 3.0 };
+
+    fn settings(line_height: f32, natural: bool) -> ScrollSettings {
+        ScrollSettings {
+            line_height,
+            natural,
+        }
+    }
+
+    #[test]
+    fn notches_scroll_lines_of_css_pixels() {
+        let delta = MouseScrollDelta::LineDelta(0.0, -1.0);
+        let (x, y, mode) = scaled_delta(delta, 2.0, &settings(20.0, false), THREE_LINES);
+        assert_eq!((x, y), (0.0, -120.0));
+        assert!(matches!(mode, WheelMode::DeltaPixel));
+    }
+
+    #[test]
+    fn page_scrolling_sends_pages() {
+        let delta = MouseScrollDelta::LineDelta(0.0, 2.0);
+        let (_, y, mode) = scaled_delta(delta, 1.0, &settings(20.0, false), NotchDistance::Page);
+        assert_eq!(y, 2.0);
+        assert!(matches!(mode, WheelMode::DeltaPage));
+    }
+
+    #[test]
+    fn touchpad_pixels_pass_through_and_natural_scrolling_inverts() {
+        let delta = MouseScrollDelta::PixelDelta(PhysicalPosition::new(4.0, -7.5));
+        let (x, y, _) = scaled_delta(delta, 2.0, &settings(20.0, false), THREE_LINES);
+        assert_eq!((x, y), (4.0, -7.5));
+        let (x, y, _) = scaled_delta(delta, 2.0, &settings(20.0, true), THREE_LINES);
+        assert_eq!((x, y), (-4.0, 7.5));
+    }
+
+    #[test]
+    fn smooth_scroll_covers_the_whole_distance_and_stops() {
+        let start = Instant::now();
+        let mut scroll = SmoothScroll::default();
+        assert_eq!(scroll.step(start), None);
+
+        scroll.add(0.0, -120.0, start);
+        let mut now = start;
+        let mut total = 0.0;
+        let mut steps = 0;
+        while let Some((x, y)) = scroll.step(now) {
+            assert_eq!(x, 0.0);
+            assert!(y < 0.0);
+            total += y;
+            steps += 1;
+            now = scroll.next_step().unwrap_or(now);
+        }
+        assert!((total + 120.0).abs() < 1e-9);
+        assert!(steps > 3, "scrolled in {} steps", steps);
+        assert!(now - start < Duration::from_millis(500));
+        assert_eq!(scroll.next_step(), None);
+    }
+
+    #[test]
+    fn reversing_the_wheel_drops_the_rest_of_the_scroll() {
+        let start = Instant::now();
+        let mut scroll = SmoothScroll::default();
+        scroll.add(0.0, 120.0, start);
+        scroll.step(start);
+        scroll.add(0.0, -60.0, start);
+        let mut total = 0.0;
+        let mut now = start;
+        while let Some((_, y)) = scroll.step(now) {
+            total += y;
+            now = scroll.next_step().unwrap_or(now);
+        }
+        assert!((total + 60.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,208 @@
+//! Single-instance mode: later launches hand their arguments to the running
+//! instance instead of opening a second window.
+//!
+//! The first instance listens on a loopback TCP port and records the port in
+//! a lock file in the temp directory, keyed by app id and user, together with
+//! a random token that later launches must echo back. The lock file is only
+//! readable by its owner on Unix. A lock file left behind by a crash is
+//! detected because nothing answers on its port any more, and is replaced.
+
+use std::collections::hash_map::RandomState;
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::hash::{BuildHasher, Hasher};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+/// A launch forwarded by a later instance.
+#[derive(Debug)]
+pub struct Launch {
+    /// Its command line arguments, without the program name.
+    pub args: Vec<String>,
+    /// Its working directory, to resolve relative paths in `args`.
+    pub cwd: Option<String>,
+}
+
+pub enum Outcome {
+    /// No other instance is running; this one now owns the app id.
+    Primary,
+    /// Another instance is running and has been handed this launch.
+    Forwarded,
+}
+
+/// Launches received but not yet handled by the event loop.
+static PENDING: Mutex<Vec<Launch>> = Mutex::new(Vec::new());
+
+/// The lock file this process created, removed again on exit.
+static OWNED_LOCK: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// How long to wait for the running instance to pick up a launch.
+const FORWARD_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Largest launch message accepted, to keep stray connections harmless.
+const MAX_MESSAGE_BYTES: u64 = 64 * 1024;
+
+/// Whether `app_id` is safe to use in a file name.
+pub fn is_valid_app_id(app_id: &str) -> bool {
+    !app_id.is_empty()
+        && app_id.len() <= 64
+        && app_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'))
+}
+
+fn lock_path(app_id: &str) -> PathBuf {
+    let user = env::var("USER")
+        .or_else(|_| env::var("USERNAME"))
+        .unwrap_or_default();
+    let user: String = user
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'))
+        .collect();
+    env::temp_dir().join(format!("pywire-{}-{}.lock", app_id, user))
+}
+
+fn random_token() -> String {
+    // `RandomState` is seeded from the OS's random source.
+    let mut token = String::new();
+    for _ in 0..2 {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u128(
+            SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_nanos(),
+        );
+        token.push_str(&format!("{:016x}", hasher.finish()));
+    }
+    token
+}
+
+/// Claim `app_id` for this process, or forward this launch to the instance
+/// that already has. `notify` is called (from a background thread) whenever a
+/// forwarded launch is waiting in [`take_pending`].
+pub fn request(app_id: &str, notify: fn()) -> io::Result<Outcome> {
+    let path = lock_path(app_id);
+    // Two attempts: the second follows removing a stale lock.
+    for _ in 0..2 {
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        match options.open(&path) {
+            Ok(mut file) => {
+                let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?;
+                let token = random_token();
+                let port = listener.local_addr()?.port();
+                if let Err(e) = writeln!(file, "{} {}", port, token) {
+                    let _ = fs::remove_file(&path);
+                    return Err(e);
+                }
+                *OWNED_LOCK.lock().unwrap() = Some(path);
+                thread::spawn(move || serve(listener, token, notify));
+                return Ok(Outcome::Primary);
+            }
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                if forward(&path).is_ok() {
+                    return Ok(Outcome::Forwarded);
+                }
+                log::info!("Removing stale single-instance lock {:?}", path);
+                let _ = fs::remove_file(&path);
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    Err(io::Error::other(format!(
+        "could not claim single-instance lock {:?}",
+        path
+    )))
+}
+
+/// Read the port and token from the lock at `path`. An instance that has just
+/// created the file may not have written them yet, so give it a moment.
+fn read_lock(path: &Path) -> io::Result<(u16, String)> {
+    for _ in 0..10 {
+        let contents = fs::read_to_string(path)?;
+        if let Some((port, token)) = contents.trim_end().split_once(' ') {
+            if let Ok(port) = port.parse() {
+                return Ok((port, token.to_string()));
+            }
+        }
+        if !contents.is_empty() {
+            break;
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+    Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        "malformed single-instance lock",
+    ))
+}
+
+/// Hand this process's launch to the instance owning the lock at `path`.
+/// Fails if nothing answers, i.e. the lock is stale.
+fn forward(path: &Path) -> io::Result<()> {
+    let (port, token) = read_lock(path)?;
+    let address = SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+    let mut stream = TcpStream::connect_timeout(&address, FORWARD_TIMEOUT)?;
+    stream.set_read_timeout(Some(FORWARD_TIMEOUT))?;
+    let message = serde_json::json!({
+        "token": token,
+        "args": env::args().skip(1).collect::<Vec<_>>(),
+        "cwd": env::current_dir().ok().map(|dir| dir.to_string_lossy().into_owned()),
+    });
+    writeln!(stream, "{}", message)?;
+    // Something is listening, so an instance is running even if it is too
+    // busy to acknowledge in time.
+    let mut reply = String::new();
+    let _ = BufReader::new(stream).read_line(&mut reply);
+    Ok(())
+}
+
+/// Accept launches from later instances for as long as the process lives.
+fn serve(listener: TcpListener, token: String, notify: fn()) {
+    for stream in listener.incoming().flatten() {
+        let _ = stream.set_read_timeout(Some(FORWARD_TIMEOUT));
+        let mut line = String::new();
+        let mut reader = BufReader::new((&stream).take(MAX_MESSAGE_BYTES));
+        if reader.read_line(&mut line).is_err() {
+            continue;
+        }
+        let Ok(message) = serde_json::from_str::<serde_json::Value>(&line) else {
+            continue;
+        };
+        if message["token"].as_str() != Some(token.as_str()) {
+            continue;
+        }
+        let launch = Launch {
+            args: message["args"]
+                .as_array()
+                .map(|args| {
+                    args.iter()
+                        .filter_map(|arg| arg.as_str().map(str::to_string))
+                        .collect()
+                })
+                .unwrap_or_default(),
+            cwd: message["cwd"].as_str().map(str::to_string),
+        };
+        PENDING.lock().unwrap().push(launch);
+        notify();
+        let _ = (&stream).write_all(b"ok\n");
+    }
+}
+
+/// The forwarded launches not handled yet.
+pub fn take_pending() -> Vec<Launch> {
+    std::mem::take(&mut *PENDING.lock().unwrap())
+}
+
+/// Give up the app id, so the next launch becomes the primary instance.
+pub fn release() {
+    if let Some(path) = OWNED_LOCK.lock().unwrap().take() {
+        let _ = fs::remove_file(path);
+    }
+}
@@ -0,0 +1,139 @@
+//! Serving files from disk for in-app URL schemes.
+//!
+//! Bundlers commonly emit `.br` and `.gz` siblings next to large assets. When
+//! the request accepts one of those encodings and the sibling exists, it is
+//! served in place of the original with a matching `Content-Encoding`.
+
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ContentEncoding {
+    Brotli,
+    Gzip,
+}
+
+impl ContentEncoding {
+    /// Server preference order: Brotli compresses better, so try it first.
+    const PREFERRED: [ContentEncoding; 2] = [ContentEncoding::Brotli, ContentEncoding::Gzip];
+
+    fn token(self) -> &'static str {
+        match self {
+            ContentEncoding::Brotli => "br",
+            ContentEncoding::Gzip => "gzip",
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            ContentEncoding::Brotli => "br",
+            ContentEncoding::Gzip => "gz",
+        }
+    }
+
+    /// The `Content-Encoding` header value for this encoding.
+    pub fn header_value(self) -> &'static str {
+        self.token()
+    }
+}
+
+/// The q-value `accept_encoding` assigns to `token`, falling back to a `*`
+/// entry. `None` if the header doesn't mention it at all.
+fn quality(accept_encoding: &str, token: &str) -> Option<f32> {
+    let mut wildcard = None;
+    for entry in accept_encoding.split(',') {
+        let mut parts = entry.split(';');
+        let name = parts.next().unwrap_or("").trim();
+        let q = parts
+            .filter_map(|param| param.trim().strip_prefix("q="))
+            .find_map(|q| q.trim().parse::<f32>().ok())
+            .unwrap_or(1.0);
+        if name.eq_ignore_ascii_case(token) {
+            return Some(q);
+        }
+        if name == "*" {
+            wildcard = Some(q);
+        }
+    }
+    wildcard
+}
+
+/// The encodings an `Accept-Encoding` header allows, in server preference
+/// order. Encodings given `q=0` are refused, even via `*`.
+pub fn accepted_encodings(accept_encoding: Option<&str>) -> Vec<ContentEncoding> {
+    let Some(accept_encoding) = accept_encoding else {
+        return vec![];
+    };
+    ContentEncoding::PREFERRED
+        .into_iter()
+        .filter(|encoding| quality(accept_encoding, encoding.token()).is_some_and(|q| q > 0.0))
+        .collect()
+}
+
+/// The file to serve for `path`: a precompressed sibling when one exists and
+/// the request accepts its encoding, otherwise `path` itself.
+pub fn resolve_precompressed(
+    path: &Path,
+    accept_encoding: Option<&str>,
+) -> (PathBuf, Option<ContentEncoding>) {
+    for encoding in accepted_encodings(accept_encoding) {
+        let mut candidate = path.as_os_str().to_owned();
+        candidate.push(".");
+        candidate.push(encoding.extension());
+        let candidate = PathBuf::from(candidate);
+        if candidate.is_file() {
+            return (candidate, Some(encoding));
+        }
+    }
+    (path.to_path_buf(), None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn prefers_brotli_when_both_are_accepted() {
+        assert_eq!(
+            accepted_encodings(Some("gzip, deflate, br")),
+            vec![ContentEncoding::Brotli, ContentEncoding::Gzip]
+        );
+    }
+
+    #[test]
+    fn honours_zero_quality_and_wildcards() {
+        assert_eq!(
+            accepted_encodings(Some("br;q=0, gzip;q=0.5")),
+            vec![ContentEncoding::Gzip]
+        );
+        assert_eq!(
+            accepted_encodings(Some("*")),
+            vec![ContentEncoding::Brotli, ContentEncoding::Gzip]
+        );
+        assert_eq!(
+            accepted_encodings(Some("*;q=0, GZIP")),
+            vec![ContentEncoding::Gzip]
+        );
+        assert!(accepted_encodings(Some("identity")).is_empty());
+        assert!(accepted_encodings(None).is_empty());
+    }
+
+    #[test]
+    fn serves_precompressed_sibling_only_when_present() {
+        let dir = std::env::temp_dir().join(format!("pw_static_files_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let asset = dir.join("app.js");
+        fs::write(&asset, "console.log(1)").unwrap();
+        fs::write(dir.join("app.js.gz"), "gz").unwrap();
+
+        let (served, encoding) = resolve_precompressed(&asset, Some("br, gzip"));
+        assert_eq!(served, dir.join("app.js.gz"));
+        assert_eq!(encoding, Some(ContentEncoding::Gzip));
+
+        let (served, encoding) = resolve_precompressed(&asset, Some("deflate"));
+        assert_eq!(served, asset);
+        assert_eq!(encoding, None);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
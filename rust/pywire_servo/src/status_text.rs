@@ -0,0 +1,95 @@
+//! The status bar text, such as the target of the hovered link, for
+//! `status_text` events.
+//!
+//! Moving the mouse across a page full of links changes the text for every
+//! link it passes over. [`StatusText`] holds a new text back for
+//! [`SETTLE_DELAY`], so only the link the mouse settles on is reported.
+//! Clearing the text is reported straight away, so the status bar empties as
+//! soon as the mouse leaves a link.
+
+use std::time::{Duration, Instant};
+
+/// How long a new text must stay before it is reported.
+pub const SETTLE_DELAY: Duration = Duration::from_millis(100);
+
+#[derive(Default)]
+pub struct StatusText {
+    /// The text Python was last told about.
+    reported: String,
+    pending: Option<String>,
+    /// When `pending` is reported.
+    deadline: Option<Instant>,
+}
+
+impl StatusText {
+    /// Servo changed the text. Returns the text to report now: the empty
+    /// text, if a non-empty one was reported.
+    pub fn update(&mut self, text: String, now: Instant) -> Option<String> {
+        if !text.is_empty() {
+            self.pending = Some(text);
+            self.deadline = Some(now + SETTLE_DELAY);
+            return None;
+        }
+        self.pending = None;
+        self.deadline = None;
+        if self.reported.is_empty() {
+            return None;
+        }
+        self.reported.clear();
+        Some(String::new())
+    }
+
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    /// The pending text, once it has stayed for [`SETTLE_DELAY`] and if it
+    /// differs from the one reported.
+    pub fn take_settled(&mut self, now: Instant) -> Option<String> {
+        if self.deadline? > now {
+            return None;
+        }
+        self.deadline = None;
+        let text = self.pending.take()?;
+        if text == self.reported {
+            return None;
+        }
+        self.reported = text.clone();
+        Some(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_the_text_the_mouse_settles_on() {
+        let start = Instant::now();
+        let mut status = StatusText::default();
+        assert_eq!(status.update("https://a.example/".into(), start), None);
+        let later = start + Duration::from_millis(30);
+        assert_eq!(status.update("https://b.example/".into(), later), None);
+        assert_eq!(status.take_settled(start + SETTLE_DELAY), None);
+        assert_eq!(
+            status.take_settled(later + SETTLE_DELAY),
+            Some("https://b.example/".into())
+        );
+        assert_eq!(status.deadline(), None);
+    }
+
+    #[test]
+    fn clears_straight_away() {
+        let start = Instant::now();
+        let mut status = StatusText::default();
+        assert_eq!(status.update(String::new(), start), None);
+        status.update("https://a.example/".into(), start);
+        status.take_settled(start + SETTLE_DELAY);
+        assert_eq!(status.update(String::new(), start), Some(String::new()));
+
+        // Passing over a link without stopping reports nothing.
+        status.update("https://b.example/".into(), start);
+        assert_eq!(status.update(String::new(), start), None);
+        assert_eq!(status.take_settled(start + SETTLE_DELAY), None);
+    }
+}
@@ -0,0 +1,23 @@
+//! The image the OS shows for the app in its dock or taskbar preview.
+
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Where the thumbnail comes from.
+pub enum ThumbnailSource {
+    /// The platform default (the app icon).
+    Default,
+    /// An encoded image (PNG, or anything else the platform can decode).
+    Image(Vec<u8>),
+    /// A scaled-down capture of the page, refreshed periodically.
+    Auto,
+}
+
+pub static THUMBNAIL: Mutex<ThumbnailSource> = Mutex::new(ThumbnailSource::Default);
+
+/// How often an automatic thumbnail is recaptured.
+pub const AUTO_REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Largest edge of an automatic thumbnail, in pixels. Matches the largest
+/// size the macOS Dock draws icons at on Retina displays.
+pub const AUTO_THUMBNAIL_SIZE: u32 = 256;
@@ -0,0 +1,272 @@
+//! Detecting a blank or frozen page, for `InitParams::watchdog_timeout_ms`.
+//!
+//! Two things count as the page being stuck:
+//!
+//! - A navigation finished loading but nothing was painted within the
+//!   timeout, which shows as a blank window.
+//! - The page's script thread didn't answer a trivial script within the
+//!   timeout. The shell sends one every timeout interval.
+//!
+//! Both are judged against work the page was given. A static page that sits
+//! idle has painted its load already and answers the scripts right away, so
+//! it is never reported. Neither is a page waiting on a modal dialog, such as
+//! `alert()` or a file picker: the watchdog is paused while one is open.
+
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Value};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Stuck {
+    /// Loaded, but never painted.
+    NoPaint,
+    /// Didn't answer a script in time.
+    ScriptHung,
+}
+
+impl Stuck {
+    pub fn reason(self) -> &'static str {
+        match self {
+            Stuck::NoPaint => "no_paint",
+            Stuck::ScriptHung => "script_hung",
+        }
+    }
+}
+
+/// A change in the active page's state worth reporting.
+#[derive(Debug, PartialEq)]
+pub enum Change {
+    Stuck(Stuck),
+    Recovered,
+}
+
+pub struct Watchdog {
+    /// `None` disables the watchdog.
+    timeout: Option<Duration>,
+    /// Whether a frame was painted since the current navigation started.
+    painted: bool,
+    /// When a loaded page that hasn't painted counts as blank.
+    paint_deadline: Option<Instant>,
+    /// When the unanswered script was sent.
+    ping_sent: Option<Instant>,
+    next_ping: Option<Instant>,
+    /// What was last reported, until the page recovers.
+    stuck: Option<Stuck>,
+    /// Whether a modal dialog is open, which blocks the page legitimately.
+    paused: bool,
+}
+
+impl Watchdog {
+    pub fn new(timeout: Option<Duration>) -> Self {
+        Watchdog {
+            timeout,
+            painted: false,
+            paint_deadline: None,
+            ping_sent: None,
+            next_ping: None,
+            stuck: None,
+            paused: false,
+        }
+    }
+
+    /// Start over for a new navigation or another active webview.
+    pub fn reset(&mut self) {
+        *self = Watchdog {
+            paused: self.paused,
+            ..Watchdog::new(self.timeout)
+        };
+    }
+
+    /// Stop judging the page while a modal dialog is open.
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    /// Judge the page again from `now`, with a full timeout before anything
+    /// counts. The script sent before the pause is no longer awaited.
+    pub fn resume(&mut self, now: Instant) {
+        self.paused = false;
+        let Some(timeout) = self.timeout else {
+            return;
+        };
+        self.ping_sent = None;
+        self.next_ping = Some(now + timeout);
+        if self.paint_deadline.is_some() {
+            self.paint_deadline = Some(now + timeout);
+        }
+    }
+
+    pub fn load_complete(&mut self, now: Instant) {
+        if let (Some(timeout), false) = (self.timeout, self.painted) {
+            self.paint_deadline = Some(now + timeout);
+        }
+    }
+
+    pub fn frame_painted(&mut self) -> Option<Change> {
+        self.painted = true;
+        self.paint_deadline = None;
+        self.recover(Stuck::NoPaint)
+    }
+
+    /// Whether to send the page a script now. It is then awaited.
+    pub fn ping_due(&mut self, now: Instant) -> bool {
+        let (Some(timeout), false) = (self.timeout, self.paused) else {
+            return false;
+        };
+        if self.ping_sent.is_some() || self.next_ping.is_some_and(|next| now < next) {
+            return false;
+        }
+        self.ping_sent = Some(now);
+        self.next_ping = Some(now + timeout);
+        true
+    }
+
+    /// The page answered the script sent at `sent`. Answers to scripts sent
+    /// before a reset don't count.
+    pub fn pong(&mut self, sent: Instant) -> Option<Change> {
+        if self.ping_sent != Some(sent) {
+            return None;
+        }
+        self.ping_sent = None;
+        self.recover(Stuck::ScriptHung)
+    }
+
+    fn recover(&mut self, from: Stuck) -> Option<Change> {
+        if self.stuck != Some(from) {
+            return None;
+        }
+        self.stuck = None;
+        Some(Change::Recovered)
+    }
+
+    /// Whether the page became stuck by `now`. Reported once until it
+    /// recovers.
+    pub fn check(&mut self, now: Instant) -> Option<Change> {
+        let timeout = self.timeout?;
+        if self.stuck.is_some() || self.paused {
+            return None;
+        }
+        let stuck = if self.paint_deadline.is_some_and(|deadline| now >= deadline) {
+            Stuck::NoPaint
+        } else if self.ping_sent.is_some_and(|sent| now >= sent + timeout) {
+            Stuck::ScriptHung
+        } else {
+            return None;
+        };
+        self.stuck = Some(stuck);
+        Some(Change::Stuck(stuck))
+    }
+
+    /// When the watchdog next needs to run.
+    pub fn deadline(&self) -> Option<Instant> {
+        let timeout = self.timeout?;
+        if self.paused {
+            return None;
+        }
+        let mut deadlines = Vec::new();
+        if self.stuck.is_none() {
+            deadlines.push(self.paint_deadline);
+            deadlines.push(self.ping_sent.map(|sent| sent + timeout));
+        }
+        if self.ping_sent.is_none() {
+            deadlines.push(self.next_ping);
+        }
+        deadlines.into_iter().flatten().min()
+    }
+}
+
+/// The `crash` event reporting `change`.
+pub fn event(change: &Change) -> Value {
+    match change {
+        Change::Stuck(stuck) => json!({"type": "unresponsive", "reason": stuck.reason()}),
+        Change::Recovered => json!({"type": "responsive"}),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    #[test]
+    fn reports_a_loaded_page_that_never_paints() {
+        let start = Instant::now();
+        let mut watchdog = Watchdog::new(Some(TIMEOUT));
+        watchdog.load_complete(start);
+        assert_eq!(watchdog.check(start + Duration::from_secs(1)), None);
+        assert_eq!(
+            watchdog.check(start + TIMEOUT),
+            Some(Change::Stuck(Stuck::NoPaint))
+        );
+        assert_eq!(watchdog.check(start + TIMEOUT * 2), None);
+        assert_eq!(watchdog.frame_painted(), Some(Change::Recovered));
+    }
+
+    #[test]
+    fn an_idle_painted_page_is_fine() {
+        let start = Instant::now();
+        let mut watchdog = Watchdog::new(Some(TIMEOUT));
+        watchdog.frame_painted();
+        watchdog.load_complete(start);
+        assert!(watchdog.ping_due(start));
+        assert_eq!(watchdog.pong(start), None);
+        assert!(!watchdog.ping_due(start + Duration::from_secs(1)));
+        assert_eq!(watchdog.check(start + TIMEOUT * 10), None);
+    }
+
+    #[test]
+    fn reports_a_page_that_stops_answering() {
+        let start = Instant::now();
+        let mut watchdog = Watchdog::new(Some(TIMEOUT));
+        assert!(watchdog.ping_due(start));
+        assert!(!watchdog.ping_due(start + TIMEOUT));
+        assert_eq!(
+            watchdog.check(start + TIMEOUT),
+            Some(Change::Stuck(Stuck::ScriptHung))
+        );
+        assert_eq!(watchdog.pong(start), Some(Change::Recovered));
+    }
+
+    #[test]
+    fn ignores_answers_from_before_a_reset() {
+        let start = Instant::now();
+        let mut watchdog = Watchdog::new(Some(TIMEOUT));
+        assert!(watchdog.ping_due(start));
+        watchdog.reset();
+        let later = start + Duration::from_secs(1);
+        assert!(watchdog.ping_due(later));
+        assert_eq!(watchdog.pong(start), None);
+        assert_eq!(
+            watchdog.check(later + TIMEOUT),
+            Some(Change::Stuck(Stuck::ScriptHung))
+        );
+    }
+
+    #[test]
+    fn a_modal_dialog_pauses_it() {
+        let start = Instant::now();
+        let mut watchdog = Watchdog::new(Some(TIMEOUT));
+        watchdog.frame_painted();
+        assert!(watchdog.ping_due(start));
+        watchdog.pause();
+        assert_eq!(watchdog.check(start + TIMEOUT * 3), None);
+        assert_eq!(watchdog.deadline(), None);
+        let closed = start + TIMEOUT * 3;
+        watchdog.resume(closed);
+        assert_eq!(watchdog.pong(start), None);
+        assert_eq!(watchdog.check(closed), None);
+        assert!(!watchdog.ping_due(closed));
+        assert!(watchdog.ping_due(closed + TIMEOUT));
+    }
+
+    #[test]
+    fn disabled_without_a_timeout() {
+        let start = Instant::now();
+        let mut watchdog = Watchdog::new(None);
+        watchdog.load_complete(start);
+        assert!(!watchdog.ping_due(start));
+        assert_eq!(watchdog.check(start + TIMEOUT), None);
+        assert_eq!(watchdog.deadline(), None);
+    }
+}
@@ -0,0 +1,151 @@
+//! Page zoom with per-origin overrides, like desktop browsers.
+//!
+//! A default level applies to every origin; zooming while on a page records
+//! an override for that page's origin, which takes precedence and is re-applied
+//! whenever the webview navigates back to it.
+
+use std::collections::HashMap;
+
+use servo::WebView;
+use url::Url;
+
+use crate::events::{emit_event, EventType};
+
+pub const MIN_ZOOM: f32 = 0.25;
+pub const MAX_ZOOM: f32 = 5.0;
+
+pub fn clamp_zoom(level: f32) -> f32 {
+    if level.is_finite() {
+        level.clamp(MIN_ZOOM, MAX_ZOOM)
+    } else {
+        1.0
+    }
+}
+
+/// Pinch zoom only magnifies; it can't shrink the page below its layout size.
+pub fn clamp_pinch_zoom(level: f32) -> f32 {
+    if level.is_finite() {
+        level.clamp(1.0, MAX_ZOOM)
+    } else {
+        1.0
+    }
+}
+
+/// Levels `pw_zoom_in` / `pw_zoom_out` step through, as in desktop browsers.
+const ZOOM_STEPS: [f32; 17] = [
+    0.25, 0.33, 0.5, 0.67, 0.75, 0.8, 0.9, 1.0, 1.1, 1.25, 1.5, 1.75, 2.0, 2.5, 3.0, 4.0, 5.0,
+];
+
+/// The next step above `level` (`zoom_in`) or below it, staying at the ends.
+pub fn step_zoom(level: f32, zoom_in: bool) -> f32 {
+    // Tolerate levels set by pinching, which fall between steps.
+    const EPSILON: f32 = 0.005;
+    let next = if zoom_in {
+        ZOOM_STEPS.iter().find(|&&step| step > level + EPSILON)
+    } else {
+        ZOOM_STEPS
+            .iter()
+            .rev()
+            .find(|&&step| step < level - EPSILON)
+    };
+    next.copied().unwrap_or_else(|| clamp_zoom(level))
+}
+
+/// Opaque origins (`data:`, `about:blank`, ...) never get their own override.
+fn origin_key(url: &Url) -> Option<String> {
+    let origin = url.origin();
+    origin.is_tuple().then(|| origin.ascii_serialization())
+}
+
+pub struct ZoomLevels {
+    default: f32,
+    overrides: HashMap<String, f32>,
+    /// The level last applied to the webview, to report only real changes.
+    effective: f32,
+}
+
+impl Default for ZoomLevels {
+    fn default() -> Self {
+        ZoomLevels {
+            default: 1.0,
+            overrides: HashMap::new(),
+            effective: 1.0,
+        }
+    }
+}
+
+impl ZoomLevels {
+    pub fn level_for(&self, url: Option<&Url>) -> f32 {
+        url.and_then(origin_key)
+            .and_then(|origin| self.overrides.get(&origin).copied())
+            .unwrap_or(self.default)
+    }
+
+    pub fn set_default(&mut self, level: f32) {
+        self.default = clamp_zoom(level);
+    }
+
+    /// Remember `level` for the origin of `url`. Returns false for opaque
+    /// origins, which can't hold an override.
+    pub fn set_override(&mut self, url: Option<&Url>, level: f32) -> bool {
+        match url.and_then(origin_key) {
+            Some(origin) => {
+                self.overrides.insert(origin, clamp_zoom(level));
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn clear_overrides(&mut self) {
+        self.overrides.clear();
+    }
+
+    /// Return the current page to the default level, forgetting its origin's
+    /// override.
+    pub fn reset_page(&mut self, webview: &WebView) {
+        if let Some(origin) = webview.url().as_ref().and_then(origin_key) {
+            self.overrides.remove(&origin);
+        }
+        self.apply(webview);
+    }
+
+    /// The level currently applied to the webview.
+    pub fn effective(&self) -> f32 {
+        self.effective
+    }
+
+    /// Zoom the current page to `level` and remember it for the page's origin.
+    /// Pages with an opaque origin are zoomed without an override.
+    pub fn zoom_page(&mut self, webview: &WebView, level: f32) {
+        let url = webview.url();
+        if self.set_override(url.as_ref(), level) {
+            self.apply(webview);
+        } else {
+            self.show(webview, url.as_ref(), clamp_zoom(level));
+        }
+    }
+
+    /// Zoom `webview` to the level for its current URL, emitting a `zoom`
+    /// event if the effective level changed.
+    pub fn apply(&mut self, webview: &WebView) {
+        let url = webview.url();
+        let level = self.level_for(url.as_ref());
+        self.show(webview, url.as_ref(), level);
+    }
+
+    fn show(&mut self, webview: &WebView, url: Option<&Url>, level: f32) {
+        webview.set_page_zoom(level);
+        if level != self.effective {
+            self.effective = level;
+            emit_event(
+                EventType::Zoom,
+                serde_json::json!({
+                    "type": "zoom",
+                    "level": level,
+                    "origin": url.and_then(origin_key),
+                }),
+            );
+        }
+    }
+}
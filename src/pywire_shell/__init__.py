@@ -1,7 +1,22 @@
 import threading
 import ctypes
+import json
+import logging
+import os
 import uvicorn
-from pywire_shell._loader import load_runtime
+from pywire_shell._loader import (
+    EVENT_CALLBACK,
+    LOG_CALLBACK,
+    NAVIGATION_CALLBACK,
+    PROTOCOL_CALLBACK,
+    RESOURCE_CALLBACK,
+    TYPED_EVENT_CALLBACK,
+    load_runtime,
+)
+from pywire_shell.errors import ErrorCode
+from pywire_shell.events import EventType
+
+__all__ = ["App", "ErrorCode", "EventType"]
 
 
 class App:
@@ -13,6 +28,30 @@ class App:
         url=None,
         pywire_app=None,
         on_event=None,
+        start_hidden=False,
+        manual_title=False,
+        user_agent=None,
+        profile_dir=None,
+        headless=False,
+        devtools_port=0,
+        intercept_close=False,
+        app_dir=None,
+        disable_file_uploads=False,
+        max_fps=0,
+        smooth_scrolling=False,
+        background_color=0,
+        transparent=False,
+        log_level=None,
+        resources_path=None,
+        proxy_url=None,
+        no_proxy=None,
+        ca_certificates=None,
+        start_muted=False,
+        autoplay_policy=None,
+        watchdog_timeout_ms=0,
+        decorations=True,
+        hide_error_pages=False,
+        disable_script_queue=False,
     ):
         self.title = title
         self.width = width
@@ -20,15 +59,103 @@ class App:
         self.url = url
         self.pywire_app = pywire_app
         self.on_event = on_event
+        self.start_hidden = start_hidden
+        self.manual_title = manual_title
+        self.user_agent = user_agent
+        self.profile_dir = profile_dir
+        self.headless = headless
+        self.devtools_port = devtools_port
+        self.intercept_close = intercept_close
+        self.app_dir = app_dir
+        self.disable_file_uploads = disable_file_uploads
+        self.max_fps = max_fps
+        self.smooth_scrolling = smooth_scrolling
+        # 0xRRGGBBAA; 0 keeps the default (white, or clear when transparent).
+        self.background_color = background_color
+        self.transparent = transparent
+        # "off", "error", "warn", "info", "debug" or "trace"; None for "warn".
+        self.log_level = log_level
+        # Servo's engine resources; None falls back to SERVO_RESOURCES_PATH.
+        self.resources_path = resources_path
+        # "http://host:port" or "https://..."; None connects directly.
+        self.proxy_url = proxy_url
+        # Hosts that bypass the proxy, as a list or a comma-separated string.
+        self.no_proxy = no_proxy
+        # A PEM file of CAs to trust instead of the built-in roots.
+        self.ca_certificates = ca_certificates
+        self.start_muted = start_muted
+        # "allow", "block-audio" or "block"; None allows all autoplay.
+        self.autoplay_policy = autoplay_policy
+        # Emit "unresponsive" for a page blank or hung this long; 0 for never.
+        self.watchdog_timeout_ms = watchdog_timeout_ms
+        # False for a frameless window whose page draws its own title bar.
+        self.decorations = decorations
+        # Leave failed loads blank; handle "navigation_failed" events instead.
+        self.hide_error_pages = hide_error_pages
+        # Run execute_javascript/post_message calls at once instead of holding
+        # those made before the first page has loaded until it has.
+        self.disable_script_queue = disable_script_queue
         self._runtime = None
+        self._deferred_calls = []
+        self._navigation_callbacks = set()
+        # token -> [ctypes callback, registration function, its leading
+        # arguments, native token or None]
+        self._event_listeners = {}
+        self._next_listener_token = 1
+        self._resource_callback = None
+        self._log_callback = None
+        # resource name -> last buffer handed to the runtime, kept alive for it
+        self._resource_buffers = {}
+        # scheme -> ctypes callback serving it
+        self._protocol_callbacks = {}
+        # calling thread -> last (body, MIME type) buffers handed to the runtime
+        self._protocol_buffers = {}
         self._server_thread = None
         self._port = 17181  # Let's use a nice custom port
 
+    def run_diagnostics(self) -> dict:
+        """Report the runtime environment, e.g. to attach to a bug report.
+
+        Includes the engine resources check; once started, also the GL
+        renderer, monitors and window size (under "runtime").
+        """
+        runtime = self._runtime or load_runtime()
+        ptr = runtime.pw_run_diagnostics()
+        if not ptr:
+            raise RuntimeError("Diagnostics unavailable")
+        try:
+            return json.loads(ctypes.string_at(ptr).decode("utf-8"))
+        finally:
+            runtime.pw_free_string(ptr)
+
     def execute_javascript(self, script: str):
-        """Execute a string of JavaScript in the webview."""
-        if not self._runtime:
-            raise RuntimeError("App not started")
-        return self._runtime.pw_execute_javascript(script.encode("utf-8"))
+        """Execute a string of JavaScript in the webview.
+
+        Calls made before the first page has loaded run once it has, in order.
+        """
+        return self._call_or_defer("pw_execute_javascript", script.encode("utf-8"))
+
+    def post_message(self, channel: str, data=None):
+        """Send `data` (anything JSON can encode) to the page on `channel`.
+
+        The page receives it as a ``pywire-message`` event on ``window`` with
+        ``detail = {channel, data}``, and through ``pywire.onMessage(channel,
+        data)`` if it set one. Messages sent before the first page has loaded
+        arrive once it has.
+        """
+        if not channel:
+            raise ValueError("Message channel must not be empty")
+        return self._call_or_defer(
+            "pw_post_message", channel.encode("utf-8"), json.dumps(data).encode("utf-8")
+        )
+
+    def add_user_script(self, script: str, run_on_current_page: bool = False):
+        """Run `script` at document start on every page loaded from now on,
+        before the page's own scripts. With `run_on_current_page`, also run it
+        on the page already shown."""
+        return self._call_or_defer(
+            "pw_add_user_script", script.encode("utf-8"), run_on_current_page
+        )
 
     def set_title(self, title: str):
         """Update the window title."""
@@ -45,6 +172,1001 @@ class App:
             return
         return self._runtime.pw_resize_window(width, height)
 
+    def set_clipboard_text(self, text: str):
+        """Put text on the system clipboard."""
+        if not self._runtime:
+            raise RuntimeError("App not started")
+        return self._runtime.pw_set_clipboard_text(text.encode("utf-8"))
+
+    def get_clipboard_text(self):
+        """Return the clipboard's text, or None if it holds no text."""
+        if not self._runtime:
+            raise RuntimeError("App not started")
+        return self._take_string(self._runtime.pw_get_clipboard_text())
+
+    def set_window_icon(self, rgba: bytes, width: int, height: int) -> bool:
+        """Set the title bar and taskbar icon from raw RGBA pixels.
+
+        Returns False if the platform ignores window icons (macOS).
+        """
+        if not self._runtime:
+            raise RuntimeError("App not started")
+        buffer = (ctypes.c_uint8 * len(rgba)).from_buffer_copy(rgba)
+        code = self._runtime.pw_set_window_icon(buffer, len(rgba), width, height)
+        if code == -4:
+            raise ValueError(self.last_error())
+        return code == 0
+
+    def set_min_window_size(self, width: int, height: int):
+        """Stop the window shrinking below width x height; (0, 0) clears it."""
+        return self._call_or_defer("pw_set_min_window_size", width, height)
+
+    def set_max_window_size(self, width: int, height: int):
+        """Stop the window growing beyond width x height.
+
+        A 0 width or height leaves that side unbounded; (0, 0) clears it.
+        """
+        return self._call_or_defer("pw_set_max_window_size", width, height)
+
+    def set_window_position(self, x: int, y: int):
+        """Move the window's outer top-left corner to (x, y) in physical pixels.
+
+        Before ``start()`` this sets where the window opens. Has no effect on
+        Wayland.
+        """
+        return self._call_or_defer("pw_set_window_position", x, y)
+
+    def set_window_level(self, level: str):
+        """Stack the window "always-on-top", "always-on-bottom" or "normal".
+
+        Before ``start()`` this sets the level the window opens with, and
+        can't tell yet whether it will be honoured. After it, returns -6
+        where the level is ignored (Wayland, headless).
+        """
+        if level not in ("normal", "always-on-top", "always-on-bottom"):
+            raise ValueError(f"Unknown window level: {level!r}")
+        return self._call_or_defer("pw_set_window_level", level.encode("utf-8"))
+
+    def set_always_on_top(self, enabled: bool):
+        """Keep the window above all others, e.g. for overlays and HUDs."""
+        return self._call_or_defer("pw_set_always_on_top", bool(enabled))
+
+    def get_window_position(self):
+        """Return the window's (x, y) in physical pixels, or None if unavailable."""
+        if not self._runtime:
+            raise RuntimeError("App not started")
+        x, y = ctypes.c_int32(), ctypes.c_int32()
+        if self._runtime.pw_get_window_position(ctypes.byref(x), ctypes.byref(y)) != 0:
+            return None
+        return x.value, y.value
+
+    def get_scale_factor(self):
+        """Return the window's device pixel ratio, or None before it exists."""
+        if not self._runtime:
+            raise RuntimeError("App not started")
+        return self._runtime.pw_get_scale_factor() or None
+
+    def set_load_timeout(self, ms: int):
+        """Abort navigations that take longer than `ms` milliseconds (0 disables)."""
+        return self._call_or_defer("pw_set_load_timeout", ms)
+
+    def stop_loading(self) -> bool:
+        """Stop the active page's load; `load_stopped` fires once it has ended.
+
+        Returns False if nothing was loading.
+        """
+        if not self._runtime:
+            raise RuntimeError("App not started")
+        return self._runtime.pw_stop_loading() == 0
+
+    def set_mixed_content_policy(self, policy: str):
+        """Handle http: subresources on https: pages: "block", "allow" or "upgrade"."""
+        if policy not in ("block", "allow", "upgrade"):
+            raise ValueError(f"Unknown mixed content policy: {policy!r}")
+        return self._call_or_defer(
+            "pw_set_mixed_content_policy", policy.encode("utf-8")
+        )
+
+    def set_cache_mode(self, mode: str):
+        """Set the caching strategy: "default", or "no-store" to clear the HTTP
+        cache when the active webview navigates and again once it has loaded.
+
+        The cache is shared by all webviews and still stores responses while a
+        page loads, so "no-store" doesn't keep a page's subresources out of it.
+
+        "reload" and "force-cache" raise ValueError: Servo can't apply a cache
+        mode to a single request, so they can't be honoured.
+        """
+        if mode not in ("default", "no-store"):
+            raise ValueError(f"Unsupported cache mode: {mode!r}")
+        return self._call_or_defer("pw_set_cache_mode", mode.encode("utf-8"))
+
+    def set_error_page(self, kind: str, html: str | None):
+        """Show `html` when a load fails with a "network", "dns" or "tls" error.
+
+        ``{{url}}`` and ``{{error}}`` are filled in with the failed URL and the
+        reason. Reloading the page retries the URL. None restores the default.
+        """
+        if kind not in ("network", "dns", "tls"):
+            raise ValueError(f"Unknown error page kind: {kind!r}")
+        return self._call_or_defer(
+            "pw_set_error_page", kind.encode("utf-8"), html.encode("utf-8") if html else None
+        )
+
+    def set_spatial_navigation(self, enabled: bool):
+        """Move focus with the arrow keys and activate with Enter (remote controls)."""
+        return self._call_or_defer("pw_set_spatial_navigation", enabled)
+
+    def set_scroll_speed(self, line_height: float = 0):
+        """Scroll `line_height` CSS pixels per wheel line (0 for the default)."""
+        if line_height < 0:
+            raise ValueError(f"Invalid line height: {line_height!r}")
+        return self._call_or_defer("pw_set_scroll_speed", float(line_height))
+
+    def set_natural_scrolling(self, enabled: bool):
+        """Invert the scroll direction so content follows the fingers."""
+        return self._call_or_defer("pw_set_natural_scrolling", bool(enabled))
+
+    def register_accelerator(self, combo: str, action_id: int, deliver_to_page: bool = False):
+        """Handle the key combo `combo` (e.g. "CmdOrCtrl+R") in the app: pressing
+        it emits an `accelerator` input event carrying `action_id`. The page only
+        sees the key too if `deliver_to_page` is set."""
+        # Called right away rather than deferred, so a bad combo raises here
+        # even before start().
+        runtime = self._runtime or load_runtime()
+        result = runtime.pw_register_accelerator(
+            combo.encode("utf-8"), action_id, bool(deliver_to_page)
+        )
+        if result == -4:
+            raise ValueError(f"Invalid key combo: {combo!r}")
+        return result
+
+    def unregister_accelerator(self, action_id: int):
+        """Stop handling the accelerator registered for `action_id`."""
+        runtime = self._runtime or load_runtime()
+        result = runtime.pw_unregister_accelerator(action_id)
+        if result == -4:
+            raise ValueError(f"Unknown accelerator: {action_id}")
+        return result
+
+    def set_color_scheme(self, scheme: str = "auto"):
+        """Make pages see a "light" or "dark" `prefers-color-scheme`, or "auto"
+        to follow the OS theme."""
+        if scheme not in ("light", "dark", "auto"):
+            raise ValueError(f"Unknown color scheme: {scheme!r}")
+        return self._call_or_defer("pw_set_color_scheme", scheme.encode("utf-8"))
+
+    def set_focus_ring(self, style=True):
+        """Force a visible focus ring: True for the built-in one, a CSS string
+        for a custom one, or False to keep the page's own focus styling."""
+        if style is True:
+            style = "on"
+        elif not style:
+            style = "off"
+        return self._call_or_defer("pw_set_focus_ring_style", style.encode("utf-8"))
+
+    def get_scroll_position(self):
+        """Return the page's (x, y) scroll offset, or None if unavailable."""
+        if not self._runtime:
+            raise RuntimeError("App not started")
+        x, y = ctypes.c_double(), ctypes.c_double()
+        if self._runtime.pw_get_scroll_position(ctypes.byref(x), ctypes.byref(y)) != 0:
+            return None
+        return x.value, y.value
+
+    def set_scroll_position(self, x: float, y: float):
+        """Scroll the page to (x, y), waiting for the current load to finish."""
+        if not self._runtime:
+            raise RuntimeError("App not started")
+        return self._runtime.pw_set_scroll_position(x, y)
+
+    def send_mouse_click(self, x: float, y: float, button: str = "left"):
+        """Click at (x, y) in CSS pixels, as from ``getBoundingClientRect()``.
+
+        `button` is "left", "middle", "right", "back" or "forward".
+        """
+        if not self._runtime:
+            raise RuntimeError("App not started")
+        if button not in ("left", "middle", "right", "back", "forward"):
+            raise ValueError(f"Unknown mouse button: {button!r}")
+        return self._runtime.pw_send_mouse_click(
+            float(x), float(y), button.encode("utf-8")
+        )
+
+    def send_key(self, combo: str):
+        """Press and release a key combo such as "Enter" or "CmdOrCtrl+A"."""
+        if not self._runtime:
+            raise RuntimeError("App not started")
+        result = self._runtime.pw_send_key(combo.encode("utf-8"))
+        if result == -4:
+            raise ValueError(f"Invalid key combo: {combo!r}")
+        return result
+
+    def send_scroll(self, dx: float, dy: float):
+        """Scroll by (dx, dy) CSS pixels with a wheel event; positive is down/right."""
+        if not self._runtime:
+            raise RuntimeError("App not started")
+        return self._runtime.pw_send_scroll(float(dx), float(dy))
+
+    def capture_dom_snapshot(self, max_depth: int = 0):
+        """Return the DOM with bounding boxes and computed styles as a dict."""
+        if not self._runtime:
+            raise RuntimeError("App not started")
+        snapshot = self._take_string(self._runtime.pw_capture_dom_snapshot(max_depth))
+        return json.loads(snapshot) if snapshot is not None else None
+
+    def find(self, text: str, forward: bool = True, case_sensitive: bool = False):
+        """Highlight `text` in the page; repeat the call to step through matches.
+
+        A ``find_result`` event reports the number of ``matches`` and the
+        current match's ``index``. An empty `text` clears the search.
+        """
+        if not self._runtime:
+            raise RuntimeError("App not started")
+        return self._runtime.pw_find(text.encode("utf-8"), forward, case_sensitive)
+
+    def find_clear(self):
+        """End the current search and remove its highlights."""
+        if not self._runtime:
+            raise RuntimeError("App not started")
+        return self._runtime.pw_find_clear()
+
+    def set_webgl_enabled(self, enabled: bool):
+        """Enable or disable WebGL. Must be called before start()."""
+        if self._runtime:
+            raise RuntimeError("WebGL can only be configured before start()")
+        return self._call_or_defer("pw_set_webgl_enabled", enabled)
+
+    def set_accelerated_canvas(self, enabled: bool):
+        """Use the GPU (True) or software (False) canvas. Call before start()."""
+        if self._runtime:
+            raise RuntimeError("Canvas acceleration can only be set before start()")
+        return self._call_or_defer("pw_set_accelerated_canvas", enabled)
+
+    def set_zoom(self, level: float):
+        """Set the default page zoom for origins without their own level."""
+        if not self._runtime:
+            raise RuntimeError("App not started")
+        return self._runtime.pw_set_zoom(level)
+
+    def set_origin_zoom(self, level: float):
+        """Zoom the current page and remember the level for its origin."""
+        if not self._runtime:
+            raise RuntimeError("App not started")
+        return self._runtime.pw_set_origin_zoom(level)
+
+    def clear_zoom_overrides(self):
+        """Forget per-origin zoom levels."""
+        if not self._runtime:
+            raise RuntimeError("App not started")
+        return self._runtime.pw_clear_zoom_overrides()
+
+    def zoom_in(self):
+        """Zoom the current page in one step, remembered for its origin."""
+        if not self._runtime:
+            raise RuntimeError("App not started")
+        return self._runtime.pw_zoom_in()
+
+    def zoom_out(self):
+        """Zoom the current page out one step, remembered for its origin."""
+        if not self._runtime:
+            raise RuntimeError("App not started")
+        return self._runtime.pw_zoom_out()
+
+    def reset_zoom(self):
+        """Return the current page to the default zoom."""
+        if not self._runtime:
+            raise RuntimeError("App not started")
+        return self._runtime.pw_reset_zoom()
+
+    def set_pinch_zoom(self, level: float):
+        """Magnify the rendered page without relayout (1.0 to 5.0)."""
+        if not self._runtime:
+            raise RuntimeError("App not started")
+        return self._runtime.pw_set_pinch_zoom(level)
+
+    def add_url_rewrite(self, pattern: str, replacement: str):
+        """Rewrite URLs matching the regex `pattern` (supports $1 group refs)."""
+        # Called right away rather than deferred, so a bad pattern raises here
+        # even before start().
+        runtime = self._runtime or load_runtime()
+        result = runtime.pw_add_url_rewrite(
+            pattern.encode("utf-8"), replacement.encode("utf-8")
+        )
+        if result == -4:
+            raise ValueError(f"Invalid URL rewrite pattern: {pattern!r}")
+        return result
+
+    def clear_url_rewrites(self):
+        """Remove all URL rewrite rules."""
+        # Not deferred either, so it only removes the rules added before it.
+        runtime = self._runtime or load_runtime()
+        return runtime.pw_clear_url_rewrites()
+
+    def navigate(self, url: str, on_finished=None):
+        """Load `url`; `on_finished(success, status)` is called once it ends."""
+        if not self._runtime:
+            raise RuntimeError("App not started")
+
+        # The native side holds only a raw pointer, so keep the ctypes wrapper
+        # alive until it has fired.
+        def finished(success, status):
+            self._navigation_callbacks.discard(callback)
+            if on_finished:
+                on_finished(success, status.decode("utf-8"))
+
+        callback = NAVIGATION_CALLBACK(finished)
+        self._navigation_callbacks.add(callback)
+        return self._runtime.pw_navigate_with_callback(url.encode("utf-8"), callback)
+
+    def get_url(self):
+        """Return the URL of the page currently shown, or None before the first
+        navigation. Changes are reported as ``{"type": "url_changed", ...}``."""
+        if not self._runtime:
+            raise RuntimeError("App not started")
+        return self._take_string(self._runtime.pw_get_url())
+
+    def create_webview(self, url: str) -> int:
+        """Open another webview (a tab) loading `url` in the background.
+
+        Returns its id; the initial webview is 1. Other methods act on the
+        active webview, and events carry the originating ``webview_id``.
+        """
+        if not self._runtime:
+            raise RuntimeError("App not started")
+        result = self._runtime.pw_create_webview(url.encode("utf-8"))
+        if result < 0:
+            raise RuntimeError(f"Could not create webview ({result})")
+        return result
+
+    def activate_webview(self, webview_id: int):
+        """Show the webview `webview_id` in place of the active one."""
+        if not self._runtime:
+            raise RuntimeError("App not started")
+        result = self._runtime.pw_activate_webview(webview_id)
+        if result == -4:
+            raise ValueError(f"Unknown webview id: {webview_id}")
+        return result
+
+    def close_webview(self, webview_id: int):
+        """Close the webview `webview_id`; closing the active one shows a
+        neighbour."""
+        if not self._runtime:
+            raise RuntimeError("App not started")
+        result = self._runtime.pw_close_webview(webview_id)
+        if result == -4:
+            raise ValueError(f"Unknown webview id: {webview_id}")
+        return result
+
+    def set_new_window_policy(self, policy: str):
+        """Handle pages opening new windows: "tab" opens and shows a new
+        webview, "event" only reports ``{"type": "new_window", ...}``."""
+        if policy not in ("tab", "event"):
+            raise ValueError(f"Unknown new window policy: {policy!r}")
+        return self._call_or_defer("pw_set_new_window_policy", policy.encode("utf-8"))
+
+    def set_native_context_menu(self, native: bool):
+        """Show the built-in macOS context menu (the default), or report
+        right-clicks as ``{"type": "context_menu", ...}`` events to build your
+        own. Other platforms always report them."""
+        return self._call_or_defer("pw_set_native_context_menu", bool(native))
+
+    def provide_credentials(self, request_id: int, username: str, password: str):
+        """Answer an ``{"type": "auth_required", ...}`` challenge.
+
+        The event's ``realm`` is None while Servo doesn't report it.
+        """
+        if not self._runtime:
+            raise RuntimeError("App not started")
+        result = self._runtime.pw_provide_credentials(
+            request_id, username.encode("utf-8"), password.encode("utf-8")
+        )
+        if result == -4:
+            raise ValueError(f"Unknown authentication request: {request_id}")
+        return result
+
+    def cancel_authentication(self, request_id: int):
+        """Decline an ``auth_required`` challenge; the page shows the 401."""
+        if not self._runtime:
+            raise RuntimeError("App not started")
+        result = self._runtime.pw_cancel_authentication(request_id)
+        if result == -4:
+            raise ValueError(f"Unknown authentication request: {request_id}")
+        return result
+
+    def allow_cert(self, request_id: int):
+        """Trust the certificate of a ``cert_error`` until exit and load the page.
+
+        Only for hosts the app knows, such as internal servers with
+        self-signed certificates.
+        """
+        if not self._runtime:
+            raise RuntimeError("App not started")
+        result = self._runtime.pw_allow_cert(request_id)
+        if result == -4:
+            raise ValueError(
+                f"Certificate error {request_id} is unknown, not allowable, "
+                "or its page is no longer shown"
+            )
+        return result
+
+    def reject_cert(self, request_id: int):
+        """Keep the error page for a ``cert_error``, as for unanswered ones."""
+        if not self._runtime:
+            raise RuntimeError("App not started")
+        result = self._runtime.pw_reject_cert(request_id)
+        if result == -4:
+            raise ValueError(f"Unknown certificate error: {request_id}")
+        return result
+
+    def respond_permission(self, request_id: int, allow: bool):
+        """Answer a ``{"type": "permission", "kind": ...}`` request."""
+        if not self._runtime:
+            raise RuntimeError("App not started")
+        result = self._runtime.pw_respond_permission(request_id, bool(allow))
+        if result == -4:
+            raise ValueError(f"Unknown permission request: {request_id}")
+        return result
+
+    def set_permission_timeout(self, ms: int):
+        """Deny permission requests left unanswered for `ms` milliseconds."""
+        return self._call_or_defer("pw_set_permission_timeout", ms)
+
+    def set_auth_timeout(self, ms: int):
+        """Cancel ``auth_required`` challenges left unanswered for `ms` milliseconds.
+
+        The page then shows the server's 401, and an ``auth_timeout`` event
+        names the challenge. 60 seconds by default.
+        """
+        return self._call_or_defer("pw_set_auth_timeout", ms)
+
+    def add_event_listener(self, listener) -> int:
+        """Call `listener(payload)` for every event, in addition to `on_event`.
+
+        Listeners run on the event loop thread in registration order, so they
+        must return quickly. Returns a token for remove_event_listener().
+        """
+
+        def deliver(payload_ptr):
+            listener(ctypes.string_at(payload_ptr).decode("utf-8"))
+
+        return self._register_listener(
+            EVENT_CALLBACK(deliver), "pw_add_event_listener_native"
+        )
+
+    def add_typed_event_listener(self, listener) -> int:
+        """Call `listener(event_type, payload)` for every event.
+
+        `event_type` is an EventType, so listeners can ignore events they don't
+        care about without decoding the payload. Same threading rules and
+        token semantics as add_event_listener().
+        """
+
+        def deliver(event_type, payload_ptr):
+            payload = ctypes.string_at(payload_ptr).decode("utf-8")
+            listener(EventType(event_type), payload)
+
+        return self._register_listener(
+            TYPED_EVENT_CALLBACK(deliver), "pw_add_typed_event_listener_native"
+        )
+
+    def register_callback(self, event_type: str, listener) -> int:
+        """Call `listener(payload)` only for events of one kind.
+
+        `event_type` is a category name ("bridge", "load", "title",
+        "console", ...) or an event's `type` ("load_status", "title", ...).
+        Same threading rules and token semantics as add_event_listener().
+        """
+
+        def deliver(payload_ptr):
+            listener(ctypes.string_at(payload_ptr).decode("utf-8"))
+
+        return self._register_listener(
+            EVENT_CALLBACK(deliver),
+            "pw_register_callback",
+            event_type.encode("utf-8"),
+        )
+
+    def remove_event_listener(self, token: int):
+        """Stop delivering events to a listener added with add_*_event_listener()."""
+        *_, native_token = self._event_listeners.pop(token)
+        if native_token is not None:
+            self._runtime.pw_remove_event_listener_native(native_token)
+
+    def _register_listener(self, callback, register, *args):
+        """Track a listener, registering it natively now or once started."""
+        token = self._next_listener_token
+        self._next_listener_token += 1
+        native_token = None
+        if self._runtime:
+            native_token = getattr(self._runtime, register)(*args, callback)
+        self._event_listeners[token] = [callback, register, args, native_token]
+        return token
+
+    def pause_all_media(self):
+        """Pause all media in the page and keep new media paused."""
+        return self._call_or_defer("pw_pause_all_media")
+
+    def resume_all_media(self):
+        """Resume the media paused by pause_all_media()."""
+        return self._call_or_defer("pw_resume_all_media")
+
+    def set_muted(self, muted=True):
+        """Mute the page's audio and video elements, or unmute them."""
+        return self._call_or_defer("pw_set_muted", bool(muted))
+
+    def set_volume(self, volume):
+        """Play the page's media at `volume` (0 to 1) times its own volume."""
+        if not 0.0 <= volume <= 1.0:
+            raise ValueError(f"Volume must be between 0 and 1, not {volume!r}")
+        return self._call_or_defer("pw_set_volume", float(volume))
+
+    def set_default_encoding(self, charset):
+        """Decode the app's pages without a declared charset as `charset`.
+
+        Covers file: URLs and the app's own schemes (register_protocol(),
+        app_dir); pages from the network and pages that declare their own
+        charset are unaffected. Pass "" to restore the default (UTF-8 with
+        auto-detection).
+        """
+        # Called right away rather than deferred, so an unknown label raises
+        # here even before start().
+        runtime = self._runtime or load_runtime()
+        result = runtime.pw_set_default_encoding((charset or "").encode("utf-8"))
+        if result == -4:
+            raise ValueError(self._take_string(runtime.pw_last_error(), runtime))
+        return result
+
+    def set_do_not_track(self, enabled):
+        """Expose navigator.doNotTrack = "1" to pages (off by default).
+
+        Only scripts see it: the `DNT: 1` request header is not sent.
+        """
+        return self._call_or_defer("pw_set_do_not_track", bool(enabled))
+
+    def set_global_privacy_control(self, enabled):
+        """Expose navigator.globalPrivacyControl to pages (off by default).
+
+        Only scripts see it: the `Sec-GPC: 1` request header is not sent.
+        """
+        return self._call_or_defer("pw_set_global_privacy_control", bool(enabled))
+
+    def set_log_callback(self, callback):
+        """Receive the engine's log records as `callback(level, target, message)`
+        instead of having them printed to stderr.
+
+        `level` is a `logging` level, so forwarding them is
+        ``lambda level, target, message: logging.getLogger(target).log(level, message)``.
+        The callback runs on engine threads. Pass None to print to stderr again.
+        Set it before start() to catch the startup messages too.
+        """
+        levels = {1: logging.ERROR, 2: logging.WARNING, 3: logging.INFO, 4: logging.DEBUG}
+
+        def log(level, target, message):
+            callback(
+                levels.get(level, logging.DEBUG - 5),
+                target.decode("utf-8", "replace"),
+                message.decode("utf-8", "replace"),
+            )
+
+        self._log_callback = LOG_CALLBACK(log) if callback else None
+        return self._call_or_defer("pw_set_log_callback", self._log_callback)
+
+    def set_resource_provider(self, provider):
+        """Serve Servo's engine resources from `provider(name) -> bytes | None`.
+
+        Lets a single-file bundle ship the resources in memory instead of in a
+        directory. Returning None falls back to `resources_path` or
+        SERVO_RESOURCES_PATH. The provider is called from engine threads,
+        possibly concurrently. Must be set before start().
+        """
+
+        def read(name, data, length):
+            try:
+                contents = provider(name.decode("utf-8"))
+            except Exception as e:
+                print(f"[pywire-shell] Resource provider failed for {name!r}: {e}")
+                return False
+            if contents is None:
+                return False
+            contents = bytes(contents)
+            buffer = ctypes.create_string_buffer(contents, max(len(contents), 1))
+            self._resource_buffers[name] = buffer
+            data[0] = ctypes.cast(buffer, ctypes.c_void_p)
+            length[0] = len(contents)
+            return True
+
+        self._resource_callback = RESOURCE_CALLBACK(read)
+        result = self._call_or_defer("pw_set_resource_callback", self._resource_callback)
+        if result == -7:
+            raise RuntimeError("Resource provider must be set before start()")
+
+    def add_resources(self, resources):
+        """Serve Servo's engine resources from memory.
+
+        `resources` maps file names (``"user-agent.css"``) to their contents,
+        or is a directory whose files are read in now, e.g. one unpacked from
+        a single-file bundle. Bundled resources take precedence over the
+        resource provider and `resources_path`; with all of them bundled,
+        neither is needed. Must be called before start().
+        """
+        if isinstance(resources, (str, os.PathLike)):
+            root = os.fspath(resources)
+            files = {}
+            for directory, _, names in os.walk(root):
+                for name in names:
+                    path = os.path.join(directory, name)
+                    relative = os.path.relpath(path, root).replace(os.sep, "/")
+                    with open(path, "rb") as f:
+                        files[relative] = f.read()
+            resources = files
+        # Added right away rather than deferred, so a bad name or a call
+        # after start() raises here.
+        runtime = self._runtime or load_runtime()
+        for name, contents in resources.items():
+            contents = bytes(contents)
+            result = runtime.pw_add_resource(name.encode("utf-8"), contents, len(contents))
+            if result == -4:
+                raise ValueError(f"Invalid resource name {name!r}")
+            if result == -7:
+                raise RuntimeError("Resources must be added before start()")
+
+    def register_protocol(self, scheme, handler):
+        """Serve `scheme://` URLs (e.g. ``app://index.html``) from the app.
+
+        `handler` is either a directory to serve files from, or a callable
+        `handler(path) -> (bytes, mime_type) | bytes | None` given the URL's
+        path without the scheme (``"index.html"``). Returning bytes alone
+        guesses the MIME type from the path; None answers 404. Range requests
+        are handled for you. The callable is called from engine threads,
+        possibly concurrently. Must be registered before start().
+        """
+        # Registered right away rather than deferred, so a bad scheme or a
+        # missing directory raises here even before start().
+        runtime = self._runtime or load_runtime()
+        if isinstance(handler, (str, os.PathLike)):
+            result = runtime.pw_register_protocol_directory(
+                scheme.encode("utf-8"), os.fsencode(handler)
+            )
+        else:
+
+            def serve(path, data, length, mime_type):
+                try:
+                    contents = handler(path.decode("utf-8"))
+                except Exception as e:
+                    print(f"[pywire-shell] Protocol handler failed for {path!r}: {e}")
+                    return False
+                if contents is None:
+                    return False
+                content_type = None
+                if isinstance(contents, tuple):
+                    contents, content_type = contents
+                contents = bytes(contents)
+                body = ctypes.create_string_buffer(contents, max(len(contents), 1))
+                buffers = [body]
+                data[0] = ctypes.cast(body, ctypes.c_void_p)
+                length[0] = len(contents)
+                if content_type:
+                    content_type = ctypes.create_string_buffer(content_type.encode("utf-8"))
+                    buffers.append(content_type)
+                    mime_type[0] = ctypes.cast(content_type, ctypes.c_void_p)
+                self._protocol_buffers[threading.get_ident()] = buffers
+                return True
+
+            callback = PROTOCOL_CALLBACK(serve)
+            self._protocol_callbacks[scheme.lower()] = callback
+            result = runtime.pw_register_protocol(scheme.encode("utf-8"), callback)
+        if result == -4:
+            raise ValueError(f"Cannot register protocol {scheme!r} for {handler!r}")
+        if result == -7:
+            raise RuntimeError("Protocols must be registered before start()")
+        return result
+
+    def set_preference(self, key, value):
+        """Set one of Servo's preferences, e.g. ``("dom.webgl2.enabled", False)``.
+
+        For settings without a method of their own. `value` must have the
+        type of the preference's default: bool, int, float, str or list.
+        Overrides the app's own settings. Must be set before start().
+        """
+        # Checked right away, so a typo raises here rather than at start().
+        runtime = self._runtime or load_runtime()
+        result = runtime.pw_set_preference(
+            key.encode("utf-8"), json.dumps(value).encode("utf-8")
+        )
+        if result == -4:
+            raise ValueError(self._take_string(runtime.pw_last_error(), runtime))
+        if result == -7:
+            raise RuntimeError("Preferences must be set before start()")
+        return result
+
+    def set_strict_resources(self, strict=True):
+        """Fail start() if required engine resources are missing.
+
+        last_error() then names the missing files. Otherwise the app starts
+        anyway and each resource that can't be read is reported as a
+        ``resource_missing`` event. Must be set before start().
+        """
+        return self._call_or_defer("pw_set_strict_resources", bool(strict))
+
+    def capture_png(self, path):
+        """Save the rendered page to a PNG file."""
+        if not self._runtime:
+            raise RuntimeError("App not started")
+        code = self._runtime.pw_capture_png(os.fsencode(path))
+        if code == -6:
+            raise RuntimeError("No frame has been rendered yet")
+        if code == -10:
+            raise OSError(self.last_error())
+        return code
+
+    def print_to_pdf(self, path, page_size="a4"):
+        """Start saving the page to a PDF file, on "a4" or "letter" paper.
+
+        A ``pdf_printed`` event follows once the file is written, or
+        ``print_failed`` if it can't be.
+        """
+        if not self._runtime:
+            raise RuntimeError("App not started")
+        code = self._runtime.pw_print_to_pdf(
+            os.fsencode(path), page_size.encode("utf-8")
+        )
+        if code == -4:
+            raise ValueError(f"Unknown page size: {page_size!r}")
+        if code == -6:
+            raise RuntimeError(self.last_error())
+        return code
+
+    def clear_cookies(self):
+        """Delete every cookie, e.g. to log the user out."""
+        if not self._runtime:
+            raise RuntimeError("App not started")
+        return self._runtime.pw_clear_cookies()
+
+    def get_user_agent(self) -> str:
+        """Return the User-Agent the engine sends."""
+        if self._runtime:
+            return self._take_string(self._runtime.pw_get_user_agent())
+        if self.user_agent:
+            return self.user_agent
+        runtime = load_runtime()
+        return self._take_string(runtime.pw_get_user_agent(), runtime)
+
+    def set_crash_recovery(self, enabled=True):
+        """Reload pages that crash, unless they crashed again within 30 s.
+
+        Crashes are reported as ``crash`` events either way; their
+        ``reloading`` says whether the page is being reloaded.
+        """
+        return self._call_or_defer("pw_set_crash_recovery", bool(enabled))
+
+    def request_memory_report(self) -> int:
+        """Ask the engine for its memory use; returns the request id.
+
+        The report arrives as a ``memory_report`` event with that
+        ``request_id``: a ``summary`` with the ``explicit`` total in bytes and
+        its split ``by_category``, and the engine's full ``report``.
+        """
+        if not self._runtime:
+            raise RuntimeError("App not started")
+        result = self._runtime.pw_get_memory_report()
+        if result < 0:
+            raise RuntimeError(f"Memory report failed: {self.last_error()}")
+        return result
+
+    def last_error(self):
+        """Describe the most recent runtime failure, or None."""
+        if not self._runtime:
+            return None
+        return self._take_string(self._runtime.pw_last_error())
+
+    def set_window_shadow(self, enabled):
+        """Show or hide the window's drop shadow (macOS and Windows)."""
+        return self._call_or_defer("pw_set_window_shadow", bool(enabled))
+
+    def set_corner_radius(self, radius):
+        """Round the window corners by `radius` logical pixels, 0 for square.
+
+        Exact on macOS; Windows 11 picks the closest of its corner styles.
+        """
+        if radius < 0:
+            raise ValueError("radius must not be negative")
+        return self._call_or_defer("pw_set_corner_radius", float(radius))
+
+    def set_thumbnail(self, source):
+        """Set the Dock image: an image path, "auto" or None (macOS only).
+
+        "auto" shows a capture of the page, refreshed every 30 seconds and on
+        refresh_thumbnail(). Returns -6 on other platforms.
+        """
+        # Called right away rather than deferred, so a missing file raises
+        # and -6 comes back even before start().
+        runtime = self._runtime or load_runtime()
+        result = runtime.pw_set_thumbnail(str(source or "").encode("utf-8"))
+        if result == -4:
+            raise FileNotFoundError(f"Cannot read thumbnail image: {source!r}")
+        return result
+
+    def refresh_thumbnail(self):
+        """Redisplay the thumbnail, recapturing the page in "auto" mode.
+
+        Returns -6 outside macOS.
+        """
+        return self._call_or_defer("pw_refresh_thumbnail")
+
+    def set_taskbar_progress(self, fraction, state="normal"):
+        """Show progress on the taskbar button or Dock icon.
+
+        `state` is "normal", "paused", "error", "indeterminate" or "none".
+        """
+        if not self._runtime:
+            raise RuntimeError("App not started")
+        result = self._runtime.pw_set_taskbar_progress(
+            float(fraction), state.encode("utf-8")
+        )
+        if result == -4:
+            raise ValueError(f"Unknown progress state: {state!r}")
+        return result
+
+    def set_auto_taskbar_progress(self, enabled):
+        """Show page load progress on the taskbar button or Dock icon."""
+        return self._call_or_defer("pw_set_auto_taskbar_progress", bool(enabled))
+
+    def set_badge_count(self, count):
+        """Show `count` on the Dock icon or taskbar button; 0 clears it."""
+        if not self._runtime:
+            raise RuntimeError("App not started")
+        if count < 0:
+            raise ValueError("count must not be negative")
+        return self._runtime.pw_set_badge_count(int(count))
+
+    def clear_badge(self):
+        """Remove the badge set by set_badge_count()."""
+        if not self._runtime:
+            raise RuntimeError("App not started")
+        return self._runtime.pw_clear_badge()
+
+    def request_single_instance(self, app_id) -> bool:
+        """Claim `app_id`, or hand this launch to the instance that has it.
+
+        Call before start(). Returns True if this is the only instance, and
+        False if another one was brought to the front instead (it receives a
+        "second_instance" event with our argv and cwd); exit in that case.
+        """
+        # Loading the library again later returns the same instance, so the
+        # claim carries over to start().
+        runtime = self._runtime or load_runtime()
+        result = runtime.pw_request_single_instance(app_id.encode("utf-8"))
+        if result == 0:
+            return True
+        if result == 1:
+            return False
+        if result == -4:
+            raise ValueError(f"Invalid app id: {app_id!r}")
+        error = self._take_string(runtime.pw_last_error(), runtime)
+        raise RuntimeError(f"Single-instance check failed: {error}")
+
+    def load_url(self, url: str):
+        """Navigate to `url` (an unparsable URL loads about:blank)."""
+        if not self._runtime:
+            self.url = url
+            return
+        return self._runtime.pw_load_url(url.encode("utf-8"))
+
+    def reload(self):
+        """Reload the current page."""
+        if not self._runtime:
+            raise RuntimeError("App not started")
+        return self._runtime.pw_reload()
+
+    def go_back(self) -> bool:
+        """Go back in history; False if there was nothing to go back to."""
+        if not self._runtime:
+            raise RuntimeError("App not started")
+        return self._runtime.pw_go_back() == 0
+
+    def go_forward(self) -> bool:
+        """Go forward in history; False if there was nothing to go forward to."""
+        if not self._runtime:
+            raise RuntimeError("App not started")
+        return self._runtime.pw_go_forward() == 0
+
+    def can_go_back(self) -> bool:
+        """Whether go_back() has an earlier history entry to go to."""
+        if not self._runtime:
+            raise RuntimeError("App not started")
+        return self._runtime.pw_can_go_back() == 1
+
+    def can_go_forward(self) -> bool:
+        """Whether go_forward() has a later history entry to go to."""
+        if not self._runtime:
+            raise RuntimeError("App not started")
+        return self._runtime.pw_can_go_forward() == 1
+
+    def maximize_window(self):
+        """Maximize the window, e.g. from a custom title bar's button."""
+        if not self._runtime:
+            raise RuntimeError("App not started")
+        return self._runtime.pw_maximize_window()
+
+    def minimize_window(self):
+        """Minimize the window to the taskbar or Dock."""
+        if not self._runtime:
+            raise RuntimeError("App not started")
+        return self._runtime.pw_minimize_window()
+
+    def restore_window(self):
+        """Bring the window back from being maximized or minimized."""
+        if not self._runtime:
+            raise RuntimeError("App not started")
+        return self._runtime.pw_restore_window()
+
+    def is_maximized(self) -> bool:
+        """Whether the window is maximized."""
+        if not self._runtime:
+            raise RuntimeError("App not started")
+        return self._runtime.pw_is_maximized() == 1
+
+    def is_minimized(self) -> bool:
+        """Whether the window is minimized."""
+        if not self._runtime:
+            raise RuntimeError("App not started")
+        return self._runtime.pw_is_minimized() == 1
+
+    def show_window(self):
+        """Show the window, e.g. one created with ``start_hidden=True``."""
+        if not self._runtime:
+            raise RuntimeError("App not started")
+        return self._runtime.pw_show_window()
+
+    def hide_window(self):
+        """Hide the window without closing it; the page keeps running."""
+        if not self._runtime:
+            raise RuntimeError("App not started")
+        return self._runtime.pw_hide_window()
+
+    def set_fullscreen(self, fullscreen: bool):
+        """Enter or leave fullscreen. Changes, including ones made by the page
+        or the OS, are reported as ``{"type": "fullscreen", "active": ...}``."""
+        if not self._runtime:
+            raise RuntimeError("App not started")
+        return self._runtime.pw_set_fullscreen(bool(fullscreen))
+
+    def shutdown(self):
+        """Close the window and shut the engine down; ``start()`` then returns."""
+        if not self._runtime:
+            raise RuntimeError("App not started")
+        return self._runtime.pw_shutdown()
+
+    def confirm_close(self):
+        """Close the window in answer to a ``close_requested`` event
+        (see ``intercept_close``)."""
+        if not self._runtime:
+            raise RuntimeError("App not started")
+        return self._runtime.pw_confirm_close()
+
+    def cancel_close(self):
+        """Keep the window open in answer to a ``close_requested`` event."""
+        if not self._runtime:
+            raise RuntimeError("App not started")
+        return self._runtime.pw_cancel_close()
+
+    def _take_string(self, ptr, runtime=None):
+        """Decode a string returned by the runtime and free it."""
+        if not ptr:
+            return None
+        try:
+            return ctypes.string_at(ptr).decode("utf-8")
+        finally:
+            (runtime or self._runtime).pw_free_string(ptr)
+
+    def _call_or_defer(self, name, *args):
+        """Call a runtime function now, or queue it until the runtime is loaded."""
+        if not self._runtime:
+            self._deferred_calls.append((name, args))
+            return 0
+        return getattr(self._runtime, name)(*args)
+
     def _on_shell_event(self, payload_ptr):
         """Callback from native shell when an event occurs in JS."""
         payload = ctypes.string_at(payload_ptr).decode("utf-8")
@@ -52,9 +1174,22 @@ class App:
         if self.on_event:
             self.on_event(payload)
 
-    def start(self):
-        """Load the native runtime and open the window. Blocks until close."""
+    def start(self, block: bool = True):
+        """Load the native runtime and open the window. Blocks until close.
+
+        On macOS this must be called on the main thread. With ``block=False``
+        it returns once the window is set up, and the caller keeps the app
+        running by calling ``poll_events()`` on this thread until it returns
+        False, e.g. from its own main loop.
+        """
         self._runtime = load_runtime()
+        for name, args in self._deferred_calls:
+            getattr(self._runtime, name)(*args)
+        self._deferred_calls.clear()
+        for listener in self._event_listeners.values():
+            callback, register, args, native_token = listener
+            if native_token is None:
+                listener[3] = getattr(self._runtime, register)(*args, callback)
 
         # If pywire_app is provided, start the server thread
         if self.pywire_app:
@@ -77,9 +1212,8 @@ class App:
             self.url = f"http://127.0.0.1:{self._port}"
 
         # Define InitParams struct locally for ctypes
-        from ctypes import Structure, c_char_p, c_uint32, c_int32, c_void_p, CFUNCTYPE
+        from ctypes import Structure, c_bool, c_char_p, c_uint32, c_int32, c_void_p
 
-        EVENT_CALLBACK = CFUNCTYPE(None, c_char_p)
         self._on_event_cb = EVENT_CALLBACK(self._on_shell_event)
 
         class InitParams(Structure):
@@ -89,21 +1223,103 @@ class App:
                 ("width", c_uint32),
                 ("height", c_int32),
                 ("on_event", c_void_p),
+                ("start_hidden", c_bool),
+                ("manual_title", c_bool),
+                ("user_agent", c_char_p),
+                ("profile_dir", c_char_p),
+                ("headless", c_bool),
+                ("devtools_port", ctypes.c_uint16),
+                ("intercept_close", c_bool),
+                ("app_dir", c_char_p),
+                ("disable_file_uploads", c_bool),
+                ("max_fps", c_uint32),
+                ("smooth_scrolling", c_bool),
+                ("background_color", c_uint32),
+                ("transparent", c_bool),
+                ("log_level", c_char_p),
+                ("resources_path", c_char_p),
+                ("proxy_url", c_char_p),
+                ("no_proxy", c_char_p),
+                ("ca_certificates", c_char_p),
+                ("start_muted", c_bool),
+                ("autoplay_policy", c_char_p),
+                ("watchdog_timeout_ms", c_uint32),
+                ("decorations", c_bool),
+                ("hide_error_pages", c_bool),
+                ("disable_script_queue", c_bool),
+                ("pump_events", c_bool),
             ]
 
+        no_proxy = self.no_proxy
+        if no_proxy is not None and not isinstance(no_proxy, str):
+            no_proxy = ",".join(no_proxy)
+
         params = InitParams(
             title=self.title.encode("utf-8"),
             url=self.url.encode("utf-8") if self.url else None,
             width=self.width,
             height=self.height,
             on_event=ctypes.cast(self._on_event_cb, c_void_p),
+            start_hidden=self.start_hidden,
+            manual_title=self.manual_title,
+            user_agent=self.user_agent.encode("utf-8") if self.user_agent else None,
+            profile_dir=os.fsencode(self.profile_dir) if self.profile_dir else None,
+            headless=self.headless,
+            devtools_port=self.devtools_port,
+            intercept_close=self.intercept_close,
+            app_dir=os.fsencode(self.app_dir) if self.app_dir else None,
+            disable_file_uploads=self.disable_file_uploads,
+            max_fps=self.max_fps,
+            smooth_scrolling=self.smooth_scrolling,
+            background_color=self.background_color,
+            transparent=self.transparent,
+            log_level=self.log_level.encode("utf-8") if self.log_level else None,
+            resources_path=(
+                os.fsencode(self.resources_path) if self.resources_path else None
+            ),
+            proxy_url=self.proxy_url.encode("utf-8") if self.proxy_url else None,
+            no_proxy=no_proxy.encode("utf-8") if no_proxy is not None else None,
+            ca_certificates=(
+                os.fsencode(self.ca_certificates) if self.ca_certificates else None
+            ),
+            start_muted=self.start_muted,
+            autoplay_policy=(
+                self.autoplay_policy.encode("utf-8") if self.autoplay_policy else None
+            ),
+            watchdog_timeout_ms=self.watchdog_timeout_ms,
+            decorations=self.decorations,
+            hide_error_pages=self.hide_error_pages,
+            disable_script_queue=self.disable_script_queue,
+            pump_events=not block,
         )
 
         print(
             f"[pywire-shell] Starting window: {self.title} ({self.width}x{self.height})"
         )
         result = self._runtime.pw_start_app(params)
+        if result != 0 or block:
+            self._report_exit("pw_start_app", result)
+
+    def poll_events(self, timeout: float = 0.0) -> bool:
+        """Run the app for up to `timeout` seconds after ``start(block=False)``.
+
+        Returns True while the app runs and False once it has closed. Call it
+        on the thread that started the app.
+        """
+        if not self._runtime:
+            raise RuntimeError("App not started")
+        result = self._runtime.pw_poll_events(max(0, int(timeout * 1000)))
+        if result == 1:
+            return True
+        self._report_exit("pw_poll_events", result)
+        return False
+
+    def _report_exit(self, function, result):
         if result != 0:
-            print(f"[pywire-shell] Error: pw_start_app returned {result}")
+            code = ErrorCode(result).name if result in list(ErrorCode) else result
+            print(
+                f"[pywire-shell] Error: {function} returned {code}: "
+                f"{self.last_error()}"
+            )
         else:
             print("[pywire-shell] Window closed successfully")
@@ -2,6 +2,25 @@ import sys
 import ctypes
 from pathlib import Path
 
+# Callback types for runtime functions that report back asynchronously
+EVENT_CALLBACK = ctypes.CFUNCTYPE(None, ctypes.c_char_p)
+TYPED_EVENT_CALLBACK = ctypes.CFUNCTYPE(None, ctypes.c_uint32, ctypes.c_char_p)
+NAVIGATION_CALLBACK = ctypes.CFUNCTYPE(None, ctypes.c_bool, ctypes.c_char_p)
+LOG_CALLBACK = ctypes.CFUNCTYPE(None, ctypes.c_uint32, ctypes.c_char_p, ctypes.c_char_p)
+RESOURCE_CALLBACK = ctypes.CFUNCTYPE(
+    ctypes.c_bool,
+    ctypes.c_char_p,
+    ctypes.POINTER(ctypes.c_void_p),
+    ctypes.POINTER(ctypes.c_size_t),
+)
+PROTOCOL_CALLBACK = ctypes.CFUNCTYPE(
+    ctypes.c_bool,
+    ctypes.c_char_p,
+    ctypes.POINTER(ctypes.c_void_p),
+    ctypes.POINTER(ctypes.c_size_t),
+    ctypes.POINTER(ctypes.c_void_p),
+)
+
 
 def get_runtime_path():
     """Locate the pywire_servo shared library."""
@@ -38,10 +57,22 @@ def load_runtime():
     lib.pw_version.restype = ctypes.c_char_p
     lib.pw_version.argtypes = []
 
+    # pw_run_diagnostics bindings
+    lib.pw_run_diagnostics.restype = ctypes.c_void_p
+    lib.pw_run_diagnostics.argtypes = []
+
     # pw_execute_javascript bindings
     lib.pw_execute_javascript.restype = ctypes.c_int32
     lib.pw_execute_javascript.argtypes = [ctypes.c_char_p]
 
+    # pw_post_message bindings
+    lib.pw_post_message.restype = ctypes.c_int32
+    lib.pw_post_message.argtypes = [ctypes.c_char_p, ctypes.c_char_p]
+
+    # pw_add_user_script bindings
+    lib.pw_add_user_script.restype = ctypes.c_int32
+    lib.pw_add_user_script.argtypes = [ctypes.c_char_p, ctypes.c_bool]
+
     # pw_set_title bindings
     lib.pw_set_title.restype = ctypes.c_int32
     lib.pw_set_title.argtypes = [ctypes.c_char_p]
@@ -50,4 +81,447 @@ def load_runtime():
     lib.pw_resize_window.restype = ctypes.c_int32
     lib.pw_resize_window.argtypes = [ctypes.c_uint32, ctypes.c_uint32]
 
+    # pw_set_load_timeout bindings
+    lib.pw_set_load_timeout.restype = ctypes.c_int32
+    lib.pw_set_load_timeout.argtypes = [ctypes.c_uint64]
+
+    # pw_stop_loading bindings
+    lib.pw_stop_loading.restype = ctypes.c_int32
+    lib.pw_stop_loading.argtypes = []
+
+    # pw_set_mixed_content_policy bindings
+    lib.pw_set_mixed_content_policy.restype = ctypes.c_int32
+    lib.pw_set_mixed_content_policy.argtypes = [ctypes.c_char_p]
+
+    # pw_set_cache_mode bindings
+    lib.pw_set_cache_mode.restype = ctypes.c_int32
+    lib.pw_set_cache_mode.argtypes = [ctypes.c_char_p]
+
+    # pw_set_spatial_navigation bindings
+    lib.pw_set_spatial_navigation.restype = ctypes.c_int32
+    lib.pw_set_spatial_navigation.argtypes = [ctypes.c_bool]
+
+    # pw_set_scroll_speed bindings
+    lib.pw_set_scroll_speed.restype = ctypes.c_int32
+    lib.pw_set_scroll_speed.argtypes = [ctypes.c_float]
+
+    # pw_set_natural_scrolling bindings
+    lib.pw_set_natural_scrolling.restype = ctypes.c_int32
+    lib.pw_set_natural_scrolling.argtypes = [ctypes.c_bool]
+
+    # pw_register_accelerator bindings
+    lib.pw_register_accelerator.restype = ctypes.c_int32
+    lib.pw_register_accelerator.argtypes = [ctypes.c_char_p, ctypes.c_uint32, ctypes.c_bool]
+
+    # pw_unregister_accelerator bindings
+    lib.pw_unregister_accelerator.restype = ctypes.c_int32
+    lib.pw_unregister_accelerator.argtypes = [ctypes.c_uint32]
+
+    # pw_set_color_scheme bindings
+    lib.pw_set_color_scheme.restype = ctypes.c_int32
+    lib.pw_set_color_scheme.argtypes = [ctypes.c_char_p]
+
+    # pw_set_focus_ring_style bindings
+    lib.pw_set_focus_ring_style.restype = ctypes.c_int32
+    lib.pw_set_focus_ring_style.argtypes = [ctypes.c_char_p]
+
+    # pw_get_scroll_position bindings
+    lib.pw_get_scroll_position.restype = ctypes.c_int32
+    lib.pw_get_scroll_position.argtypes = [
+        ctypes.POINTER(ctypes.c_double),
+        ctypes.POINTER(ctypes.c_double),
+    ]
+
+    # pw_set_scroll_position bindings
+    lib.pw_set_scroll_position.restype = ctypes.c_int32
+    lib.pw_set_scroll_position.argtypes = [ctypes.c_double, ctypes.c_double]
+
+    # pw_send_mouse_click bindings
+    lib.pw_send_mouse_click.restype = ctypes.c_int32
+    lib.pw_send_mouse_click.argtypes = [ctypes.c_double, ctypes.c_double, ctypes.c_char_p]
+
+    # pw_send_key bindings
+    lib.pw_send_key.restype = ctypes.c_int32
+    lib.pw_send_key.argtypes = [ctypes.c_char_p]
+
+    # pw_send_scroll bindings
+    lib.pw_send_scroll.restype = ctypes.c_int32
+    lib.pw_send_scroll.argtypes = [ctypes.c_double, ctypes.c_double]
+
+    # pw_capture_dom_snapshot bindings (caller frees with pw_free_string)
+    lib.pw_capture_dom_snapshot.restype = ctypes.c_void_p
+    lib.pw_capture_dom_snapshot.argtypes = [ctypes.c_uint32]
+
+    # pw_find bindings
+    lib.pw_find.restype = ctypes.c_int32
+    lib.pw_find.argtypes = [ctypes.c_char_p, ctypes.c_bool, ctypes.c_bool]
+
+    # pw_find_clear bindings
+    lib.pw_find_clear.restype = ctypes.c_int32
+    lib.pw_find_clear.argtypes = []
+
+    # pw_set_webgl_enabled bindings
+    lib.pw_set_webgl_enabled.restype = ctypes.c_int32
+    lib.pw_set_webgl_enabled.argtypes = [ctypes.c_bool]
+
+    # pw_set_accelerated_canvas bindings
+    lib.pw_set_accelerated_canvas.restype = ctypes.c_int32
+    lib.pw_set_accelerated_canvas.argtypes = [ctypes.c_bool]
+
+    # pw_set_zoom bindings
+    lib.pw_set_zoom.restype = ctypes.c_int32
+    lib.pw_set_zoom.argtypes = [ctypes.c_float]
+
+    # pw_set_origin_zoom bindings
+    lib.pw_set_origin_zoom.restype = ctypes.c_int32
+    lib.pw_set_origin_zoom.argtypes = [ctypes.c_float]
+
+    # pw_clear_zoom_overrides bindings
+    lib.pw_clear_zoom_overrides.restype = ctypes.c_int32
+    lib.pw_clear_zoom_overrides.argtypes = []
+
+    # pw_zoom_in bindings
+    lib.pw_zoom_in.restype = ctypes.c_int32
+    lib.pw_zoom_in.argtypes = []
+
+    # pw_zoom_out bindings
+    lib.pw_zoom_out.restype = ctypes.c_int32
+    lib.pw_zoom_out.argtypes = []
+
+    # pw_reset_zoom bindings
+    lib.pw_reset_zoom.restype = ctypes.c_int32
+    lib.pw_reset_zoom.argtypes = []
+
+    # pw_set_pinch_zoom bindings
+    lib.pw_set_pinch_zoom.restype = ctypes.c_int32
+    lib.pw_set_pinch_zoom.argtypes = [ctypes.c_float]
+
+    # pw_add_url_rewrite bindings
+    lib.pw_add_url_rewrite.restype = ctypes.c_int32
+    lib.pw_add_url_rewrite.argtypes = [ctypes.c_char_p, ctypes.c_char_p]
+
+    # pw_clear_url_rewrites bindings
+    lib.pw_clear_url_rewrites.restype = ctypes.c_int32
+    lib.pw_clear_url_rewrites.argtypes = []
+
+    # pw_navigate_with_callback bindings
+    lib.pw_navigate_with_callback.restype = ctypes.c_int32
+    lib.pw_navigate_with_callback.argtypes = [ctypes.c_char_p, NAVIGATION_CALLBACK]
+
+    # pw_create_webview bindings
+    lib.pw_create_webview.restype = ctypes.c_int32
+    lib.pw_create_webview.argtypes = [ctypes.c_char_p]
+
+    # pw_activate_webview bindings
+    lib.pw_activate_webview.restype = ctypes.c_int32
+    lib.pw_activate_webview.argtypes = [ctypes.c_uint32]
+
+    # pw_close_webview bindings
+    lib.pw_close_webview.restype = ctypes.c_int32
+    lib.pw_close_webview.argtypes = [ctypes.c_uint32]
+
+    # pw_set_new_window_policy bindings
+    lib.pw_set_new_window_policy.restype = ctypes.c_int32
+    lib.pw_set_new_window_policy.argtypes = [ctypes.c_char_p]
+
+    # pw_set_native_context_menu bindings
+    lib.pw_set_native_context_menu.restype = ctypes.c_int32
+    lib.pw_set_native_context_menu.argtypes = [ctypes.c_bool]
+
+    # pw_provide_credentials bindings
+    lib.pw_provide_credentials.restype = ctypes.c_int32
+    lib.pw_provide_credentials.argtypes = [ctypes.c_uint32, ctypes.c_char_p, ctypes.c_char_p]
+
+    # pw_cancel_authentication bindings
+    lib.pw_cancel_authentication.restype = ctypes.c_int32
+    lib.pw_cancel_authentication.argtypes = [ctypes.c_uint32]
+
+    # pw_allow_cert bindings
+    lib.pw_allow_cert.restype = ctypes.c_int32
+    lib.pw_allow_cert.argtypes = [ctypes.c_uint32]
+
+    # pw_reject_cert bindings
+    lib.pw_reject_cert.restype = ctypes.c_int32
+    lib.pw_reject_cert.argtypes = [ctypes.c_uint32]
+
+    # pw_set_error_page bindings
+    lib.pw_set_error_page.restype = ctypes.c_int32
+    lib.pw_set_error_page.argtypes = [ctypes.c_char_p, ctypes.c_char_p]
+
+    # pw_respond_permission bindings
+    lib.pw_respond_permission.restype = ctypes.c_int32
+    lib.pw_respond_permission.argtypes = [ctypes.c_uint32, ctypes.c_bool]
+
+    # pw_set_permission_timeout bindings
+    lib.pw_set_permission_timeout.restype = ctypes.c_int32
+    lib.pw_set_permission_timeout.argtypes = [ctypes.c_uint64]
+
+    # pw_set_auth_timeout bindings
+    lib.pw_set_auth_timeout.restype = ctypes.c_int32
+    lib.pw_set_auth_timeout.argtypes = [ctypes.c_uint64]
+
+    # pw_get_url bindings
+    lib.pw_get_url.restype = ctypes.c_void_p
+    lib.pw_get_url.argtypes = []
+
+    # pw_add_event_listener_native bindings
+    lib.pw_add_event_listener_native.restype = ctypes.c_uint32
+    lib.pw_add_event_listener_native.argtypes = [EVENT_CALLBACK]
+
+    # pw_add_typed_event_listener_native bindings
+    lib.pw_add_typed_event_listener_native.restype = ctypes.c_uint32
+    lib.pw_add_typed_event_listener_native.argtypes = [TYPED_EVENT_CALLBACK]
+
+    # pw_register_callback bindings
+    lib.pw_register_callback.restype = ctypes.c_uint32
+    lib.pw_register_callback.argtypes = [ctypes.c_char_p, EVENT_CALLBACK]
+
+    # pw_remove_event_listener_native bindings
+    lib.pw_remove_event_listener_native.restype = ctypes.c_int32
+    lib.pw_remove_event_listener_native.argtypes = [ctypes.c_uint32]
+
+    # pw_pause_all_media bindings
+    lib.pw_pause_all_media.restype = ctypes.c_int32
+    lib.pw_pause_all_media.argtypes = []
+
+    # pw_resume_all_media bindings
+    lib.pw_resume_all_media.restype = ctypes.c_int32
+    lib.pw_resume_all_media.argtypes = []
+
+    # pw_set_muted bindings
+    lib.pw_set_muted.restype = ctypes.c_int32
+    lib.pw_set_muted.argtypes = [ctypes.c_bool]
+
+    # pw_set_volume bindings
+    lib.pw_set_volume.restype = ctypes.c_int32
+    lib.pw_set_volume.argtypes = [ctypes.c_float]
+
+    # pw_set_default_encoding bindings
+    lib.pw_set_default_encoding.restype = ctypes.c_int32
+    lib.pw_set_default_encoding.argtypes = [ctypes.c_char_p]
+
+    # pw_set_do_not_track bindings
+    lib.pw_set_do_not_track.restype = ctypes.c_int32
+    lib.pw_set_do_not_track.argtypes = [ctypes.c_bool]
+
+    # pw_set_global_privacy_control bindings
+    lib.pw_set_global_privacy_control.restype = ctypes.c_int32
+    lib.pw_set_global_privacy_control.argtypes = [ctypes.c_bool]
+
+    # pw_set_log_callback bindings
+    lib.pw_set_log_callback.restype = ctypes.c_int32
+    lib.pw_set_log_callback.argtypes = [LOG_CALLBACK]
+
+    # pw_set_resource_callback bindings
+    lib.pw_set_resource_callback.restype = ctypes.c_int32
+    lib.pw_set_resource_callback.argtypes = [RESOURCE_CALLBACK]
+
+    # pw_add_resource bindings
+    lib.pw_add_resource.restype = ctypes.c_int32
+    lib.pw_add_resource.argtypes = [ctypes.c_char_p, ctypes.c_char_p, ctypes.c_size_t]
+
+    # pw_register_protocol bindings
+    lib.pw_register_protocol.restype = ctypes.c_int32
+    lib.pw_register_protocol.argtypes = [ctypes.c_char_p, PROTOCOL_CALLBACK]
+
+    # pw_register_protocol_directory bindings
+    lib.pw_register_protocol_directory.restype = ctypes.c_int32
+    lib.pw_register_protocol_directory.argtypes = [ctypes.c_char_p, ctypes.c_char_p]
+
+    # pw_set_preference bindings
+    lib.pw_set_preference.restype = ctypes.c_int32
+    lib.pw_set_preference.argtypes = [ctypes.c_char_p, ctypes.c_char_p]
+
+    # pw_set_strict_resources bindings
+    lib.pw_set_strict_resources.restype = ctypes.c_int32
+    lib.pw_set_strict_resources.argtypes = [ctypes.c_bool]
+
+    # pw_set_crash_recovery bindings
+    lib.pw_set_crash_recovery.restype = ctypes.c_int32
+    lib.pw_set_crash_recovery.argtypes = [ctypes.c_bool]
+
+    # pw_get_memory_report bindings
+    lib.pw_get_memory_report.restype = ctypes.c_int32
+    lib.pw_get_memory_report.argtypes = []
+
+    # pw_last_error bindings
+    lib.pw_last_error.restype = ctypes.c_void_p
+    lib.pw_last_error.argtypes = []
+
+    # pw_set_window_shadow bindings
+    lib.pw_set_window_shadow.restype = ctypes.c_int32
+    lib.pw_set_window_shadow.argtypes = [ctypes.c_bool]
+
+    # pw_set_corner_radius bindings
+    lib.pw_set_corner_radius.restype = ctypes.c_int32
+    lib.pw_set_corner_radius.argtypes = [ctypes.c_double]
+
+    # pw_set_thumbnail bindings
+    lib.pw_set_thumbnail.restype = ctypes.c_int32
+    lib.pw_set_thumbnail.argtypes = [ctypes.c_char_p]
+
+    # pw_refresh_thumbnail bindings
+    lib.pw_refresh_thumbnail.restype = ctypes.c_int32
+    lib.pw_refresh_thumbnail.argtypes = []
+
+    # pw_set_taskbar_progress bindings
+    lib.pw_set_taskbar_progress.restype = ctypes.c_int32
+    lib.pw_set_taskbar_progress.argtypes = [ctypes.c_float, ctypes.c_char_p]
+
+    # pw_set_auto_taskbar_progress bindings
+    lib.pw_set_auto_taskbar_progress.restype = ctypes.c_int32
+    lib.pw_set_auto_taskbar_progress.argtypes = [ctypes.c_bool]
+
+    # pw_set_badge_count bindings
+    lib.pw_set_badge_count.restype = ctypes.c_int32
+    lib.pw_set_badge_count.argtypes = [ctypes.c_uint32]
+
+    # pw_clear_badge bindings
+    lib.pw_clear_badge.restype = ctypes.c_int32
+    lib.pw_clear_badge.argtypes = []
+
+    # pw_request_single_instance bindings
+    lib.pw_request_single_instance.restype = ctypes.c_int32
+    lib.pw_request_single_instance.argtypes = [ctypes.c_char_p]
+
+    # pw_load_url bindings
+    lib.pw_load_url.restype = ctypes.c_int32
+    lib.pw_load_url.argtypes = [ctypes.c_char_p]
+
+    # pw_reload bindings
+    lib.pw_reload.restype = ctypes.c_int32
+    lib.pw_reload.argtypes = []
+
+    # pw_go_back bindings
+    lib.pw_go_back.restype = ctypes.c_int32
+    lib.pw_go_back.argtypes = []
+
+    # pw_go_forward bindings
+    lib.pw_go_forward.restype = ctypes.c_int32
+    lib.pw_go_forward.argtypes = []
+
+    # pw_can_go_back bindings
+    lib.pw_can_go_back.restype = ctypes.c_int32
+    lib.pw_can_go_back.argtypes = []
+
+    # pw_can_go_forward bindings
+    lib.pw_can_go_forward.restype = ctypes.c_int32
+    lib.pw_can_go_forward.argtypes = []
+
+    # pw_poll_events bindings
+    lib.pw_poll_events.restype = ctypes.c_int32
+    lib.pw_poll_events.argtypes = [ctypes.c_uint32]
+
+    # pw_shutdown bindings
+    lib.pw_shutdown.restype = ctypes.c_int32
+    lib.pw_shutdown.argtypes = []
+
+    # pw_confirm_close bindings
+    lib.pw_confirm_close.restype = ctypes.c_int32
+    lib.pw_confirm_close.argtypes = []
+
+    # pw_cancel_close bindings
+    lib.pw_cancel_close.restype = ctypes.c_int32
+    lib.pw_cancel_close.argtypes = []
+
+    # pw_show_window bindings
+    lib.pw_show_window.restype = ctypes.c_int32
+    lib.pw_show_window.argtypes = []
+
+    # pw_hide_window bindings
+    lib.pw_hide_window.restype = ctypes.c_int32
+    lib.pw_hide_window.argtypes = []
+
+    # pw_set_fullscreen bindings
+    lib.pw_set_fullscreen.restype = ctypes.c_int32
+    lib.pw_set_fullscreen.argtypes = [ctypes.c_bool]
+
+    # pw_set_clipboard_text bindings
+    lib.pw_set_clipboard_text.restype = ctypes.c_int32
+    lib.pw_set_clipboard_text.argtypes = [ctypes.c_char_p]
+
+    # pw_get_clipboard_text bindings
+    lib.pw_get_clipboard_text.restype = ctypes.c_void_p
+    lib.pw_get_clipboard_text.argtypes = []
+
+    # pw_set_window_icon bindings
+    lib.pw_set_window_icon.restype = ctypes.c_int32
+    lib.pw_set_window_icon.argtypes = [
+        ctypes.POINTER(ctypes.c_uint8),
+        ctypes.c_size_t,
+        ctypes.c_uint32,
+        ctypes.c_uint32,
+    ]
+
+    # pw_set_min_window_size bindings
+    lib.pw_set_min_window_size.restype = ctypes.c_int32
+    lib.pw_set_min_window_size.argtypes = [ctypes.c_uint32, ctypes.c_uint32]
+
+    # pw_set_max_window_size bindings
+    lib.pw_set_max_window_size.restype = ctypes.c_int32
+    lib.pw_set_max_window_size.argtypes = [ctypes.c_uint32, ctypes.c_uint32]
+
+    # pw_set_window_position bindings
+    lib.pw_set_window_position.restype = ctypes.c_int32
+    lib.pw_set_window_position.argtypes = [ctypes.c_int32, ctypes.c_int32]
+
+    # pw_maximize_window bindings
+    lib.pw_maximize_window.restype = ctypes.c_int32
+    lib.pw_maximize_window.argtypes = []
+
+    # pw_minimize_window bindings
+    lib.pw_minimize_window.restype = ctypes.c_int32
+    lib.pw_minimize_window.argtypes = []
+
+    # pw_restore_window bindings
+    lib.pw_restore_window.restype = ctypes.c_int32
+    lib.pw_restore_window.argtypes = []
+
+    # pw_is_maximized bindings
+    lib.pw_is_maximized.restype = ctypes.c_int32
+    lib.pw_is_maximized.argtypes = []
+
+    # pw_is_minimized bindings
+    lib.pw_is_minimized.restype = ctypes.c_int32
+    lib.pw_is_minimized.argtypes = []
+
+    # pw_set_window_level bindings
+    lib.pw_set_window_level.restype = ctypes.c_int32
+    lib.pw_set_window_level.argtypes = [ctypes.c_char_p]
+
+    # pw_set_always_on_top bindings
+    lib.pw_set_always_on_top.restype = ctypes.c_int32
+    lib.pw_set_always_on_top.argtypes = [ctypes.c_bool]
+
+    # pw_get_window_position bindings
+    lib.pw_get_window_position.restype = ctypes.c_int32
+    lib.pw_get_window_position.argtypes = [
+        ctypes.POINTER(ctypes.c_int32),
+        ctypes.POINTER(ctypes.c_int32),
+    ]
+
+    # pw_get_scale_factor bindings
+    lib.pw_get_scale_factor.restype = ctypes.c_double
+    lib.pw_get_scale_factor.argtypes = []
+
+    # pw_capture_png bindings
+    lib.pw_capture_png.restype = ctypes.c_int32
+    lib.pw_capture_png.argtypes = [ctypes.c_char_p]
+
+    # pw_print_to_pdf bindings
+    lib.pw_print_to_pdf.restype = ctypes.c_int32
+    lib.pw_print_to_pdf.argtypes = [ctypes.c_char_p, ctypes.c_char_p]
+
+    # pw_clear_cookies bindings
+    lib.pw_clear_cookies.restype = ctypes.c_int32
+    lib.pw_clear_cookies.argtypes = []
+
+    # pw_get_user_agent bindings
+    lib.pw_get_user_agent.restype = ctypes.c_void_p
+    lib.pw_get_user_agent.argtypes = []
+
+    # pw_free_string bindings
+    lib.pw_free_string.restype = None
+    lib.pw_free_string.argtypes = [ctypes.c_void_p]
+
     return lib
@@ -0,0 +1,23 @@
+from enum import IntEnum
+
+
+class ErrorCode(IntEnum):
+    """Failure codes returned by the native runtime's functions; 0 is success.
+
+    Mirrors `ErrorCode` in the native runtime; values never change.
+    `App.last_error()` describes the most recent failure.
+    """
+
+    NULL_ARGUMENT = -1
+    LOOP_EXITED = -2
+    NOT_STARTED = -3
+    INVALID_ARGUMENT = -4
+    TIMEOUT = -5
+    UNAVAILABLE = -6
+    ALREADY_STARTED = -7
+    MISSING_RESOURCES = -8
+    LOCK_FAILED = -9
+    WRITE_FAILED = -10
+    WINDOW_FAILED = -11
+    PANICKED = -12
+    WRONG_THREAD = -13
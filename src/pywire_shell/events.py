@@ -0,0 +1,28 @@
+from enum import IntEnum
+
+
+class EventType(IntEnum):
+    """Numeric event categories reported to typed event listeners.
+
+    Mirrors `EventType` in the native runtime; values never change.
+    """
+
+    BRIDGE = 0
+    LOAD = 1
+    TITLE = 2
+    CONSOLE = 3
+    NAVIGATION = 4
+    CLOSE = 5
+    ZOOM = 6
+    NETWORK = 7
+    INSTANCE = 8
+    WINDOW = 9
+    STORAGE = 10
+    DEVTOOLS = 11
+    INPUT = 12
+    PERMISSION = 13
+    PRINT = 14
+    FIND = 15
+    RESOURCE = 16
+    MEMORY = 17
+    CRASH = 18
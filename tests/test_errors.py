@@ -0,0 +1,20 @@
+from pywire_shell.errors import ErrorCode
+
+
+def test_error_code_values_are_stable():
+    """ErrorCode values are part of the native FFI contract."""
+    assert [(e.name, e.value) for e in ErrorCode] == [
+        ("NULL_ARGUMENT", -1),
+        ("LOOP_EXITED", -2),
+        ("NOT_STARTED", -3),
+        ("INVALID_ARGUMENT", -4),
+        ("TIMEOUT", -5),
+        ("UNAVAILABLE", -6),
+        ("ALREADY_STARTED", -7),
+        ("MISSING_RESOURCES", -8),
+        ("LOCK_FAILED", -9),
+        ("WRITE_FAILED", -10),
+        ("WINDOW_FAILED", -11),
+        ("PANICKED", -12),
+        ("WRONG_THREAD", -13),
+    ]
@@ -0,0 +1,26 @@
+from pywire_shell.events import EventType
+
+
+def test_event_type_values_are_stable():
+    """EventType values are part of the native FFI contract."""
+    assert [(e.name, e.value) for e in EventType] == [
+        ("BRIDGE", 0),
+        ("LOAD", 1),
+        ("TITLE", 2),
+        ("CONSOLE", 3),
+        ("NAVIGATION", 4),
+        ("CLOSE", 5),
+        ("ZOOM", 6),
+        ("NETWORK", 7),
+        ("INSTANCE", 8),
+        ("WINDOW", 9),
+        ("STORAGE", 10),
+        ("DEVTOOLS", 11),
+        ("INPUT", 12),
+        ("PERMISSION", 13),
+        ("PRINT", 14),
+        ("FIND", 15),
+        ("RESOURCE", 16),
+        ("MEMORY", 17),
+        ("CRASH", 18),
+    ]
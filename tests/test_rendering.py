@@ -0,0 +1,30 @@
+import threading
+import time
+
+from pywire_shell import App
+
+
+def frames_painted(app):
+    """Frames painted so far, or 0 before the app is up."""
+    runtime = app.run_diagnostics().get("runtime") or {}
+    return runtime.get("rendering", {}).get("frames_painted", 0)
+
+
+def test_idle_page_stops_painting():
+    """Once an idle page has painted its load, it paints nothing more."""
+    app = App(url="data:text/html,<p>Idle</p>", headless=True)
+    thread = threading.Thread(target=app.start, daemon=True)
+    thread.start()
+    try:
+        deadline = time.monotonic() + 20
+        while frames_painted(app) == 0:
+            assert time.monotonic() < deadline, "The page was never painted"
+            time.sleep(0.1)
+        # Let the load settle before counting.
+        time.sleep(1)
+        before = frames_painted(app)
+        time.sleep(2)
+        assert frames_painted(app) == before
+    finally:
+        app.shutdown()
+        thread.join(10)
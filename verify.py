@@ -8,7 +8,9 @@ sys.path.insert(0, os.path.join(os.getcwd(), "src"))
 from pywire_shell import App
 
 def main():
-    html_path = os.path.abspath("verify.html")
+    # e.g. `python verify.py verify_animation.html`
+    page = sys.argv[1] if len(sys.argv) > 1 else "verify.html"
+    html_path = os.path.abspath(page)
     url = f"file://{html_path}"
     
     app = App(